@@ -0,0 +1,111 @@
+/*
+ * C API for jsl, an implementation of JSON Schema Language.
+ *
+ * Build the library with `cargo build --release -p jsl_capi`, and link
+ * against the resulting `libjsl_capi` shared library.
+ *
+ * Schemas, validation errors and the strings they contain are owned by the
+ * library, and are only valid until freed with the matching `jsl_*_free`
+ * function. Functions which can fail return a `jsl_status`, and describe what
+ * went wrong in a message available from `jsl_last_error_message`.
+ */
+
+#ifndef JSL_H
+#define JSL_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The outcome of a call to this library. */
+typedef enum jsl_status {
+  /* The call succeeded. */
+  JSL_OK = 0,
+  /* An argument was a null pointer, or otherwise unusable. */
+  JSL_INVALID_ARGUMENT = 1,
+  /* A buffer was not valid JSON, or a schema was not shaped like one. */
+  JSL_INVALID_JSON = 2,
+  /* A schema did not match any of the JSL forms. */
+  JSL_INVALID_FORM = 3,
+  /* A schema defines the same property as both required and optional. */
+  JSL_AMBIGUOUS_PROPERTY = 4,
+  /* A schema refers to a definition which does not exist. */
+  JSL_NO_SUCH_DEFINITION = 5,
+  /* Validation followed more references than the configured maximum depth. */
+  JSL_MAX_DEPTH_EXCEEDED = 6,
+  /* Something else went wrong, including bugs in this library. */
+  JSL_OTHER = 7,
+} jsl_status;
+
+/* A parsed, root schema. */
+typedef struct jsl_schema jsl_schema;
+
+/* The validation errors produced by validating an instance. */
+typedef struct jsl_errors jsl_errors;
+
+/* How validation should proceed. */
+typedef struct jsl_config {
+  /* The maximum number of errors to produce, or 0 for all of them. */
+  size_t max_errors;
+  /* The maximum number of references to follow before failing. */
+  size_t max_depth;
+  /* Whether properties a schema doesn't mention are errors. */
+  bool strict_instance_semantics;
+} jsl_config;
+
+/*
+ * Parse a schema from a buffer of `len` bytes of JSON. On success, `*out` is
+ * set to a schema which must be freed with `jsl_schema_free`.
+ */
+jsl_status jsl_schema_parse(const char *data, size_t len, jsl_schema **out);
+
+/* Free a schema. Does nothing if `schema` is null. */
+void jsl_schema_free(jsl_schema *schema);
+
+/* The default validation configuration. */
+jsl_config jsl_config_default(void);
+
+/*
+ * Validate a buffer of `len` bytes of JSON against a schema. `config` may be
+ * null, to use the default configuration. On success, `*out` is set to the
+ * validation errors, which must be freed with `jsl_errors_free`. An instance
+ * is valid if there are no errors.
+ */
+jsl_status jsl_validate(const jsl_schema *schema, const jsl_config *config,
+                        const char *data, size_t len, jsl_errors **out);
+
+/* The number of validation errors. Returns 0 if `errors` is null. */
+size_t jsl_errors_count(const jsl_errors *errors);
+
+/*
+ * The instance path and schema path of the validation error at `index`, as
+ * JSON Pointers.
+ *
+ * The returned string is NUL-terminated, and lives as long as `errors`. If
+ * `len` isn't null, the string's length, without the NUL terminator, is
+ * written to it. Since instance paths can contain NUL characters, callers
+ * which care about them should use `len`. Returns null if `errors` is null or
+ * `index` is out of range.
+ */
+const char *jsl_errors_instance_path(const jsl_errors *errors, size_t index,
+                                     size_t *len);
+const char *jsl_errors_schema_path(const jsl_errors *errors, size_t index,
+                                   size_t *len);
+
+/* Free validation errors. Does nothing if `errors` is null. */
+void jsl_errors_free(jsl_errors *errors);
+
+/*
+ * A description of the last failure on the calling thread, or null if nothing
+ * has failed. The string lives until the next failure on the same thread.
+ */
+const char *jsl_last_error_message(void);
+
+#ifdef __cplusplus
+} /* extern "C" */
+#endif
+
+#endif /* JSL_H */
@@ -0,0 +1,425 @@
+//! A C API for [`jsl`](https://docs.rs/jsl), for embedding the validator in
+//! other languages.
+//!
+//! The shared library this crate builds, `libjsl_capi`, exports the functions
+//! in this crate. They're declared for C and C++ in `include/jsl.h`.
+//! `tests/header_test.rs` fails if the header's functions, status values or
+//! config fields differ from this crate's.
+//!
+//! Schemas, validation results and their strings are owned by this library,
+//! and are only valid until they're freed with the matching `jsl_*_free`
+//! function. Functions which can fail return a [`jsl_status`](enum.jsl_status.html),
+//! and describe what went wrong in a message available from
+//! [`jsl_last_error_message`](fn.jsl_last_error_message.html).
+
+#![allow(non_camel_case_types)]
+
+use jsl::errors::JslError;
+use jsl::schema::{Schema, Serde};
+use jsl::validator::{Config, Validator};
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+/// The outcome of a call to this library.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum jsl_status {
+    /// The call succeeded.
+    JSL_OK = 0,
+
+    /// An argument was a null pointer, or otherwise unusable.
+    JSL_INVALID_ARGUMENT = 1,
+
+    /// A buffer was not valid JSON, or a schema was not shaped like one.
+    JSL_INVALID_JSON = 2,
+
+    /// See [`JslError::InvalidForm`](../jsl/errors/enum.JslError.html#variant.InvalidForm).
+    JSL_INVALID_FORM = 3,
+
+    /// See [`JslError::AmbiguousProperty`](../jsl/errors/enum.JslError.html#variant.AmbiguousProperty).
+    JSL_AMBIGUOUS_PROPERTY = 4,
+
+    /// See [`JslError::NoSuchDefinition`](../jsl/errors/enum.JslError.html#variant.NoSuchDefinition).
+    JSL_NO_SUCH_DEFINITION = 5,
+
+    /// See [`JslError::MaxDepthExceeded`](../jsl/errors/enum.JslError.html#variant.MaxDepthExceeded).
+    JSL_MAX_DEPTH_EXCEEDED = 6,
+
+    /// Something else went wrong, including bugs in this library.
+    JSL_OTHER = 7,
+}
+
+/// A parsed, root schema.
+pub struct jsl_schema {
+    schema: Schema,
+}
+
+/// The validation errors produced by validating an instance.
+pub struct jsl_errors {
+    /// Each error's instance path and schema path, with a trailing NUL byte.
+    errors: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+/// How validation should proceed. See
+/// [`Config`](../jsl/validator/struct.Config.html) for what each option means.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct jsl_config {
+    pub max_errors: usize,
+    pub max_depth: usize,
+    pub strict_instance_semantics: bool,
+}
+
+impl From<jsl_config> for Config {
+    fn from(config: jsl_config) -> Self {
+        let mut out = Config::new();
+        out.max_errors(config.max_errors)
+            .max_depth(config.max_depth)
+            .strict_instance_semantics(config.strict_instance_semantics);
+        out
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+type Failure = (jsl_status, String);
+
+/// Run the body of an exported function, recording any failure as the last
+/// error and keeping panics from unwinding into C.
+fn run(body: impl FnOnce() -> Result<(), Failure>) -> jsl_status {
+    let (status, message) = match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => return jsl_status::JSL_OK,
+        Ok(Err(failure)) => failure,
+        Err(_) => (jsl_status::JSL_OTHER, "panic in jsl".to_owned()),
+    };
+
+    let message = CString::new(message.replace('\0', "\\0")).unwrap();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    status
+}
+
+fn failure(error: JslError) -> Failure {
+    let status = match error {
+        JslError::InvalidForm => jsl_status::JSL_INVALID_FORM,
+        JslError::AmbiguousProperty { .. } => jsl_status::JSL_AMBIGUOUS_PROPERTY,
+        JslError::NoSuchDefinition { .. } => jsl_status::JSL_NO_SUCH_DEFINITION,
+        JslError::MaxDepthExceeded => jsl_status::JSL_MAX_DEPTH_EXCEEDED,
+    };
+
+    (status, error.to_string())
+}
+
+/// Borrow a buffer passed in from C.
+unsafe fn buffer<'a>(data: *const c_char, len: usize) -> Result<&'a [u8], Failure> {
+    if data.is_null() {
+        if len == 0 {
+            return Ok(&[]);
+        }
+
+        return Err(null_argument("data"));
+    }
+
+    Ok(slice::from_raw_parts(data as *const u8, len))
+}
+
+fn null_argument(name: &str) -> Failure {
+    (
+        jsl_status::JSL_INVALID_ARGUMENT,
+        format!("{} must not be null", name),
+    )
+}
+
+fn invalid_json(error: serde_json::Error) -> Failure {
+    (jsl_status::JSL_INVALID_JSON, error.to_string())
+}
+
+/// Parse a schema from a buffer of `len` bytes of JSON. On success, `*out`
+/// is set to a schema which must be freed with `jsl_schema_free`.
+///
+/// # Safety
+///
+/// `data` must point to at least `len` readable bytes, and `out` must be a
+/// valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn jsl_schema_parse(
+    data: *const c_char,
+    len: usize,
+    out: *mut *mut jsl_schema,
+) -> jsl_status {
+    run(|| {
+        if out.is_null() {
+            return Err(null_argument("out"));
+        }
+
+        let serde: Serde = serde_json::from_slice(buffer(data, len)?).map_err(invalid_json)?;
+        let schema = Schema::from_serde(serde).map_err(failure)?;
+        *out = Box::into_raw(Box::new(jsl_schema { schema }));
+        Ok(())
+    })
+}
+
+/// Free a schema. Does nothing if `schema` is null.
+///
+/// # Safety
+///
+/// `schema` must be null, or have come from `jsl_schema_parse` and not
+/// already have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn jsl_schema_free(schema: *mut jsl_schema) {
+    if !schema.is_null() {
+        drop(Box::from_raw(schema));
+    }
+}
+
+/// The default validation configuration.
+#[no_mangle]
+pub extern "C" fn jsl_config_default() -> jsl_config {
+    // These must match `Config::default`, which is checked in the tests.
+    jsl_config {
+        max_errors: 0,
+        max_depth: 32,
+        strict_instance_semantics: false,
+    }
+}
+
+/// Validate a buffer of `len` bytes of JSON against a schema. `config` may be
+/// null, to use the default configuration. On success, `*out` is set to the
+/// validation errors, which must be freed with `jsl_errors_free`. An instance
+/// is valid if there are no errors.
+///
+/// # Safety
+///
+/// `schema` must be a live schema from `jsl_schema_parse`. `config` must be
+/// null or valid to read. `data` must point to at least `len` readable
+/// bytes, and `out` must be a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn jsl_validate(
+    schema: *const jsl_schema,
+    config: *const jsl_config,
+    data: *const c_char,
+    len: usize,
+    out: *mut *mut jsl_errors,
+) -> jsl_status {
+    run(|| {
+        if out.is_null() {
+            return Err(null_argument("out"));
+        }
+
+        let schema = match schema.as_ref() {
+            Some(schema) => &schema.schema,
+            None => return Err(null_argument("schema")),
+        };
+
+        let config = config.as_ref().map_or_else(Config::default, |&c| c.into());
+        let instance: serde_json::Value =
+            serde_json::from_slice(buffer(data, len)?).map_err(invalid_json)?;
+
+        let errors = Validator::new_with_config(config)
+            .validate(schema, &instance)
+            .map_err(failure)?;
+
+        let errors = errors
+            .iter()
+            .map(|error| {
+                (
+                    nul_terminated(error.instance_path().to_string()),
+                    nul_terminated(error.schema_path().to_string()),
+                )
+            })
+            .collect();
+
+        *out = Box::into_raw(Box::new(jsl_errors { errors }));
+        Ok(())
+    })
+}
+
+fn nul_terminated(s: String) -> Vec<u8> {
+    let mut bytes = s.into_bytes();
+    bytes.push(0);
+    bytes
+}
+
+/// The number of validation errors. Returns 0 if `errors` is null.
+///
+/// # Safety
+///
+/// `errors` must be null, or a live result from `jsl_validate`.
+#[no_mangle]
+pub unsafe extern "C" fn jsl_errors_count(errors: *const jsl_errors) -> usize {
+    errors.as_ref().map_or(0, |errors| errors.errors.len())
+}
+
+/// The instance path of the validation error at `index`, as a JSON Pointer.
+/// See [`jsl_errors_schema_path`](fn.jsl_errors_schema_path.html).
+///
+/// # Safety
+///
+/// See [`jsl_errors_schema_path`](fn.jsl_errors_schema_path.html).
+#[no_mangle]
+pub unsafe extern "C" fn jsl_errors_instance_path(
+    errors: *const jsl_errors,
+    index: usize,
+    len: *mut usize,
+) -> *const c_char {
+    path(errors, index, len, |(instance_path, _)| instance_path)
+}
+
+/// The schema path of the validation error at `index`, as a JSON Pointer.
+///
+/// The returned string is NUL-terminated, and lives as long as `errors`. If
+/// `len` isn't null, the string's length, without the NUL terminator, is
+/// written to it. Since instance paths can contain NUL characters, callers
+/// which care about them should use `len`. Returns null if `errors` is null
+/// or `index` is out of range.
+///
+/// # Safety
+///
+/// `errors` must be null, or a live result from `jsl_validate`. `len` must
+/// be null or valid to write to.
+#[no_mangle]
+pub unsafe extern "C" fn jsl_errors_schema_path(
+    errors: *const jsl_errors,
+    index: usize,
+    len: *mut usize,
+) -> *const c_char {
+    path(errors, index, len, |(_, schema_path)| schema_path)
+}
+
+unsafe fn path(
+    errors: *const jsl_errors,
+    index: usize,
+    len: *mut usize,
+    which: impl Fn(&(Vec<u8>, Vec<u8>)) -> &Vec<u8>,
+) -> *const c_char {
+    let path = match errors.as_ref().and_then(|errors| errors.errors.get(index)) {
+        Some(error) => which(error),
+        None => return ptr::null(),
+    };
+
+    if let Some(len) = len.as_mut() {
+        *len = path.len() - 1;
+    }
+
+    path.as_ptr() as *const c_char
+}
+
+/// Free validation errors. Does nothing if `errors` is null.
+///
+/// # Safety
+///
+/// `errors` must be null, or have come from `jsl_validate` and not already
+/// have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn jsl_errors_free(errors: *mut jsl_errors) {
+    if !errors.is_null() {
+        drop(Box::from_raw(errors));
+    }
+}
+
+/// A description of the last failure on the calling thread, or null if
+/// nothing has failed. The string lives until the next failure on the same
+/// thread.
+#[no_mangle]
+pub extern "C" fn jsl_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    fn parse(data: &str) -> Result<*mut jsl_schema, (jsl_status, String)> {
+        let mut schema = ptr::null_mut();
+        match unsafe { jsl_schema_parse(data.as_ptr() as *const c_char, data.len(), &mut schema) } {
+            jsl_status::JSL_OK => Ok(schema),
+            status => Err((status, last_error())),
+        }
+    }
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(jsl_last_error_message()) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            parse("{"),
+            Err((
+                jsl_status::JSL_INVALID_JSON,
+                "EOF while parsing an object at line 1 column 1".to_owned()
+            ))
+        );
+        assert_eq!(
+            parse(r#"{ "type": "string", "elements": {} }"#),
+            Err((
+                jsl_status::JSL_INVALID_FORM,
+                "invalid schema form".to_owned()
+            ))
+        );
+        assert_eq!(
+            parse(r#"{ "ref": "a" }"#),
+            Err((
+                jsl_status::JSL_NO_SUCH_DEFINITION,
+                "no such definition: a".to_owned()
+            ))
+        );
+
+        let status = unsafe { jsl_schema_parse(ptr::null(), 1, &mut ptr::null_mut()) };
+        assert_eq!(status, jsl_status::JSL_INVALID_ARGUMENT);
+    }
+
+    #[test]
+    fn config_default() {
+        assert_eq!(Config::from(jsl_config_default()), Config::default());
+    }
+
+    #[test]
+    fn validate() {
+        let schema = parse(r#"{ "values": { "type": "string" } }"#).unwrap();
+        let instance = r#"{ "a": "", "b\u0000": 1 }"#;
+
+        let mut config = jsl_config_default();
+        config.max_errors = 5;
+
+        let mut errors = ptr::null_mut();
+        let status = unsafe {
+            jsl_validate(
+                schema,
+                &config,
+                instance.as_ptr() as *const c_char,
+                instance.len(),
+                &mut errors,
+            )
+        };
+
+        assert_eq!(status, jsl_status::JSL_OK);
+        unsafe {
+            assert_eq!(jsl_errors_count(errors), 1);
+
+            let mut len = 0;
+            let path = jsl_errors_instance_path(errors, 0, &mut len);
+            assert_eq!(slice::from_raw_parts(path as *const u8, len), b"/b\0");
+
+            let path = jsl_errors_schema_path(errors, 0, ptr::null_mut());
+            assert_eq!(CStr::from_ptr(path).to_str().unwrap(), "/values/type");
+
+            assert!(jsl_errors_schema_path(errors, 1, ptr::null_mut()).is_null());
+
+            jsl_errors_free(errors);
+            jsl_schema_free(schema);
+        }
+    }
+}
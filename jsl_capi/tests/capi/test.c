@@ -0,0 +1,125 @@
+/*
+ * Exercises the C API from C. Built and run by tests/capi_test.rs.
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "jsl.h"
+
+#define CHECK(cond)                                                            \
+  do {                                                                         \
+    if (!(cond)) {                                                             \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+      exit(1);                                                                 \
+    }                                                                          \
+  } while (0)
+
+static jsl_status parse(const char *data, jsl_schema **out) {
+  return jsl_schema_parse(data, strlen(data), out);
+}
+
+static jsl_status validate(const jsl_schema *schema, const jsl_config *config,
+                           const char *data, jsl_errors **out) {
+  return jsl_validate(schema, config, data, strlen(data), out);
+}
+
+static void test_parse_errors(void) {
+  jsl_schema *schema = NULL;
+
+  CHECK(parse("{", &schema) == JSL_INVALID_JSON);
+  CHECK(parse("{ \"type\": 3 }", &schema) == JSL_INVALID_JSON);
+  CHECK(parse("{ \"type\": \"string\", \"ref\": \"a\" }", &schema) ==
+        JSL_INVALID_FORM);
+  CHECK(parse("{ \"properties\": { \"a\": {} }, "
+              "\"optionalProperties\": { \"a\": {} } }",
+              &schema) == JSL_AMBIGUOUS_PROPERTY);
+  CHECK(parse("{ \"ref\": \"missing\" }", &schema) == JSL_NO_SUCH_DEFINITION);
+  CHECK(strcmp(jsl_last_error_message(), "no such definition: missing") == 0);
+  CHECK(schema == NULL);
+
+  CHECK(jsl_schema_parse(NULL, 1, &schema) == JSL_INVALID_ARGUMENT);
+  CHECK(jsl_schema_parse("{}", 2, NULL) == JSL_INVALID_ARGUMENT);
+}
+
+static void test_validate(void) {
+  jsl_schema *schema = NULL;
+  jsl_errors *errors = NULL;
+
+  CHECK(parse("{ \"definitions\": { \"name\": { \"type\": \"string\" } }, "
+              "\"properties\": { \"name\": { \"ref\": \"name\" }, "
+              "\"tags\": { \"elements\": { \"type\": \"string\" } } } }",
+              &schema) == JSL_OK);
+
+  /* A valid instance has no errors. */
+  CHECK(validate(schema, NULL, "{ \"name\": \"a\", \"tags\": [] }", &errors) ==
+        JSL_OK);
+  CHECK(jsl_errors_count(errors) == 0);
+  CHECK(jsl_errors_instance_path(errors, 0, NULL) == NULL);
+  jsl_errors_free(errors);
+
+  /* The same schema can be used again. */
+  CHECK(validate(schema, NULL, "{ \"name\": 1, \"tags\": [] }", &errors) ==
+        JSL_OK);
+  CHECK(jsl_errors_count(errors) == 1);
+
+  size_t len = 0;
+  const char *path = jsl_errors_instance_path(errors, 0, &len);
+  CHECK(strcmp(path, "/name") == 0 && len == 5);
+  CHECK(strcmp(jsl_errors_schema_path(errors, 0, NULL),
+               "/definitions/name/type") == 0);
+  jsl_errors_free(errors);
+
+  CHECK(validate(schema, NULL, "{ \"name\": \"a\", \"tags\": [\"a\", 2] }",
+                 &errors) == JSL_OK);
+  CHECK(jsl_errors_count(errors) == 1);
+  CHECK(strcmp(jsl_errors_instance_path(errors, 0, NULL), "/tags/1") == 0);
+  CHECK(strcmp(jsl_errors_schema_path(errors, 0, NULL),
+               "/properties/tags/elements/type") == 0);
+  jsl_errors_free(errors);
+
+  /* Instances must be JSON. */
+  CHECK(validate(schema, NULL, "{ name: 1 }", &errors) == JSL_INVALID_JSON);
+
+  jsl_schema_free(schema);
+}
+
+static void test_config(void) {
+  jsl_schema *schema = NULL;
+  jsl_errors *errors = NULL;
+
+  CHECK(parse("{ \"properties\": { \"a\": { \"type\": \"string\" } } }",
+              &schema) == JSL_OK);
+
+  jsl_config config = jsl_config_default();
+  CHECK(config.max_errors == 0);
+  CHECK(config.max_depth == 32);
+  CHECK(!config.strict_instance_semantics);
+
+  config.strict_instance_semantics = true;
+  config.max_errors = 1;
+  CHECK(validate(schema, &config, "{ \"a\": 1, \"b\": 2 }", &errors) == JSL_OK);
+  CHECK(jsl_errors_count(errors) == 1);
+  jsl_errors_free(errors);
+  jsl_schema_free(schema);
+
+  CHECK(parse("{ \"definitions\": { \"a\": { \"ref\": \"a\" } }, "
+              "\"ref\": \"a\" }",
+              &schema) == JSL_OK);
+  CHECK(validate(schema, NULL, "null", &errors) == JSL_MAX_DEPTH_EXCEEDED);
+  CHECK(strcmp(jsl_last_error_message(), "maximum reference depth exceeeded during validation") == 0);
+  jsl_schema_free(schema);
+}
+
+int main(void) {
+  CHECK(jsl_last_error_message() == NULL);
+
+  test_parse_errors();
+  test_validate();
+  test_config();
+
+  jsl_schema_free(NULL);
+  jsl_errors_free(NULL);
+  return 0;
+}
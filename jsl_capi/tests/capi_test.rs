@@ -0,0 +1,53 @@
+//! Builds `tests/capi/test.c` against the shared library and `include/jsl.h`,
+//! and runs it. Requires a C compiler, found as `$CC` or `cc`.
+//!
+//! `cargo test` only builds the library as an rlib, so this builds the shared
+//! library itself first.
+
+#![cfg(unix)]
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn c_api() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    // Integration tests are built into target/<profile>/deps, and the shared
+    // library into target/<profile>.
+    let lib_dir = env::current_exe()
+        .unwrap()
+        .parent()
+        .and_then(|deps| deps.parent())
+        .unwrap()
+        .to_owned();
+
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned());
+    let mut build = Command::new(cargo);
+    build.args(["build", "--quiet", "--lib"]);
+    if lib_dir.ends_with("release") {
+        build.arg("--release");
+    }
+
+    let status = build.current_dir(&root).status().unwrap();
+    assert!(status.success(), "building the shared library failed");
+
+    let exe = lib_dir.join("jsl_capi_test");
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_owned());
+    let status = Command::new(cc)
+        .arg(root.join("tests/capi/test.c"))
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .args(["-ljsl_capi", "-Wall", "-Werror", "-o"])
+        .arg(&exe)
+        .status()
+        .unwrap();
+    assert!(status.success(), "compiling test.c failed");
+
+    let status = Command::new(&exe).status().unwrap();
+    assert!(status.success(), "test.c failed");
+}
@@ -0,0 +1,180 @@
+//! Checks that `include/jsl.h` declares exactly what `src/lib.rs` exports: the
+//! same functions with the same signatures, the same `jsl_status` values, and
+//! the same `jsl_config` fields.
+//!
+//! The header is written by hand, so that it can be documented for C readers.
+//! This test is what keeps it in sync.
+
+use std::collections::BTreeSet;
+
+const HEADER: &str = include_str!("../include/jsl.h");
+const SOURCE: &str = include_str!("../src/lib.rs");
+
+/// Remove all whitespace, so that declarations can be compared regardless of
+/// how they are wrapped.
+fn squash(s: &str) -> String {
+    s.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// Remove C comments.
+fn strip_comments(mut s: &str) -> String {
+    let mut out = String::new();
+    while let Some(start) = s.find("/*") {
+        out.push_str(&s[..start]);
+        let end = s[start..].find("*/").expect("unterminated comment");
+        s = &s[start + end + 2..];
+    }
+
+    out.push_str(s);
+    out
+}
+
+/// The C spelling of a Rust FFI type.
+fn c_type(rust: &str) -> String {
+    let rust = rust.trim();
+    if let Some(pointee) = rust.strip_prefix("*const ") {
+        let pointee = c_type(pointee);
+        if pointee.ends_with('*') {
+            format!("{} const *", pointee)
+        } else {
+            format!("const {} *", pointee)
+        }
+    } else if let Some(pointee) = rust.strip_prefix("*mut ") {
+        format!("{} *", c_type(pointee))
+    } else {
+        match rust {
+            "c_char" => "char".to_owned(),
+            "usize" => "size_t".to_owned(),
+            "()" => "void".to_owned(),
+            other => other.to_owned(),
+        }
+    }
+}
+
+/// The text between the brackets starting at `s`, which must start with
+/// `open`.
+fn bracketed(s: &str, open: char, close: char) -> &str {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return &s[1..i];
+            }
+        }
+    }
+
+    panic!("unbalanced {}", open)
+}
+
+/// The C prototypes of the functions `src/lib.rs` exports.
+fn source_functions() -> BTreeSet<String> {
+    let mut out = BTreeSet::new();
+    for item in SOURCE.split("#[no_mangle]").skip(1) {
+        let after_fn = &item[item.find("fn ").expect("export is not a function") + 3..];
+        let name = &after_fn[..after_fn.find('(').unwrap()];
+        let params_start = &after_fn[name.len()..];
+        let params = bracketed(params_start, '(', ')');
+
+        let rest = &params_start[params.len() + 2..];
+        let ret = match rest.trim_start().strip_prefix("->") {
+            Some(ret) => c_type(&ret[..ret.find('{').unwrap()]),
+            None => "void".to_owned(),
+        };
+
+        let params: Vec<_> = params
+            .split(',')
+            .map(str::trim)
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                let (name, typ) = param.split_at(param.find(':').unwrap());
+                format!("{} {}", c_type(&typ[1..]), name)
+            })
+            .collect();
+
+        let params = if params.is_empty() {
+            "void".to_owned()
+        } else {
+            params.join(", ")
+        };
+
+        out.insert(squash(&format!("{} {}({});", ret, name, params)));
+    }
+
+    out
+}
+
+/// The function prototypes `include/jsl.h` declares.
+fn header_functions() -> BTreeSet<String> {
+    let declarations: String = strip_comments(HEADER)
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    declarations
+        .split(';')
+        .map(str::trim)
+        .filter(|decl| decl.ends_with(')') && !decl.contains("typedef"))
+        .map(|decl| squash(&format!("{};", decl)))
+        .collect()
+}
+
+/// The body of the first item in `s` after `marker`, between braces.
+fn body_after<'a>(s: &'a str, marker: &str) -> &'a str {
+    let start = s.find(marker).unwrap_or_else(|| panic!("no {}", marker));
+    let open = s[start..].find('{').unwrap();
+    bracketed(&s[start + open..], '{', '}')
+}
+
+#[test]
+fn functions() {
+    assert_eq!(header_functions(), source_functions());
+}
+
+#[test]
+fn status_values() {
+    let source: BTreeSet<_> = body_after(SOURCE, "pub enum jsl_status")
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("JSL_"))
+        .map(squash)
+        .collect();
+
+    let header: BTreeSet<_> = body_after(&strip_comments(HEADER), "typedef enum jsl_status")
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("JSL_"))
+        .map(squash)
+        .collect();
+
+    assert_eq!(header, source);
+}
+
+#[test]
+fn config_fields() {
+    let source: Vec<_> = body_after(SOURCE, "pub struct jsl_config")
+        .lines()
+        .map(str::trim)
+        .filter_map(|line| line.strip_prefix("pub "))
+        .map(|field| {
+            let (name, typ) = field.split_at(field.find(':').unwrap());
+            squash(&format!(
+                "{} {};",
+                c_type(typ[1..].trim_end_matches(',')),
+                name
+            ))
+        })
+        .collect();
+
+    let header: Vec<_> = body_after(&strip_comments(HEADER), "typedef struct jsl_config")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(squash)
+        .collect();
+
+    assert_eq!(header, source);
+}
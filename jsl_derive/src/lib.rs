@@ -0,0 +1,523 @@
+//! `#[derive(JslSchema)]` for the [`jsl`](https://docs.rs/jsl) crate.
+//!
+//! This crate is not meant to be used directly. Instead, enable the `derive`
+//! feature of `jsl`, and use the re-exported `jsl::JslSchema` derive macro.
+//!
+//! The derived schema follows how serde would represent the type in JSON:
+//!
+//! * Structs with named fields become the properties form. Fields of type
+//!   `Option<T>`, or marked `#[serde(default)]`, are optional properties. If
+//!   the struct itself is marked `#[serde(default)]`, every field is optional.
+//! * Newtype structs are represented like the type they wrap.
+//! * Enums with only unit variants become the enum form.
+//! * Internally tagged enums (`#[serde(tag = "...")]`) become the
+//!   discriminator form.
+//!
+//! `#[serde(rename)]`, `#[serde(rename_all)]` and `#[serde(skip)]` are
+//! honored. Other serde representations, such as externally or adjacently
+//! tagged enums, are rejected at compile time.
+
+extern crate proc_macro;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, GenericArgument, LitStr,
+    PathArguments, Type,
+};
+
+#[proc_macro_derive(JslSchema, attributes(serde))]
+pub fn derive_jsl_schema(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream, Error> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "JslSchema cannot be derived for generic types",
+        ));
+    }
+
+    let ident = &input.ident;
+    let attrs = SerdeAttrs::parse(&input.attrs)?;
+    let name = attrs.rename.clone().unwrap_or_else(|| unraw(ident));
+
+    let (inline, named) = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(_) => (
+                expand_fields(&data.fields, attrs.rename_all, attrs.default)?,
+                true,
+            ),
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                (quote! { <#ty as ::jsl::JslSchema>::jsl_schema_ref(defs) }, false)
+            }
+            _ => {
+                return Err(Error::new(
+                    ident.span(),
+                    "JslSchema can only be derived for structs with named fields, or newtype structs",
+                ))
+            }
+        },
+        Data::Enum(data) => {
+            let mut variants = Vec::new();
+            for variant in &data.variants {
+                let variant_attrs = SerdeAttrs::parse(&variant.attrs)?;
+                if variant_attrs.skip {
+                    continue;
+                }
+
+                let variant_name = variant_attrs.rename.unwrap_or_else(|| {
+                    RenameRule::apply_to_variant(attrs.rename_all, &unraw(&variant.ident))
+                });
+                variants.push((variant_name, variant, variant_attrs.rename_all));
+            }
+
+            match attrs.tag {
+                Some(tag) => (expand_discriminator(&tag, &variants)?, true),
+                None => (expand_enum(ident.span(), &variants)?, true),
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                ident.span(),
+                "JslSchema cannot be derived for unions",
+            ))
+        }
+    };
+
+    let jsl_schema_ref = if named {
+        quote! {
+            fn jsl_schema_ref(
+                defs: &mut ::std::collections::BTreeMap<::std::string::String, ::jsl::Schema>,
+            ) -> ::jsl::Schema {
+                ::jsl::derive::definition_ref::<Self>(#name, defs)
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    Ok(quote! {
+        impl ::jsl::JslSchema for #ident {
+            #jsl_schema_ref
+
+            fn jsl_schema_inline(
+                defs: &mut ::std::collections::BTreeMap<::std::string::String, ::jsl::Schema>,
+            ) -> ::jsl::Schema {
+                #inline
+            }
+        }
+    })
+}
+
+/// Build the properties form for a set of named fields. If `default` is set,
+/// every field is optional.
+fn expand_fields(
+    fields: &Fields,
+    rename_all: Option<RenameRule>,
+    default: bool,
+) -> Result<TokenStream, Error> {
+    let props = expand_props(fields, rename_all, default)?;
+    let has_required = props.has_required || props.is_empty;
+    let inserts = props.inserts;
+
+    Ok(quote! {
+        let mut required = ::std::collections::BTreeMap::new();
+        let mut optional = ::std::collections::BTreeMap::new();
+        #(#inserts)*
+        ::jsl::Schema::from_parts(
+            None,
+            ::std::boxed::Box::new(::jsl::Form::Properties(required, optional, #has_required)),
+            ::std::collections::BTreeMap::new(),
+        )
+    })
+}
+
+struct Props {
+    inserts: Vec<TokenStream>,
+    has_required: bool,
+    is_empty: bool,
+}
+
+/// Build statements inserting each field into `required` or `optional`.
+fn expand_props(
+    fields: &Fields,
+    rename_all: Option<RenameRule>,
+    default: bool,
+) -> Result<Props, Error> {
+    let mut props = Props {
+        inserts: Vec::new(),
+        has_required: false,
+        is_empty: true,
+    };
+
+    for field in fields {
+        let attrs = SerdeAttrs::parse(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+
+        if attrs.flatten {
+            return Err(Error::new(
+                field.span(),
+                "JslSchema does not support #[serde(flatten)]",
+            ));
+        }
+
+        let ident = field
+            .ident
+            .as_ref()
+            .ok_or_else(|| Error::new(field.span(), "expected a named field"))?;
+        let name = attrs
+            .rename
+            .unwrap_or_else(|| RenameRule::apply_to_field(rename_all, &unraw(ident)));
+
+        props.is_empty = false;
+        if let Some(inner) = option_inner(&field.ty) {
+            props.inserts.push(quote! {
+                optional.insert(#name.to_owned(), <#inner as ::jsl::JslSchema>::jsl_schema_ref(defs));
+            });
+        } else {
+            let ty = &field.ty;
+            let map = if default || attrs.default {
+                quote! { optional }
+            } else {
+                props.has_required = true;
+                quote! { required }
+            };
+
+            props.inserts.push(quote! {
+                #map.insert(#name.to_owned(), <#ty as ::jsl::JslSchema>::jsl_schema_ref(defs));
+            });
+        }
+    }
+
+    Ok(props)
+}
+
+/// Build the enum form for an enum of unit variants.
+fn expand_enum(
+    span: Span,
+    variants: &[(String, &syn::Variant, Option<RenameRule>)],
+) -> Result<TokenStream, Error> {
+    if variants.is_empty() {
+        return Err(Error::new(
+            span,
+            "JslSchema cannot be derived for enums without variants",
+        ));
+    }
+
+    let mut names = Vec::new();
+    for (name, variant, _) in variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new(
+                variant.span(),
+                "JslSchema requires enums with non-unit variants to be internally tagged, using #[serde(tag = \"...\")]",
+            ));
+        }
+
+        names.push(name);
+    }
+
+    Ok(quote! {
+        ::jsl::Schema::from_parts(
+            None,
+            ::std::boxed::Box::new(::jsl::Form::Enum(
+                vec![#(#names.to_owned()),*].into_iter().collect(),
+            )),
+            ::std::collections::BTreeMap::new(),
+        )
+    })
+}
+
+/// Build the discriminator form for an internally tagged enum.
+fn expand_discriminator(
+    tag: &str,
+    variants: &[(String, &syn::Variant, Option<RenameRule>)],
+) -> Result<TokenStream, Error> {
+    let mut mappings = Vec::new();
+    for (name, variant, rename_all) in variants {
+        let mapping = match &variant.fields {
+            Fields::Unit => quote! {
+                ::jsl::Schema::from_parts(
+                    None,
+                    ::std::boxed::Box::new(::jsl::Form::Properties(
+                        ::std::collections::BTreeMap::new(),
+                        ::std::collections::BTreeMap::new(),
+                        true,
+                    )),
+                    ::std::collections::BTreeMap::new(),
+                )
+            },
+            Fields::Named(_) => {
+                let fields = expand_fields(&variant.fields, *rename_all, false)?;
+                quote! { { #fields } }
+            }
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                quote! {
+                    {
+                        let (required, optional) = ::jsl::derive::variant_properties(
+                            #name,
+                            <#ty as ::jsl::JslSchema>::jsl_schema_inline(defs),
+                            defs,
+                        );
+                        ::jsl::Schema::from_parts(
+                            None,
+                            ::std::boxed::Box::new(::jsl::Form::Properties(required, optional, true)),
+                            ::std::collections::BTreeMap::new(),
+                        )
+                    }
+                }
+            }
+            Fields::Unnamed(_) => {
+                return Err(Error::new(
+                    variant.span(),
+                    "JslSchema does not support tuple variants in internally tagged enums",
+                ))
+            }
+        };
+
+        mappings.push(quote! {
+            mapping.insert(#name.to_owned(), #mapping);
+        });
+    }
+
+    Ok(quote! {
+        let mut mapping = ::std::collections::BTreeMap::new();
+        #(#mappings)*
+        ::jsl::Schema::from_parts(
+            None,
+            ::std::boxed::Box::new(::jsl::Form::Discriminator(#tag.to_owned(), mapping)),
+            ::std::collections::BTreeMap::new(),
+        )
+    })
+}
+
+/// If `ty` is `Option<T>`, returns `T`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+
+    let segment = path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn unraw(ident: &syn::Ident) -> String {
+    let name = ident.to_string();
+    match name.strip_prefix("r#") {
+        Some(name) => name.to_owned(),
+        None => name,
+    }
+}
+
+/// The subset of serde's attributes which affect the derived schema.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+    tag: Option<String>,
+    default: bool,
+    skip: bool,
+    flatten: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self, Error> {
+        let mut out = Self::default();
+
+        for attr in attrs {
+            if !attr.path().is_ident("serde") {
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    out.rename = Some(parse_deserialize_name(&meta)?);
+                } else if meta.path.is_ident("rename_all") {
+                    let rule = parse_deserialize_name(&meta)?;
+                    out.rename_all = Some(
+                        RenameRule::from_str(&rule)
+                            .ok_or_else(|| meta.error(format!("unknown rename rule: {}", rule)))?,
+                    );
+                } else if meta.path.is_ident("tag") {
+                    out.tag = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("content") || meta.path.is_ident("untagged") {
+                    return Err(meta.error(
+                        "JslSchema only supports internally tagged enums, using #[serde(tag = \"...\")]",
+                    ));
+                } else if meta.path.is_ident("default") {
+                    out.default = true;
+                    skip_meta_value(&meta)?;
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                    out.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    out.flatten = true;
+                } else {
+                    skip_meta_value(&meta)?;
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(out)
+    }
+}
+
+/// Parse `name = "..."` or `name(deserialize = "...")`.
+///
+/// Schemas describe the input to deserialization, so the deserialize name is
+/// the one that matters when the two differ.
+fn parse_deserialize_name(meta: &syn::meta::ParseNestedMeta) -> Result<String, Error> {
+    if meta.input.peek(syn::Token![=]) {
+        return Ok(meta.value()?.parse::<LitStr>()?.value());
+    }
+
+    let mut name = None;
+    meta.parse_nested_meta(|nested| {
+        let value = nested.value()?.parse::<LitStr>()?.value();
+        if nested.path.is_ident("deserialize") {
+            name = Some(value);
+        }
+
+        Ok(())
+    })?;
+
+    name.ok_or_else(|| meta.error("expected a deserialize name"))
+}
+
+fn skip_meta_value(meta: &syn::meta::ParseNestedMeta) -> Result<(), Error> {
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|nested| skip_meta_value(&nested))?;
+    }
+
+    Ok(())
+}
+
+/// The case conversions supported by `#[serde(rename_all = "...")]`.
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn from_str(rule: &str) -> Option<Self> {
+        Some(match rule {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            _ => return None,
+        })
+    }
+
+    /// Rename a variant, which is presumed to be in Pascal.
+    fn apply_to_variant(rule: Option<Self>, variant: &str) -> String {
+        let rule = match rule {
+            Some(rule) => rule,
+            None => return variant.to_owned(),
+        };
+
+        match rule {
+            RenameRule::Pascal => variant.to_owned(),
+            RenameRule::Lower => variant.to_ascii_lowercase(),
+            RenameRule::Upper => variant.to_ascii_uppercase(),
+            RenameRule::Camel => lowercase_first(variant),
+            RenameRule::Snake => {
+                let mut snake = String::new();
+                for (i, ch) in variant.char_indices() {
+                    if i > 0 && ch.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(ch.to_ascii_lowercase());
+                }
+                snake
+            }
+            RenameRule::ScreamingSnake => {
+                Self::apply_to_variant(Some(RenameRule::Snake), variant).to_ascii_uppercase()
+            }
+            RenameRule::Kebab => {
+                Self::apply_to_variant(Some(RenameRule::Snake), variant).replace('_', "-")
+            }
+            RenameRule::ScreamingKebab => {
+                Self::apply_to_variant(Some(RenameRule::ScreamingSnake), variant)
+                    .replace('_', "-")
+            }
+        }
+    }
+
+    /// Rename a field, which is presumed to be in snake_case.
+    fn apply_to_field(rule: Option<Self>, field: &str) -> String {
+        let rule = match rule {
+            Some(rule) => rule,
+            None => return field.to_owned(),
+        };
+
+        match rule {
+            RenameRule::Lower | RenameRule::Snake => field.to_owned(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            RenameRule::Camel => {
+                lowercase_first(&Self::apply_to_field(Some(RenameRule::Pascal), field))
+            }
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
+
+/// Lowercase the first character of a string.
+fn lowercase_first(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
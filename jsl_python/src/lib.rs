@@ -0,0 +1,238 @@
+//! Python bindings for [`jsl`](https://docs.rs/jsl), built with
+//! [PyO3](https://pyo3.rs).
+//!
+//! This crate can be built with [maturin](https://www.maturin.rs) into a `jsl`
+//! extension module that validates data with exactly the same semantics as
+//! Rust code using `jsl`:
+//!
+//! ```python
+//! import jsl
+//!
+//! schema = jsl.Schema.from_json('{ "properties": { "name": { "type": "string" } } }')
+//! validator = jsl.Validator(strict=True, max_errors=10)
+//!
+//! [(e.instance_path, e.schema_path) for e in validator.validate(schema, { "name": 3 })]
+//! # [('/name', '/properties/name/type')]
+//! ```
+//!
+//! Instances are plain Python values, as `json.load` would produce: dicts,
+//! lists, tuples, strings, numbers, booleans and `None`. They're converted
+//! directly, without going through a JSON string.
+//!
+//! `Validator.validate_many` validates a batch of instances without holding
+//! the GIL, so other Python threads can run in the meantime.
+
+use ::jsl::schema::{Schema, Serde};
+use ::jsl::validator::{Config, Validator};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+use serde_json::{Map, Number, Value};
+
+create_exception!(
+    jsl,
+    JslError,
+    PyValueError,
+    "Raised when a schema is invalid, or validation exceeds its maximum depth."
+);
+
+/// A parsed, root schema.
+#[pyclass(name = "Schema", module = "jsl", frozen)]
+pub struct PySchema {
+    schema: Schema,
+}
+
+#[pymethods]
+impl PySchema {
+    /// Parse a schema from a JSON string. Raises `JslError` if the data
+    /// isn't a valid schema.
+    #[staticmethod]
+    fn from_json(data: &str) -> PyResult<Self> {
+        let serde: Serde =
+            serde_json::from_str(data).map_err(|err| JslError::new_err(err.to_string()))?;
+        let schema = Schema::from_serde(serde).map_err(|err| JslError::new_err(err.to_string()))?;
+        Ok(PySchema { schema })
+    }
+
+    /// Convert the schema back into a JSON string.
+    fn to_json(&self) -> String {
+        serde_json::to_string(&self.schema.clone().into_serde()).unwrap()
+    }
+}
+
+/// A single problem with an instance, as pointers into the instance and the
+/// schema.
+#[pyclass(name = "ValidationError", module = "jsl", frozen)]
+pub struct PyValidationError {
+    #[pyo3(get)]
+    instance_path: String,
+
+    #[pyo3(get)]
+    schema_path: String,
+}
+
+#[pymethods]
+impl PyValidationError {
+    fn __repr__(&self) -> String {
+        format!(
+            "ValidationError(instance_path={:?}, schema_path={:?})",
+            self.instance_path, self.schema_path
+        )
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.instance_path == other.instance_path && self.schema_path == other.schema_path
+    }
+}
+
+/// Validates instances against schemas.
+#[pyclass(name = "Validator", module = "jsl", frozen)]
+pub struct PyValidator {
+    validator: Validator,
+}
+
+#[pymethods]
+impl PyValidator {
+    /// Construct a validator. The options mirror
+    /// [`Config`](../jsl/validator/struct.Config.html), and default to the same
+    /// values.
+    #[new]
+    #[pyo3(signature = (*, strict = false, max_errors = 0, max_depth = 32))]
+    fn new(strict: bool, max_errors: usize, max_depth: usize) -> Self {
+        let mut config = Config::new();
+        config
+            .strict_instance_semantics(strict)
+            .max_errors(max_errors)
+            .max_depth(max_depth);
+
+        PyValidator {
+            validator: Validator::new_with_config(config),
+        }
+    }
+
+    /// Validate an instance against a schema, returning a list of
+    /// `ValidationError`s. Raises `JslError` if the maximum reference depth
+    /// is exceeded.
+    fn validate(
+        &self,
+        schema: &PySchema,
+        instance: &Bound<'_, PyAny>,
+    ) -> PyResult<Vec<PyValidationError>> {
+        self.errors(&schema.schema, &to_value(instance)?)
+    }
+
+    /// Validate each of an iterable of instances against a schema, returning
+    /// a list of lists of `ValidationError`s. The GIL is released while the
+    /// instances are validated.
+    fn validate_many(
+        &self,
+        py: Python<'_>,
+        schema: &PySchema,
+        instances: &Bound<'_, PyAny>,
+    ) -> PyResult<Vec<Vec<PyValidationError>>> {
+        let instances = instances
+            .try_iter()?
+            .map(|instance| to_value(&instance?))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        py.allow_threads(|| {
+            instances
+                .iter()
+                .map(|instance| self.errors(&schema.schema, instance))
+                .collect()
+        })
+    }
+}
+
+impl PyValidator {
+    fn errors(&self, schema: &Schema, instance: &Value) -> PyResult<Vec<PyValidationError>> {
+        let errors = self
+            .validator
+            .validate(schema, instance)
+            .map_err(|err| JslError::new_err(err.to_string()))?;
+
+        Ok(errors
+            .iter()
+            .map(|error| PyValidationError {
+                instance_path: error.instance_path().to_string(),
+                schema_path: error.schema_path().to_string(),
+            })
+            .collect())
+    }
+}
+
+/// Convert a Python value into JSON, the way `json.dumps` would, but
+/// rejecting values that aren't JSON, such as `NaN`.
+fn to_value(value: &Bound<'_, PyAny>) -> PyResult<Value> {
+    if value.is_none() {
+        return Ok(Value::Null);
+    }
+
+    // bool is a subclass of int, so it must be checked first.
+    if let Ok(value) = value.downcast::<PyBool>() {
+        return Ok(Value::Bool(value.is_true()));
+    }
+
+    if let Ok(value) = value.downcast::<PyInt>() {
+        if let Ok(n) = value.extract::<i64>() {
+            return Ok(Value::Number(n.into()));
+        }
+
+        if let Ok(n) = value.extract::<u64>() {
+            return Ok(Value::Number(n.into()));
+        }
+
+        // Integers beyond 64 bits are only representable as floats.
+        return number(value.extract::<f64>()?);
+    }
+
+    if let Ok(value) = value.downcast::<PyFloat>() {
+        return number(value.value());
+    }
+
+    if let Ok(value) = value.downcast::<PyString>() {
+        return Ok(Value::String(value.to_str()?.to_owned()));
+    }
+
+    if let Ok(value) = value.downcast::<PyList>() {
+        return value.iter().map(|item| to_value(&item)).collect();
+    }
+
+    if let Ok(value) = value.downcast::<PyTuple>() {
+        return value.iter().map(|item| to_value(&item)).collect();
+    }
+
+    if let Ok(value) = value.downcast::<PyDict>() {
+        let mut map = Map::new();
+        for (key, item) in value.iter() {
+            let key = key
+                .downcast::<PyString>()
+                .map_err(|_| PyTypeError::new_err("dict keys must be strings"))?;
+            map.insert(key.to_str()?.to_owned(), to_value(&item)?);
+        }
+
+        return Ok(Value::Object(map));
+    }
+
+    Err(PyTypeError::new_err(format!(
+        "{} is not a JSON value",
+        value.get_type().name()?
+    )))
+}
+
+fn number(n: f64) -> PyResult<Value> {
+    Number::from_f64(n)
+        .map(Value::Number)
+        .ok_or_else(|| PyValueError::new_err(format!("{} is not a JSON number", n)))
+}
+
+/// The `jsl` extension module.
+#[pymodule]
+pub fn jsl(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PySchema>()?;
+    m.add_class::<PyValidator>()?;
+    m.add_class::<PyValidationError>()?;
+    m.add("JslError", m.py().get_type::<JslError>())?;
+    Ok(())
+}
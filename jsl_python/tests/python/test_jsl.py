@@ -0,0 +1,118 @@
+"""Tests for the Python bindings.
+
+These run under `cargo test -p jsl_python`, through tests/python_test.rs.
+With the extension module installed, for example with `maturin develop`, they
+also run under pytest.
+"""
+
+import threading
+
+import jsl
+
+SCHEMA = """
+{
+    "definitions": { "name": { "type": "string" } },
+    "properties": {
+        "name": { "ref": "name" },
+        "tags": { "elements": { "type": "string" } }
+    },
+    "optionalProperties": {
+        "scores": { "values": { "type": "float64" } }
+    }
+}
+"""
+
+
+def errors(result):
+    return sorted((error.instance_path, error.schema_path) for error in result)
+
+
+def test_validate():
+    schema = jsl.Schema.from_json(SCHEMA)
+    validator = jsl.Validator()
+
+    assert validator.validate(schema, {"name": "a", "tags": ["b"]}) == []
+
+    # The same schema can be used again, with native dicts, lists and tuples.
+    result = validator.validate(
+        schema, {"name": 1, "tags": ("a", None), "scores": {"x": 1, "y": True}}
+    )
+    assert errors(result) == [
+        ("/name", "/definitions/name/type"),
+        ("/scores/y", "/optionalProperties/scores/values/type"),
+        ("/tags/1", "/properties/tags/elements/type"),
+    ]
+
+    error = result[0]
+    assert repr(error).startswith("ValidationError(instance_path=")
+
+
+def test_options():
+    schema = jsl.Schema.from_json(SCHEMA)
+    instance = {"name": "a", "tags": [], "x": 1, "y": 2}
+
+    assert jsl.Validator().validate(schema, instance) == []
+    assert len(jsl.Validator(strict=True).validate(schema, instance)) == 2
+    assert len(jsl.Validator(strict=True, max_errors=1).validate(schema, instance)) == 1
+
+    schema = jsl.Schema.from_json(
+        '{ "definitions": { "a": { "ref": "a" } }, "ref": "a" }'
+    )
+    try:
+        jsl.Validator(max_depth=4).validate(schema, None)
+        assert False, "expected JslError"
+    except jsl.JslError as err:
+        assert "depth" in str(err)
+
+
+def test_invalid_schema():
+    for data in ['{ "ref": "missing" }', '{ "type": 3 }', "{"]:
+        try:
+            jsl.Schema.from_json(data)
+            assert False, "expected JslError"
+        except jsl.JslError:
+            pass
+
+    assert issubclass(jsl.JslError, ValueError)
+
+
+def test_invalid_instance():
+    schema = jsl.Schema.from_json("{}")
+    validator = jsl.Validator()
+
+    for instance, error in [
+        (object(), TypeError),
+        ({1: 2}, TypeError),
+        (float("nan"), ValueError),
+    ]:
+        try:
+            validator.validate(schema, instance)
+            assert False, "expected " + error.__name__
+        except error:
+            pass
+
+    assert validator.validate(schema, 2**70) == []
+
+
+def test_validate_many():
+    schema = jsl.Schema.from_json(SCHEMA)
+    validator = jsl.Validator()
+    instances = [{"name": str(i), "tags": []} if i % 2 else {} for i in range(100)]
+
+    results = []
+    thread = threading.Thread(
+        target=lambda: results.extend(validator.validate_many(schema, instances))
+    )
+    thread.start()
+    thread.join()
+
+    assert len(results) == 100
+    assert results[1] == []
+    assert errors(results[0]) == [("", "/properties/name"), ("", "/properties/tags")]
+
+    assert validator.validate_many(schema, iter([])) == []
+
+
+def test_to_json():
+    schema = jsl.Schema.from_json('{ "elements": { "type": "string" } }')
+    assert jsl.Schema.from_json(schema.to_json()).to_json() == schema.to_json()
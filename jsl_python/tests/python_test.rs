@@ -0,0 +1,33 @@
+//! Runs the Python tests in `tests/python/test_jsl.py` against the bindings,
+//! in an embedded interpreter.
+
+use jsl_python::jsl;
+use pyo3::ffi::c_str;
+use pyo3::prelude::*;
+use pyo3::types::PyModule;
+
+#[test]
+fn python() {
+    pyo3::append_to_inittab!(jsl);
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        let tests = PyModule::from_code(
+            py,
+            c_str!(include_str!("python/test_jsl.py")),
+            c_str!("test_jsl.py"),
+            c_str!("test_jsl"),
+        )
+        .unwrap();
+
+        for (name, test) in tests.dict() {
+            let name: String = name.extract().unwrap();
+            if name.starts_with("test_") {
+                if let Err(err) = test.call0() {
+                    err.display(py);
+                    panic!("{} failed", name);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,15 @@
+//! JavaScript bindings for [`jsl`](https://docs.rs/jsl), for use in
+//! WebAssembly.
+//!
+//! The bindings themselves live in `jsl`'s `wasm` module, behind its `wasm`
+//! feature. This crate re-exports them from a `cdylib`, so that `jsl` itself
+//! can stay an `rlib`. Build a package with:
+//!
+//! ```text
+//! wasm-pack build jsl_wasm
+//! ```
+//!
+//! Run the tests for these bindings under Node with `wasm-pack test --node`,
+//! or with `cargo test -p jsl_wasm -- --ignored`, which does the same.
+
+pub use jsl::wasm::*;
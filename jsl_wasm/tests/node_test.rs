@@ -0,0 +1,23 @@
+//! Runs the tests in `wasm_test.rs` under Node, with `wasm-pack test --node`.
+//!
+//! This needs `wasm-pack`, Node, and the `wasm32-unknown-unknown` target, so
+//! it is ignored by default. Run it with:
+//!
+//! ```text
+//! cargo test -p jsl_wasm -- --ignored
+//! ```
+
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+#[ignore]
+fn node() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let status = Command::new("wasm-pack")
+        .args(["test", "--node"])
+        .current_dir(&root)
+        .status()
+        .expect("running wasm-pack failed; is it installed?");
+    assert!(status.success(), "wasm-pack test --node failed");
+}
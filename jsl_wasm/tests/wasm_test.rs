@@ -0,0 +1,102 @@
+//! Tests for the JavaScript bindings. Run these under Node with:
+//!
+//! ```text
+//! wasm-pack test --node
+//! ```
+//!
+//! `node_test.rs` does this too, when its ignored test is run.
+
+#![cfg(target_arch = "wasm32")]
+
+use jsl_wasm::{WasmSchema, WasmValidator};
+use serde::Serialize;
+use serde_json::{json, Value};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn js(value: Value) -> JsValue {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .unwrap()
+}
+
+fn rust(value: JsValue) -> Value {
+    serde_wasm_bindgen::from_value(value).unwrap()
+}
+
+fn schema() -> WasmSchema {
+    WasmSchema::new(js(json!({
+        "definitions": { "name": { "type": "string" } },
+        "properties": {
+            "name": { "ref": "name" },
+            "age": { "type": "uint8" },
+        },
+    })))
+    .unwrap()
+}
+
+#[wasm_bindgen_test]
+fn validate() {
+    let schema = schema();
+    let validator = WasmValidator::new(JsValue::UNDEFINED).unwrap();
+
+    let errors = validator.validate(&schema, js(json!({ "name": "a", "age": 3 })));
+    assert_eq!(rust(errors.unwrap()), json!([]));
+
+    // The same schema handle can be used again.
+    let errors = validator.validate(&schema, js(json!({ "name": 1, "age": 300 })));
+    let mut errors = rust(errors.unwrap());
+    errors
+        .as_array_mut()
+        .unwrap()
+        .sort_by_key(|error| error["instancePath"].to_string());
+
+    assert_eq!(
+        errors,
+        json!([
+            { "instancePath": "/age", "schemaPath": "/properties/age/type" },
+            { "instancePath": "/name", "schemaPath": "/definitions/name/type" },
+        ])
+    );
+}
+
+#[wasm_bindgen_test]
+fn options() {
+    let schema = schema();
+    let validator = WasmValidator::new(js(json!({ "strict": true, "maxErrors": 1 }))).unwrap();
+
+    let errors = validator.validate(&schema, js(json!({ "name": "a", "age": 3, "x": null })));
+    assert_eq!(
+        rust(errors.unwrap()),
+        json!([{ "instancePath": "/x", "schemaPath": "" }])
+    );
+
+    assert!(WasmValidator::new(js(json!({ "strictness": true }))).is_err());
+}
+
+#[wasm_bindgen_test]
+fn invalid_schema() {
+    assert!(WasmSchema::new(js(json!({ "ref": "missing" }))).is_err());
+    assert!(WasmSchema::new(js(json!({ "type": 3 }))).is_err());
+}
+
+#[wasm_bindgen_test]
+fn max_depth() {
+    let schema = WasmSchema::new(js(json!({
+        "definitions": { "a": { "ref": "a" } },
+        "ref": "a",
+    })))
+    .unwrap();
+
+    let validator = WasmValidator::new(js(json!({ "maxDepth": 4 }))).unwrap();
+    assert!(validator.validate(&schema, js(json!(null))).is_err());
+}
+
+#[wasm_bindgen_test]
+fn to_json() {
+    let schema = WasmSchema::new(js(json!({ "elements": { "type": "string" } }))).unwrap();
+    assert_eq!(
+        rust(schema.to_json().unwrap()),
+        json!({ "definitions": {}, "elements": { "type": "string" } })
+    );
+}
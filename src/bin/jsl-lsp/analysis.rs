@@ -0,0 +1,817 @@
+//! Answers to what a language server is asked about a schema document.
+//!
+//! `Schema::from_serde` is the authority on whether a schema is valid, but it
+//! stops at the first error and doesn't say where it is. To place
+//! diagnostics, each schema object in a document is checked on its own, with
+//! its subschemas replaced by empty ones. Whatever is wrong with that object
+//! alone is reported at that object.
+
+use crate::document::{Document, Node, Span};
+use jsl::{Form, JslError, Schema, SerdeSchema, Type};
+use lsp_types::{CompletionItem, CompletionItemKind, Diagnostic, DiagnosticSeverity};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+const KEYWORDS: &[&str] = &[
+    "ref",
+    "type",
+    "enum",
+    "elements",
+    "properties",
+    "optionalProperties",
+    "values",
+    "discriminator",
+];
+
+const DISCRIMINATOR_KEYWORDS: &[&str] = &["tag", "mapping"];
+
+const TYPES: &[&str] = &[
+    "boolean",
+    "number",
+    "float32",
+    "float64",
+    "int8",
+    "uint8",
+    "int16",
+    "uint16",
+    "int32",
+    "uint32",
+    "int64",
+    "uint64",
+    "string",
+    "timestamp",
+];
+
+/// How many levels of subschemas hover text describes.
+const HOVER_DEPTH: usize = 3;
+
+/// What a JSON value within a schema document is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Role {
+    /// A schema.
+    Schema,
+    /// An object whose values are schemas, like `properties`.
+    Schemas,
+    /// The value of a `discriminator` keyword.
+    Discriminator,
+    /// Anything else, like the values of `enum`.
+    Other,
+}
+
+impl Role {
+    fn child(self, key: &str) -> Role {
+        match (self, key) {
+            (Role::Schema, "definitions")
+            | (Role::Schema, "properties")
+            | (Role::Schema, "optionalProperties") => Role::Schemas,
+            (Role::Schema, "elements") | (Role::Schema, "values") => Role::Schema,
+            (Role::Schema, "discriminator") => Role::Discriminator,
+            (Role::Discriminator, "mapping") => Role::Schemas,
+            (Role::Schemas, _) => Role::Schema,
+            _ => Role::Other,
+        }
+    }
+}
+
+/// A schema object within a document.
+struct SchemaNode<'a> {
+    node: &'a Node,
+
+    /// If this schema is a discriminator variant, the discriminator's tag.
+    tag: Option<&'a str>,
+}
+
+/// An index of the schemas, definitions and refs in a document.
+pub struct Analysis<'a> {
+    document: &'a Document,
+    schemas: Vec<SchemaNode<'a>>,
+    definitions: Vec<(&'a str, &'a Span)>,
+    refs: Vec<(&'a str, &'a Span)>,
+}
+
+impl<'a> Analysis<'a> {
+    pub fn new(document: &'a Document) -> Self {
+        let mut analysis = Self {
+            document,
+            schemas: Vec::new(),
+            definitions: Vec::new(),
+            refs: Vec::new(),
+        };
+
+        if let Ok(root) = document.tree() {
+            analysis.collect(root, Role::Schema, None);
+
+            let defs = root.get("definitions").and_then(Node::members);
+            for member in defs.unwrap_or_default() {
+                analysis
+                    .definitions
+                    .push((member.key.as_str(), &member.key_span));
+            }
+        }
+
+        analysis
+    }
+
+    /// Find every schema object beneath a node, in document order.
+    fn collect(&mut self, node: &'a Node, role: Role, tag: Option<&'a str>) {
+        let members = match node.members() {
+            Some(members) => members,
+            None => return,
+        };
+
+        if role == Role::Schema {
+            self.schemas.push(SchemaNode { node, tag });
+            if let Some(Some(name)) = node.get("ref").map(Node::as_str) {
+                self.refs.push((name, &node.get("ref").unwrap().span));
+            }
+        }
+
+        for member in members {
+            let child_tag = match (role, member.key.as_str()) {
+                (Role::Discriminator, "mapping") => node.get("tag").and_then(Node::as_str),
+                (Role::Schemas, _) => tag,
+                _ => None,
+            };
+
+            match role.child(&member.key) {
+                Role::Other => {}
+                child => self.collect(&member.value, child, child_tag),
+            }
+        }
+    }
+
+    /// The names of the document's definitions.
+    pub fn definition_names(&self) -> Vec<String> {
+        self.definitions
+            .iter()
+            .map(|(name, _)| (*name).to_owned())
+            .collect()
+    }
+
+    /// Everything wrong with the document, as a schema.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let root = match self.document.tree() {
+            Ok(root) => root,
+            Err(err) => {
+                let offset = self.document.error_offset(err);
+                let end = self.document.text()[offset..]
+                    .chars()
+                    .next()
+                    .map_or(offset, |c| offset + c.len_utf8());
+
+                return vec![self.diagnostic(&(offset..end), err.to_string())];
+            }
+        };
+
+        let mut diagnostics = Vec::new();
+        let defined: BTreeSet<_> = self.definitions.iter().map(|(name, _)| *name).collect();
+        for (name, span) in &self.refs {
+            if !defined.contains(name) {
+                let error = JslError::NoSuchDefinition {
+                    definition: (*name).to_owned(),
+                };
+
+                diagnostics.push(self.diagnostic(span, error.to_string()));
+            }
+        }
+
+        for schema in &self.schemas {
+            if let Err(message) = check(self.isolate(schema)) {
+                let span = self.error_span(schema.node, &message);
+                diagnostics.push(self.diagnostic(span, message));
+            }
+        }
+
+        // Checking each schema on its own should find every error. In case it
+        // doesn't, still report whatever the document as a whole has wrong.
+        if diagnostics.is_empty() {
+            if let Err(message) = check(root.to_value()) {
+                diagnostics.push(self.diagnostic(&root.span, message));
+            }
+        }
+
+        diagnostics
+    }
+
+    fn diagnostic(&self, span: &Span, message: String) -> Diagnostic {
+        Diagnostic {
+            range: self.document.range(span),
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some("jsl".to_owned()),
+            message,
+            ..Diagnostic::default()
+        }
+    }
+
+    /// Construct a root schema with just the keywords of `schema`, with its
+    /// subschemas replaced by empty ones and every definition it might refer
+    /// to stubbed out. The root schema's own definitions are checked here
+    /// too, in case they aren't objects.
+    ///
+    /// Discriminator variants are wrapped in a discriminator of their own,
+    /// so that the rules about what variants may contain are checked against
+    /// the variant rather than the discriminator.
+    fn isolate(&self, schema: &SchemaNode) -> Value {
+        let mut object = Map::new();
+        for member in schema.node.members().unwrap() {
+            let value = match (member.key.as_str(), member.value.members()) {
+                ("definitions", Some(_)) => {
+                    let mut defs = self.stubs();
+                    if let Value::Object(values) = empty_values(&member.value, json!({})) {
+                        defs.as_object_mut().unwrap().extend(values);
+                    }
+
+                    defs
+                }
+                ("elements", Some(_)) | ("values", Some(_)) => json!({}),
+                ("properties", _) | ("optionalProperties", _) => {
+                    empty_values(&member.value, json!({}))
+                }
+                ("discriminator", Some(members)) => Value::Object(
+                    members
+                        .iter()
+                        .map(|member| {
+                            let value = match member.key.as_str() {
+                                "mapping" => {
+                                    empty_values(&member.value, json!({ "properties": {} }))
+                                }
+                                _ => member.value.to_value(),
+                            };
+
+                            (member.key.clone(), value)
+                        })
+                        .collect(),
+                ),
+                _ => member.value.to_value(),
+            };
+
+            object.insert(member.key.clone(), value);
+        }
+
+        let mut object = match schema.tag {
+            Some(tag) => json!({
+                "discriminator": { "tag": tag, "mapping": { "": object } },
+            }),
+            None => Value::Object(object),
+        };
+
+        if object.get("definitions").is_none() {
+            object["definitions"] = self.stubs();
+        }
+
+        object
+    }
+
+    /// Empty definitions for everything a schema in the document might refer
+    /// to.
+    fn stubs(&self) -> Value {
+        let names = self.definitions.iter().chain(&self.refs);
+        Value::Object(
+            names
+                .map(|(name, _)| ((*name).to_owned(), json!({})))
+                .collect(),
+        )
+    }
+
+    /// Find the most specific part of a schema to blame for an error.
+    fn error_span<'b>(&self, node: &'b Node, message: &str) -> &'b Span {
+        for keyword in &["optionalProperties", "properties"] {
+            let members = node.get(keyword).and_then(Node::members);
+            for member in members.unwrap_or_default() {
+                let error = JslError::AmbiguousProperty {
+                    property: member.key.clone(),
+                };
+
+                if error.to_string() == message {
+                    return &member.key_span;
+                }
+            }
+        }
+
+        &node.span
+    }
+
+    /// Suggest keywords, types or definitions at a byte offset.
+    ///
+    /// This works from the text, rather than the parsed document, because
+    /// the document is rarely valid JSON while it's being typed. `known`
+    /// lists the definitions to suggest for `ref`s, which callers may
+    /// remember from the last time the document was valid.
+    pub fn completions(&self, offset: usize, known: &[String]) -> Vec<CompletionItem> {
+        let position = match scan(&self.document.text()[..offset]) {
+            Some(position) => position,
+            None => return Vec::new(),
+        };
+
+        let (labels, kind): (Vec<&str>, _) = match (position.role, &position.value_of) {
+            (Role::Schema, None) => {
+                let root = if position.depth == 1 {
+                    Some("definitions")
+                } else {
+                    None
+                };
+
+                let keywords = root.into_iter().chain(KEYWORDS.iter().copied());
+                (keywords.collect(), CompletionItemKind::KEYWORD)
+            }
+            (Role::Discriminator, None) => {
+                (DISCRIMINATOR_KEYWORDS.to_vec(), CompletionItemKind::KEYWORD)
+            }
+            (Role::Schema, Some(key)) if key == "type" => {
+                (TYPES.to_vec(), CompletionItemKind::ENUM_MEMBER)
+            }
+            (Role::Schema, Some(key)) if key == "ref" => (
+                known.iter().map(String::as_str).collect(),
+                CompletionItemKind::REFERENCE,
+            ),
+            _ => return Vec::new(),
+        };
+
+        labels
+            .into_iter()
+            .filter(|label| {
+                position.value_of.is_some() || !position.keys.iter().any(|key| key == label)
+            })
+            .map(|label| CompletionItem {
+                label: label.to_owned(),
+                kind: Some(kind),
+                insert_text: if position.in_string {
+                    None
+                } else {
+                    Some(format!("{:?}", label))
+                },
+                ..CompletionItem::default()
+            })
+            .collect()
+    }
+
+    /// Find the definition that a `ref` at a byte offset refers to.
+    pub fn definition(&self, offset: usize) -> Option<&'a Span> {
+        let name = self
+            .refs
+            .iter()
+            .find(|(_, span)| contains(span, offset))
+            .map(|(name, _)| name)?;
+
+        self.definitions
+            .iter()
+            .find(|(def, _)| def == name)
+            .map(|(_, span)| *span)
+    }
+
+    /// Find every `ref` to the definition at a byte offset, either because
+    /// the offset is within a `ref` or the definition's name.
+    pub fn references(&self, offset: usize, include_declaration: bool) -> Vec<&'a Span> {
+        let name = self
+            .refs
+            .iter()
+            .chain(&self.definitions)
+            .find(|(_, span)| contains(span, offset))
+            .map(|(name, _)| *name);
+
+        let name = match name {
+            Some(name) => name,
+            None => return Vec::new(),
+        };
+
+        let declarations = self.definitions.iter().filter(|_| include_declaration);
+        declarations
+            .chain(&self.refs)
+            .filter(|(def, _)| *def == name)
+            .map(|(_, span)| *span)
+            .collect()
+    }
+
+    /// Describe the innermost schema at a byte offset, and, if it's a `ref`,
+    /// the definitions it resolves to.
+    pub fn hover(&self, offset: usize) -> Option<(String, &'a Span)> {
+        let node = self
+            .schemas
+            .iter()
+            .rev()
+            .find(|schema| contains(&schema.node.span, offset))?
+            .node;
+
+        let mut schema = self.resolve(node)?;
+        let mut text = format!("`{}`", summary(&schema, HOVER_DEPTH));
+
+        let mut seen = BTreeSet::new();
+        while let Form::Ref(def) = schema.form() {
+            let target = self
+                .document
+                .tree()
+                .ok()?
+                .get("definitions")
+                .and_then(|defs| defs.get(def));
+
+            let target = match target {
+                Some(target) if seen.insert(def.clone()) => target,
+                _ => break,
+            };
+
+            let next = self.resolve(target)?;
+            text.push_str(&format!("\n\n`{}`: `{}`", def, summary(&next, HOVER_DEPTH)));
+            schema = next;
+        }
+
+        Some((text, &node.span))
+    }
+
+    /// Convert a schema object in the document into a `Schema`.
+    fn resolve(&self, node: &Node) -> Option<Schema> {
+        let mut value = node.to_value();
+        if value.get("definitions").is_none() {
+            value["definitions"] = self.stubs();
+        }
+
+        check(value).ok()
+    }
+}
+
+fn check(value: Value) -> Result<Schema, String> {
+    let serde: SerdeSchema = serde_json::from_value(value).map_err(|err| err.to_string())?;
+    Schema::from_serde(serde).map_err(|err| err.to_string())
+}
+
+/// Replace the values of an object with `empty`, if they're objects
+/// themselves. Anything else is left as it is, so that it's still an error.
+fn empty_values(node: &Node, empty: Value) -> Value {
+    match node.members() {
+        Some(members) => Value::Object(
+            members
+                .iter()
+                .map(|member| {
+                    let value = match member.value.members() {
+                        Some(_) => empty.clone(),
+                        None => member.value.to_value(),
+                    };
+
+                    (member.key.clone(), value)
+                })
+                .collect(),
+        ),
+        None => node.to_value(),
+    }
+}
+
+fn contains(span: &Span, offset: usize) -> bool {
+    span.start <= offset && offset <= span.end
+}
+
+/// Describe a schema in a single line, down to `depth` levels of subschemas.
+fn summary(schema: &Schema, depth: usize) -> String {
+    if depth == 0 {
+        return "…".to_owned();
+    }
+
+    match schema.form() {
+        Form::Empty => "any".to_owned(),
+        Form::Ref(def) => def.clone(),
+        Form::Type(typ) => type_name(typ),
+        Form::Enum(values) => {
+            let mut values: Vec<_> = values.iter().map(|value| format!("{:?}", value)).collect();
+            values.sort();
+            values.join(" | ")
+        }
+        Form::Elements(sub_schema) => format!("array of {}", summary(sub_schema, depth - 1)),
+        Form::Values(sub_schema) => format!("map of {}", summary(sub_schema, depth - 1)),
+        Form::Properties(required, optional, _) => {
+            let required = required
+                .iter()
+                .map(|(name, sub_schema)| format!("{}: {}", name, summary(sub_schema, depth - 1)));
+
+            let optional = optional
+                .iter()
+                .map(|(name, sub_schema)| format!("{}?: {}", name, summary(sub_schema, depth - 1)));
+
+            let properties: Vec<_> = required.chain(optional).collect();
+            if properties.is_empty() {
+                "{}".to_owned()
+            } else {
+                format!("{{ {} }}", properties.join(", "))
+            }
+        }
+        Form::Discriminator(tag, mapping) => {
+            let variants: Vec<_> = mapping.keys().map(|value| format!("{:?}", value)).collect();
+
+            format!("{:?} in {}", tag, variants.join(" | "))
+        }
+    }
+}
+
+fn type_name(typ: &Type) -> String {
+    let schema = Schema::from_parts(None, Box::new(Form::Type(typ.clone())), BTreeMap::new());
+    schema.into_serde().typ.unwrap_or_default()
+}
+
+/// Where in a schema document some, possibly incomplete, text ends.
+#[derive(Debug)]
+struct TextPosition {
+    /// The role of the innermost object that the text ends in.
+    role: Role,
+
+    /// How many objects and arrays the text ends within.
+    depth: usize,
+
+    /// The keys already present in the innermost object.
+    keys: Vec<String>,
+
+    /// If the text ends in the value of a member, that member's key.
+    value_of: Option<String>,
+
+    /// Whether the text ends within a string.
+    in_string: bool,
+}
+
+/// Work out where some text ends, if it's within an object.
+fn scan(text: &str) -> Option<TextPosition> {
+    struct Frame {
+        object: bool,
+        key: Option<String>,
+        keys: Vec<String>,
+        after_colon: bool,
+    }
+
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut in_string = false;
+    let mut chars = text.char_indices();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '"' => {
+                let mut escaped = false;
+                let end = chars.by_ref().find_map(|(i, c)| match c {
+                    _ if escaped => {
+                        escaped = false;
+                        None
+                    }
+                    '\\' => {
+                        escaped = true;
+                        None
+                    }
+                    '"' => Some(i),
+                    _ => None,
+                });
+
+                let end = match end {
+                    Some(end) => end,
+                    None => {
+                        in_string = true;
+                        break;
+                    }
+                };
+
+                if let Some(frame) = stack.last_mut() {
+                    if frame.object && !frame.after_colon {
+                        let key: String =
+                            serde_json::from_str(&text[start..=end]).unwrap_or_default();
+                        frame.keys.push(key.clone());
+                        frame.key = Some(key);
+                    }
+                }
+            }
+            '{' | '[' => stack.push(Frame {
+                object: c == '{',
+                key: None,
+                keys: Vec::new(),
+                after_colon: false,
+            }),
+            '}' | ']' => {
+                stack.pop();
+            }
+            ':' | ',' => {
+                if let Some(frame) = stack.last_mut() {
+                    frame.after_colon = c == ':';
+                    if c == ',' {
+                        frame.key = None;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let (top, parents) = stack.split_last()?;
+    if !top.object {
+        return None;
+    }
+
+    let mut role = Role::Schema;
+    for frame in parents {
+        role = match (frame.object, &frame.key) {
+            (true, Some(key)) => role.child(key),
+            _ => Role::Other,
+        };
+    }
+
+    // When typing a key, the key itself shouldn't count as already present.
+    let mut keys = top.keys.clone();
+    if !top.after_colon && top.key.is_some() && !in_string {
+        keys.pop();
+    }
+
+    Some(TextPosition {
+        role,
+        depth: stack.len(),
+        keys,
+        value_of: if top.after_colon {
+            top.key.clone()
+        } else {
+            None
+        },
+        in_string,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostics(text: &str) -> Vec<(String, String)> {
+        let document = Document::new(text.to_owned());
+        Analysis::new(&document)
+            .diagnostics()
+            .into_iter()
+            .map(|diagnostic| {
+                let start = document.offset(diagnostic.range.start);
+                let end = document.offset(diagnostic.range.end);
+                (text[start..end].to_owned(), diagnostic.message)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid() {
+        assert_eq!(
+            diagnostics(r#"{ "definitions": { "a": {} }, "elements": { "ref": "a" } }"#),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            diagnostics(
+                r#"{
+                    "properties": {
+                        "a": { "ref": "missing" },
+                        "b": { "type": "string", "enum": ["x"] },
+                        "c": { "elements": { "type": "int128" } }
+                    },
+                    "optionalProperties": { "a": {} }
+                }"#
+            ),
+            vec![
+                (
+                    r#""missing""#.to_owned(),
+                    "no such definition: missing".to_owned()
+                ),
+                (r#""a""#.to_owned(), "ambiguous property: a".to_owned()),
+                (
+                    r#"{ "type": "string", "enum": ["x"] }"#.to_owned(),
+                    "invalid schema form".to_owned()
+                ),
+                (
+                    r#"{ "type": "int128" }"#.to_owned(),
+                    "invalid schema form".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn discriminator_errors() {
+        assert_eq!(
+            diagnostics(
+                r#"{
+                    "discriminator": {
+                        "tag": "kind",
+                        "mapping": {
+                            "a": { "properties": { "kind": {} } },
+                            "b": { "type": "string" },
+                            "c": { "properties": { "x": {} } }
+                        }
+                    }
+                }"#
+            ),
+            vec![
+                (
+                    r#""kind""#.to_owned(),
+                    "ambiguous property: kind".to_owned()
+                ),
+                (
+                    r#"{ "type": "string" }"#.to_owned(),
+                    "invalid schema form".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn syntax_error() {
+        let diagnostics = diagnostics("{\n  \"type\": }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, "}");
+    }
+
+    fn completions(text: &str, known: &[&str]) -> Vec<String> {
+        let document = Document::new(text.replace('|', ""));
+        let known: Vec<_> = known.iter().map(|&name| name.to_owned()).collect();
+
+        Analysis::new(&document)
+            .completions(text.find('|').unwrap(), &known)
+            .into_iter()
+            .map(|item| item.insert_text.unwrap_or(item.label))
+            .collect()
+    }
+
+    #[test]
+    fn complete() {
+        assert_eq!(
+            completions(r#"{ "type": "string", "|"#, &[]),
+            vec![
+                "definitions",
+                "ref",
+                "enum",
+                "elements",
+                "properties",
+                "optionalProperties",
+                "values",
+                "discriminator",
+            ]
+        );
+
+        assert_eq!(
+            completions(r#"{ "elements": { "type": |"#, &[])[..2],
+            [r#""boolean""#, r#""number""#]
+        );
+
+        assert_eq!(
+            completions(r#"{ "values": { "ref": "|" } }"#, &["a", "b"]),
+            vec!["a", "b"]
+        );
+
+        assert_eq!(
+            completions(r#"{ "discriminator": { |"#, &[]),
+            vec![r#""tag""#, r#""mapping""#]
+        );
+
+        assert_eq!(
+            completions(r#"{ "properties": { |"#, &[]),
+            Vec::<String>::new()
+        );
+        assert_eq!(completions(r#"{ "enum": ["|"#, &[]), Vec::<String>::new());
+    }
+
+    #[test]
+    fn navigate() {
+        let text = r#"{
+            "definitions": { "a": { "ref": "b" }, "b": {} },
+            "elements": { "ref": "b" }
+        }"#;
+
+        let document = Document::new(text.to_owned());
+        let analysis = Analysis::new(&document);
+        let ref_offset = text.rfind("\"b\"").unwrap() + 1;
+        let def_offset = text.find("\"b\":").unwrap() + 1;
+
+        assert_eq!(
+            analysis.definition(ref_offset).map(|span| span.start),
+            Some(def_offset - 1)
+        );
+
+        let references: Vec<_> = analysis
+            .references(def_offset, true)
+            .into_iter()
+            .map(|span| span.start)
+            .collect();
+        assert_eq!(
+            references,
+            vec![
+                def_offset - 1,
+                text.find("\"b\" }").unwrap(),
+                ref_offset - 1
+            ]
+        );
+    }
+
+    #[test]
+    fn hover() {
+        let text = r#"{
+            "definitions": {
+                "a": { "ref": "b" },
+                "b": { "properties": { "x": { "type": "uint8" } }, "optionalProperties": { "y": { "elements": {} } } }
+            },
+            "values": { "ref": "a" }
+        }"#;
+
+        let document = Document::new(text.to_owned());
+        let analysis = Analysis::new(&document);
+        let (hover, span) = analysis.hover(text.rfind("\"a\"").unwrap()).unwrap();
+
+        assert_eq!(&text[span.clone()], r#"{ "ref": "a" }"#);
+        assert_eq!(
+            hover,
+            "`a`\n\n`a`: `b`\n\n`b`: `{ x: uint8, y?: array of any }`"
+        );
+    }
+}
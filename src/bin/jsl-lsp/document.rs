@@ -0,0 +1,296 @@
+//! JSON documents which remember where each of their values came from.
+//!
+//! `serde_json` discards source positions, but the language server needs
+//! them to put diagnostics and results in the right place. Documents are
+//! first checked with `serde_json`, so the parser here only has to find the
+//! boundaries of each value in text already known to be valid JSON.
+
+use lsp_types::Position;
+use serde_json::{Map, Value};
+use std::ops::Range;
+
+/// A byte range within a document's text.
+pub type Span = Range<usize>;
+
+/// A JSON value, along with its location.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub span: Span,
+    pub kind: Kind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    Scalar(Value),
+    Array(Vec<Node>),
+    Object(Vec<Member>),
+}
+
+/// A member of a JSON object, along with the location of its key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub key: String,
+    pub key_span: Span,
+    pub value: Node,
+}
+
+impl Node {
+    /// The members of this node, if it's an object.
+    pub fn members(&self) -> Option<&[Member]> {
+        match &self.kind {
+            Kind::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    /// The value of the last member of this node with the given key, if it's
+    /// an object. Like `serde_json`, later duplicate keys win.
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        self.members()?
+            .iter()
+            .rev()
+            .find(|member| member.key == key)
+            .map(|member| &member.value)
+    }
+
+    /// This node's value, if it's a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            Kind::Scalar(Value::String(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Convert this node into an ordinary JSON value.
+    pub fn to_value(&self) -> Value {
+        match &self.kind {
+            Kind::Scalar(value) => value.clone(),
+            Kind::Array(elements) => Value::Array(elements.iter().map(Node::to_value).collect()),
+            Kind::Object(members) => Value::Object(
+                members
+                    .iter()
+                    .map(|member| (member.key.clone(), member.value.to_value()))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
+
+/// A text document, and its parsed contents if it's valid JSON.
+#[derive(Debug)]
+pub struct Document {
+    text: String,
+    lines: Vec<usize>,
+    tree: Result<Node, serde_json::Error>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let lines = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        let tree = serde_json::from_str::<Value>(&text).map(|_| Parser::new(&text).parse());
+        Self { text, lines, tree }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The document's contents, or why they're not valid JSON.
+    pub fn tree(&self) -> Result<&Node, &serde_json::Error> {
+        self.tree.as_ref()
+    }
+
+    /// Convert a byte offset into an LSP position, which counts UTF-16 code
+    /// units within a line.
+    pub fn position(&self, offset: usize) -> Position {
+        let line = self.lines.partition_point(|&start| start <= offset) - 1;
+        let start = self.lines[line];
+        let character = self.text[start..offset].encode_utf16().count();
+        Position::new(line as u32, character as u32)
+    }
+
+    pub fn range(&self, span: &Span) -> lsp_types::Range {
+        lsp_types::Range::new(self.position(span.start), self.position(span.end))
+    }
+
+    /// Convert an LSP position into a byte offset, clamping it to the
+    /// document.
+    pub fn offset(&self, position: Position) -> usize {
+        let start = match self.lines.get(position.line as usize) {
+            Some(&start) => start,
+            None => return self.text.len(),
+        };
+
+        let mut units = 0;
+        for (i, c) in self.text[start..].char_indices() {
+            if units >= position.character as usize || c == '\n' {
+                return start + i;
+            }
+
+            units += c.len_utf16();
+        }
+
+        self.text.len()
+    }
+
+    /// Convert a `serde_json` line and column, which count bytes from one,
+    /// into a byte offset.
+    pub fn error_offset(&self, error: &serde_json::Error) -> usize {
+        let start = match self.lines.get(error.line().saturating_sub(1)) {
+            Some(&start) => start,
+            None => return self.text.len(),
+        };
+
+        let mut offset = (start + error.column().saturating_sub(1)).min(self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        offset
+    }
+}
+
+/// Finds the boundaries of values in valid JSON text.
+struct Parser<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, offset: 0 }
+    }
+
+    fn parse(&mut self) -> Node {
+        self.skip_whitespace();
+        self.value()
+    }
+
+    fn value(&mut self) -> Node {
+        let start = self.offset;
+        let kind = match self.peek() {
+            b'{' => {
+                self.offset += 1;
+                let mut members = Vec::new();
+                while self.next_item(b'}') {
+                    let key_start = self.offset;
+                    let key = self.string();
+                    let key_span = key_start..self.offset;
+
+                    self.skip_whitespace();
+                    self.offset += 1; // the ':'
+                    self.skip_whitespace();
+
+                    let value = self.value();
+                    members.push(Member {
+                        key,
+                        key_span,
+                        value,
+                    });
+                }
+
+                Kind::Object(members)
+            }
+            b'[' => {
+                self.offset += 1;
+                let mut elements = Vec::new();
+                while self.next_item(b']') {
+                    elements.push(self.value());
+                }
+
+                Kind::Array(elements)
+            }
+            b'"' => Kind::Scalar(Value::String(self.string())),
+            _ => {
+                let len = self.text[start..]
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || "+-.".contains(c)))
+                    .unwrap_or(self.text.len() - start);
+                self.offset += len;
+                Kind::Scalar(serde_json::from_str(&self.text[start..self.offset]).unwrap())
+            }
+        };
+
+        Node {
+            span: start..self.offset,
+            kind,
+        }
+    }
+
+    /// Move to the next item of an array or object, returning false if
+    /// `close` ends it instead.
+    fn next_item(&mut self, close: u8) -> bool {
+        self.skip_whitespace();
+        if self.peek() == b',' {
+            self.offset += 1;
+            self.skip_whitespace();
+        }
+
+        if self.peek() == close {
+            self.offset += 1;
+            return false;
+        }
+
+        true
+    }
+
+    fn string(&mut self) -> String {
+        let start = self.offset;
+        let bytes = self.text.as_bytes();
+        self.offset += 1;
+        while bytes[self.offset] != b'"' {
+            self.offset += if bytes[self.offset] == b'\\' { 2 } else { 1 };
+        }
+
+        self.offset += 1;
+        serde_json::from_str(&self.text[start..self.offset]).unwrap()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.offset..];
+        self.offset += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn peek(&self) -> u8 {
+        self.text.as_bytes()[self.offset]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse() {
+        let text = r#"{ "a": [1, -2.5e3, "\"éé"], "b": {}, "c": null }"#;
+        let document = Document::new(text.to_owned());
+        let tree = document.tree().unwrap();
+
+        assert_eq!(
+            tree.to_value(),
+            json!({ "a": [1, -2.5e3, "\"éé"], "b": {}, "c": null })
+        );
+
+        let members = tree.members().unwrap();
+        assert_eq!(&text[members[0].key_span.clone()], r#""a""#);
+        assert_eq!(
+            &text[members[0].value.span.clone()],
+            r#"[1, -2.5e3, "\"éé"]"#
+        );
+        assert_eq!(&text[members[2].value.span.clone()], "null");
+    }
+
+    #[test]
+    fn positions() {
+        let document = Document::new("{\n  \"é😀\": 1\n}".to_owned());
+        let offset = document.text().find(':').unwrap();
+
+        assert_eq!(document.position(offset), Position::new(1, 7));
+        assert_eq!(document.offset(Position::new(1, 7)), offset);
+        assert_eq!(document.offset(Position::new(1, 99)), offset + 3);
+        assert_eq!(document.offset(Position::new(9, 0)), document.text().len());
+    }
+}
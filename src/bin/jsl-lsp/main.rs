@@ -0,0 +1,260 @@
+//! `jsl-lsp`, a language server for JSL schema files.
+//!
+//! The server speaks the Language Server Protocol over standard input and
+//! output. For each open document, it publishes diagnostics whenever the
+//! document changes, and answers requests for:
+//!
+//! * completion of keywords, `type` names, and `ref` targets,
+//! * go-to-definition from a `ref` to its definition,
+//! * references to a definition, from either the definition or a `ref`, and
+//! * hover text describing a schema, and what its `ref`s resolve to.
+//!
+//! Documents are synchronized in full on each change.
+
+mod analysis;
+mod document;
+
+use crate::analysis::Analysis;
+use crate::document::Document;
+use failure::Error;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationType, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, GotoDefinition, HoverRequest, References, Request as RequestType,
+};
+use lsp_types::{
+    CompletionOptions, CompletionResponse, GotoDefinitionResponse, Hover, HoverContents,
+    HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, PublishDiagnosticsParams,
+    ServerCapabilities, TextDocumentPositionParams, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Url,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+
+fn main() -> Result<(), Error> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["\"".to_owned()]),
+            ..CompletionOptions::default()
+        }),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        ..ServerCapabilities::default()
+    };
+
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+    Server::default().run(connection)?;
+    io_threads.join()?;
+
+    Ok(())
+}
+
+/// An open document.
+struct Open {
+    document: Document,
+
+    /// The document's definitions as of the last time it was valid JSON, for
+    /// completing `ref`s while it's being edited.
+    definitions: Vec<String>,
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Url, Open>,
+}
+
+impl Server {
+    /// Handle messages until the client shuts the server down or disconnects.
+    /// The connection is dropped afterwards, so that its I/O threads can
+    /// finish.
+    fn run(&mut self, connection: Connection) -> Result<(), Error> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+
+                    let response = self.handle_request(request);
+                    connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => {
+                    if let Some(uri) = self.handle_notification(notification) {
+                        let diagnostics = self.publish_diagnostics(uri);
+                        connection.sender.send(Message::Notification(diagnostics))?;
+                    }
+                }
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            Completion::METHOD => respond::<Completion, _>(request, |params| {
+                let position = params.text_document_position;
+                self.completion(position).map(CompletionResponse::Array)
+            }),
+            GotoDefinition::METHOD => respond::<GotoDefinition, _>(request, |params| {
+                self.definition(params.text_document_position_params)
+                    .map(GotoDefinitionResponse::Scalar)
+            }),
+            References::METHOD => respond::<References, _>(request, |params| {
+                let include_declaration = params.context.include_declaration;
+                self.references(params.text_document_position, include_declaration)
+            }),
+            HoverRequest::METHOD => respond::<HoverRequest, _>(request, |params| {
+                self.hover(params.text_document_position_params)
+            }),
+            _ => Err((ErrorCode::MethodNotFound, "unsupported request".to_owned())),
+        };
+
+        match result {
+            Ok(result) => Response::new_ok(id, result),
+            Err((code, message)) => Response::new_err(id, code as i32, message),
+        }
+    }
+
+    /// Update the documents the server knows about, returning the document
+    /// whose diagnostics should be published, if any.
+    fn handle_notification(&mut self, notification: Notification) -> Option<Url> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = cast::<DidOpenTextDocument>(notification.params)?;
+                let item = params.text_document;
+                self.update(item.uri.clone(), item.text);
+                Some(item.uri)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = cast::<DidChangeTextDocument>(notification.params)?;
+                let change = params.content_changes.into_iter().last()?;
+                self.update(params.text_document.uri.clone(), change.text);
+                Some(params.text_document.uri)
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = cast::<DidCloseTextDocument>(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                Some(params.text_document.uri)
+            }
+            _ => None,
+        }
+    }
+
+    fn update(&mut self, uri: Url, text: String) {
+        let document = Document::new(text);
+        let definitions = match document.tree() {
+            Ok(_) => Analysis::new(&document).definition_names(),
+            Err(_) => self
+                .documents
+                .remove(&uri)
+                .map(|open| open.definitions)
+                .unwrap_or_default(),
+        };
+
+        self.documents.insert(
+            uri,
+            Open {
+                document,
+                definitions,
+            },
+        );
+    }
+
+    /// Publish the diagnostics for a document. Closed documents have theirs
+    /// cleared.
+    fn publish_diagnostics(&self, uri: Url) -> Notification {
+        let diagnostics = self
+            .documents
+            .get(&uri)
+            .map(|open| Analysis::new(&open.document).diagnostics())
+            .unwrap_or_default();
+
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        Notification::new(PublishDiagnostics::METHOD.to_owned(), params)
+    }
+
+    fn completion(
+        &self,
+        position: TextDocumentPositionParams,
+    ) -> Option<Vec<lsp_types::CompletionItem>> {
+        let open = self.documents.get(&position.text_document.uri)?;
+        let offset = open.document.offset(position.position);
+        Some(Analysis::new(&open.document).completions(offset, &open.definitions))
+    }
+
+    fn definition(&self, position: TextDocumentPositionParams) -> Option<Location> {
+        let uri = position.text_document.uri;
+        let document = &self.documents.get(&uri)?.document;
+        let span = Analysis::new(document).definition(document.offset(position.position))?;
+        Some(Location::new(uri, document.range(span)))
+    }
+
+    fn references(
+        &self,
+        position: TextDocumentPositionParams,
+        include_declaration: bool,
+    ) -> Option<Vec<Location>> {
+        let uri = position.text_document.uri;
+        let document = &self.documents.get(&uri)?.document;
+        let offset = document.offset(position.position);
+        let spans = Analysis::new(document).references(offset, include_declaration);
+
+        let locations = spans
+            .into_iter()
+            .map(|span| Location::new(uri.clone(), document.range(span)))
+            .collect();
+
+        Some(locations)
+    }
+
+    fn hover(&self, position: TextDocumentPositionParams) -> Option<Hover> {
+        let document = &self.documents.get(&position.text_document.uri)?.document;
+        let (text, span) = Analysis::new(document).hover(document.offset(position.position))?;
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: text,
+            }),
+            range: Some(document.range(span)),
+        })
+    }
+}
+
+/// Answer a request of type `R` using `handler`. Requests about unknown
+/// documents, or positions with nothing to say about them, get a null
+/// result.
+fn respond<R, T>(
+    request: Request,
+    handler: impl FnOnce(R::Params) -> Option<T>,
+) -> Result<serde_json::Value, (ErrorCode, String)>
+where
+    R: RequestType,
+    T: Serialize,
+{
+    let params = serde_json::from_value(request.params)
+        .map_err(|err| (ErrorCode::InvalidParams, err.to_string()))?;
+
+    serde_json::to_value(handler(params)).map_err(|err| (ErrorCode::InternalError, err.to_string()))
+}
+
+/// Parse the parameters of a notification of type `N`, ignoring the
+/// notification if they're malformed.
+fn cast<N>(params: serde_json::Value) -> Option<N::Params>
+where
+    N: NotificationType,
+    N::Params: DeserializeOwned,
+{
+    serde_json::from_value(params).ok()
+}
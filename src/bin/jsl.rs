@@ -0,0 +1,371 @@
+//! The `jsl` command-line tool.
+//!
+//! Run `jsl validate SCHEMA [INSTANCE]...` to check that some JSON files are
+//! valid against a schema. Instances may be paths, glob patterns, or `-` for
+//! standard input, which is also read if no instances are given.
+//!
+//! Run `jsl coverage SCHEMA [INSTANCE]...` to report which parts of a schema
+//! some JSON files exercise, as JSON or, with `--format lcov`, as lcov-like
+//! text. Instances are given as for `validate`, and invalid ones are counted
+//! too.
+//!
+//! The exit code describes the worst outcome among all of the instances:
+//!
+//! * 0: every instance is valid,
+//! * 1: at least one instance is invalid,
+//! * 2: the command-line arguments are malformed,
+//! * 3: an instance or the schema could not be read or parsed as JSON, or
+//! * 4: the schema is not a valid schema, or validation against it could not
+//!   be completed (for example, because `--max-depth` was exceeded).
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use jsl::coverage::Coverage;
+use jsl::{Config, Schema, SerdeSchema, ValidationError, Validator};
+use serde_json::error::Category;
+use serde_json::{json, Value};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+const EXIT_VALID: i32 = 0;
+const EXIT_INVALID: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_UNPARSEABLE: i32 = 3;
+const EXIT_BAD_SCHEMA: i32 = 4;
+
+const STDIN: &str = "-";
+
+fn main() {
+    let matches = app().get_matches_safe().unwrap_or_else(|err| {
+        // Help and version output are not errors, and clap exits with 0 for
+        // those. Everything else is a usage error.
+        if !err.use_stderr() {
+            err.exit();
+        }
+
+        eprintln!("{}", err.message);
+        process::exit(EXIT_USAGE);
+    });
+
+    let code = match matches.subcommand() {
+        ("validate", Some(matches)) => validate(matches),
+        ("coverage", Some(matches)) => coverage(matches),
+        _ => unreachable!("clap requires a subcommand"),
+    };
+
+    process::exit(code);
+}
+
+fn app() -> App<'static, 'static> {
+    App::new("jsl")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about(env!("CARGO_PKG_DESCRIPTION"))
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Validates JSON instances against a schema")
+                .arg(
+                    Arg::with_name("SCHEMA")
+                        .help("The schema to validate against")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("INSTANCE")
+                        .help("Files or glob patterns to validate, or - for standard input")
+                        .multiple(true),
+                )
+                .arg(
+                    Arg::with_name("strict")
+                        .long("strict")
+                        .help("Reject properties that the schema does not mention"),
+                )
+                .arg(
+                    Arg::with_name("max-errors")
+                        .long("max-errors")
+                        .value_name("N")
+                        .validator(is_usize)
+                        .help("Stop after N errors per instance; 0 means no limit"),
+                )
+                .arg(
+                    Arg::with_name("max-depth")
+                        .long("max-depth")
+                        .value_name("N")
+                        .validator(is_usize)
+                        .help("Give up after following N refs"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .possible_values(&["human", "json"])
+                        .default_value("human")
+                        .help("How to output results"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("coverage")
+                .about("Reports which parts of a schema JSON instances exercise")
+                .arg(
+                    Arg::with_name("SCHEMA")
+                        .help("The schema to measure coverage of")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("INSTANCE")
+                        .help("Files or glob patterns to validate, or - for standard input")
+                        .multiple(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .possible_values(&["json", "lcov"])
+                        .default_value("json")
+                        .help("How to output the report"),
+                ),
+        )
+}
+
+fn is_usize(value: String) -> Result<(), String> {
+    value
+        .parse::<usize>()
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// The result of validating one instance.
+enum Outcome {
+    Valid,
+    Invalid(Vec<ValidationError<'static>>),
+    Unparseable(String),
+    Failed(String),
+}
+
+impl Outcome {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Outcome::Valid => EXIT_VALID,
+            Outcome::Invalid(_) => EXIT_INVALID,
+            Outcome::Unparseable(_) => EXIT_UNPARSEABLE,
+            Outcome::Failed(_) => EXIT_BAD_SCHEMA,
+        }
+    }
+}
+
+fn validate(matches: &ArgMatches) -> i32 {
+    let schema = match load_schema(matches.value_of("SCHEMA").unwrap()) {
+        Ok(schema) => schema,
+        Err((code, message)) => {
+            eprintln!("error: {}", message);
+            return code;
+        }
+    };
+
+    let mut config = Config::new();
+    config.strict_instance_semantics(matches.is_present("strict"));
+    if let Some(max_errors) = matches.value_of("max-errors") {
+        config.max_errors(max_errors.parse().unwrap());
+    }
+    if let Some(max_depth) = matches.value_of("max-depth") {
+        config.max_depth(max_depth.parse().unwrap());
+    }
+
+    let validator = Validator::new_with_config(config);
+    let json = matches.value_of("format") == Some("json");
+
+    let mut code = EXIT_VALID;
+    let mut results = Vec::new();
+    for (name, instance) in load_instances(matches) {
+        let outcome = instance.map_or_else(Outcome::Unparseable, |instance| {
+            match validator.validate(&schema, &instance) {
+                Ok(ref errors) if errors.is_empty() => Outcome::Valid,
+                Ok(errors) => Outcome::Invalid(
+                    errors
+                        .into_iter()
+                        .map(ValidationError::into_owned)
+                        .collect(),
+                ),
+                Err(err) => Outcome::Failed(err.to_string()),
+            }
+        });
+
+        code = code.max(outcome.exit_code());
+        if json {
+            results.push(json_result(&name, &outcome));
+        } else {
+            print_human(&name, &outcome);
+        }
+    }
+
+    if json {
+        println!("{}", Value::Array(results));
+    }
+
+    code
+}
+
+fn coverage(matches: &ArgMatches) -> i32 {
+    let path = matches.value_of("SCHEMA").unwrap();
+    let schema = match load_schema(path) {
+        Ok(schema) => schema,
+        Err((code, message)) => {
+            eprintln!("error: {}", message);
+            return code;
+        }
+    };
+
+    // The report goes to standard output, so problems with instances are
+    // reported on standard error instead.
+    let mut code = EXIT_VALID;
+    let mut coverage = Coverage::new(&schema);
+    for (name, instance) in load_instances(matches) {
+        let outcome = match instance {
+            Ok(instance) => match coverage.add(&instance) {
+                Ok(ref errors) if errors.is_empty() => Outcome::Valid,
+                Ok(errors) => Outcome::Invalid(errors),
+                Err(err) => Outcome::Failed(err.to_string()),
+            },
+            Err(message) => Outcome::Unparseable(message),
+        };
+
+        code = code.max(outcome.exit_code());
+        match outcome {
+            Outcome::Valid => {}
+            Outcome::Invalid(_) => eprintln!("{}: invalid", name),
+            Outcome::Unparseable(message) | Outcome::Failed(message) => {
+                eprintln!("{}: error: {}", name, message)
+            }
+        }
+    }
+
+    if matches.value_of("format") == Some("lcov") {
+        print!("{}", coverage.to_lcov(path));
+    } else {
+        println!("{}", coverage.to_json());
+    }
+
+    code
+}
+
+/// Load each of the instances given on the command line, along with the name
+/// to report it by.
+fn load_instances<'a>(
+    matches: &'a ArgMatches,
+) -> impl Iterator<Item = (String, Result<Value, String>)> + 'a {
+    let instances: Vec<_> = matches
+        .values_of("INSTANCE")
+        .map(|values| values.collect())
+        .unwrap_or_else(|| vec![STDIN]);
+
+    instances
+        .into_iter()
+        .flat_map(expand)
+        .map(|path| match path {
+            Ok(path) if path == STDIN => ("<stdin>".to_owned(), load_instance(&path)),
+            Ok(path) => {
+                let instance = load_instance(&path);
+                (path, instance)
+            }
+            Err((name, message)) => (name, Err(message)),
+        })
+}
+
+/// Read and parse a schema, or describe why that's not possible along with
+/// the exit code to use.
+///
+/// A schema file which isn't JSON is unparseable input, like an instance
+/// would be. A schema file which is JSON, but not a valid schema, is an
+/// invalid schema.
+fn load_schema(path: &str) -> Result<Schema, (i32, String)> {
+    let fail = |code, err: &dyn fmt::Display| (code, format!("{}: {}", path, err));
+
+    let data = read(path).map_err(|err| fail(EXIT_UNPARSEABLE, &err))?;
+    let serde: SerdeSchema = serde_json::from_str(&data).map_err(|err| match err.classify() {
+        Category::Data => fail(EXIT_BAD_SCHEMA, &err),
+        Category::Io | Category::Syntax | Category::Eof => fail(EXIT_UNPARSEABLE, &err),
+    })?;
+
+    Schema::from_serde(serde).map_err(|err| fail(EXIT_BAD_SCHEMA, &err))
+}
+
+/// Turn an instance argument into the paths it refers to, or the name and
+/// reason for each part of it that can't be read.
+///
+/// Arguments containing glob metacharacters are expanded. A pattern that
+/// matches nothing is reported as an unreadable instance, rather than
+/// silently validating nothing.
+fn expand(arg: &str) -> Vec<Result<String, (String, String)>> {
+    if arg == STDIN || !arg.contains(&['*', '?', '['][..]) {
+        return vec![Ok(arg.to_owned())];
+    }
+
+    let paths = match glob::glob(arg) {
+        Ok(paths) => paths,
+        Err(err) => return vec![Err((arg.to_owned(), err.to_string()))],
+    };
+
+    let paths: Vec<_> = paths
+        .map(|path| match path {
+            Ok(path) => Ok(path.to_string_lossy().into_owned()),
+            Err(err) => Err((err.path().to_string_lossy().into_owned(), err.to_string())),
+        })
+        .collect();
+
+    if paths.is_empty() {
+        return vec![Err((arg.to_owned(), "no files match pattern".to_owned()))];
+    }
+
+    paths
+}
+
+fn load_instance(path: &str) -> Result<Value, String> {
+    let data = read(path).map_err(|err| err.to_string())?;
+    serde_json::from_str(&data).map_err(|err| err.to_string())
+}
+
+fn read(path: &str) -> io::Result<String> {
+    if path == STDIN {
+        let mut data = String::new();
+        io::stdin().read_to_string(&mut data)?;
+        Ok(data)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+fn print_human(name: &str, outcome: &Outcome) {
+    match outcome {
+        Outcome::Valid => println!("{}: valid", name),
+        Outcome::Invalid(errors) => {
+            println!("{}: invalid", name);
+            for error in errors {
+                println!(
+                    "  instance path: {:?}, schema path: {:?}",
+                    error.instance_path().to_string(),
+                    error.schema_path().to_string()
+                );
+            }
+        }
+        Outcome::Unparseable(message) | Outcome::Failed(message) => {
+            println!("{}: error: {}", name, message)
+        }
+    }
+}
+
+fn json_result(name: &str, outcome: &Outcome) -> Value {
+    match outcome {
+        Outcome::Valid => json!({ "instance": name, "valid": true, "errors": [] }),
+        Outcome::Invalid(errors) => json!({
+            "instance": name,
+            "valid": false,
+            "errors": errors.iter().map(|error| json!({
+                "instancePath": error.instance_path().to_string(),
+                "schemaPath": error.schema_path().to_string(),
+            })).collect::<Vec<_>>(),
+        }),
+        Outcome::Unparseable(message) | Outcome::Failed(message) => {
+            json!({ "instance": name, "valid": false, "error": message })
+        }
+    }
+}
@@ -0,0 +1,472 @@
+//! Construct schemas in code.
+//!
+//! [`SchemaBuilder`](struct.SchemaBuilder.html) is a more convenient, and
+//! safer, alternative to nesting calls to
+//! [`Schema::from_parts`](../schema/struct.Schema.html#method.from_parts):
+//!
+//! ```
+//! use jsl::{Schema, SchemaBuilder};
+//!
+//! let schema = SchemaBuilder::object()
+//!     .required("id", Schema::uint64())
+//!     .optional("name", Schema::string())
+//!     .optional("friends", SchemaBuilder::elements(SchemaBuilder::reference("user")))
+//!     .definition("user", SchemaBuilder::object().required("id", Schema::uint64()))
+//!     .build()
+//!     .unwrap();
+//!
+//! assert!(schema.is_root());
+//! ```
+//!
+//! A builder checks the same rules as
+//! [`Schema::from_serde`](../schema/struct.Schema.html#method.from_serde):
+//! properties cannot be both required and optional, discriminator variants
+//! must be properties forms that don't mention the tag, and every `ref` must
+//! refer to a definition of the root schema. Builders also reject defining
+//! the same definition twice, and extra data under a keyword's name, either
+//! of which would otherwise be silently lost. Mistakes are reported by
+//! [`build`](struct.SchemaBuilder.html#method.build), as the same
+//! [`JslError`s](../errors/enum.JslError.html) `from_serde` returns.
+
+use crate::errors::JslError;
+use crate::schema::{Form, Schema, Type};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The keywords of a schema, which extra data may not use as keys.
+const KEYWORDS: &[&str] = &[
+    "definitions",
+    "ref",
+    "type",
+    "enum",
+    "elements",
+    "properties",
+    "optionalProperties",
+    "values",
+    "discriminator",
+];
+
+macro_rules! type_constructors {
+    ($($name:ident => $typ:ident,)*) => {
+        /// Shorthands for constructing schemas of the `type` form.
+        impl Schema {
+            $(
+                #[doc = concat!("Construct a non-root schema of type `", stringify!($name), "`.")]
+                pub fn $name() -> Schema {
+                    Schema::from_parts(None, Box::new(Form::Type(Type::$typ)), BTreeMap::new())
+                }
+            )*
+        }
+    };
+}
+
+type_constructors! {
+    boolean => Boolean,
+    number => Number,
+    float32 => Float32,
+    float64 => Float64,
+    int8 => Int8,
+    uint8 => Uint8,
+    int16 => Int16,
+    uint16 => Uint16,
+    int32 => Int32,
+    uint32 => Uint32,
+    int64 => Int64,
+    uint64 => Uint64,
+    string => String,
+    timestamp => Timestamp,
+}
+
+/// Builds a schema, checking that it is valid.
+///
+/// Methods that take a subschema accept either a `Schema` or another
+/// `SchemaBuilder`. Only the outermost builder should be built; it becomes
+/// the root schema, and nested builders become its subschemas.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaBuilder {
+    defs: Option<BTreeMap<String, Schema>>,
+    form: Form,
+    extra: BTreeMap<String, Value>,
+    error: Option<JslError>,
+}
+
+impl SchemaBuilder {
+    fn new(form: Form) -> Self {
+        Self {
+            defs: None,
+            form,
+            extra: BTreeMap::new(),
+            error: None,
+        }
+    }
+
+    /// Start a schema of the empty form, which accepts any instance.
+    pub fn empty() -> Self {
+        Self::new(Form::Empty)
+    }
+
+    /// Start a schema of the ref form.
+    pub fn reference(definition: &str) -> Self {
+        Self::new(Form::Ref(definition.to_owned()))
+    }
+
+    /// Start a schema of the type form.
+    pub fn typ(typ: Type) -> Self {
+        Self::new(Form::Type(typ))
+    }
+
+    /// Start a schema of the enum form. There must be at least one value, and
+    /// no value may appear twice.
+    pub fn enumeration<I, S>(values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut set = BTreeSet::new();
+        let mut duplicate = false;
+        for value in values {
+            duplicate |= !set.insert(value.into());
+        }
+
+        let mut builder = Self::new(Form::Empty);
+        if duplicate || set.is_empty() {
+            builder.fail(JslError::InvalidForm);
+        }
+
+        builder.form = Form::Enum(set);
+        builder
+    }
+
+    /// Start a schema of the elements form.
+    pub fn elements<S: Into<SchemaBuilder>>(sub_schema: S) -> Self {
+        let mut builder = Self::new(Form::Empty);
+        let sub_schema = builder.sub_schema(sub_schema);
+        builder.form = Form::Elements(sub_schema);
+        builder
+    }
+
+    /// Start a schema of the values form.
+    pub fn values<S: Into<SchemaBuilder>>(sub_schema: S) -> Self {
+        let mut builder = Self::new(Form::Empty);
+        let sub_schema = builder.sub_schema(sub_schema);
+        builder.form = Form::Values(sub_schema);
+        builder
+    }
+
+    /// Start a schema of the properties form, with no properties yet. Add
+    /// them with [`required`](#method.required) and
+    /// [`optional`](#method.optional).
+    pub fn object() -> Self {
+        Self::new(Form::Properties(BTreeMap::new(), BTreeMap::new(), true))
+    }
+
+    /// Start a schema of the discriminator form, with no variants yet. Add
+    /// them with [`variant`](#method.variant).
+    pub fn discriminator(tag: &str) -> Self {
+        Self::new(Form::Discriminator(tag.to_owned(), BTreeMap::new()))
+    }
+
+    /// Add a required property to a schema started with
+    /// [`object`](#method.object).
+    pub fn required<S: Into<SchemaBuilder>>(self, name: &str, sub_schema: S) -> Self {
+        self.property(name, sub_schema, false)
+    }
+
+    /// Add an optional property to a schema started with
+    /// [`object`](#method.object).
+    pub fn optional<S: Into<SchemaBuilder>>(self, name: &str, sub_schema: S) -> Self {
+        self.property(name, sub_schema, true)
+    }
+
+    fn property<S: Into<SchemaBuilder>>(
+        mut self,
+        name: &str,
+        sub_schema: S,
+        optional: bool,
+    ) -> Self {
+        let sub_schema = self.sub_schema(sub_schema);
+        let error = match self.form {
+            Form::Properties(ref mut required, ref mut optional_props, _) => {
+                if required.contains_key(name) || optional_props.contains_key(name) {
+                    Some(JslError::AmbiguousProperty {
+                        property: name.to_owned(),
+                    })
+                } else {
+                    let props = if optional { optional_props } else { required };
+                    props.insert(name.to_owned(), sub_schema);
+                    None
+                }
+            }
+            _ => Some(JslError::InvalidForm),
+        };
+
+        if let Some(error) = error {
+            self.fail(error);
+        }
+        self
+    }
+
+    /// Add a variant to a schema started with
+    /// [`discriminator`](#method.discriminator). The variant must be of the
+    /// properties form, and must not have a property with the same name as
+    /// the tag.
+    pub fn variant<S: Into<SchemaBuilder>>(mut self, value: &str, sub_schema: S) -> Self {
+        let sub_schema = self.sub_schema(sub_schema);
+        let error = match self.form {
+            Form::Discriminator(ref tag, ref mut mapping) => match sub_schema.form() {
+                Form::Properties(required, optional, _) => {
+                    if required.contains_key(tag) || optional.contains_key(tag) {
+                        Some(JslError::AmbiguousProperty {
+                            property: tag.clone(),
+                        })
+                    } else if mapping.contains_key(value) {
+                        Some(JslError::InvalidForm)
+                    } else {
+                        mapping.insert(value.to_owned(), sub_schema);
+                        None
+                    }
+                }
+                _ => Some(JslError::InvalidForm),
+            },
+            _ => Some(JslError::InvalidForm),
+        };
+
+        if let Some(error) = error {
+            self.fail(error);
+        }
+        self
+    }
+
+    /// Add a definition. Only the outermost builder, which becomes the root
+    /// schema, may have definitions, and each name may only be defined once.
+    pub fn definition<S: Into<SchemaBuilder>>(mut self, name: &str, sub_schema: S) -> Self {
+        let sub_schema = self.sub_schema(sub_schema);
+        let defs = self.defs.get_or_insert_with(BTreeMap::new);
+        if defs.insert(name.to_owned(), sub_schema).is_some() {
+            self.fail(JslError::InvalidForm);
+        }
+        self
+    }
+
+    /// Add extra, non-keyword data to the schema. Using a keyword, such as
+    /// `type`, as `key` is an error.
+    pub fn extra(mut self, key: &str, value: Value) -> Self {
+        if KEYWORDS.contains(&key) {
+            self.fail(JslError::InvalidForm);
+        }
+
+        self.extra.insert(key.to_owned(), value);
+        self
+    }
+
+    /// Add a `description` to the schema's extra data. Code and documentation
+    /// generators use this to document what the schema represents.
+    pub fn description(self, description: &str) -> Self {
+        self.extra("description", Value::String(description.to_owned()))
+    }
+
+    /// Build a root schema, checking that it is valid.
+    pub fn build(self) -> Result<Schema, JslError> {
+        let (defs, form, extra) = self.into_schema()?.into_parts();
+        let schema = Schema::from_parts(Some(defs.unwrap_or_default()), form, extra);
+
+        if let Some(def) = schema.missing_ref() {
+            return Err(JslError::NoSuchDefinition {
+                definition: def.to_owned(),
+            });
+        }
+
+        Ok(schema)
+    }
+
+    fn fail(&mut self, error: JslError) {
+        self.error.get_or_insert(error);
+    }
+
+    /// Convert a nested builder into a non-root schema, recording any errors
+    /// it has.
+    fn sub_schema<S: Into<SchemaBuilder>>(&mut self, sub_schema: S) -> Schema {
+        let sub_schema = sub_schema.into();
+        if sub_schema.defs.is_some() {
+            self.fail(JslError::InvalidForm);
+        }
+
+        match sub_schema.into_schema() {
+            Ok(schema) => schema,
+            Err(error) => {
+                self.fail(error);
+                Schema::from_parts(None, Box::new(Form::Empty), BTreeMap::new())
+            }
+        }
+    }
+
+    fn into_schema(self) -> Result<Schema, JslError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let form = match self.form {
+            // Like `from_serde`, only use `optionalProperties` for errors when
+            // there are no required properties to speak of.
+            Form::Properties(required, optional, _) => {
+                let has_required = !required.is_empty() || optional.is_empty();
+                Form::Properties(required, optional, has_required)
+            }
+            form => form,
+        };
+
+        Ok(Schema::from_parts(self.defs, Box::new(form), self.extra))
+    }
+}
+
+impl From<Schema> for SchemaBuilder {
+    /// Start from an existing schema. Root schemas may only be used as the
+    /// outermost builder, unless their definitions are empty.
+    fn from(schema: Schema) -> Self {
+        let (defs, form, extra) = schema.into_parts();
+        Self {
+            defs: defs.filter(|defs| !defs.is_empty()),
+            form: *form,
+            extra,
+            error: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(data: Value) -> Schema {
+        Schema::from_serde(serde_json::from_value(data).unwrap()).unwrap()
+    }
+
+    fn error(builder: SchemaBuilder) -> JslError {
+        builder.build().unwrap_err()
+    }
+
+    #[test]
+    fn build() {
+        let schema = SchemaBuilder::object()
+            .required("id", Schema::uint64())
+            .optional("tags", SchemaBuilder::values(Schema::string()))
+            .optional("status", SchemaBuilder::enumeration(vec!["ON", "OFF"]))
+            .required(
+                "event",
+                SchemaBuilder::discriminator("type")
+                    .variant("a", SchemaBuilder::object())
+                    .variant(
+                        "b",
+                        SchemaBuilder::object().optional("at", Schema::timestamp()),
+                    ),
+            )
+            .required(
+                "items",
+                SchemaBuilder::elements(SchemaBuilder::reference("item")),
+            )
+            .definition("item", SchemaBuilder::empty().description("Anything."))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            schema,
+            parse(json!({
+                "definitions": {
+                    "item": { "description": "Anything." },
+                },
+                "properties": {
+                    "id": { "type": "uint64" },
+                    "event": {
+                        "discriminator": {
+                            "tag": "type",
+                            "mapping": {
+                                "a": { "properties": {} },
+                                "b": { "optionalProperties": { "at": { "type": "timestamp" } } },
+                            },
+                        },
+                    },
+                    "items": { "elements": { "ref": "item" } },
+                },
+                "optionalProperties": {
+                    "tags": { "values": { "type": "string" } },
+                    "status": { "enum": ["ON", "OFF"] },
+                },
+            }))
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            error(
+                SchemaBuilder::object()
+                    .required("a", Schema::string())
+                    .optional("a", Schema::string())
+            ),
+            JslError::AmbiguousProperty {
+                property: "a".to_owned()
+            }
+        );
+
+        assert_eq!(
+            error(SchemaBuilder::discriminator("type").variant("a", Schema::string())),
+            JslError::InvalidForm
+        );
+
+        assert_eq!(
+            error(SchemaBuilder::discriminator("type").variant(
+                "a",
+                SchemaBuilder::object().required("type", Schema::string())
+            )),
+            JslError::AmbiguousProperty {
+                property: "type".to_owned()
+            }
+        );
+
+        assert_eq!(
+            error(SchemaBuilder::elements(SchemaBuilder::reference("missing"))),
+            JslError::NoSuchDefinition {
+                definition: "missing".to_owned()
+            }
+        );
+
+        assert_eq!(
+            error(SchemaBuilder::elements(
+                SchemaBuilder::empty().definition("nested", Schema::string())
+            )),
+            JslError::InvalidForm
+        );
+
+        assert_eq!(
+            error(SchemaBuilder::empty().required("a", Schema::string())),
+            JslError::InvalidForm
+        );
+
+        assert_eq!(
+            error(SchemaBuilder::enumeration(Vec::<String>::new())),
+            JslError::InvalidForm
+        );
+
+        assert_eq!(
+            error(
+                SchemaBuilder::empty()
+                    .definition("a", Schema::string())
+                    .definition("a", Schema::uint8())
+            ),
+            JslError::InvalidForm
+        );
+
+        assert_eq!(
+            error(SchemaBuilder::typ(Type::String).extra("type", json!("uint8"))),
+            JslError::InvalidForm
+        );
+
+        assert_eq!(
+            error(SchemaBuilder::elements(
+                SchemaBuilder::empty().extra("properties", json!({}))
+            )),
+            JslError::InvalidForm
+        );
+    }
+}
@@ -0,0 +1,1261 @@
+//! Convert between schemas and Apache Avro schemas.
+//!
+//! [`Generator`](struct.Generator.html) turns a root schema into an Avro
+//! schema, and [`parse`](fn.parse.html) turns an Avro schema back into a root
+//! schema. The mapping is:
+//!
+//! * Properties forms become records, and optional properties become unions
+//!   with `null` that default to `null`.
+//! * Enum forms become enums, elements forms arrays, and values forms maps.
+//! * Discriminator forms become unions of records, one per mapping.
+//! * The `timestamp` type becomes a `long` with the `timestamp-micros`
+//!   logical type.
+//!
+//! Definitions become named types, which are written out in full where they
+//! are first used and referred to by name everywhere else. This is what lets
+//! recursive definitions be represented at all.
+//!
+//! Where Avro can't express something exactly, the generated schema keeps
+//! enough information to recover the original in attributes prefixed with
+//! `jsl`, such as `"jslType": "int8"` on an `int`, or `"jslName"` on a field
+//! whose property name isn't a valid Avro name. Avro implementations ignore
+//! these attributes, and [`parse`](fn.parse.html) uses them so that schemas
+//! survive a round trip. Every approximation is still reported, as the Avro
+//! schema itself accepts different data than the original.
+
+use crate::codegen::{
+    c_identifier, child_path, description, mapping_path, parse_type_name, pascal_case,
+    property_path, sorted_properties, type_name, Approximation, Names, Output,
+};
+use crate::pointer::JsonPointer;
+use crate::schema::{Form, Schema, Type};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Generates Avro schemas from schemas.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate an Avro schema for a root schema.
+    ///
+    /// The output's code is the Avro schema, as pretty-printed JSON.
+    pub fn generate(&self, schema: &Schema) -> Output {
+        let no_defs = BTreeMap::new();
+        let defs = schema.definitions().as_ref().unwrap_or(&no_defs);
+
+        let mut state = State {
+            defs,
+            names: Names::default(),
+            emitted: BTreeMap::new(),
+            visited: BTreeSet::new(),
+            inlining: BTreeSet::new(),
+            approximations: Vec::new(),
+        };
+
+        let mut avro = state.avro(schema, &c_identifier(&self.config.root_name), &[]);
+
+        // Named types inherit their namespace from the type enclosing them, so
+        // only the outermost named types need one.
+        if let Some(ref namespace) = self.config.namespace {
+            let outermost = match avro {
+                Value::Array(ref mut members) => members.iter_mut().collect(),
+                ref mut avro => vec![avro],
+            };
+
+            for avro in outermost {
+                if let Some(avro) = avro.as_object_mut().filter(|a| a.contains_key("name")) {
+                    avro.insert("namespace".to_owned(), json!(namespace));
+                }
+            }
+        }
+
+        for name in defs.keys() {
+            if !state.visited.contains(name.as_str()) {
+                state.approximate(
+                    &["definitions".to_owned(), name.clone()],
+                    "definition is not used by the root schema, so it is omitted".to_owned(),
+                );
+            }
+        }
+
+        let mut code = serde_json::to_string_pretty(&avro).expect("error serializing avro");
+        code.push('\n');
+        Output::new(code, state.approximations)
+    }
+}
+
+/// Configuration for how Avro schemas should be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    root_name: String,
+    namespace: Option<String>,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the type generated for the root schema, if the root
+    /// schema becomes a named type. The default is `Root`.
+    pub fn root_name(&mut self, root_name: &str) -> &mut Self {
+        self.root_name = root_name.to_owned();
+        self
+    }
+
+    /// Sets the namespace of the generated named types. By default, they
+    /// have no namespace.
+    pub fn namespace(&mut self, namespace: Option<&str>) -> &mut Self {
+        self.namespace = namespace.map(str::to_owned);
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            root_name: "Root".to_owned(),
+            namespace: None,
+        }
+    }
+}
+
+struct State<'a> {
+    defs: &'a BTreeMap<String, Schema>,
+    names: Names,
+    emitted: BTreeMap<&'a str, Value>,
+    visited: BTreeSet<&'a str>,
+    inlining: BTreeSet<&'a str>,
+    approximations: Vec<Approximation>,
+}
+
+impl<'a> State<'a> {
+    fn approximate(&mut self, path: &[String], message: String) {
+        self.approximations
+            .push(Approximation::new(JsonPointer::new(path.to_vec()), message));
+    }
+
+    /// The Avro schema for a schema. `hint` is the name to give the schema,
+    /// if it becomes a named type.
+    fn avro(&mut self, schema: &'a Schema, hint: &str, path: &[String]) -> Value {
+        match schema.form() {
+            Form::Empty => {
+                self.approximate(
+                    path,
+                    "the empty form is represented as a JSON-encoded string".to_owned(),
+                );
+                json!({ "type": "string", "jslType": "empty" })
+            }
+            Form::Ref(def) => self.reference(def, path),
+            Form::Type(typ) => self.primitive(typ, path),
+            Form::Enum(values) => {
+                let name = self.names.claim(hint);
+                self.enumeration(schema, values, &name, path, None)
+            }
+            Form::Elements(sub_schema) => json!({
+                "type": "array",
+                "items": self.avro(
+                    sub_schema,
+                    &format!("{}Item", hint),
+                    &child_path(path, &["elements"]),
+                ),
+            }),
+            Form::Values(sub_schema) => json!({
+                "type": "map",
+                "values": self.avro(
+                    sub_schema,
+                    &format!("{}Value", hint),
+                    &child_path(path, &["values"]),
+                ),
+            }),
+            Form::Properties(..) => {
+                let name = self.names.claim(hint);
+                self.record(schema, &name, path, None, None)
+            }
+            Form::Discriminator(tag, mapping) => {
+                let names = self.variant_names(hint, mapping);
+                self.union(tag, mapping, names, path, None)
+            }
+        }
+    }
+
+    /// The Avro schema for a reference to a definition.
+    ///
+    /// Definitions which become named types are written out in full the
+    /// first time they are referred to. Other definitions are inlined.
+    fn reference(&mut self, def: &str, path: &[String]) -> Value {
+        let (def, schema) = self
+            .defs
+            .get_key_value(def)
+            .expect("reference to non-existent definition");
+
+        self.visited.insert(def);
+        if let Some(avro) = self.emitted.get(def.as_str()) {
+            return avro.clone();
+        }
+
+        let def_path = vec!["definitions".to_owned(), def.clone()];
+        match schema.form() {
+            Form::Properties(..) => {
+                let name = self.names.claim(&c_identifier(def));
+                self.emitted.insert(def, json!(name));
+                self.record(schema, &name, &def_path, Some(def), None)
+            }
+            Form::Enum(values) => {
+                let name = self.names.claim(&c_identifier(def));
+                self.emitted.insert(def, json!(name));
+                self.enumeration(schema, values, &name, &def_path, Some(def))
+            }
+            Form::Discriminator(tag, mapping) => {
+                let names = self.variant_names(&c_identifier(def), mapping);
+                self.emitted.insert(def, json!(names));
+                self.union(tag, mapping, names, &def_path, Some(def))
+            }
+            _ => {
+                if !self.inlining.insert(def) {
+                    self.approximate(
+                        path,
+                        format!(
+                            "definition {:?} is recursive without passing through a named type, so it is represented as a JSON-encoded string",
+                            def
+                        ),
+                    );
+                    return json!({ "type": "string", "jslType": "empty" });
+                }
+
+                let avro = self.avro(schema, &pascal_case(def), &def_path);
+                self.inlining.remove(def.as_str());
+                avro
+            }
+        }
+    }
+
+    fn primitive(&mut self, typ: &Type, path: &[String]) -> Value {
+        let avro = match typ {
+            Type::Boolean => "boolean",
+            Type::Float32 => "float",
+            Type::Number | Type::Float64 => "double",
+            Type::Int8 | Type::Uint8 | Type::Int16 | Type::Uint16 | Type::Int32 => "int",
+            Type::Uint32 | Type::Int64 | Type::Uint64 => "long",
+            Type::String => "string",
+            Type::Timestamp => {
+                self.approximate(
+                    path,
+                    "timestamps are stored as microseconds since the epoch, losing their UTC offset"
+                        .to_owned(),
+                );
+                return json!({ "type": "long", "logicalType": "timestamp-micros" });
+            }
+        };
+
+        match typ {
+            Type::Boolean | Type::Float32 | Type::Float64 | Type::Int32 | Type::Int64 => {
+                json!(avro)
+            }
+            Type::String => json!(avro),
+            Type::Number => json!({ "type": avro, "jslType": "number" }),
+            Type::Uint64 => {
+                self.approximate(
+                    path,
+                    "uint64 is stored as long, which cannot hold values above 2^63 - 1".to_owned(),
+                );
+                json!({ "type": avro, "jslType": "uint64" })
+            }
+            _ => {
+                self.approximate(path, format!("{} is widened to {}", type_name(typ), avro));
+                json!({ "type": avro, "jslType": type_name(typ) })
+            }
+        }
+    }
+
+    /// The start of a named type's Avro schema.
+    fn named(
+        &self,
+        kind: &str,
+        name: &str,
+        schema: &Schema,
+        definition: Option<&str>,
+    ) -> Map<String, Value> {
+        let mut avro = Map::new();
+        avro.insert("type".to_owned(), json!(kind));
+        avro.insert("name".to_owned(), json!(name));
+        if let Some(description) = description(schema) {
+            avro.insert("doc".to_owned(), json!(description));
+        }
+
+        if let Some(definition) = definition {
+            avro.insert("jslDefinition".to_owned(), json!(definition));
+        }
+
+        avro
+    }
+
+    fn enumeration(
+        &mut self,
+        schema: &Schema,
+        values: &BTreeSet<String>,
+        name: &str,
+        path: &[String],
+        definition: Option<&str>,
+    ) -> Value {
+        let values: Vec<_> = values.iter().collect();
+
+        let mut used = Names::default();
+        let mut renamed = Map::new();
+        let mut symbols = Vec::new();
+        for value in values {
+            let symbol = used.claim(&c_identifier(value));
+            if &symbol != value {
+                self.approximate(
+                    path,
+                    format!("enum value {:?} is renamed to {}", value, symbol),
+                );
+                renamed.insert(symbol.clone(), json!(value));
+            }
+
+            symbols.push(json!(symbol));
+        }
+
+        let mut avro = self.named("enum", name, schema, definition);
+        avro.insert("symbols".to_owned(), Value::Array(symbols));
+        if !renamed.is_empty() {
+            avro.insert("jslSymbols".to_owned(), Value::Object(renamed));
+        }
+
+        Value::Object(avro)
+    }
+
+    /// Emit a record for a properties form. Variants of a discriminator also
+    /// record their tag and tag value.
+    fn record(
+        &mut self,
+        schema: &'a Schema,
+        name: &str,
+        path: &[String],
+        definition: Option<&str>,
+        tag: Option<(&str, &str)>,
+    ) -> Value {
+        let (required, optional) = match schema.form() {
+            Form::Properties(required, optional, _) => (required, optional),
+            _ => unreachable!("records are only made from properties forms"),
+        };
+
+        let mut field_names = Names::default();
+        let mut fields = Vec::new();
+        for (property, sub_schema, is_optional) in sorted_properties(required, optional) {
+            let path = property_path(path, property, is_optional);
+            let field_name = field_names.claim(&c_identifier(property));
+            let mut typ = self.avro(
+                sub_schema,
+                &format!("{}{}", name, pascal_case(property)),
+                &path,
+            );
+
+            let mut field = Map::new();
+            field.insert("name".to_owned(), json!(field_name));
+            if is_optional {
+                typ = match typ {
+                    Value::Array(mut members) => {
+                        members.insert(0, json!("null"));
+                        Value::Array(members)
+                    }
+                    typ => json!(["null", typ]),
+                };
+                field.insert("default".to_owned(), Value::Null);
+            }
+
+            field.insert("type".to_owned(), typ);
+            if let Some(description) = description(sub_schema) {
+                field.insert("doc".to_owned(), json!(description));
+            }
+
+            if &field_name != property {
+                self.approximate(
+                    &path,
+                    format!("property {:?} is renamed to {}", property, field_name),
+                );
+                field.insert("jslName".to_owned(), json!(property));
+            }
+
+            fields.push(Value::Object(field));
+        }
+
+        let mut avro = self.named("record", name, schema, definition);
+        avro.insert("fields".to_owned(), Value::Array(fields));
+        if let Some((tag, value)) = tag {
+            avro.insert("jslTag".to_owned(), json!(tag));
+            avro.insert("jslTagValue".to_owned(), json!(value));
+        }
+
+        Value::Object(avro)
+    }
+
+    fn variant_names(&mut self, base: &str, mapping: &BTreeMap<String, Schema>) -> Vec<String> {
+        mapping
+            .keys()
+            .map(|value| self.names.claim(&format!("{}{}", base, pascal_case(value))))
+            .collect()
+    }
+
+    /// Emit a union of records for a discriminator form.
+    fn union(
+        &mut self,
+        tag: &str,
+        mapping: &'a BTreeMap<String, Schema>,
+        names: Vec<String>,
+        path: &[String],
+        definition: Option<&str>,
+    ) -> Value {
+        self.approximate(
+            path,
+            format!(
+                "discriminator on {:?} is represented as a union of records, so the tag is implied by which record is used",
+                tag
+            ),
+        );
+
+        let members = mapping
+            .iter()
+            .zip(names)
+            .map(|((value, sub_schema), name)| {
+                let path = mapping_path(path, value);
+                self.record(sub_schema, &name, &path, definition, Some((tag, value)))
+            })
+            .collect();
+
+        Value::Array(members)
+    }
+}
+
+/// An error converting an Avro schema with [`parse`](fn.parse.html).
+///
+/// This arises when an Avro schema is malformed, such as a record without a
+/// `fields` array, or when it refers to a named type it doesn't define.
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub struct AvroError {
+    message: String,
+}
+
+impl AvroError {
+    /// What was wrong with the Avro schema.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for AvroError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid avro schema: {}", self.message)
+    }
+}
+
+impl std::error::Error for AvroError {}
+
+/// Parse an Avro schema into a root schema.
+///
+/// Named types which are referred to by name, or which have a `jslDefinition`
+/// attribute, become definitions. Other named types are inlined. A union of
+/// records with `jslTag` attributes becomes a discriminator; any other union
+/// with more than one type besides `null` is approximated with the empty form.
+pub fn parse(avro: &Value) -> Result<(Schema, Vec<Approximation>), AvroError> {
+    let mut converter = Converter {
+        named: BTreeMap::new(),
+        referenced: BTreeSet::new(),
+        defs: BTreeMap::new(),
+        building: BTreeSet::new(),
+        approximations: Vec::new(),
+    };
+
+    converter.collect(avro)?;
+
+    // The root itself must be inline, even if it is also a definition.
+    let root = match avro.get("type").and_then(Value::as_str) {
+        Some("record") | Some("error") | Some("enum") | Some("fixed") => {
+            let name = simple_name(avro)?;
+            if converter.is_def(name, avro) {
+                converter.definition(name)?;
+            }
+
+            converter.named_inline(avro, &[])?
+        }
+        _ => converter.convert(avro, &[])?,
+    };
+
+    let schema = Schema::from_parts(
+        Some(converter.defs),
+        Box::new(root.form().clone()),
+        root.extra().clone(),
+    );
+
+    Ok((schema, converter.approximations))
+}
+
+fn invalid(message: String) -> AvroError {
+    AvroError { message }
+}
+
+fn from_form(form: Form) -> Schema {
+    Schema::from_parts(None, Box::new(form), BTreeMap::new())
+}
+
+/// Add a `description` to a schema, from an Avro `doc`.
+fn with_doc(schema: Schema, avro: &Value) -> Schema {
+    match avro.get("doc").and_then(Value::as_str) {
+        Some(doc) => {
+            let mut extra = schema.extra().clone();
+            extra.insert("description".to_owned(), json!(doc));
+            Schema::from_parts(None, Box::new(schema.form().clone()), extra)
+        }
+        None => schema,
+    }
+}
+
+/// The name of a named type, without its namespace.
+fn simple_name(avro: &Value) -> Result<&str, AvroError> {
+    avro.get("name")
+        .and_then(Value::as_str)
+        .map(unqualified)
+        .ok_or_else(|| invalid("named type without a name".to_owned()))
+}
+
+fn unqualified(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+fn is_primitive(name: &str) -> bool {
+    [
+        "null", "boolean", "int", "long", "float", "double", "bytes", "string",
+    ]
+    .contains(&name)
+}
+
+struct Converter<'a> {
+    named: BTreeMap<&'a str, &'a Value>,
+    referenced: BTreeSet<&'a str>,
+    defs: BTreeMap<String, Schema>,
+    building: BTreeSet<String>,
+    approximations: Vec<Approximation>,
+}
+
+impl<'a> Converter<'a> {
+    fn approximate(&mut self, path: &[String], message: String) {
+        self.approximations
+            .push(Approximation::new(JsonPointer::new(path.to_vec()), message));
+    }
+
+    /// Find every named type, and every name which is referred to.
+    fn collect(&mut self, avro: &'a Value) -> Result<(), AvroError> {
+        match avro {
+            Value::String(name) => {
+                if !is_primitive(name) {
+                    self.referenced.insert(unqualified(name));
+                }
+            }
+            Value::Array(members) => {
+                for member in members {
+                    self.collect(member)?;
+                }
+            }
+            Value::Object(obj) => match obj.get("type") {
+                Some(Value::String(typ)) => match typ.as_str() {
+                    "record" | "error" | "enum" | "fixed" => {
+                        let name = simple_name(avro)?;
+                        if self.named.insert(name, avro).is_some() {
+                            return Err(invalid(format!("{} is already defined", name)));
+                        }
+
+                        if typ == "record" || typ == "error" {
+                            for field in fields(avro)? {
+                                self.collect(field_type(field)?)?;
+                            }
+                        }
+                    }
+                    "array" => self.collect(items(avro)?)?,
+                    "map" => self.collect(values(avro)?)?,
+                    _ => {}
+                },
+                Some(typ) => self.collect(typ)?,
+                None => return Err(invalid("schema without a type".to_owned())),
+            },
+            _ => return Err(invalid(format!("unexpected schema: {}", avro))),
+        }
+
+        Ok(())
+    }
+
+    /// Whether a named type becomes a definition.
+    fn is_def(&self, name: &str, avro: &Value) -> bool {
+        avro.get("jslTag").is_none()
+            && (avro.get("jslDefinition").is_some() || self.referenced.contains(name))
+    }
+
+    /// Ensure the named type `name` is a definition, and return the name of
+    /// the definition.
+    fn definition(&mut self, name: &str) -> Result<String, AvroError> {
+        let avro = *self
+            .named
+            .get(name)
+            .ok_or_else(|| invalid(format!("no such named type: {}", name)))?;
+
+        let def = avro
+            .get("jslDefinition")
+            .and_then(Value::as_str)
+            .unwrap_or(name)
+            .to_owned();
+
+        if !self.defs.contains_key(&def) && self.building.insert(def.clone()) {
+            let path = vec!["definitions".to_owned(), def.clone()];
+            let schema = self.named_inline(avro, &path)?;
+            self.defs.insert(def.clone(), schema);
+        }
+
+        Ok(def)
+    }
+
+    fn convert(&mut self, avro: &'a Value, path: &[String]) -> Result<Schema, AvroError> {
+        match avro {
+            Value::String(name) => match self.primitive(name, path) {
+                Some(form) => Ok(from_form(form)),
+                None => {
+                    let name = unqualified(name);
+                    let named = *self
+                        .named
+                        .get(name)
+                        .ok_or_else(|| invalid(format!("no such named type: {}", name)))?;
+
+                    if named.get("jslTag").is_some() {
+                        self.named_inline(named, path)
+                    } else {
+                        Ok(from_form(Form::Ref(self.definition(name)?)))
+                    }
+                }
+            },
+            Value::Array(members) => self.union(members.iter().collect(), path),
+            Value::Object(obj) => match obj.get("type") {
+                Some(Value::String(typ)) => match typ.as_str() {
+                    "record" | "error" | "enum" | "fixed" => {
+                        let name = simple_name(avro)?;
+                        if self.is_def(name, avro) {
+                            Ok(from_form(Form::Ref(self.definition(name)?)))
+                        } else {
+                            self.named_inline(avro, path)
+                        }
+                    }
+                    "array" => {
+                        let items = self.convert(items(avro)?, &child_path(path, &["elements"]))?;
+                        Ok(from_form(Form::Elements(items)))
+                    }
+                    "map" => {
+                        let values = self.convert(values(avro)?, &child_path(path, &["values"]))?;
+                        Ok(from_form(Form::Values(values)))
+                    }
+                    typ => self.annotated(obj, typ, path).map(from_form),
+                },
+                Some(typ) => self.convert(typ, path),
+                None => Err(invalid("schema without a type".to_owned())),
+            },
+            _ => Err(invalid(format!("unexpected schema: {}", avro))),
+        }
+    }
+
+    /// Convert a named type in place, rather than referring to it.
+    fn named_inline(&mut self, avro: &'a Value, path: &[String]) -> Result<Schema, AvroError> {
+        let schema = match avro["type"].as_str() {
+            Some("enum") => {
+                let renamed = avro.get("jslSymbols");
+                let symbols = avro
+                    .get("symbols")
+                    .and_then(Value::as_array)
+                    .ok_or_else(|| invalid("enum without symbols".to_owned()))?;
+
+                if symbols.is_empty() {
+                    return Err(invalid("enum without symbols".to_owned()));
+                }
+
+                let mut values = BTreeSet::new();
+                for symbol in symbols {
+                    let symbol = symbol
+                        .as_str()
+                        .ok_or_else(|| invalid("non-string enum symbol".to_owned()))?;
+                    let value = renamed
+                        .and_then(|renamed| renamed.get(symbol))
+                        .and_then(Value::as_str)
+                        .unwrap_or(symbol);
+                    if !values.insert(value.to_owned()) {
+                        return Err(invalid(format!("duplicate enum symbol: {}", value)));
+                    }
+                }
+
+                from_form(Form::Enum(values))
+            }
+            Some("fixed") => {
+                self.approximate(path, "fixed is represented as a string".to_owned());
+                from_form(Form::Type(Type::String))
+            }
+            _ => self.properties(avro, path)?,
+        };
+
+        Ok(with_doc(schema, avro))
+    }
+
+    fn properties(&mut self, avro: &'a Value, path: &[String]) -> Result<Schema, AvroError> {
+        let mut required = BTreeMap::new();
+        let mut optional = BTreeMap::new();
+        for field in fields(avro)? {
+            let name = field
+                .get("jslName")
+                .or_else(|| field.get("name"))
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid("field without a name".to_owned()))?;
+
+            let typ = field_type(field)?;
+            let is_optional = typ
+                .as_array()
+                .is_some_and(|members| members.contains(&json!("null")));
+
+            let path = property_path(path, name, is_optional);
+            let schema = if is_optional {
+                let members = typ.as_array().unwrap();
+                let rest: Vec<_> = members.iter().filter(|m| *m != "null").collect();
+                self.union(rest, &path)?
+            } else {
+                self.convert(typ, &path)?
+            };
+
+            let schema = with_doc(schema, field);
+            if required.contains_key(name) || optional.contains_key(name) {
+                return Err(invalid(format!("duplicate field: {}", name)));
+            }
+
+            if is_optional {
+                optional.insert(name.to_owned(), schema);
+            } else {
+                required.insert(name.to_owned(), schema);
+            }
+        }
+
+        Ok(from_form(Form::Properties(required, optional, true)))
+    }
+
+    fn union(&mut self, members: Vec<&'a Value>, path: &[String]) -> Result<Schema, AvroError> {
+        let rest: Vec<_> = members.iter().filter(|m| ***m != "null").cloned().collect();
+        if rest.len() != members.len() {
+            self.approximate(
+                path,
+                "null is only supported as an optional property".to_owned(),
+            );
+        }
+
+        if rest.len() == 1 {
+            return self.convert(rest[0], path);
+        }
+
+        // A union of records with tags is a discriminator.
+        let records: Option<Vec<&'a Value>> = rest
+            .iter()
+            .map(|member| {
+                let record = match member {
+                    Value::String(name) => *self.named.get(unqualified(name))?,
+                    member => *member,
+                };
+
+                record.get("jslTag").map(|_| record)
+            })
+            .collect();
+
+        let records = match records {
+            Some(ref records) if !records.is_empty() => records,
+            _ => {
+                self.approximate(
+                    path,
+                    "union is represented as the empty form, which accepts any value".to_owned(),
+                );
+                return Ok(from_form(Form::Empty));
+            }
+        };
+
+        match records[0].get("jslDefinition").and_then(Value::as_str) {
+            Some(def) => {
+                if !self.defs.contains_key(def) && self.building.insert(def.to_owned()) {
+                    let path = vec!["definitions".to_owned(), def.to_owned()];
+                    let schema = self.discriminator(records, &path)?;
+                    self.defs.insert(def.to_owned(), schema);
+                }
+
+                Ok(from_form(Form::Ref(def.to_owned())))
+            }
+            None => self.discriminator(records, path),
+        }
+    }
+
+    fn discriminator(
+        &mut self,
+        records: &[&'a Value],
+        path: &[String],
+    ) -> Result<Schema, AvroError> {
+        let tag = records[0]["jslTag"]
+            .as_str()
+            .ok_or_else(|| invalid("non-string jslTag".to_owned()))?;
+
+        let mut mapping = BTreeMap::new();
+        for record in records {
+            if record["jslTag"] != tag {
+                return Err(invalid(
+                    "union members have different jslTag values".to_owned(),
+                ));
+            }
+
+            let value = record
+                .get("jslTagValue")
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid("record without a jslTagValue".to_owned()))?;
+
+            let schema = self.named_inline(record, &mapping_path(path, value))?;
+            mapping.insert(value.to_owned(), schema);
+        }
+
+        Ok(from_form(Form::Discriminator(tag.to_owned(), mapping)))
+    }
+
+    /// Convert a primitive type, taking into account `jslType` and
+    /// `logicalType` attributes.
+    fn annotated(
+        &mut self,
+        avro: &Map<String, Value>,
+        typ: &str,
+        path: &[String],
+    ) -> Result<Form, AvroError> {
+        if let Some(jsl_type) = avro.get("jslType").and_then(Value::as_str) {
+            if jsl_type == "empty" {
+                return Ok(Form::Empty);
+            }
+
+            if let Some(jsl_type) = parse_type_name(jsl_type) {
+                return Ok(Form::Type(jsl_type));
+            }
+        }
+
+        match avro.get("logicalType").and_then(Value::as_str) {
+            Some("timestamp-millis") | Some("timestamp-micros") => {
+                return Ok(Form::Type(Type::Timestamp));
+            }
+            Some(logical_type) => {
+                self.approximate(
+                    path,
+                    format!(
+                        "logical type {} is represented as its underlying type",
+                        logical_type
+                    ),
+                );
+            }
+            None => {}
+        }
+
+        self.primitive(typ, path)
+            .ok_or_else(|| invalid(format!("unknown type: {}", typ)))
+    }
+
+    fn primitive(&mut self, typ: &str, path: &[String]) -> Option<Form> {
+        Some(match typ {
+            "null" => {
+                self.approximate(
+                    path,
+                    "null is represented as the empty form, which accepts any value".to_owned(),
+                );
+                Form::Empty
+            }
+            "boolean" => Form::Type(Type::Boolean),
+            "int" => Form::Type(Type::Int32),
+            "long" => Form::Type(Type::Int64),
+            "float" => Form::Type(Type::Float32),
+            "double" => Form::Type(Type::Float64),
+            "string" => Form::Type(Type::String),
+            "bytes" => {
+                self.approximate(path, "bytes are represented as a string".to_owned());
+                Form::Type(Type::String)
+            }
+            _ => return None,
+        })
+    }
+}
+
+fn fields(record: &Value) -> Result<&Vec<Value>, AvroError> {
+    record
+        .get("fields")
+        .and_then(Value::as_array)
+        .ok_or_else(|| invalid("record without fields".to_owned()))
+}
+
+fn field_type(field: &Value) -> Result<&Value, AvroError> {
+    field
+        .get("type")
+        .ok_or_else(|| invalid("field without a type".to_owned()))
+}
+
+fn items(array: &Value) -> Result<&Value, AvroError> {
+    array
+        .get("items")
+        .ok_or_else(|| invalid("array without items".to_owned()))
+}
+
+fn values(map: &Value) -> Result<&Value, AvroError> {
+    map.get("values")
+        .ok_or_else(|| invalid("map without values".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn schema(schema: Value) -> Schema {
+        Schema::from_serde(serde_json::from_value(schema).unwrap()).unwrap()
+    }
+
+    fn paths(approximations: &[Approximation]) -> Vec<String> {
+        approximations
+            .iter()
+            .map(|a| a.schema_path().to_string())
+            .collect()
+    }
+
+    fn user() -> Schema {
+        schema(json!({
+            "definitions": {
+                "event": {
+                    "discriminator": {
+                        "tag": "kind",
+                        "mapping": {
+                            "created": {
+                                "properties": {
+                                    "at": { "type": "timestamp" },
+                                },
+                            },
+                            "replied": {
+                                "properties": {
+                                    "reply": { "ref": "event" },
+                                },
+                            },
+                        },
+                    },
+                },
+                "status": {
+                    "description": "Whether the user is active.",
+                    "enum": ["ACTIVE", "on-hold"],
+                },
+                "unused": { "type": "string" },
+            },
+            "properties": {
+                "id": { "type": "uint64" },
+                "age": { "type": "int8" },
+                "status": { "ref": "status" },
+                "events": { "elements": { "ref": "event" } },
+                "scores": { "values": { "type": "float64" } },
+            },
+            "optionalProperties": {
+                "display-name": {
+                    "description": "What to call the user.",
+                    "type": "string",
+                },
+                "previous": { "ref": "status" },
+                "metadata": {},
+            },
+        }))
+    }
+
+    #[test]
+    fn generate() {
+        let mut config = Config::new();
+        config.root_name("User").namespace(Some("com.example"));
+        let output = Generator::new_with_config(config).generate(&user());
+
+        assert_eq!(
+            serde_json::from_str::<Value>(output.code()).unwrap(),
+            json!({
+                "type": "record",
+                "name": "User",
+                "namespace": "com.example",
+                "fields": [
+                    { "name": "age", "type": { "type": "int", "jslType": "int8" } },
+                    {
+                        "name": "display_name",
+                        "type": ["null", "string"],
+                        "default": null,
+                        "doc": "What to call the user.",
+                        "jslName": "display-name",
+                    },
+                    {
+                        "name": "events",
+                        "type": {
+                            "type": "array",
+                            "items": [
+                                {
+                                    "type": "record",
+                                    "name": "eventCreated",
+                                    "jslDefinition": "event",
+                                    "jslTag": "kind",
+                                    "jslTagValue": "created",
+                                    "fields": [
+                                        {
+                                            "name": "at",
+                                            "type": {
+                                                "type": "long",
+                                                "logicalType": "timestamp-micros",
+                                            },
+                                        },
+                                    ],
+                                },
+                                {
+                                    "type": "record",
+                                    "name": "eventReplied",
+                                    "jslDefinition": "event",
+                                    "jslTag": "kind",
+                                    "jslTagValue": "replied",
+                                    "fields": [
+                                        {
+                                            "name": "reply",
+                                            "type": ["eventCreated", "eventReplied"],
+                                        },
+                                    ],
+                                },
+                            ],
+                        },
+                    },
+                    { "name": "id", "type": { "type": "long", "jslType": "uint64" } },
+                    {
+                        "name": "metadata",
+                        "type": ["null", { "type": "string", "jslType": "empty" }],
+                        "default": null,
+                    },
+                    {
+                        "name": "previous",
+                        "type": [
+                            "null",
+                            {
+                                "type": "enum",
+                                "name": "status",
+                                "doc": "Whether the user is active.",
+                                "jslDefinition": "status",
+                                "symbols": ["ACTIVE", "on_hold"],
+                                "jslSymbols": { "on_hold": "on-hold" },
+                            },
+                        ],
+                        "default": null,
+                    },
+                    { "name": "scores", "type": { "type": "map", "values": "double" } },
+                    { "name": "status", "type": "status" },
+                ],
+            })
+        );
+
+        assert_eq!(
+            paths(output.approximations()),
+            vec![
+                "/properties/age",
+                "/optionalProperties/display-name",
+                "/definitions/event",
+                "/definitions/event/discriminator/mapping/created/properties/at",
+                "/properties/id",
+                "/optionalProperties/metadata",
+                "/definitions/status",
+                "/definitions/unused",
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let original = user();
+        let output = Generator::new().generate(&original);
+        let (parsed, _) = parse(&serde_json::from_str(output.code()).unwrap()).unwrap();
+
+        let mut defs = original.definitions().clone().unwrap();
+        defs.remove("unused");
+        let expected = Schema::from_parts(
+            Some(defs),
+            Box::new(original.form().clone()),
+            original.extra().clone(),
+        );
+
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn parse_avro() {
+        let (parsed, approximations) = parse(&json!({
+            "type": "record",
+            "name": "com.example.Node",
+            "doc": "A tree node.",
+            "fields": [
+                { "name": "label", "type": ["string", "null"] },
+                { "name": "children", "type": { "type": "array", "items": "com.example.Node" } },
+                { "name": "hash", "type": { "type": "fixed", "name": "Hash", "size": 16 } },
+                { "name": "data", "type": "bytes" },
+                {
+                    "name": "seen",
+                    "type": { "type": "long", "logicalType": "timestamp-millis" },
+                },
+                { "name": "day", "type": { "type": "int", "logicalType": "date" } },
+                { "name": "attrs", "type": { "type": "map", "values": ["int", "string"] } },
+                {
+                    "name": "color",
+                    "type": { "type": "enum", "name": "Color", "symbols": ["RED"] },
+                },
+            ],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            parsed,
+            schema(json!({
+                "definitions": {
+                    "Node": {
+                        "description": "A tree node.",
+                        "properties": {
+                            "children": { "elements": { "ref": "Node" } },
+                            "hash": { "type": "string" },
+                            "data": { "type": "string" },
+                            "seen": { "type": "timestamp" },
+                            "day": { "type": "int32" },
+                            "attrs": { "values": {} },
+                            "color": { "enum": ["RED"] },
+                        },
+                        "optionalProperties": {
+                            "label": { "type": "string" },
+                        },
+                    },
+                },
+                "description": "A tree node.",
+                "properties": {
+                    "children": { "elements": { "ref": "Node" } },
+                    "hash": { "type": "string" },
+                    "data": { "type": "string" },
+                    "seen": { "type": "timestamp" },
+                    "day": { "type": "int32" },
+                    "attrs": { "values": {} },
+                    "color": { "enum": ["RED"] },
+                },
+                "optionalProperties": {
+                    "label": { "type": "string" },
+                },
+            }))
+        );
+
+        // Each approximation is reported twice: once for the definition, and
+        // once for the root.
+        assert_eq!(
+            paths(&approximations),
+            vec![
+                "/definitions/Node/properties/hash",
+                "/definitions/Node/properties/data",
+                "/definitions/Node/properties/day",
+                "/definitions/Node/properties/attrs/values",
+                "/properties/hash",
+                "/properties/data",
+                "/properties/day",
+                "/properties/attrs/values",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            parse(
+                &json!({ "type": "record", "name": "A", "fields": [{ "name": "b", "type": "B" }] })
+            )
+            .unwrap_err(),
+            AvroError {
+                message: "no such named type: B".to_owned(),
+            }
+        );
+
+        assert_eq!(
+            parse(&json!({ "type": "record", "name": "A" })).unwrap_err(),
+            AvroError {
+                message: "record without fields".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_malformed() {
+        let error = |avro| parse(&avro).unwrap_err().message().to_owned();
+
+        assert_eq!(error(json!({ "type": "array" })), "array without items");
+        assert_eq!(
+            error(json!({ "type": "array", "items": { "type": "map" } })),
+            "map without values"
+        );
+        assert_eq!(
+            error(json!({
+                "type": "record",
+                "name": "A",
+                "fields": [{ "name": "b", "type": { "type": "array" } }],
+            })),
+            "array without items"
+        );
+        assert_eq!(
+            error(json!({ "type": "record", "name": "A", "fields": {} })),
+            "record without fields"
+        );
+        assert_eq!(
+            error(json!({ "type": "record", "name": "A", "fields": [{ "name": "b" }] })),
+            "field without a type"
+        );
+        assert_eq!(
+            error(json!({ "type": "record", "name": "A", "fields": [{ "type": "int" }] })),
+            "field without a name"
+        );
+        assert_eq!(
+            error(json!({ "type": "enum", "name": "E", "symbols": [] })),
+            "enum without symbols"
+        );
+        assert_eq!(
+            error(json!({ "type": "enum", "name": "E", "symbols": ["A", "B", "A"] })),
+            "duplicate enum symbol: A"
+        );
+        assert_eq!(
+            error(json!({
+                "type": "enum",
+                "name": "E",
+                "symbols": ["A", "B"],
+                "jslSymbols": { "B": "A" },
+            })),
+            "duplicate enum symbol: A"
+        );
+        assert_eq!(
+            error(json!({
+                "type": "record",
+                "name": "A",
+                "fields": [{
+                    "name": "b",
+                    "type": { "type": "record", "name": "A", "fields": [] },
+                }],
+            })),
+            "A is already defined"
+        );
+        assert_eq!(
+            error(json!([
+                { "type": "enum", "name": "A", "symbols": ["X"] },
+                { "type": "fixed", "name": "A", "size": 4 },
+            ])),
+            "A is already defined"
+        );
+    }
+}
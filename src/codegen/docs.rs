@@ -0,0 +1,578 @@
+//! Generate human-readable documentation from schemas.
+//!
+//! The output is a single Markdown or HTML document, with one section for
+//! the root schema and one for each definition. Properties are documented in
+//! a table of their type, whether they are required, and their description.
+//! Enums list their values, and discriminators list their variants. Every
+//! `ref` links to the section for the definition it refers to.
+//!
+//! Nested objects, and the variants of a discriminator, get a subsection of
+//! their own, directly after the section they appear in.
+//!
+//! Descriptions are taken from the `description` in each schema's extra
+//! data. They are included as-is in Markdown output, and escaped in HTML
+//! output.
+
+use crate::codegen::{description, sorted_properties, type_name, Names};
+use crate::schema::{Form, Schema};
+use std::collections::BTreeMap;
+
+/// Generates documentation from schemas.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate documentation for a root schema.
+    pub fn generate(&self, schema: &Schema) -> String {
+        let no_defs = BTreeMap::new();
+        let defs = schema.definitions().as_ref().unwrap_or(&no_defs);
+
+        let mut ids = Names::default();
+        ids.claim("root");
+        let def_ids = defs
+            .keys()
+            .map(|name| {
+                (
+                    name.as_str(),
+                    ids.claim(&format!("definitions-{}", slug(name))),
+                )
+            })
+            .collect();
+
+        let mut state = State {
+            def_ids,
+            ids,
+            sections: Vec::new(),
+        };
+
+        if schema.form() != &Form::Empty || description(schema).is_some() {
+            state.section("root", "Root".to_owned(), 2, schema);
+        }
+
+        for (name, sub_schema) in defs {
+            let id = state.def_ids[name.as_str()].clone();
+            state.section(&id, name.clone(), 2, sub_schema);
+        }
+
+        match self.config.format {
+            Format::Markdown => markdown(&self.config.title, &state.sections),
+            Format::Html => html(&self.config.title, &state.sections),
+        }
+    }
+}
+
+/// The format of generated documentation.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Format {
+    /// A Markdown document, using GitHub-flavored tables.
+    Markdown,
+
+    /// A standalone HTML page.
+    Html,
+}
+
+/// Configuration for how documentation should be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    title: String,
+    format: Format,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the title of the document. The default is `Schema`.
+    pub fn title(&mut self, title: &str) -> &mut Self {
+        self.title = title.to_owned();
+        self
+    }
+
+    /// Sets the format of the document. The default is `Format::Markdown`.
+    pub fn format(&mut self, format: Format) -> &mut Self {
+        self.format = format;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            title: "Schema".to_owned(),
+            format: Format::Markdown,
+        }
+    }
+}
+
+/// A piece of text within a block.
+enum Inline {
+    Text(String),
+    Code(String),
+    Link(String, String),
+}
+
+enum Block {
+    Paragraph(Vec<Inline>),
+    List(Vec<Vec<Inline>>),
+    Table(Vec<&'static str>, Vec<Vec<Vec<Inline>>>),
+}
+
+struct Section {
+    id: String,
+    title: String,
+    level: usize,
+    description: Option<String>,
+    blocks: Vec<Block>,
+}
+
+struct State<'a> {
+    def_ids: BTreeMap<&'a str, String>,
+    ids: Names,
+    sections: Vec<Section>,
+}
+
+impl<'a> State<'a> {
+    /// Document a schema in a section of its own, followed by subsections for
+    /// any nested objects.
+    fn section(&mut self, id: &str, title: String, level: usize, schema: &'a Schema) {
+        let index = self.sections.len();
+        self.sections.push(Section {
+            id: id.to_owned(),
+            title: title.clone(),
+            level,
+            description: description(schema).map(str::to_owned),
+            blocks: Vec::new(),
+        });
+
+        let sub_level = (level + 1).min(6);
+        let blocks = match schema.form() {
+            Form::Properties(required, optional, _) => {
+                vec![self.properties(id, &title, sub_level, required, optional)]
+            }
+            Form::Enum(values) => {
+                let values: Vec<_> = values.iter().collect();
+                vec![
+                    Block::Paragraph(vec![Inline::Text("One of:".to_owned())]),
+                    Block::List(
+                        values
+                            .into_iter()
+                            .map(|value| vec![Inline::Code(string_literal(value))])
+                            .collect(),
+                    ),
+                ]
+            }
+            Form::Discriminator(tag, mapping) => {
+                let mut rows = Vec::new();
+                for (value, sub_schema) in mapping {
+                    let variant_id = self.ids.claim(&format!("{}-{}", id, slug(value)));
+                    let variant_title = format!("{} ({})", title, value);
+                    rows.push(vec![
+                        vec![Inline::Code(string_literal(value))],
+                        vec![Inline::Link(variant_title.clone(), variant_id.clone())],
+                        description(sub_schema)
+                            .map(|d| vec![Inline::Text(d.to_owned())])
+                            .unwrap_or_default(),
+                    ]);
+
+                    self.section(&variant_id, variant_title, sub_level, sub_schema);
+                }
+
+                vec![
+                    Block::Paragraph(vec![
+                        Inline::Text("An object, whose variant is determined by the ".to_owned()),
+                        Inline::Code(tag.clone()),
+                        Inline::Text(" property:".to_owned()),
+                    ]),
+                    Block::Table(vec!["Value", "Variant", "Description"], rows),
+                ]
+            }
+            _ => {
+                let mut inlines = vec![Inline::Text("Type: ".to_owned())];
+                inlines.extend(self.type_inlines(id, &title, "", sub_level, schema));
+                vec![Block::Paragraph(inlines)]
+            }
+        };
+
+        self.sections[index].blocks = blocks;
+    }
+
+    fn properties(
+        &mut self,
+        id: &str,
+        title: &str,
+        sub_level: usize,
+        required: &'a BTreeMap<String, Schema>,
+        optional: &'a BTreeMap<String, Schema>,
+    ) -> Block {
+        let properties = sorted_properties(required, optional);
+        if properties.is_empty() {
+            return Block::Paragraph(vec![Inline::Text(
+                "An object with no properties.".to_owned(),
+            )]);
+        }
+
+        let rows = properties
+            .into_iter()
+            .map(|(property, sub_schema, is_optional)| {
+                vec![
+                    vec![Inline::Code(property.clone())],
+                    self.type_inlines(id, title, property, sub_level, sub_schema),
+                    vec![Inline::Text(
+                        if is_optional { "no" } else { "yes" }.to_owned(),
+                    )],
+                    description(sub_schema)
+                        .map(|d| vec![Inline::Text(d.to_owned())])
+                        .unwrap_or_default(),
+                ]
+            })
+            .collect();
+
+        Block::Table(vec!["Property", "Type", "Required", "Description"], rows)
+    }
+
+    /// Describe the type of a schema, as it appears in a table or paragraph.
+    /// Nested objects get a subsection, named after `property`.
+    fn type_inlines(
+        &mut self,
+        id: &str,
+        title: &str,
+        property: &str,
+        sub_level: usize,
+        schema: &'a Schema,
+    ) -> Vec<Inline> {
+        match schema.form() {
+            Form::Empty => vec![Inline::Text("any value".to_owned())],
+            Form::Ref(def) => vec![Inline::Link(
+                def.clone(),
+                self.def_ids[def.as_str()].clone(),
+            )],
+            Form::Type(typ) => vec![Inline::Code(type_name(typ).to_owned())],
+            Form::Enum(values) => {
+                let values: Vec<_> = values.iter().collect();
+
+                let mut inlines = vec![Inline::Text("one of ".to_owned())];
+                for (i, value) in values.into_iter().enumerate() {
+                    if i > 0 {
+                        inlines.push(Inline::Text(", ".to_owned()));
+                    }
+                    inlines.push(Inline::Code(string_literal(value)));
+                }
+                inlines
+            }
+            Form::Elements(sub_schema) => {
+                let mut inlines = vec![Inline::Text("array of ".to_owned())];
+                let property = format!("{}[]", property);
+                inlines.extend(self.type_inlines(id, title, &property, sub_level, sub_schema));
+                inlines
+            }
+            Form::Values(sub_schema) => {
+                let mut inlines = vec![Inline::Text("map of ".to_owned())];
+                let property = format!("{}{{}}", property);
+                inlines.extend(self.type_inlines(id, title, &property, sub_level, sub_schema));
+                inlines
+            }
+            Form::Properties(..) | Form::Discriminator(..) => {
+                let sub_id = self.ids.claim(&format!("{}-{}", id, slug(property)));
+                let sub_title = format!("{}.{}", title, property);
+                self.section(&sub_id, sub_title.clone(), sub_level, schema);
+                vec![Inline::Link(sub_title, sub_id)]
+            }
+        }
+    }
+}
+
+/// Make a string usable in an HTML id, and a URL fragment.
+fn slug(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        if c.is_alphanumeric() {
+            out.extend(c.to_lowercase());
+        } else if !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+
+    out.trim_matches('-').to_owned()
+}
+
+fn string_literal(value: &str) -> String {
+    serde_json::to_string(value).expect("error serializing string")
+}
+
+fn markdown(title: &str, sections: &[Section]) -> String {
+    let mut out = format!("# {}\n", title);
+    for section in sections {
+        out.push_str(&format!(
+            "\n<a id=\"{}\"></a>\n{} {}\n",
+            section.id,
+            "#".repeat(section.level),
+            section.title
+        ));
+
+        if let Some(ref description) = section.description {
+            out.push_str(&format!("\n{}\n", description));
+        }
+
+        for block in &section.blocks {
+            out.push('\n');
+            match block {
+                Block::Paragraph(inlines) => {
+                    out.push_str(&format!("{}\n", markdown_inlines(inlines)));
+                }
+                Block::List(items) => {
+                    for item in items {
+                        out.push_str(&format!("- {}\n", markdown_inlines(item)));
+                    }
+                }
+                Block::Table(header, rows) => {
+                    out.push_str(&format!("| {} |\n", header.join(" | ")));
+                    out.push_str(&format!("|{}\n", " --- |".repeat(header.len())));
+                    for row in rows {
+                        for cell in row {
+                            let text = markdown_inlines(cell)
+                                .replace('|', "\\|")
+                                .replace('\n', " ");
+                            if text.is_empty() {
+                                out.push_str("| ");
+                            } else {
+                                out.push_str(&format!("| {} ", text));
+                            }
+                        }
+                        out.push_str("|\n");
+                    }
+                }
+            }
+        }
+    }
+
+    out
+}
+
+fn markdown_inlines(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) => text.clone(),
+            Inline::Code(code) => {
+                if code.contains('`') {
+                    format!("`` {} ``", code)
+                } else {
+                    format!("`{}`", code)
+                }
+            }
+            Inline::Link(text, id) => format!("[{}](#{})", text, id),
+        })
+        .collect()
+}
+
+fn html(title: &str, sections: &[Section]) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n</head>\n<body>\n<h1>{0}</h1>\n",
+        escape(title)
+    );
+
+    for section in sections {
+        out.push_str(&format!(
+            "<h{0} id=\"{1}\">{2}</h{0}>\n",
+            section.level,
+            section.id,
+            escape(&section.title)
+        ));
+
+        if let Some(ref description) = section.description {
+            out.push_str(&format!("<p>{}</p>\n", escape(description)));
+        }
+
+        for block in &section.blocks {
+            match block {
+                Block::Paragraph(inlines) => {
+                    out.push_str(&format!("<p>{}</p>\n", html_inlines(inlines)));
+                }
+                Block::List(items) => {
+                    out.push_str("<ul>\n");
+                    for item in items {
+                        out.push_str(&format!("<li>{}</li>\n", html_inlines(item)));
+                    }
+                    out.push_str("</ul>\n");
+                }
+                Block::Table(header, rows) => {
+                    out.push_str("<table>\n<thead>\n<tr>");
+                    for cell in header {
+                        out.push_str(&format!("<th>{}</th>", cell));
+                    }
+                    out.push_str("</tr>\n</thead>\n<tbody>\n");
+                    for row in rows {
+                        out.push_str("<tr>");
+                        for cell in row {
+                            out.push_str(&format!("<td>{}</td>", html_inlines(cell)));
+                        }
+                        out.push_str("</tr>\n");
+                    }
+                    out.push_str("</tbody>\n</table>\n");
+                }
+            }
+        }
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn html_inlines(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) => escape(text),
+            Inline::Code(code) => format!("<code>{}</code>", escape(code)),
+            Inline::Link(text, id) => format!("<a href=\"#{}\">{}</a>", id, escape(text)),
+        })
+        .collect()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn user() -> Schema {
+        Schema::from_serde(
+            serde_json::from_value(json!({
+                "definitions": {
+                    "event": {
+                        "description": "Something that happened.",
+                        "discriminator": {
+                            "tag": "kind",
+                            "mapping": {
+                                "created": {
+                                    "properties": {
+                                        "at": { "type": "timestamp" },
+                                    },
+                                },
+                                "deleted": { "properties": {} },
+                            },
+                        },
+                    },
+                    "status": { "enum": ["INACTIVE", "ACTIVE"] },
+                },
+                "description": "A user.",
+                "properties": {
+                    "id": { "type": "uint64", "description": "The user's ID." },
+                    "status": { "ref": "status" },
+                    "events": { "elements": { "ref": "event" } },
+                },
+                "optionalProperties": {
+                    "location": {
+                        "properties": {
+                            "lat": { "type": "float64" },
+                        },
+                    },
+                    "scores": { "values": { "type": "int8" } },
+                },
+            }))
+            .unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn markdown() {
+        let mut config = Config::new();
+        config.title("Users");
+
+        assert_eq!(
+            Generator::new_with_config(config).generate(&user()),
+            r##"# Users
+
+<a id="root"></a>
+## Root
+
+A user.
+
+| Property | Type | Required | Description |
+| --- | --- | --- | --- |
+| `events` | array of [event](#definitions-event) | yes | |
+| `id` | `uint64` | yes | The user's ID. |
+| `location` | [Root.location](#root-location) | no | |
+| `scores` | map of `int8` | no | |
+| `status` | [status](#definitions-status) | yes | |
+
+<a id="root-location"></a>
+### Root.location
+
+| Property | Type | Required | Description |
+| --- | --- | --- | --- |
+| `lat` | `float64` | yes | |
+
+<a id="definitions-event"></a>
+## event
+
+Something that happened.
+
+An object, whose variant is determined by the `kind` property:
+
+| Value | Variant | Description |
+| --- | --- | --- |
+| `"created"` | [event (created)](#definitions-event-created) | |
+| `"deleted"` | [event (deleted)](#definitions-event-deleted) | |
+
+<a id="definitions-event-created"></a>
+### event (created)
+
+| Property | Type | Required | Description |
+| --- | --- | --- | --- |
+| `at` | `timestamp` | yes | |
+
+<a id="definitions-event-deleted"></a>
+### event (deleted)
+
+An object with no properties.
+
+<a id="definitions-status"></a>
+## status
+
+One of:
+
+- `"ACTIVE"`
+- `"INACTIVE"`
+"##
+        );
+    }
+
+    #[test]
+    fn html() {
+        let mut config = Config::new();
+        config.format(Format::Html);
+        let html = Generator::new_with_config(config).generate(&user());
+
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains("<h2 id=\"root\">Root</h2>\n<p>A user.</p>\n"));
+        assert!(html.contains(
+            "<tr><td><code>id</code></td><td><code>uint64</code></td><td>yes</td><td>The user's ID.</td></tr>"
+        ));
+        assert!(html.contains("<a href=\"#definitions-status\">status</a>"));
+        assert!(html.contains("<li><code>&quot;ACTIVE&quot;</code></li>"));
+        assert!(html.ends_with("</body>\n</html>\n"));
+    }
+}
@@ -0,0 +1,651 @@
+//! Generate GraphQL schema definitions (SDL) from schemas.
+//!
+//! Every definition of the properties, enum, or discriminator form becomes a
+//! named GraphQL type, as does the root schema if it has one of those forms.
+//! Other definitions are inlined wherever they are used. The mapping is:
+//!
+//! * Properties forms become object types, or input types if
+//!   [`Config::input`](struct.Config.html#method.input) is set. Required
+//!   properties are non-null fields, and optional properties are nullable.
+//! * Enum forms become enums.
+//! * Discriminator forms become unions or interfaces of object types, one per
+//!   mapping, depending on the configured
+//!   [`DiscriminatorStyle`](enum.DiscriminatorStyle.html). Input types cannot
+//!   be unions or interfaces, so input discriminators become `@oneOf` input
+//!   types instead.
+//!   A discriminator with an empty mapping would be an empty union, which
+//!   GraphQL does not allow, so it becomes the custom JSON scalar instead.
+//! * Elements forms become lists.
+//! * Values forms and the empty form become a custom JSON scalar, as GraphQL
+//!   has no map or "any" type.
+//!
+//! GraphQL's `Int` is a signed 32-bit integer, and it has no timestamp type,
+//! so some JSL types are only approximated. Every approximation is reported
+//! in the generator's [`Output`](../struct.Output.html).
+
+use crate::codegen::{
+    c_identifier, child_path, description, mapping_path, pascal_case, property_path,
+    sorted_properties, type_name, Approximation, Names, Output,
+};
+use crate::pointer::JsonPointer;
+use crate::schema::{Form, Schema, Type};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Generates GraphQL SDL from schemas.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate GraphQL SDL for a root schema.
+    pub fn generate(&self, schema: &Schema) -> Output {
+        let no_defs = BTreeMap::new();
+        let defs = schema.definitions().as_ref().unwrap_or(&no_defs);
+
+        let mut names = Names::default();
+        let root_name = if is_named(schema) {
+            Some(names.claim(&type_identifier(&self.config.root_name)))
+        } else {
+            None
+        };
+
+        let def_names = defs
+            .iter()
+            .filter(|(_, sub_schema)| is_named(sub_schema))
+            .map(|(name, _)| (name.as_str(), names.claim(&type_identifier(name))))
+            .collect();
+
+        let mut state = State {
+            config: &self.config,
+            defs,
+            def_names,
+            names,
+            inlining: BTreeSet::new(),
+            uses_json: false,
+            items: Vec::new(),
+            approximations: Vec::new(),
+        };
+
+        if let Some(root_name) = root_name {
+            state.named_type(schema, &root_name, &[]);
+        }
+
+        for (name, sub_schema) in defs {
+            if let Some(type_name) = state.def_names.get(name.as_str()).cloned() {
+                let path = vec!["definitions".to_owned(), name.clone()];
+                state.named_type(sub_schema, &type_name, &path);
+            }
+        }
+
+        if state.uses_json {
+            state
+                .items
+                .insert(0, format!("scalar {}\n", self.config.json_scalar));
+        }
+
+        Output::new(state.items.join("\n"), state.approximations)
+    }
+}
+
+/// How discriminator forms are represented in GraphQL.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum DiscriminatorStyle {
+    /// A union of the object types for each mapping.
+    Union,
+
+    /// An interface with just the tag field, which the object types for each
+    /// mapping implement.
+    Interface,
+}
+
+/// Configuration for how GraphQL SDL should be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    root_name: String,
+    json_scalar: String,
+    input: bool,
+    discriminator_style: DiscriminatorStyle,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the type generated for the root schema. The default
+    /// is `Root`.
+    pub fn root_name(&mut self, root_name: &str) -> &mut Self {
+        self.root_name = root_name.to_owned();
+        self
+    }
+
+    /// Sets the name of the custom scalar used for values forms and the empty
+    /// form. The default is `JSON`.
+    pub fn json_scalar(&mut self, json_scalar: &str) -> &mut Self {
+        self.json_scalar = json_scalar.to_owned();
+        self
+    }
+
+    /// Sets whether to generate input types rather than object types. The
+    /// default is false.
+    pub fn input(&mut self, input: bool) -> &mut Self {
+        self.input = input;
+        self
+    }
+
+    /// Sets how discriminators are represented in object types. The default
+    /// is `DiscriminatorStyle::Union`.
+    pub fn discriminator_style(&mut self, style: DiscriminatorStyle) -> &mut Self {
+        self.discriminator_style = style;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            root_name: "Root".to_owned(),
+            json_scalar: "JSON".to_owned(),
+            input: false,
+            discriminator_style: DiscriminatorStyle::Union,
+        }
+    }
+}
+
+/// Whether a schema becomes a named GraphQL type.
+fn is_named(schema: &Schema) -> bool {
+    match schema.form() {
+        Form::Properties(..) | Form::Enum(_) => true,
+        Form::Discriminator(_, mapping) => !mapping.is_empty(),
+        _ => false,
+    }
+}
+
+struct State<'a> {
+    config: &'a Config,
+    defs: &'a BTreeMap<String, Schema>,
+    def_names: BTreeMap<&'a str, String>,
+    names: Names,
+    inlining: BTreeSet<&'a str>,
+    uses_json: bool,
+    items: Vec<String>,
+    approximations: Vec<Approximation>,
+}
+
+impl<'a> State<'a> {
+    fn approximate(&mut self, path: &[String], message: String) {
+        self.approximations
+            .push(Approximation::new(JsonPointer::new(path.to_vec()), message));
+    }
+
+    /// Emit a named type for a properties, enum, or discriminator form.
+    fn named_type(&mut self, schema: &'a Schema, name: &str, path: &[String]) {
+        let index = self.items.len();
+        self.items.push(String::new());
+
+        let mut item = block_description("", description(schema));
+        match schema.form() {
+            Form::Enum(values) => {
+                let values: Vec<_> = values.iter().collect();
+
+                let mut used = Names::default();
+                item.push_str(&format!("enum {} {{\n", name));
+                for value in values {
+                    let mut ident = c_identifier(value);
+                    if ["true", "false", "null"].contains(&ident.as_str()) {
+                        ident.push('_');
+                    }
+
+                    let ident = used.claim(&ident);
+                    if &ident != value {
+                        self.approximate(
+                            path,
+                            format!("enum value {:?} is renamed to {}", value, ident),
+                        );
+                    }
+
+                    item.push_str(&format!("  {}\n", ident));
+                }
+                item.push_str("}\n");
+            }
+            Form::Properties(..) => {
+                item.push_str(&self.object(schema, name, path, None));
+            }
+            Form::Discriminator(tag, mapping) => {
+                let mut variants = Vec::new();
+                for (value, sub_schema) in mapping {
+                    let variant = self.names.claim(&format!("{}{}", name, pascal_case(value)));
+                    variants.push((value, sub_schema, variant));
+                }
+
+                if self.config.input {
+                    self.approximate(
+                        path,
+                        format!(
+                            "input types cannot be unions, so the discriminator on {:?} is a @oneOf input type, where the tag is implied by which field is set",
+                            tag
+                        ),
+                    );
+
+                    let mut fields = Names::default();
+                    item.push_str(&format!("input {} @oneOf {{\n", name));
+                    for (value, _, variant) in &variants {
+                        let field = fields.claim(&c_identifier(value));
+                        item.push_str(&format!("  {}: {}\n", field, variant));
+                    }
+                    item.push_str("}\n");
+                } else {
+                    self.approximate(
+                        path,
+                        format!(
+                            "the discriminator tag {:?} is represented as String, rather than an enum of its mapping values",
+                            tag
+                        ),
+                    );
+
+                    match self.config.discriminator_style {
+                        DiscriminatorStyle::Union => {
+                            let members: Vec<_> = variants.iter().map(|v| v.2.as_str()).collect();
+                            item.push_str(&format!("union {} = {}\n", name, members.join(" | ")));
+                        }
+                        DiscriminatorStyle::Interface => {
+                            item.push_str(&format!(
+                                "interface {} {{\n  {}: String!\n}}\n",
+                                name,
+                                c_identifier(tag)
+                            ));
+                        }
+                    }
+                }
+
+                let tag = if self.config.input { None } else { Some(tag) };
+                for (value, sub_schema, variant) in variants {
+                    let index = self.items.len();
+                    self.items.push(String::new());
+
+                    let path = mapping_path(path, value);
+                    let mut variant_item = block_description("", description(sub_schema));
+                    variant_item.push_str(&self.object(
+                        sub_schema,
+                        &variant,
+                        &path,
+                        tag.map(|tag| (tag.as_str(), name)),
+                    ));
+                    self.items[index] = variant_item;
+                }
+            }
+            _ => unreachable!("only properties, enum, and discriminator forms are named"),
+        }
+
+        self.items[index] = item;
+    }
+
+    /// Render an object (or input) type for a properties form. Variants of a
+    /// discriminator have a tag field, and the name of their discriminator's
+    /// type.
+    fn object(
+        &mut self,
+        schema: &'a Schema,
+        name: &str,
+        path: &[String],
+        tag: Option<(&str, &str)>,
+    ) -> String {
+        let (required, optional) = match schema.form() {
+            Form::Properties(required, optional, _) => (required, optional),
+            _ => unreachable!("objects are only made from properties forms"),
+        };
+
+        let mut out = if self.config.input {
+            format!("input {}", name)
+        } else {
+            format!("type {}", name)
+        };
+
+        let mut fields = Names::default();
+        let mut body = String::new();
+        if let Some((tag, parent)) = tag {
+            if self.config.discriminator_style == DiscriminatorStyle::Interface {
+                out.push_str(&format!(" implements {}", parent));
+            }
+
+            body.push_str(&format!(
+                "  {}: String!\n",
+                fields.claim(&c_identifier(tag))
+            ));
+        }
+
+        for (property, sub_schema, is_optional) in sorted_properties(required, optional) {
+            let path = property_path(path, property, is_optional);
+            let field = fields.claim(&c_identifier(property));
+            if &field != property {
+                self.approximate(
+                    &path,
+                    format!("property {:?} is renamed to {}", property, field),
+                );
+            }
+
+            let hint = format!("{}{}", name, pascal_case(property));
+            let typ = self.type_ref(sub_schema, &hint, &path);
+            let bang = if is_optional { "" } else { "!" };
+
+            body.push_str(&block_description("  ", description(sub_schema)));
+            body.push_str(&format!("  {}: {}{}\n", field, typ, bang));
+        }
+
+        if body.is_empty() {
+            self.approximate(
+                path,
+                "GraphQL types must have a field, so a placeholder `_: Boolean` is added"
+                    .to_owned(),
+            );
+            body.push_str("  _: Boolean\n");
+        }
+
+        out.push_str(&format!(" {{\n{}}}\n", body));
+        out
+    }
+
+    /// The GraphQL type reference for a schema, without its non-null marker.
+    fn type_ref(&mut self, schema: &'a Schema, hint: &str, path: &[String]) -> String {
+        match schema.form() {
+            Form::Empty => self.json_scalar(path, "the empty form"),
+            Form::Ref(def) => {
+                if let Some(name) = self.def_names.get(def.as_str()) {
+                    return name.clone();
+                }
+
+                let defs = self.defs;
+                let (def, sub_schema) = defs
+                    .get_key_value(def.as_str())
+                    .expect("reference to non-existent definition");
+
+                if !self.inlining.insert(def) {
+                    self.approximate(
+                        path,
+                        format!(
+                            "definition {:?} is recursive without passing through a named type",
+                            def
+                        ),
+                    );
+                    return self.json_scalar(path, "the recursive reference");
+                }
+
+                let def_path = vec!["definitions".to_owned(), def.clone()];
+                let typ = self.type_ref(sub_schema, &type_identifier(def), &def_path);
+                self.inlining.remove(def.as_str());
+                typ
+            }
+            Form::Type(typ) => self.scalar(typ, path),
+            Form::Discriminator(_, mapping) if mapping.is_empty() => {
+                self.json_scalar(path, "the discriminator with an empty mapping")
+            }
+            Form::Enum(_) | Form::Properties(..) | Form::Discriminator(..) => {
+                let name = self.names.claim(hint);
+                self.named_type(schema, &name, path);
+                name
+            }
+            Form::Elements(sub_schema) => {
+                let path = child_path(path, &["elements"]);
+                format!(
+                    "[{}!]",
+                    self.type_ref(sub_schema, &format!("{}Item", hint), &path)
+                )
+            }
+            Form::Values(_) => self.json_scalar(path, "the values form"),
+        }
+    }
+
+    fn json_scalar(&mut self, path: &[String], what: &str) -> String {
+        self.uses_json = true;
+        self.approximate(
+            path,
+            format!(
+                "{} is represented by the {} scalar",
+                what, self.config.json_scalar
+            ),
+        );
+        self.config.json_scalar.clone()
+    }
+
+    fn scalar(&mut self, typ: &Type, path: &[String]) -> String {
+        let graphql = match typ {
+            Type::Boolean => "Boolean",
+            Type::Int8 | Type::Uint8 | Type::Int16 | Type::Uint16 | Type::Int32 => "Int",
+            Type::Number | Type::Float32 | Type::Float64 => "Float",
+            Type::Uint32 | Type::Int64 | Type::Uint64 => "Float",
+            Type::String | Type::Timestamp => "String",
+        };
+
+        match typ {
+            Type::Int8 | Type::Uint8 | Type::Int16 | Type::Uint16 => {
+                self.approximate(path, format!("{} is widened to Int", type_name(typ)));
+            }
+            Type::Uint32 | Type::Int64 | Type::Uint64 => {
+                self.approximate(
+                    path,
+                    format!(
+                        "{} does not fit in Int, so it is represented as Float",
+                        type_name(typ)
+                    ),
+                );
+            }
+            Type::Timestamp => {
+                self.approximate(path, "timestamp is represented as String".to_owned());
+            }
+            _ => {}
+        }
+
+        graphql.to_owned()
+    }
+}
+
+/// Make a string usable as a GraphQL type name.
+fn type_identifier(name: &str) -> String {
+    c_identifier(&pascal_case(name))
+}
+
+/// Render a GraphQL description, as a block string.
+fn block_description(indent: &str, description: Option<&str>) -> String {
+    let description = match description {
+        Some(description) => description.replace("\"\"\"", "\\\"\"\""),
+        None => return String::new(),
+    };
+
+    if description.contains('\n') {
+        let mut out = format!("{}\"\"\"\n", indent);
+        for line in description.lines() {
+            if line.is_empty() {
+                out.push('\n');
+            } else {
+                out.push_str(&format!("{}{}\n", indent, line));
+            }
+        }
+        out.push_str(&format!("{}\"\"\"\n", indent));
+        out
+    } else {
+        format!("{}\"\"\"{}\"\"\"\n", indent, description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn generate(config: &Config) -> Output {
+        let schema = Schema::from_serde(
+            serde_json::from_value(json!({
+                "definitions": {
+                    "event": {
+                        "description": "Something that happened.",
+                        "discriminator": {
+                            "tag": "kind",
+                            "mapping": {
+                                "created": {
+                                    "properties": {
+                                        "at": { "type": "timestamp" },
+                                    },
+                                },
+                                "deleted": { "properties": {} },
+                            },
+                        },
+                    },
+                    "status": { "enum": ["ACTIVE", "on-hold"] },
+                    "tags": { "elements": { "type": "string" } },
+                },
+                "properties": {
+                    "id": { "type": "uint64" },
+                    "status": { "ref": "status" },
+                    "events": { "elements": { "ref": "event" } },
+                    "tags": { "ref": "tags" },
+                },
+                "optionalProperties": {
+                    "name": {
+                        "description": "The user's name.",
+                        "type": "string",
+                    },
+                    "scores": { "values": { "type": "int32" } },
+                    "location": {
+                        "properties": {
+                            "lat": { "type": "float64" },
+                        },
+                    },
+                },
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+
+        Generator::new_with_config(config.clone()).generate(&schema)
+    }
+
+    fn paths(output: &Output) -> Vec<String> {
+        output
+            .approximations()
+            .iter()
+            .map(|a| a.schema_path().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn object_types() {
+        let mut config = Config::new();
+        config.root_name("user").json_scalar("JSONObject");
+        let output = generate(&config);
+
+        assert_eq!(
+            output.code(),
+            r#"scalar JSONObject
+
+type User {
+  events: [Event!]!
+  id: Float!
+  location: UserLocation
+  """The user's name."""
+  name: String
+  scores: JSONObject
+  status: Status!
+  tags: [String!]!
+}
+
+type UserLocation {
+  lat: Float!
+}
+
+"""Something that happened."""
+union Event = EventCreated | EventDeleted
+
+type EventCreated {
+  kind: String!
+  at: String!
+}
+
+type EventDeleted {
+  kind: String!
+}
+
+enum Status {
+  ACTIVE
+  on_hold
+}
+"#
+        );
+
+        assert_eq!(
+            paths(&output),
+            vec![
+                "/properties/id",
+                "/optionalProperties/scores",
+                "/definitions/event",
+                "/definitions/event/discriminator/mapping/created/properties/at",
+                "/definitions/status",
+            ]
+        );
+    }
+
+    #[test]
+    fn interfaces() {
+        let mut config = Config::new();
+        config.discriminator_style(DiscriminatorStyle::Interface);
+        let code = generate(&config).code().to_owned();
+
+        assert!(code.contains("interface Event {\n  kind: String!\n}\n"));
+        assert!(code.contains("type EventCreated implements Event {\n  kind: String!\n"));
+    }
+
+    #[test]
+    fn input_types() {
+        let mut config = Config::new();
+        config.input(true);
+        let output = generate(&config);
+        let code = output.code();
+
+        assert!(code.contains("input Root {\n"));
+        assert!(code.contains(
+            "input Event @oneOf {\n  created: EventCreated\n  deleted: EventDeleted\n}\n"
+        ));
+        assert!(code.contains("input EventCreated {\n  at: String!\n}\n"));
+        assert!(code.contains("input EventDeleted {\n  _: Boolean\n}\n"));
+        assert!(
+            paths(&output).contains(&"/definitions/event/discriminator/mapping/deleted".to_owned())
+        );
+    }
+
+    #[test]
+    fn empty_discriminator() {
+        let schema = Schema::from_serde(
+            serde_json::from_value(json!({
+                "definitions": {
+                    "nothing": {
+                        "discriminator": { "tag": "kind", "mapping": {} },
+                    },
+                },
+                "properties": {
+                    "nothing": { "ref": "nothing" },
+                },
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+
+        let output = Generator::new().generate(&schema);
+        assert_eq!(
+            output.code(),
+            "scalar JSON\n\ntype Root {\n  nothing: JSON!\n}\n"
+        );
+        assert_eq!(paths(&output), vec!["/definitions/nothing"]);
+    }
+}
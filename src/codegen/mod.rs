@@ -0,0 +1,288 @@
+//! Generate code from schemas.
+//!
+//! Each submodule of this module turns a root [`Schema`](../schema/struct.Schema.html)
+//! into source code for some other language or tool. All generators produce
+//! deterministic output: definitions, properties, and enum values are always
+//! emitted in sorted order, so that generated code can be committed and
+//! diffed.
+//!
+//! Some target languages cannot express everything a schema can. Generators
+//! for those languages return an [`Output`](struct.Output.html), which lists
+//! each place where the generated code only approximates the schema.
+
+pub mod avro;
+pub mod docs;
+pub mod graphql;
+pub mod proto;
+pub mod rust;
+pub mod sql;
+pub mod typescript;
+
+use crate::pointer::JsonPointer;
+use crate::schema::{Schema, Type};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Generated code, along with any approximations made generating it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Output {
+    code: String,
+    approximations: Vec<Approximation>,
+}
+
+impl Output {
+    pub fn new(code: String, approximations: Vec<Approximation>) -> Self {
+        Self {
+            code,
+            approximations,
+        }
+    }
+
+    /// The generated code.
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// The places where the generated code does not exactly represent the
+    /// schema.
+    pub fn approximations(&self) -> &[Approximation] {
+        &self.approximations
+    }
+}
+
+/// A part of a schema which could not be represented exactly.
+///
+/// Like a [`ValidationError`](../validator/struct.ValidationError.html), this
+/// is not an error in the Rust sense. It describes a lossy mapping, such as
+/// an `int8` being widened to a 32-bit integer type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Approximation {
+    schema_path: JsonPointer<String, Vec<String>>,
+    message: String,
+}
+
+impl Approximation {
+    pub fn new(schema_path: JsonPointer<String, Vec<String>>, message: String) -> Self {
+        Self {
+            schema_path,
+            message,
+        }
+    }
+
+    /// A pointer into the part of the schema which was approximated.
+    pub fn schema_path(&self) -> &JsonPointer<String, Vec<String>> {
+        &self.schema_path
+    }
+
+    /// A human-readable description of the approximation.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Get the `description` of a schema, if it has a string one in its extra
+/// data.
+pub(crate) fn description(schema: &Schema) -> Option<&str> {
+    schema.extra().get("description").and_then(|d| d.as_str())
+}
+
+/// The name of a type, as it appears in the `type` keyword.
+pub(crate) fn type_name(typ: &Type) -> &'static str {
+    match typ {
+        Type::Boolean => "boolean",
+        Type::Number => "number",
+        Type::Float32 => "float32",
+        Type::Float64 => "float64",
+        Type::Int8 => "int8",
+        Type::Uint8 => "uint8",
+        Type::Int16 => "int16",
+        Type::Uint16 => "uint16",
+        Type::Int32 => "int32",
+        Type::Uint32 => "uint32",
+        Type::Int64 => "int64",
+        Type::Uint64 => "uint64",
+        Type::String => "string",
+        Type::Timestamp => "timestamp",
+    }
+}
+
+/// Get the properties of a properties form, sorted by name. The boolean is
+/// whether the property is optional.
+pub(crate) fn sorted_properties<'a>(
+    required: &'a BTreeMap<String, Schema>,
+    optional: &'a BTreeMap<String, Schema>,
+) -> Vec<(&'a String, &'a Schema, bool)> {
+    let mut entries: Vec<_> = required
+        .iter()
+        .map(|(k, v)| (k, v, false))
+        .chain(optional.iter().map(|(k, v)| (k, v, true)))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+/// Extend a schema path with some tokens.
+pub(crate) fn child_path(path: &[String], tokens: &[&str]) -> Vec<String> {
+    let mut path = path.to_vec();
+    path.extend(tokens.iter().map(|t| (*t).to_owned()));
+    path
+}
+
+/// The schema path of a property, within the schema at `path`.
+pub(crate) fn property_path(path: &[String], property: &str, optional: bool) -> Vec<String> {
+    let keyword = if optional {
+        "optionalProperties"
+    } else {
+        "properties"
+    };
+
+    child_path(path, &[keyword, property])
+}
+
+/// The schema path of a discriminator mapping, within the schema at `path`.
+pub(crate) fn mapping_path(path: &[String], value: &str) -> Vec<String> {
+    child_path(path, &["discriminator", "mapping", value])
+}
+
+/// The type with a given name, as it appears in the `type` keyword.
+pub(crate) fn parse_type_name(name: &str) -> Option<Type> {
+    Some(match name {
+        "boolean" => Type::Boolean,
+        "number" => Type::Number,
+        "float32" => Type::Float32,
+        "float64" => Type::Float64,
+        "int8" => Type::Int8,
+        "uint8" => Type::Uint8,
+        "int16" => Type::Int16,
+        "uint16" => Type::Uint16,
+        "int32" => Type::Int32,
+        "uint32" => Type::Uint32,
+        "int64" => Type::Int64,
+        "uint64" => Type::Uint64,
+        "string" => Type::String,
+        "timestamp" => Type::Timestamp,
+        _ => return None,
+    })
+}
+
+/// Make a string usable as an identifier in C-like languages, by replacing
+/// anything other than ASCII letters, digits, and underscores.
+pub(crate) fn c_identifier(name: &str) -> String {
+    let ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    if ident.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        ident
+    } else {
+        format!("_{}", ident)
+    }
+}
+
+/// Split an identifier-ish string into words.
+///
+/// Words are separated by any non-alphanumeric character, and by changes of
+/// case, so `"userId"`, `"user_id"` and `"UserID"` all become `["user",
+/// "id"]`-like word lists.
+fn words(s: &str) -> Vec<String> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+
+        if !word.is_empty() && c.is_uppercase() {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_lower) {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+
+        word.push(c);
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+/// Convert a string into `PascalCase`.
+pub(crate) fn pascal_case(s: &str) -> String {
+    words(s)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first
+                    .to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Convert a string into `snake_case`.
+pub(crate) fn snake_case(s: &str) -> String {
+    words(s)
+        .iter()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Allocates unique names within a single namespace.
+#[derive(Debug, Default)]
+pub(crate) struct Names {
+    used: BTreeSet<String>,
+}
+
+impl Names {
+    /// Claim `name`, or `name` with the smallest numeric suffix that isn't
+    /// already taken.
+    pub(crate) fn claim(&mut self, name: &str) -> String {
+        let mut candidate = name.to_owned();
+        let mut n = 2;
+        while self.used.contains(&candidate) {
+            candidate = format!("{}{}", name, n);
+            n += 1;
+        }
+
+        self.used.insert(candidate.clone());
+        candidate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case_conversion() {
+        assert_eq!(pascal_case("user_id"), "UserId");
+        assert_eq!(pascal_case("userID"), "UserId");
+        assert_eq!(pascal_case("HTTPServer"), "HttpServer");
+        assert_eq!(pascal_case("foo-bar baz"), "FooBarBaz");
+        assert_eq!(snake_case("userId"), "user_id");
+        assert_eq!(snake_case("HTTPServer"), "http_server");
+        assert_eq!(snake_case("created_at"), "created_at");
+    }
+
+    #[test]
+    fn unique_names() {
+        let mut names = Names::default();
+        assert_eq!(names.claim("Foo"), "Foo");
+        assert_eq!(names.claim("Foo"), "Foo2");
+        assert_eq!(names.claim("Foo"), "Foo3");
+    }
+}
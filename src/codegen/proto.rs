@@ -0,0 +1,2082 @@
+//! Convert between schemas and Protocol Buffers (proto3) definitions.
+//!
+//! [`Generator`](struct.Generator.html) turns a root schema into a `.proto`
+//! file, and [`parse`](fn.parse.html) turns a `.proto` file back into a root
+//! schema. The mapping is:
+//!
+//! * Properties forms become messages, and each property a field.
+//! * Enum forms become enums, with an added `_UNSPECIFIED` zero value.
+//! * Elements forms become `repeated` fields, and values forms become
+//!   `map<string, T>` fields.
+//! * Discriminator forms become a message containing a `oneof`, with one
+//!   message per mapping.
+//! * The `timestamp` type becomes `google.protobuf.Timestamp`, and the empty
+//!   form becomes `google.protobuf.Value`.
+//!
+//! Field numbers must never change once a message is in use, so they are
+//! kept in the schema itself: a property's `protoField` extra data is its
+//! field number, and an enum's `protoEnumValues` extra data maps each value
+//! to its number. Properties and values without a number are numbered after
+//! the highest existing one, in sorted order.
+//! [`assign_numbers`](fn.assign_numbers.html) fills in every missing number,
+//! so that they can be saved back into the schema.
+//!
+//! Neither direction is exact. Each conversion reports the places where it
+//! had to approximate, such as widening an `int8` to `int32`.
+
+use crate::codegen::{
+    c_identifier, child_path, description, mapping_path, pascal_case, property_path, snake_case,
+    sorted_properties, type_name, Approximation, Names, Output,
+};
+use crate::pointer::JsonPointer;
+use crate::schema::{Form, Schema, Type};
+use crate::visit::{fold_children, Fold, Walker};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// The extra data key holding a property's field number.
+pub const FIELD_NUMBER: &str = "protoField";
+
+/// The extra data key holding an enum's value numbers.
+pub const ENUM_NUMBERS: &str = "protoEnumValues";
+
+const MAX_FIELD_NUMBER: u64 = 536_870_911;
+const RESERVED_FIELD_NUMBERS: RangeInclusive<u64> = 19000..=19999;
+
+/// Generates proto3 definitions from schemas.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate a `.proto` file for a root schema.
+    pub fn generate(&self, schema: &Schema) -> Output {
+        let no_defs = BTreeMap::new();
+        let defs = schema.definitions().as_ref().unwrap_or(&no_defs);
+
+        let mut types = Names::default();
+        let mut values = BTreeSet::new();
+        let root_name = types.claim(&type_identifier(&self.config.root_name));
+        let def_names = defs
+            .iter()
+            .filter(|(_, sub_schema)| is_named(sub_schema))
+            .map(|(name, _)| (name.as_str(), types.claim(&type_identifier(name))))
+            .collect();
+
+        let mut state = State {
+            defs,
+            def_names,
+            inlining: BTreeSet::new(),
+            imports: BTreeSet::new(),
+            approximations: Vec::new(),
+        };
+
+        let mut items = vec![state.item(schema, &root_name, &[], &mut values)];
+        for (name, sub_schema) in defs {
+            if let Some(type_name) = state.def_names.get(name.as_str()).cloned() {
+                let path = vec!["definitions".to_owned(), name.clone()];
+                items.push(state.item(sub_schema, &type_name, &path, &mut values));
+            }
+        }
+
+        let mut header = "syntax = \"proto3\";\n".to_owned();
+        if let Some(ref package) = self.config.package {
+            header.push_str(&format!("\npackage {};\n", package));
+        }
+
+        if !state.imports.is_empty() {
+            header.push('\n');
+            for import in &state.imports {
+                header.push_str(&format!("import \"{}\";\n", import));
+            }
+        }
+
+        items.insert(0, header);
+        Output::new(items.join("\n"), state.approximations)
+    }
+}
+
+/// Configuration for how proto3 definitions should be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    root_name: String,
+    package: Option<String>,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the message generated for the root schema. The
+    /// default is `Root`.
+    pub fn root_name(&mut self, root_name: &str) -> &mut Self {
+        self.root_name = root_name.to_owned();
+        self
+    }
+
+    /// Sets the package declared by the generated file. By default, no
+    /// package is declared.
+    pub fn package(&mut self, package: Option<&str>) -> &mut Self {
+        self.package = package.map(str::to_owned);
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            root_name: "Root".to_owned(),
+            package: None,
+        }
+    }
+}
+
+/// Fill in the field and enum value numbers a schema is missing.
+///
+/// The returned schema has a `protoField` on every property and discriminator
+/// mapping, and a complete `protoEnumValues` on every enum. Existing numbers
+/// are kept, so converting the returned schema to proto3 will produce the same
+/// numbers as converting the original.
+pub fn assign_numbers(schema: &Schema) -> Schema {
+    Walker::new().fold(schema.clone(), &mut Pin)
+}
+
+/// Numbers the properties, mappings, and enum values of each schema, after
+/// numbering those of its subschemas.
+struct Pin;
+
+impl Fold for Pin {
+    fn fold_schema(&mut self, path: &[String], schema: Schema) -> Schema {
+        let schema = fold_children(self, path, schema);
+        let mut extra = schema.extra().clone();
+        let form = pinned(&schema, &mut extra);
+        Schema::from_parts(schema.definitions().clone(), Box::new(form), extra)
+    }
+}
+
+/// The form of a schema whose subschemas have been numbered, with the numbers
+/// of its own enum values added to `extra`.
+fn pinned(schema: &Schema, extra: &mut BTreeMap<String, Value>) -> Form {
+    match schema.form() {
+        Form::Enum(values) => {
+            let values: Vec<_> = values.iter().collect();
+            let numbers = enum_numbers(schema, &values);
+            let numbers = values
+                .iter()
+                .zip(numbers)
+                .map(|(value, number)| ((*value).clone(), Value::from(number)))
+                .collect();
+
+            extra.insert(ENUM_NUMBERS.to_owned(), Value::Object(numbers));
+            schema.form().clone()
+        }
+        Form::Properties(required, optional, has_required) => {
+            let entries = sorted_properties(required, optional);
+            let numbers = field_numbers(entries.iter().map(|(_, s, _)| *s));
+
+            let mut required = BTreeMap::new();
+            let mut optional = BTreeMap::new();
+            for ((name, sub_schema, is_optional), number) in entries.into_iter().zip(numbers) {
+                let sub_schema = with_number(sub_schema.clone(), number);
+                if is_optional {
+                    optional.insert(name.clone(), sub_schema);
+                } else {
+                    required.insert(name.clone(), sub_schema);
+                }
+            }
+
+            Form::Properties(required, optional, *has_required)
+        }
+        Form::Discriminator(tag, mapping) => {
+            let entries: Vec<_> = mapping.iter().collect();
+            let numbers = field_numbers(entries.iter().map(|(_, s)| *s));
+            let mapping = entries
+                .into_iter()
+                .zip(numbers)
+                .map(|((value, sub_schema), number)| {
+                    (value.clone(), with_number(sub_schema.clone(), number))
+                })
+                .collect();
+
+            Form::Discriminator(tag.clone(), mapping)
+        }
+        form => form.clone(),
+    }
+}
+
+fn with_number(schema: Schema, number: u64) -> Schema {
+    let mut extra = schema.extra().clone();
+    extra.insert(FIELD_NUMBER.to_owned(), Value::from(number));
+    Schema::from_parts(None, Box::new(schema.form().clone()), extra)
+}
+
+/// Whether a schema becomes a named message or enum, rather than being
+/// inlined wherever it is used.
+fn is_named(schema: &Schema) -> bool {
+    matches!(
+        schema.form(),
+        Form::Properties(..) | Form::Discriminator(..) | Form::Enum(_)
+    )
+}
+
+/// The field number a schema has been given, if it has a usable one.
+fn pinned_field(schema: &Schema) -> Option<u64> {
+    schema
+        .extra()
+        .get(FIELD_NUMBER)
+        .and_then(Value::as_u64)
+        .filter(|n| (1..=MAX_FIELD_NUMBER).contains(n) && !RESERVED_FIELD_NUMBERS.contains(n))
+}
+
+fn pinned_enum_value(schema: &Schema, value: &str) -> Option<u64> {
+    schema
+        .extra()
+        .get(ENUM_NUMBERS)
+        .and_then(|numbers| numbers.get(value))
+        .and_then(Value::as_u64)
+        .filter(|n| (1..=i32::MAX as u64).contains(n))
+}
+
+fn field_numbers<'a>(schemas: impl Iterator<Item = &'a Schema>) -> Vec<u64> {
+    let pinned: Vec<_> = schemas.map(pinned_field).collect();
+    number(&pinned, &[RESERVED_FIELD_NUMBERS])
+}
+
+fn enum_numbers(schema: &Schema, values: &[&String]) -> Vec<u64> {
+    let pinned: Vec<_> = values
+        .iter()
+        .map(|value| pinned_enum_value(schema, value))
+        .collect();
+
+    number(&pinned, &[])
+}
+
+/// Give every entry a unique number. Pinned numbers are kept, unless an
+/// earlier entry already has the same number. Everything else is numbered
+/// after the highest pinned number.
+fn number(pinned: &[Option<u64>], reserved: &[RangeInclusive<u64>]) -> Vec<u64> {
+    let mut used = BTreeSet::new();
+    let kept: Vec<_> = pinned
+        .iter()
+        .map(|n| n.filter(|n| used.insert(*n)))
+        .collect();
+
+    let mut next = used.iter().max().map_or(1, |n| n + 1);
+    kept.into_iter()
+        .map(|n| {
+            n.unwrap_or_else(|| {
+                while reserved.iter().any(|range| range.contains(&next)) {
+                    next += 1;
+                }
+
+                next += 1;
+                next - 1
+            })
+        })
+        .collect()
+}
+
+enum Label {
+    Single,
+    Repeated,
+    Map,
+}
+
+/// A message being generated.
+#[derive(Default)]
+struct Message {
+    types: Names,
+    values: BTreeSet<String>,
+    fields: Names,
+    nested: Vec<String>,
+    lines: Vec<(u64, String)>,
+    oneof: Option<(String, Vec<(u64, String)>)>,
+}
+
+impl Message {
+    fn render(mut self, name: &str) -> String {
+        let mut sections = self.nested;
+
+        self.lines.sort();
+        let mut fields: String = self.lines.into_iter().map(|(_, line)| line).collect();
+        if let Some((name, mut lines)) = self.oneof {
+            lines.sort();
+            fields.push_str(&format!("oneof {} {{\n", name));
+            fields.push_str(&indent(
+                &lines.into_iter().map(|(_, l)| l).collect::<String>(),
+            ));
+            fields.push_str("}\n");
+        }
+
+        if !fields.is_empty() {
+            sections.push(fields);
+        }
+
+        if sections.is_empty() {
+            format!("message {} {{}}\n", name)
+        } else {
+            format!("message {} {{\n{}}}\n", name, indent(&sections.join("\n")))
+        }
+    }
+}
+
+struct State<'a> {
+    defs: &'a BTreeMap<String, Schema>,
+    def_names: BTreeMap<&'a str, String>,
+    inlining: BTreeSet<&'a str>,
+    imports: BTreeSet<&'static str>,
+    approximations: Vec<Approximation>,
+}
+
+impl<'a> State<'a> {
+    fn approximate(&mut self, path: &[String], message: String) {
+        self.approximations
+            .push(Approximation::new(JsonPointer::new(path.to_vec()), message));
+    }
+
+    /// Emit a message or enum for a schema. Enum values are claimed in
+    /// `values`, the scope enclosing the item.
+    fn item(
+        &mut self,
+        schema: &'a Schema,
+        name: &str,
+        path: &[String],
+        values: &mut BTreeSet<String>,
+    ) -> String {
+        let mut out = comment(description(schema));
+        match schema.form() {
+            Form::Enum(enum_values) => {
+                out.push_str(&self.enumeration(schema, enum_values, name, path, values));
+            }
+            Form::Properties(required, optional, _) => {
+                let mut msg = Message::default();
+                let entries = sorted_properties(required, optional);
+                let numbers =
+                    self.field_numbers(entries.iter().map(|(property, sub_schema, optional)| {
+                        (property_path(path, property, *optional), *sub_schema)
+                    }));
+
+                for ((property, sub_schema, optional), number) in entries.into_iter().zip(numbers) {
+                    let path = property_path(path, property, optional);
+                    let line = self.field(&mut msg, property, sub_schema, optional, &path, number);
+                    msg.lines.push((number, line));
+                }
+
+                out.push_str(&msg.render(name));
+            }
+            Form::Discriminator(tag, mapping) => {
+                self.approximate(
+                    path,
+                    format!(
+                        "discriminator on {:?} is represented as a oneof, whose JSON encoding nests each variant under its own key",
+                        tag
+                    ),
+                );
+
+                let mut msg = Message::default();
+                let entries: Vec<_> = mapping.iter().collect();
+                let numbers = self.field_numbers(
+                    entries
+                        .iter()
+                        .map(|(value, sub_schema)| (mapping_path(path, value), *sub_schema)),
+                );
+
+                let mut lines = Vec::new();
+                for ((value, sub_schema), number) in entries.into_iter().zip(numbers) {
+                    let path = mapping_path(path, value);
+                    let variant = msg.types.claim(&type_identifier(value));
+                    let item = self.item(sub_schema, &variant, &path, &mut msg.values);
+                    msg.nested.push(item);
+
+                    let field = msg.fields.claim(&c_identifier(value));
+                    lines.push((number, field_line(&variant, &field, value, number)));
+                }
+
+                msg.oneof = Some((c_identifier(tag), lines));
+                out.push_str(&msg.render(name));
+            }
+            _ => {
+                self.approximate(
+                    path,
+                    format!(
+                        "schema is not an object, so it is wrapped in message {} as field `value`",
+                        name
+                    ),
+                );
+
+                let mut msg = Message::default();
+                let line = self.field(&mut msg, "value", schema, false, path, 1);
+                msg.lines.push((1, line));
+                out.push_str(&msg.render(name));
+            }
+        }
+
+        out
+    }
+
+    /// Number a list of fields, reporting pinned numbers which had to change.
+    fn field_numbers(
+        &mut self,
+        fields: impl Iterator<Item = (Vec<String>, &'a Schema)>,
+    ) -> Vec<u64> {
+        let fields: Vec<_> = fields.collect();
+        let numbers = field_numbers(fields.iter().map(|(_, schema)| *schema));
+
+        for ((path, schema), number) in fields.iter().zip(&numbers) {
+            if let Some(pinned) = schema.extra().get(FIELD_NUMBER) {
+                if pinned.as_u64() != Some(*number) {
+                    self.approximate(
+                        path,
+                        format!(
+                            "field number {} is unusable, so {} is used instead",
+                            pinned, number
+                        ),
+                    );
+                }
+            }
+        }
+
+        numbers
+    }
+
+    fn enumeration(
+        &mut self,
+        schema: &'a Schema,
+        enum_values: &'a BTreeSet<String>,
+        name: &str,
+        path: &[String],
+        values: &mut BTreeSet<String>,
+    ) -> String {
+        let prefix = snake_case(name).to_uppercase();
+        let zero = claim_value(values, &format!("{}_UNSPECIFIED", prefix), &prefix);
+        self.approximate(
+            path,
+            format!("proto3 enums must have a zero value, so {} is added", zero),
+        );
+
+        let enum_values: Vec<_> = enum_values.iter().collect();
+        let numbers = enum_numbers(schema, &enum_values);
+
+        let mut lines = vec![(0, format!("{} = 0;\n", zero))];
+        for (value, number) in enum_values.into_iter().zip(numbers) {
+            let ident = claim_value(values, &c_identifier(value), &prefix);
+            if &ident != value {
+                self.approximate(
+                    path,
+                    format!("enum value {:?} is renamed to {}", value, ident),
+                );
+            }
+
+            lines.push((number, format!("{} = {};\n", ident, number)));
+        }
+
+        lines.sort();
+        let body: String = lines.into_iter().map(|(_, line)| line).collect();
+        format!("enum {} {{\n{}}}\n", name, indent(&body))
+    }
+
+    /// Render a field, adding any types it needs to `msg`.
+    fn field(
+        &mut self,
+        msg: &mut Message,
+        property: &str,
+        schema: &'a Schema,
+        optional: bool,
+        path: &[String],
+        number: u64,
+    ) -> String {
+        let (label, typ) = self.field_type(schema, &type_identifier(property), path, msg);
+        let label = match label {
+            Label::Single if optional => "optional ",
+            Label::Single => "",
+            Label::Repeated => "repeated ",
+            Label::Map => "",
+        };
+
+        if optional && label != "optional " {
+            self.approximate(
+                path,
+                "proto3 cannot distinguish a missing repeated or map field from an empty one"
+                    .to_owned(),
+            );
+        }
+
+        let field = msg.fields.claim(&c_identifier(property));
+        let mut out = comment(description(schema));
+        out.push_str(&field_line(
+            &format!("{}{}", label, typ),
+            &field,
+            property,
+            number,
+        ));
+        out
+    }
+
+    /// The label and type of a field.
+    fn field_type(
+        &mut self,
+        schema: &'a Schema,
+        hint: &str,
+        path: &[String],
+        msg: &mut Message,
+    ) -> (Label, String) {
+        match schema.form() {
+            Form::Elements(sub_schema) => {
+                let path = child_path(path, &["elements"]);
+                let typ = self.single_type(sub_schema, &format!("{}Item", hint), &path, msg);
+                (Label::Repeated, typ)
+            }
+            Form::Values(sub_schema) => {
+                let path = child_path(path, &["values"]);
+                let typ = self.single_type(sub_schema, &format!("{}Value", hint), &path, msg);
+                (Label::Map, format!("map<string, {}>", typ))
+            }
+            Form::Ref(def) if !self.def_names.contains_key(def.as_str()) => {
+                match self.inline_def(def, path) {
+                    Some((def, path)) => {
+                        let defs = self.defs;
+                        let result = self.field_type(&defs[def], hint, &path, msg);
+                        self.inlining.remove(def);
+                        result
+                    }
+                    None => (Label::Single, "google.protobuf.Value".to_owned()),
+                }
+            }
+            _ => (Label::Single, self.single_type(schema, hint, path, msg)),
+        }
+    }
+
+    /// The type of a schema, where it cannot be `repeated` or a `map`.
+    fn single_type(
+        &mut self,
+        schema: &'a Schema,
+        hint: &str,
+        path: &[String],
+        msg: &mut Message,
+    ) -> String {
+        match schema.form() {
+            Form::Empty => {
+                self.imports.insert("google/protobuf/struct.proto");
+                "google.protobuf.Value".to_owned()
+            }
+            Form::Type(typ) => self.scalar(typ, path),
+            Form::Ref(def) => {
+                if let Some(name) = self.def_names.get(def.as_str()) {
+                    return name.clone();
+                }
+
+                match self.inline_def(def, path) {
+                    Some((def, path)) => {
+                        let defs = self.defs;
+                        let typ = self.single_type(&defs[def], hint, &path, msg);
+                        self.inlining.remove(def);
+                        typ
+                    }
+                    None => "google.protobuf.Value".to_owned(),
+                }
+            }
+            Form::Enum(_) | Form::Properties(..) | Form::Discriminator(..) => {
+                let name = msg.types.claim(hint);
+                let item = self.item(schema, &name, path, &mut msg.values);
+                msg.nested.push(item);
+                name
+            }
+            Form::Elements(_) | Form::Values(_) => {
+                self.approximate(
+                    path,
+                    "proto3 cannot nest repeated or map fields, so this is wrapped in a message with field `value`"
+                        .to_owned(),
+                );
+
+                let name = msg.types.claim(hint);
+                let mut inner = Message::default();
+                let line = self.field(&mut inner, "value", schema, false, path, 1);
+                inner.lines.push((1, line));
+                msg.nested.push(inner.render(&name));
+                name
+            }
+        }
+    }
+
+    /// Start inlining a definition which isn't its own message or enum.
+    ///
+    /// Returns `None`, and falls back to `google.protobuf.Value`, if the
+    /// definition is already being inlined, as it would otherwise recurse
+    /// forever.
+    fn inline_def(&mut self, def: &'a str, path: &[String]) -> Option<(&'a str, Vec<String>)> {
+        let (def, _) = self.defs.get_key_value(def)?;
+        if self.inlining.insert(def) {
+            Some((def, vec!["definitions".to_owned(), def.clone()]))
+        } else {
+            self.imports.insert("google/protobuf/struct.proto");
+            self.approximate(
+                path,
+                format!(
+                    "definition {:?} is recursive without passing through a message, so google.protobuf.Value is used",
+                    def
+                ),
+            );
+            None
+        }
+    }
+
+    fn scalar(&mut self, typ: &Type, path: &[String]) -> String {
+        let proto = match typ {
+            Type::Boolean => "bool",
+            Type::Number | Type::Float64 => "double",
+            Type::Float32 => "float",
+            Type::Int8 | Type::Int16 | Type::Int32 => "int32",
+            Type::Uint8 | Type::Uint16 | Type::Uint32 => "uint32",
+            Type::Int64 => "int64",
+            Type::Uint64 => "uint64",
+            Type::String => "string",
+            Type::Timestamp => {
+                self.imports.insert("google/protobuf/timestamp.proto");
+                "google.protobuf.Timestamp"
+            }
+        };
+
+        match typ {
+            Type::Int8 | Type::Int16 | Type::Uint8 | Type::Uint16 => {
+                self.approximate(path, format!("{} is widened to {}", type_name(typ), proto));
+            }
+            Type::Int64 | Type::Uint64 => {
+                self.approximate(
+                    path,
+                    format!("proto3 JSON encodes {} values as strings", proto),
+                );
+            }
+            _ => {}
+        }
+
+        proto.to_owned()
+    }
+}
+
+fn field_line(typ: &str, field: &str, json: &str, number: u64) -> String {
+    if json_name(field) == json {
+        format!("{} {} = {};\n", typ, field, number)
+    } else {
+        format!(
+            "{} {} = {} [json_name = {}];\n",
+            typ,
+            field,
+            number,
+            serde_json::to_string(json).expect("error serializing string")
+        )
+    }
+}
+
+/// Claim an enum value name in a scope, prefixing it if it's already taken.
+fn claim_value(values: &mut BTreeSet<String>, ident: &str, prefix: &str) -> String {
+    let mut candidate = ident.to_owned();
+    if values.contains(&candidate) {
+        candidate = format!("{}_{}", prefix, ident);
+    }
+
+    let mut n = 2;
+    while values.contains(&candidate) {
+        candidate = format!("{}_{}{}", prefix, ident, n);
+        n += 1;
+    }
+
+    values.insert(candidate.clone());
+    candidate
+}
+
+/// Make a string usable as a message or enum name.
+fn type_identifier(name: &str) -> String {
+    c_identifier(&pascal_case(name))
+}
+
+/// The name proto3's JSON mapping uses for a field, absent a `json_name`.
+fn json_name(field: &str) -> String {
+    let mut out = String::new();
+    let mut upper = false;
+    for c in field.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+fn comment(description: Option<&str>) -> String {
+    let mut out = String::new();
+    for line in description.into_iter().flat_map(str::lines) {
+        if line.is_empty() {
+            out.push_str("//\n");
+        } else {
+            out.push_str(&format!("// {}\n", line));
+        }
+    }
+
+    out
+}
+
+fn indent(s: &str) -> String {
+    s.lines()
+        .map(|line| {
+            if line.is_empty() {
+                "\n".to_owned()
+            } else {
+                format!("  {}\n", line)
+            }
+        })
+        .collect()
+}
+
+/// An error parsing a `.proto` file with [`parse`](fn.parse.html).
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub enum ProtoError {
+    /// The file could not be parsed.
+    ///
+    /// Only the subset of the `.proto` language which is relevant to proto3
+    /// messages and enums is supported. Services, extensions, and proto2
+    /// files are rejected or ignored.
+    Invalid { line: usize, message: String },
+
+    /// The file does not define a message or enum with the requested root
+    /// name.
+    NoSuchDefinition { definition: String },
+}
+
+impl fmt::Display for ProtoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtoError::Invalid { line, message } => {
+                write!(f, "invalid proto definition on line {}: {}", line, message)
+            }
+            ProtoError::NoSuchDefinition { definition } => {
+                write!(f, "no such message or enum: {}", definition)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProtoError {}
+
+/// Parse a proto3 file into a root schema.
+///
+/// The message (or enum) named `root` becomes the root schema. Top-level
+/// messages and enums become definitions, named as they are in the file, and
+/// nested ones are inlined wherever they are used, unless they are recursive.
+///
+/// A message whose only fields are a single `oneof` of messages becomes a
+/// discriminator, with the `oneof`'s name as its tag. Every property records
+/// its field number in its `protoField` extra data, and every enum records
+/// its value numbers in `protoEnumValues`. `UNSPECIFIED` zero values are
+/// dropped.
+pub fn parse(source: &str, root: &str) -> Result<(Schema, Vec<Approximation>), ProtoError> {
+    let file = Parser::new(source)?.file()?;
+
+    let mut converter = Converter {
+        file: &file,
+        cyclic: file.cyclic(),
+        referenced: BTreeSet::new(),
+        converting: BTreeSet::new(),
+        approximations: Vec::new(),
+    };
+
+    let root_schema = if file.messages.contains_key(root) {
+        converter.message(root, &[])?
+    } else if file.enums.contains_key(root) {
+        converter.enumeration(root)
+    } else {
+        return Err(ProtoError::NoSuchDefinition {
+            definition: root.to_owned(),
+        });
+    };
+
+    let mut defs = BTreeMap::new();
+    for name in file.messages.keys() {
+        if name != root && converter.is_def(name) {
+            let path = vec!["definitions".to_owned(), name.clone()];
+            defs.insert(name.clone(), converter.message(name, &path)?);
+        }
+    }
+
+    for name in file.enums.keys() {
+        if name != root && converter.is_def(name) {
+            defs.insert(name.clone(), converter.enumeration(name));
+        }
+    }
+
+    // The root only needs to be a definition if something refers to it.
+    if converter.referenced.contains(root) {
+        let path = vec!["definitions".to_owned(), root.to_owned()];
+        let def = if file.messages.contains_key(root) {
+            converter.message(root, &path)?
+        } else {
+            converter.enumeration(root)
+        };
+
+        defs.insert(root.to_owned(), def);
+    }
+
+    let schema = Schema::from_parts(
+        Some(defs),
+        Box::new(root_schema.form().clone()),
+        root_schema.extra().clone(),
+    );
+
+    Ok((schema, converter.approximations))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Str(String),
+    Symbol(char),
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    package: Option<String>,
+}
+
+#[derive(Default)]
+struct File {
+    package: Option<String>,
+    messages: BTreeMap<String, MessageDecl>,
+    enums: BTreeMap<String, Vec<(String, i64)>>,
+}
+
+#[derive(Default)]
+struct MessageDecl {
+    fields: Vec<FieldDecl>,
+    oneofs: Vec<String>,
+}
+
+struct FieldDecl {
+    name: String,
+    json_name: Option<String>,
+    number: i64,
+    label: FieldLabel,
+    typ: String,
+    oneof: Option<usize>,
+    line: usize,
+}
+
+#[derive(PartialEq)]
+enum FieldLabel {
+    Required,
+    Optional,
+    Repeated,
+    Map(String),
+}
+
+fn invalid(line: usize, message: String) -> ProtoError {
+    ProtoError::Invalid { line, message }
+}
+
+impl Parser {
+    fn new(source: &str) -> Result<Self, ProtoError> {
+        let mut tokens = Vec::new();
+        let mut line = 1;
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\n' => line += 1,
+                c if c.is_whitespace() => {}
+                '/' if chars.peek() == Some(&'/') => {
+                    while chars.peek().is_some_and(|&c| c != '\n') {
+                        chars.next();
+                    }
+                }
+                '/' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    let mut prev = ' ';
+                    loop {
+                        match chars.next() {
+                            Some('/') if prev == '*' => break,
+                            Some(c) => {
+                                if c == '\n' {
+                                    line += 1;
+                                }
+                                prev = c;
+                            }
+                            None => return Err(invalid(line, "unterminated comment".to_owned())),
+                        }
+                    }
+                }
+                '"' | '\'' => {
+                    let mut s = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(q) if q == c => break,
+                            Some('\\') => match chars.next() {
+                                Some('n') => s.push('\n'),
+                                Some('t') => s.push('\t'),
+                                Some(c) => s.push(c),
+                                None => break,
+                            },
+                            Some('\n') | None => {
+                                return Err(invalid(line, "unterminated string".to_owned()))
+                            }
+                            Some(c) => s.push(c),
+                        }
+                    }
+
+                    tokens.push((Token::Str(s), line));
+                }
+                c if c.is_ascii_alphabetic() || c == '_' || c == '.' => {
+                    let mut s = c.to_string();
+                    while let Some(&c) = chars.peek() {
+                        if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+                            s.push(c);
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+
+                    tokens.push((Token::Ident(s), line));
+                }
+                c if c.is_ascii_digit() => {
+                    let mut s = c.to_string();
+                    while let Some(&c) = chars.peek() {
+                        if c.is_ascii_alphanumeric() || c == '.' {
+                            s.push(c);
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+
+                    tokens.push((Token::Number(s), line));
+                }
+                c => tokens.push((Token::Symbol(c), line)),
+            }
+        }
+
+        Ok(Self {
+            tokens,
+            pos: 0,
+            package: None,
+        })
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Result<Token, ProtoError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .map(|(token, _)| token.clone())
+            .ok_or_else(|| invalid(self.line(), "unexpected end of file".to_owned()))?;
+
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), ProtoError> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(invalid(self.line(), format!("expected {:?}", symbol)))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ProtoError> {
+        match self.next()? {
+            Token::Ident(s) => Ok(s),
+            token => Err(invalid(
+                self.line(),
+                format!("expected identifier, got {:?}", token),
+            )),
+        }
+    }
+
+    fn integer(&mut self) -> Result<i64, ProtoError> {
+        let negative = self.eat('-');
+        let n = match self.next()? {
+            Token::Number(ref s) if s.starts_with("0x") || s.starts_with("0X") => {
+                i64::from_str_radix(&s[2..], 16).ok()
+            }
+            Token::Number(ref s) if s.len() > 1 && s.starts_with('0') => {
+                i64::from_str_radix(&s[1..], 8).ok()
+            }
+            Token::Number(s) => s.parse().ok(),
+            _ => None,
+        };
+
+        n.map(|n| if negative { -n } else { n })
+            .ok_or_else(|| invalid(self.line(), "expected integer".to_owned()))
+    }
+
+    /// Skip a statement, up to and including its `;` or block.
+    fn skip_statement(&mut self) -> Result<(), ProtoError> {
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::Symbol(';') if depth == 0 => return Ok(()),
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol('}') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn file(mut self) -> Result<File, ProtoError> {
+        let mut file = File::default();
+        while self.peek().is_some() {
+            if self.eat(';') {
+                continue;
+            }
+
+            let line = self.line();
+            match self.ident()?.as_str() {
+                "syntax" => {
+                    self.expect('=')?;
+                    match self.next()? {
+                        Token::Str(ref s) if s == "proto3" => {}
+                        _ => return Err(invalid(line, "only proto3 is supported".to_owned())),
+                    }
+                    self.expect(';')?;
+                }
+                "package" => {
+                    self.package = Some(self.ident()?);
+                    self.expect(';')?;
+                }
+                "message" => self.message(&mut file, "")?,
+                "enum" => self.enumeration(&mut file, "")?,
+                "import" | "option" | "service" | "extend" => self.skip_statement()?,
+                keyword => return Err(invalid(line, format!("unexpected {:?}", keyword))),
+            }
+        }
+
+        file.package = self.package;
+        Ok(file)
+    }
+
+    fn message(&mut self, file: &mut File, scope: &str) -> Result<(), ProtoError> {
+        let line = self.line();
+        let name = qualify(scope, &self.ident()?);
+        let mut decl = MessageDecl::default();
+        self.expect('{')?;
+
+        while !self.eat('}') {
+            if self.eat(';') {
+                continue;
+            }
+
+            let line = self.line();
+            match self.peek() {
+                Some(Token::Ident(keyword)) => match keyword.as_str() {
+                    "message" => {
+                        self.pos += 1;
+                        self.message(file, &name)?;
+                    }
+                    "enum" => {
+                        self.pos += 1;
+                        self.enumeration(file, &name)?;
+                    }
+                    "option" | "reserved" | "extensions" | "extend" => self.skip_statement()?,
+                    "required" => {
+                        return Err(invalid(line, "required fields are not proto3".to_owned()))
+                    }
+                    "oneof" => {
+                        self.pos += 1;
+                        let oneof = decl.oneofs.len();
+                        decl.oneofs.push(self.ident()?);
+                        self.expect('{')?;
+                        while !self.eat('}') {
+                            if self.peek() == Some(&Token::Ident("option".to_owned())) {
+                                self.skip_statement()?;
+                            } else {
+                                let mut field = self.field()?;
+                                field.oneof = Some(oneof);
+                                decl.fields.push(field);
+                            }
+                        }
+                    }
+                    _ => {
+                        let field = self.field()?;
+                        decl.fields.push(field);
+                    }
+                },
+                _ => return Err(invalid(line, "expected field".to_owned())),
+            }
+        }
+
+        let mut json_names = BTreeMap::new();
+        for field in &decl.fields {
+            if let Some(other) = json_names.insert(field_json_name(field), &field.name) {
+                return Err(invalid(
+                    field.line,
+                    format!(
+                        "fields {} and {} have the same JSON name {:?}",
+                        other,
+                        field.name,
+                        field_json_name(field)
+                    ),
+                ));
+            }
+        }
+
+        define(file, line, &name)?;
+        file.messages.insert(name, decl);
+        Ok(())
+    }
+
+    fn field(&mut self) -> Result<FieldDecl, ProtoError> {
+        let line = self.line();
+        let mut typ = self.ident()?;
+        let label = match typ.as_str() {
+            "optional" => FieldLabel::Optional,
+            "repeated" => FieldLabel::Repeated,
+            "map" if self.eat('<') => {
+                let key = self.ident()?;
+                self.expect(',')?;
+                let value = self.ident()?;
+                self.expect('>')?;
+                typ = value;
+                FieldLabel::Map(key)
+            }
+            _ => FieldLabel::Required,
+        };
+
+        if label == FieldLabel::Optional || label == FieldLabel::Repeated {
+            typ = self.ident()?;
+        }
+
+        let name = self.ident()?;
+        self.expect('=')?;
+        let number = self.integer()?;
+
+        let mut json_name = None;
+        if self.eat('[') {
+            while !self.eat(']') {
+                if self.peek() == Some(&Token::Ident("json_name".to_owned())) {
+                    self.pos += 1;
+                    self.expect('=')?;
+                    match self.next()? {
+                        Token::Str(s) => json_name = Some(s),
+                        _ => return Err(invalid(self.line(), "expected string".to_owned())),
+                    }
+                } else {
+                    self.next()?;
+                }
+            }
+        }
+
+        self.expect(';')?;
+        Ok(FieldDecl {
+            name,
+            json_name,
+            number,
+            label,
+            typ,
+            oneof: None,
+            line,
+        })
+    }
+
+    fn enumeration(&mut self, file: &mut File, scope: &str) -> Result<(), ProtoError> {
+        let line = self.line();
+        let name = qualify(scope, &self.ident()?);
+        let mut values = Vec::new();
+        self.expect('{')?;
+
+        while !self.eat('}') {
+            if self.eat(';') {
+                continue;
+            }
+
+            let value = self.ident()?;
+            if value == "option" || value == "reserved" {
+                self.skip_statement()?;
+                continue;
+            }
+
+            self.expect('=')?;
+            let number = self.integer()?;
+            if self.eat('[') {
+                while !self.eat(']') {
+                    self.next()?;
+                }
+            }
+
+            self.expect(';')?;
+            values.push((value, number));
+        }
+
+        define(file, line, &name)?;
+        file.enums.insert(name, values);
+        Ok(())
+    }
+}
+
+/// Check that no message or enum in `file` is already called `name`.
+fn define(file: &File, line: usize, name: &str) -> Result<(), ProtoError> {
+    if file.messages.contains_key(name) || file.enums.contains_key(name) {
+        return Err(invalid(line, format!("{} is already defined", name)));
+    }
+
+    Ok(())
+}
+
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+/// What a field's type name refers to.
+enum Resolved<'a> {
+    Scalar(Type),
+    Bytes,
+    Timestamp,
+    Value,
+    WellKnown(&'a str),
+    Message(&'a str),
+    Enum(&'a str),
+}
+
+impl File {
+    /// Resolve a type name used within the message `scope`, searching from
+    /// the innermost scope outwards.
+    fn resolve<'a>(&'a self, scope: &str, typ: &'a str) -> Option<Resolved<'a>> {
+        let scalar = match typ {
+            "bool" => Some(Type::Boolean),
+            "double" => Some(Type::Float64),
+            "float" => Some(Type::Float32),
+            "int32" | "sint32" | "sfixed32" => Some(Type::Int32),
+            "uint32" | "fixed32" => Some(Type::Uint32),
+            "int64" | "sint64" | "sfixed64" => Some(Type::Int64),
+            "uint64" | "fixed64" => Some(Type::Uint64),
+            "string" => Some(Type::String),
+            _ => None,
+        };
+
+        if let Some(scalar) = scalar {
+            return Some(Resolved::Scalar(scalar));
+        }
+
+        match typ.trim_start_matches('.') {
+            "bytes" => return Some(Resolved::Bytes),
+            "google.protobuf.Timestamp" => return Some(Resolved::Timestamp),
+            "google.protobuf.Value" => return Some(Resolved::Value),
+            well_known if well_known.starts_with("google.protobuf.") => {
+                return Some(Resolved::WellKnown(well_known));
+            }
+            _ => {}
+        }
+
+        let mut candidates = Vec::new();
+        let unqualified = self.package.as_ref().and_then(|package| {
+            typ.trim_start_matches('.')
+                .strip_prefix(&format!("{}.", package))
+        });
+
+        if typ.starts_with('.') {
+            candidates.extend(unqualified.map(str::to_owned));
+        } else {
+            let mut scope = Some(scope);
+            while let Some(s) = scope {
+                candidates.push(qualify(s, typ));
+                if let Some(unqualified) = unqualified {
+                    candidates.push(qualify(s, unqualified));
+                }
+
+                scope = if s.is_empty() {
+                    None
+                } else {
+                    Some(s.rfind('.').map_or("", |i| &s[..i]))
+                };
+            }
+        }
+
+        candidates.into_iter().find_map(|candidate| {
+            if let Some((name, _)) = self.messages.get_key_value(&candidate) {
+                Some(Resolved::Message(name))
+            } else if let Some((name, _)) = self.enums.get_key_value(&candidate) {
+                Some(Resolved::Enum(name))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The messages which can contain themselves.
+    fn cyclic(&self) -> BTreeSet<String> {
+        let edges: BTreeMap<&str, Vec<&str>> = self
+            .messages
+            .iter()
+            .map(|(name, decl)| {
+                let targets = decl
+                    .fields
+                    .iter()
+                    .filter_map(|field| match self.resolve(name, &field.typ) {
+                        Some(Resolved::Message(target)) => Some(target),
+                        _ => None,
+                    })
+                    .collect();
+
+                (name.as_str(), targets)
+            })
+            .collect();
+
+        let mut cyclic = BTreeSet::new();
+        for start in edges.keys() {
+            let mut seen = BTreeSet::new();
+            let mut stack = edges[start].clone();
+            while let Some(name) = stack.pop() {
+                if name == *start {
+                    cyclic.insert((*start).to_owned());
+                    break;
+                }
+
+                if seen.insert(name) {
+                    stack.extend(&edges[name]);
+                }
+            }
+        }
+
+        cyclic
+    }
+}
+
+struct Converter<'a> {
+    file: &'a File,
+    cyclic: BTreeSet<String>,
+    referenced: BTreeSet<String>,
+    /// The messages being converted, further up the stack.
+    converting: BTreeSet<String>,
+    approximations: Vec<Approximation>,
+}
+
+impl<'a> Converter<'a> {
+    fn approximate(&mut self, path: &[String], message: String) {
+        self.approximations
+            .push(Approximation::new(JsonPointer::new(path.to_vec()), message));
+    }
+
+    /// Whether a message or enum becomes a definition, rather than being
+    /// inlined.
+    fn is_def(&self, name: &str) -> bool {
+        !name.contains('.') || self.cyclic.contains(name)
+    }
+
+    fn message(&mut self, name: &str, path: &[String]) -> Result<Schema, ProtoError> {
+        // A oneof variant can contain the message it belongs to. Such a
+        // message is cyclic, and so a definition, which can be referred to.
+        if self.converting.contains(name) {
+            self.referenced.insert(name.to_owned());
+            return Ok(Schema::from_parts(
+                None,
+                Box::new(Form::Ref(name.to_owned())),
+                BTreeMap::new(),
+            ));
+        }
+
+        self.converting.insert(name.to_owned());
+        let schema = self.convert_message(name, path);
+        self.converting.remove(name);
+        schema
+    }
+
+    fn convert_message(&mut self, name: &str, path: &[String]) -> Result<Schema, ProtoError> {
+        let file = self.file;
+        let decl = &file.messages[name];
+        if let Some(schema) = self.discriminator(name, decl, path)? {
+            return Ok(schema);
+        }
+
+        for (index, oneof) in decl.oneofs.iter().enumerate() {
+            if decl.fields.iter().any(|f| f.oneof == Some(index)) {
+                self.approximate(
+                    path,
+                    format!(
+                        "the fields of oneof {} become optional properties, which need not be exclusive",
+                        oneof
+                    ),
+                );
+            }
+        }
+
+        let mut required = BTreeMap::new();
+        let mut optional = BTreeMap::new();
+        for field in &decl.fields {
+            let property = field_json_name(field);
+            let is_optional = field.label == FieldLabel::Optional || field.oneof.is_some();
+            let path = property_path(path, &property, is_optional);
+            if !is_optional {
+                self.approximate(
+                    &path,
+                    format!(
+                        "proto3 JSON omits field {} when it has its default value, but it becomes a required property",
+                        field.name
+                    ),
+                );
+            }
+
+            let schema = with_number(self.field(name, field, &path)?, field.number as u64);
+
+            if is_optional {
+                optional.insert(property, schema);
+            } else {
+                required.insert(property, schema);
+            }
+        }
+
+        Ok(Schema::from_parts(
+            None,
+            Box::new(Form::Properties(required, optional, true)),
+            BTreeMap::new(),
+        ))
+    }
+
+    /// Convert a message to a discriminator, if all it contains is a oneof of
+    /// messages which themselves become properties forms.
+    fn discriminator(
+        &mut self,
+        name: &str,
+        decl: &MessageDecl,
+        path: &[String],
+    ) -> Result<Option<Schema>, ProtoError> {
+        if decl.oneofs.len() != 1 || decl.fields.iter().any(|f| f.oneof.is_none()) {
+            return Ok(None);
+        }
+
+        let file = self.file;
+        let mut variants = Vec::new();
+        for field in &decl.fields {
+            match file.resolve(name, &field.typ) {
+                Some(Resolved::Message(variant)) => variants.push((field, variant)),
+                _ => return Ok(None),
+            }
+        }
+
+        let tag = &decl.oneofs[0];
+        let approximations = self.approximations.len();
+        let referenced = self.referenced.clone();
+        let mut mapping = BTreeMap::new();
+        for (field, variant) in variants {
+            let value = field_json_name(field);
+            let schema = self.message(variant, &mapping_path(path, &value))?;
+            match schema.form() {
+                Form::Properties(required, optional, _)
+                    if !required.contains_key(tag) && !optional.contains_key(tag) => {}
+                _ => {
+                    self.approximations.truncate(approximations);
+                    self.referenced = referenced;
+                    return Ok(None);
+                }
+            }
+
+            mapping.insert(value, with_number(schema, field.number as u64));
+        }
+
+        if mapping.is_empty() {
+            self.approximate(
+                path,
+                format!(
+                    "oneof {} has no fields, so it becomes a discriminator with no mapping, which accepts no instances",
+                    tag
+                ),
+            );
+        }
+
+        self.approximate(
+            path,
+            format!(
+                "oneof {} is represented as a discriminator, whose JSON encoding puts the variant in a tag property",
+                tag
+            ),
+        );
+
+        Ok(Some(Schema::from_parts(
+            None,
+            Box::new(Form::Discriminator(tag.clone(), mapping)),
+            BTreeMap::new(),
+        )))
+    }
+
+    fn field(
+        &mut self,
+        scope: &str,
+        field: &FieldDecl,
+        path: &[String],
+    ) -> Result<Schema, ProtoError> {
+        match field.label {
+            FieldLabel::Repeated => {
+                let path = child_path(path, &["elements"]);
+                let schema = self.field_type(scope, field, &path)?;
+                Ok(Schema::from_parts(
+                    None,
+                    Box::new(Form::Elements(schema)),
+                    BTreeMap::new(),
+                ))
+            }
+            FieldLabel::Map(ref key) => {
+                if key != "string" {
+                    self.approximate(path, format!("map keys of type {} become strings", key));
+                }
+
+                let path = child_path(path, &["values"]);
+                let schema = self.field_type(scope, field, &path)?;
+                Ok(Schema::from_parts(
+                    None,
+                    Box::new(Form::Values(schema)),
+                    BTreeMap::new(),
+                ))
+            }
+            _ => self.field_type(scope, field, path),
+        }
+    }
+
+    fn field_type(
+        &mut self,
+        scope: &str,
+        field: &FieldDecl,
+        path: &[String],
+    ) -> Result<Schema, ProtoError> {
+        let file = self.file;
+        let form = match file.resolve(scope, &field.typ) {
+            Some(Resolved::Scalar(typ)) => Form::Type(typ),
+            Some(Resolved::Bytes) => {
+                self.approximate(path, "bytes become base64-encoded strings".to_owned());
+                Form::Type(Type::String)
+            }
+            Some(Resolved::Timestamp) => Form::Type(Type::Timestamp),
+            Some(Resolved::Value) => Form::Empty,
+            Some(Resolved::WellKnown(typ)) => {
+                self.approximate(path, format!("{} accepts any value", typ));
+                Form::Empty
+            }
+            Some(Resolved::Message(name)) => {
+                if self.is_def(name) {
+                    self.referenced.insert(name.to_owned());
+                    Form::Ref(name.to_owned())
+                } else {
+                    return self.message(name, path);
+                }
+            }
+            Some(Resolved::Enum(name)) => {
+                if self.is_def(name) {
+                    self.referenced.insert(name.to_owned());
+                    Form::Ref(name.to_owned())
+                } else {
+                    return Ok(self.enumeration(name));
+                }
+            }
+            None => return Err(invalid(field.line, format!("unknown type {:?}", field.typ))),
+        };
+
+        Ok(Schema::from_parts(None, Box::new(form), BTreeMap::new()))
+    }
+
+    fn enumeration(&mut self, name: &str) -> Schema {
+        let mut values = BTreeSet::new();
+        let mut numbers = Map::new();
+        for (value, number) in &self.file.enums[name] {
+            if *number == 0 && value.ends_with("UNSPECIFIED") {
+                continue;
+            }
+
+            values.insert(value.clone());
+            numbers.insert(value.clone(), Value::from(*number));
+        }
+
+        let mut extra = BTreeMap::new();
+        extra.insert(ENUM_NUMBERS.to_owned(), Value::Object(numbers));
+        Schema::from_parts(None, Box::new(Form::Enum(values)), extra)
+    }
+}
+
+fn field_json_name(field: &FieldDecl) -> String {
+    field
+        .json_name
+        .clone()
+        .unwrap_or_else(|| json_name(&field.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn schema(schema: serde_json::Value) -> Schema {
+        Schema::from_serde(serde_json::from_value(schema).unwrap()).unwrap()
+    }
+
+    fn schema_json(schema: Schema) -> serde_json::Value {
+        serde_json::to_value(schema.into_serde()).unwrap()
+    }
+
+    fn paths(approximations: &[Approximation]) -> Vec<String> {
+        approximations
+            .iter()
+            .map(|a| a.schema_path().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn generate() {
+        let mut config = Config::new();
+        config.root_name("User").package(Some("example.v1"));
+
+        let output = Generator::new_with_config(config).generate(&schema(json!({
+            "definitions": {
+                "event": {
+                    "description": "Something that happened.",
+                    "discriminator": {
+                        "tag": "kind",
+                        "mapping": {
+                            "created": {
+                                "properties": {
+                                    "at": { "type": "timestamp" },
+                                },
+                            },
+                            "deleted": {
+                                "properties": {},
+                                "protoField": 7,
+                            },
+                        },
+                    },
+                },
+                "status": {
+                    "enum": ["ACTIVE", "on-hold"],
+                    "protoEnumValues": { "on-hold": 2 },
+                },
+            },
+            "properties": {
+                "id": { "type": "uint64", "protoField": 1 },
+                "status": { "ref": "status" },
+                "events": { "elements": { "ref": "event" }, "protoField": 3 },
+                "scores": { "values": { "type": "int8" } },
+            },
+            "optionalProperties": {
+                "display-name": { "type": "string", "protoField": 2 },
+                "location": {
+                    "properties": {
+                        "lat": { "type": "float64" },
+                    },
+                },
+            },
+        })));
+
+        assert_eq!(
+            output.code(),
+            r#"syntax = "proto3";
+
+package example.v1;
+
+import "google/protobuf/timestamp.proto";
+
+message User {
+  message Location {
+    double lat = 1;
+  }
+
+  uint64 id = 1;
+  optional string display_name = 2 [json_name = "display-name"];
+  repeated Event events = 3;
+  optional Location location = 4;
+  map<string, int32> scores = 5;
+  Status status = 6;
+}
+
+// Something that happened.
+message Event {
+  message Created {
+    google.protobuf.Timestamp at = 1;
+  }
+
+  message Deleted {}
+
+  oneof kind {
+    Deleted deleted = 7;
+    Created created = 8;
+  }
+}
+
+enum Status {
+  STATUS_UNSPECIFIED = 0;
+  on_hold = 2;
+  ACTIVE = 3;
+}
+"#
+        );
+
+        assert_eq!(
+            paths(output.approximations()),
+            vec![
+                "/properties/id",
+                "/properties/scores/values",
+                "/definitions/event",
+                "/definitions/status",
+                "/definitions/status",
+            ]
+        );
+    }
+
+    #[test]
+    fn assign() {
+        let pinned = assign_numbers(&schema(json!({
+            "properties": {
+                "a": { "type": "string" },
+                "b": { "type": "string", "protoField": 18999 },
+                "c": { "enum": ["X", "Y"], "protoEnumValues": { "Y": 1 } },
+            },
+        })));
+
+        assert_eq!(
+            pinned,
+            schema(json!({
+                "properties": {
+                    "a": { "type": "string", "protoField": 20000 },
+                    "b": { "type": "string", "protoField": 18999 },
+                    "c": {
+                        "enum": ["X", "Y"],
+                        "protoEnumValues": { "X": 2, "Y": 1 },
+                        "protoField": 20001,
+                    },
+                },
+            }))
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let original = assign_numbers(&schema(json!({
+            "definitions": {
+                "Event": {
+                    "discriminator": {
+                        "tag": "kind",
+                        "mapping": {
+                            "created": {
+                                "properties": {
+                                    "at": { "type": "timestamp" },
+                                },
+                            },
+                            "deleted": { "properties": {} },
+                        },
+                    },
+                },
+                "Status": { "enum": ["ACTIVE", "INACTIVE"] },
+                "Tree": {
+                    "properties": {
+                        "children": { "elements": { "ref": "Tree" } },
+                    },
+                },
+            },
+            "properties": {
+                "id": { "type": "uint32" },
+                "status": { "ref": "Status" },
+                "events": { "elements": { "ref": "Event" } },
+                "tree": { "ref": "Tree" },
+                "labels": { "values": { "type": "string" } },
+                "extra": {},
+            },
+            "optionalProperties": {
+                "display-name": { "type": "string" },
+                "location": {
+                    "properties": {
+                        "lat": { "type": "float64" },
+                    },
+                },
+            },
+        })));
+
+        let output = Generator::new().generate(&original);
+        let (parsed, _) = parse(output.code(), "Root").unwrap();
+        assert_eq!(parsed, original);
+    }
+
+    #[test]
+    fn parse_proto() {
+        let (schema, approximations) = parse(
+            r#"
+            syntax = "proto3";
+            package shop;
+
+            import "google/protobuf/any.proto";
+
+            /* An order. */
+            message Order {
+              option deprecated = true;
+              reserved 2, 15 to 20;
+
+              message Line {
+                string sku = 1;
+                repeated Line parts = 2;
+              }
+
+              enum Priority {
+                PRIORITY_UNSPECIFIED = 0;
+                HIGH = 2 [deprecated = true];
+              }
+
+              int64 order_id = 1;
+              repeated Line lines = 3;
+              map<int32, .shop.Customer> customers = 4;
+              Priority priority = 5;
+              bytes signature = 6;
+              google.protobuf.Any metadata = 7;
+              oneof payment {
+                string card = 8;
+                string voucher = 9;
+              }
+            }
+
+            message Customer {
+              sint32 age = 1 [json_name = "AGE"];
+            }
+            "#,
+            "Order",
+        )
+        .unwrap();
+
+        assert_eq!(
+            schema_json(schema),
+            json!({
+                "definitions": {
+                    "Customer": {
+                        "properties": {
+                            "AGE": { "type": "int32", "protoField": 1 },
+                        },
+                    },
+                    "Order.Line": {
+                        "properties": {
+                            "sku": { "type": "string", "protoField": 1 },
+                            "parts": {
+                                "elements": { "ref": "Order.Line" },
+                                "protoField": 2,
+                            },
+                        },
+                    },
+                },
+                "properties": {
+                    "orderId": { "type": "int64", "protoField": 1 },
+                    "lines": { "elements": { "ref": "Order.Line" }, "protoField": 3 },
+                    "customers": { "values": { "ref": "Customer" }, "protoField": 4 },
+                    "priority": {
+                        "enum": ["HIGH"],
+                        "protoEnumValues": { "HIGH": 2 },
+                        "protoField": 5,
+                    },
+                    "signature": { "type": "string", "protoField": 6 },
+                    "metadata": { "protoField": 7 },
+                },
+                "optionalProperties": {
+                    "card": { "type": "string", "protoField": 8 },
+                    "voucher": { "type": "string", "protoField": 9 },
+                },
+            })
+        );
+
+        assert_eq!(
+            paths(&approximations),
+            vec![
+                "",
+                "/properties/orderId",
+                "/properties/lines",
+                "/properties/customers",
+                "/properties/customers",
+                "/properties/priority",
+                "/properties/signature",
+                "/properties/signature",
+                "/properties/metadata",
+                "/properties/metadata",
+                "/definitions/Customer/properties/AGE",
+                "/definitions/Order.Line/properties/sku",
+                "/definitions/Order.Line/properties/parts",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_recursive_oneof() {
+        let (schema, approximations) = parse(
+            r#"
+            syntax = "proto3";
+
+            message Expr {
+              oneof kind {
+                Expr neg = 1;
+                Lit lit = 2;
+              }
+            }
+
+            message Lit {
+              int64 value = 1;
+            }
+            "#,
+            "Expr",
+        )
+        .unwrap();
+
+        assert_eq!(
+            schema_json(schema),
+            json!({
+                "definitions": {
+                    "Expr": {
+                        "properties": {},
+                        "optionalProperties": {
+                            "neg": { "ref": "Expr", "protoField": 1 },
+                            "lit": { "ref": "Lit", "protoField": 2 },
+                        },
+                    },
+                    "Lit": {
+                        "properties": {
+                            "value": { "type": "int64", "protoField": 1 },
+                        },
+                    },
+                },
+                "properties": {},
+                "optionalProperties": {
+                    "neg": { "ref": "Expr", "protoField": 1 },
+                    "lit": { "ref": "Lit", "protoField": 2 },
+                },
+            })
+        );
+
+        assert_eq!(
+            paths(&approximations),
+            vec!["", "/definitions/Lit/properties/value", "/definitions/Expr"]
+        );
+    }
+
+    #[test]
+    fn parse_empty_oneof() {
+        let (schema, approximations) = parse("message Root {\n  oneof kind {}\n}", "Root").unwrap();
+
+        assert_eq!(
+            schema_json(schema),
+            json!({
+                "definitions": {},
+                "discriminator": { "tag": "kind", "mapping": {} },
+            })
+        );
+
+        assert_eq!(
+            approximations
+                .iter()
+                .map(|a| a.message().to_owned())
+                .collect::<Vec<_>>(),
+            vec![
+                "oneof kind has no fields, so it becomes a discriminator with no mapping, which accepts no instances",
+                "oneof kind is represented as a discriminator, whose JSON encoding puts the variant in a tag property",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            parse("syntax = \"proto2\";", "Root").unwrap_err(),
+            ProtoError::Invalid {
+                line: 1,
+                message: "only proto3 is supported".to_owned(),
+            }
+        );
+
+        assert_eq!(
+            parse("message Root {\n  Missing m = 1;\n}", "Root").unwrap_err(),
+            ProtoError::Invalid {
+                line: 2,
+                message: "unknown type \"Missing\"".to_owned(),
+            }
+        );
+
+        assert_eq!(
+            parse(
+                "message Root {\n  string a_b = 1;\n  string aB = 2;\n}",
+                "Root"
+            )
+            .unwrap_err(),
+            ProtoError::Invalid {
+                line: 3,
+                message: "fields a_b and aB have the same JSON name \"aB\"".to_owned(),
+            }
+        );
+
+        assert_eq!(
+            parse(
+                "message Root {\n  string a = 1;\n  string b = 2 [json_name = \"a\"];\n}",
+                "Root"
+            )
+            .unwrap_err(),
+            ProtoError::Invalid {
+                line: 3,
+                message: "fields a and b have the same JSON name \"a\"".to_owned(),
+            }
+        );
+
+        assert_eq!(
+            parse("message Root {}\nmessage Root {}", "Root").unwrap_err(),
+            ProtoError::Invalid {
+                line: 2,
+                message: "Root is already defined".to_owned(),
+            }
+        );
+
+        assert_eq!(
+            parse("message Root {}\nenum Root {\n  A = 0;\n}", "Root").unwrap_err(),
+            ProtoError::Invalid {
+                line: 2,
+                message: "Root is already defined".to_owned(),
+            }
+        );
+
+        assert_eq!(
+            parse("message Foo {}", "Root").unwrap_err(),
+            ProtoError::NoSuchDefinition {
+                definition: "Root".to_owned(),
+            }
+        );
+    }
+}
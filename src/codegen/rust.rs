@@ -0,0 +1,631 @@
+//! Generate Rust types from schemas.
+//!
+//! The generated code consists of plain structs and enums which derive
+//! serde's `Serialize` and `Deserialize`, such that deserializing JSON into
+//! the generated root type accepts nearly the same JSON as validating it
+//! against the schema. It depends only on `serde`, `serde_json`, and (for
+//! timestamps) `chrono`.
+//!
+//! Deserializing is not quite validating, however:
+//!
+//! * By default, unknown properties are ignored, as under non-strict
+//!   instance semantics. Enable
+//!   [`Config::strict_instance_semantics`](struct.Config.html#method.strict_instance_semantics)
+//!   to reject them with `#[serde(deny_unknown_fields)]`, as under strict
+//!   instance semantics.
+//! * Optional properties become `Option`s, so an optional property that is
+//!   `null` deserializes as `None`, whereas validation rejects it.
+//!
+//! Because generation is just a function from a `Schema` to a `String`, it can
+//! be run from a `build.rs` script:
+//!
+//! ```no_run
+//! use jsl::codegen::rust::Generator;
+//! use jsl::{Schema, SerdeSchema};
+//! use std::{env, fs, path::Path};
+//!
+//! let schema: SerdeSchema = serde_json::from_str(&fs::read_to_string("schema.json").unwrap()).unwrap();
+//! let schema = Schema::from_serde(schema).unwrap();
+//!
+//! let out_dir = env::var("OUT_DIR").unwrap();
+//! fs::write(Path::new(&out_dir).join("schema.rs"), Generator::new().generate(&schema)).unwrap();
+//! ```
+//!
+//! And then `include!(concat!(env!("OUT_DIR"), "/schema.rs"));` from within
+//! your crate.
+
+use crate::codegen::{description, pascal_case, snake_case, Names};
+use crate::schema::{Form, Schema, Type};
+use crate::visit::{Context, Visitor, Walker};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Generates Rust source code from schemas.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate Rust source code for a root schema.
+    ///
+    /// Each entry in the schema's `definitions` becomes a named type. The
+    /// root schema itself becomes a type named after the configured
+    /// `root_name`. Properties and discriminator forms become structs and
+    /// enums, and nested ones are named after the path that leads to them.
+    pub fn generate(&self, schema: &Schema) -> String {
+        let no_defs = BTreeMap::new();
+        let defs = schema.definitions().as_ref().unwrap_or(&no_defs);
+
+        let mut names = Names::default();
+        let root_name = names.claim(&self.config.root_name);
+        let def_names = defs
+            .keys()
+            .map(|name| (name.as_str(), names.claim(&pascal_case(name))))
+            .collect();
+
+        let mut state = State {
+            config: &self.config,
+            def_names,
+            boxed: boxed_refs(defs),
+            names,
+            items: Vec::new(),
+            uses_map: false,
+        };
+
+        state.named_type(schema, None, &root_name);
+        for (name, sub_schema) in defs {
+            let type_name = state.def_names[name.as_str()].clone();
+            state.named_type(sub_schema, Some(name), &type_name);
+        }
+
+        let mut out = String::new();
+        out.push_str("use serde::{Deserialize, Serialize};\n");
+        if state.uses_map {
+            out.push_str("use std::collections::HashMap;\n");
+        }
+
+        for item in state.items {
+            out.push('\n');
+            out.push_str(&item);
+        }
+
+        out
+    }
+}
+
+/// Configuration for how Rust code should be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    root_name: String,
+    timestamp_type: String,
+    strict_instance_semantics: bool,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the type generated for the root schema. The default
+    /// is `Root`.
+    pub fn root_name(&mut self, root_name: &str) -> &mut Self {
+        self.root_name = root_name.to_owned();
+        self
+    }
+
+    /// Sets the Rust type used for the `timestamp` type. The default is
+    /// `chrono::DateTime<chrono::FixedOffset>`.
+    pub fn timestamp_type(&mut self, timestamp_type: &str) -> &mut Self {
+        self.timestamp_type = timestamp_type.to_owned();
+        self
+    }
+
+    /// Sets whether generated types should follow strict instance semantics,
+    /// as described in
+    /// [`validator::Config`](../../validator/struct.Config.html#method.strict_instance_semantics).
+    ///
+    /// If enabled, structs and discriminator enums are marked
+    /// `#[serde(deny_unknown_fields)]`, so that deserializing fails on
+    /// properties the schema doesn't mention. By default, this is disabled.
+    pub fn strict_instance_semantics(&mut self, strict_instance_semantics: bool) -> &mut Self {
+        self.strict_instance_semantics = strict_instance_semantics;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            root_name: "Root".to_owned(),
+            timestamp_type: "chrono::DateTime<chrono::FixedOffset>".to_owned(),
+            strict_instance_semantics: false,
+        }
+    }
+}
+
+struct State<'a> {
+    config: &'a Config,
+    def_names: HashMap<&'a str, String>,
+    boxed: HashSet<(Option<&'a str>, &'a str)>,
+    names: Names,
+    items: Vec<String>,
+    uses_map: bool,
+}
+
+impl<'a> State<'a> {
+    /// Emit a named item for a top-level schema (the root or a definition).
+    fn named_type(&mut self, schema: &'a Schema, owner: Option<&'a str>, name: &str) {
+        match schema.form() {
+            Form::Enum(_) | Form::Properties(..) | Form::Discriminator(..) => {
+                self.item(schema, owner, name);
+            }
+            _ => {
+                let index = self.reserve_item();
+                let typ = self.type_expr(schema, owner, name, false);
+                let mut item = doc_comment("", description(schema));
+                item.push_str(&format!("pub type {} = {};\n", name, typ));
+                self.items[index] = item;
+            }
+        }
+    }
+
+    /// The Rust type expression for a schema, emitting any items it needs.
+    ///
+    /// `direct` indicates whether the value is stored inline in its parent,
+    /// rather than behind an indirection like `Vec` or `HashMap`. Recursive
+    /// refs which are direct must be boxed.
+    fn type_expr(
+        &mut self,
+        schema: &'a Schema,
+        owner: Option<&'a str>,
+        hint: &str,
+        direct: bool,
+    ) -> String {
+        match schema.form() {
+            Form::Empty => "serde_json::Value".to_owned(),
+            Form::Ref(def) => {
+                let name = self.def_names[def.as_str()].clone();
+                if direct && self.boxed.contains(&(owner, def.as_str())) {
+                    format!("Box<{}>", name)
+                } else {
+                    name
+                }
+            }
+            Form::Type(typ) => match typ {
+                Type::Boolean => "bool".to_owned(),
+                Type::Number | Type::Float64 => "f64".to_owned(),
+                Type::Float32 => "f32".to_owned(),
+                Type::Int8 => "i8".to_owned(),
+                Type::Uint8 => "u8".to_owned(),
+                Type::Int16 => "i16".to_owned(),
+                Type::Uint16 => "u16".to_owned(),
+                Type::Int32 => "i32".to_owned(),
+                Type::Uint32 => "u32".to_owned(),
+                Type::Int64 => "i64".to_owned(),
+                Type::Uint64 => "u64".to_owned(),
+                Type::String => "String".to_owned(),
+                Type::Timestamp => self.config.timestamp_type.clone(),
+            },
+            Form::Elements(sub_schema) => format!(
+                "Vec<{}>",
+                self.type_expr(sub_schema, owner, &format!("{}Item", hint), false)
+            ),
+            Form::Values(sub_schema) => {
+                self.uses_map = true;
+                format!(
+                    "HashMap<String, {}>",
+                    self.type_expr(sub_schema, owner, &format!("{}Value", hint), false)
+                )
+            }
+            Form::Enum(_) | Form::Properties(..) | Form::Discriminator(..) => {
+                let name = self.names.claim(hint);
+                self.item(schema, owner, &name);
+                name
+            }
+        }
+    }
+
+    /// Emit a struct or enum named `name` for a schema.
+    fn item(&mut self, schema: &'a Schema, owner: Option<&'a str>, name: &str) {
+        let index = self.reserve_item();
+        let mut item = doc_comment("", description(schema));
+
+        match schema.form() {
+            Form::Enum(values) => {
+                let values: Vec<_> = values.iter().collect();
+
+                item.push_str(
+                    "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]\n",
+                );
+                item.push_str(&format!("pub enum {} {{\n", name));
+
+                let mut variant_names = Names::default();
+                for value in values {
+                    let variant = variant_names.claim(&identifier(&pascal_case(value), "Empty"));
+                    item.push_str(&format!("    #[serde(rename = {:?})]\n", value));
+                    item.push_str(&format!("    {},\n", variant));
+                }
+
+                item.push_str("}\n");
+            }
+            Form::Properties(required, optional, _) => {
+                item.push_str("#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n");
+                if self.config.strict_instance_semantics {
+                    item.push_str("#[serde(deny_unknown_fields)]\n");
+                }
+
+                item.push_str(&format!("pub struct {} {{\n", name));
+                item.push_str(&self.fields(required, optional, owner, name, "    ", "pub "));
+                item.push_str("}\n");
+            }
+            Form::Discriminator(tag, mapping) => {
+                item.push_str("#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n");
+                if self.config.strict_instance_semantics {
+                    item.push_str(&format!("#[serde(tag = {:?}, deny_unknown_fields)]\n", tag));
+                } else {
+                    item.push_str(&format!("#[serde(tag = {:?})]\n", tag));
+                }
+
+                item.push_str(&format!("pub enum {} {{\n", name));
+
+                let mut variant_names = Names::default();
+                for (value, sub_schema) in mapping {
+                    let variant = variant_names.claim(&identifier(&pascal_case(value), "Empty"));
+                    item.push_str(&doc_comment("    ", description(sub_schema)));
+                    item.push_str(&format!("    #[serde(rename = {:?})]\n", value));
+
+                    if let Form::Properties(required, optional, _) = sub_schema.form() {
+                        let hint = format!("{}{}", name, variant);
+                        let fields = self.fields(required, optional, owner, &hint, "        ", "");
+                        if fields.is_empty() {
+                            item.push_str(&format!("    {} {{}},\n", variant));
+                        } else {
+                            item.push_str(&format!("    {} {{\n{}    }},\n", variant, fields));
+                        }
+                    }
+                }
+
+                item.push_str("}\n");
+            }
+            _ => unreachable!("item called on non-item form"),
+        }
+
+        self.items[index] = item;
+    }
+
+    /// Reserve a place for an item, so that it is emitted before any items
+    /// generated while constructing it.
+    fn reserve_item(&mut self) -> usize {
+        self.items.push(String::new());
+        self.items.len() - 1
+    }
+
+    /// Render the fields of a struct or struct-like enum variant.
+    fn fields(
+        &mut self,
+        required: &'a BTreeMap<String, Schema>,
+        optional: &'a BTreeMap<String, Schema>,
+        owner: Option<&'a str>,
+        parent: &str,
+        indent: &str,
+        visibility: &str,
+    ) -> String {
+        let mut props: Vec<_> = required
+            .iter()
+            .map(|(k, v)| (k, v, true))
+            .chain(optional.iter().map(|(k, v)| (k, v, false)))
+            .collect();
+        props.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut out = String::new();
+        let mut field_names = Names::default();
+        for (property, sub_schema, is_required) in props {
+            let field = field_names.claim(&identifier(&snake_case(property), "empty"));
+            let hint = format!("{}{}", parent, pascal_case(property));
+            let typ = self.type_expr(sub_schema, owner, &hint, true);
+
+            out.push_str(&doc_comment(indent, description(sub_schema)));
+            if field.trim_start_matches("r#") != property {
+                out.push_str(&format!("{}#[serde(rename = {:?})]\n", indent, property));
+            }
+
+            if is_required {
+                out.push_str(&format!("{}{}{}: {},\n", indent, visibility, field, typ));
+            } else {
+                out.push_str(&format!(
+                    "{}#[serde(default, skip_serializing_if = \"Option::is_none\")]\n",
+                    indent
+                ));
+                out.push_str(&format!(
+                    "{}{}{}: Option<{}>,\n",
+                    indent, visibility, field, typ
+                ));
+            }
+        }
+
+        out
+    }
+}
+
+/// Find the refs which must be boxed to avoid infinitely-sized types.
+///
+/// A ref from the root or a definition (the "owner") to another definition
+/// must be boxed if it is stored inline, and the referred-to definition can
+/// itself reach the owner through inline refs.
+fn boxed_refs(defs: &BTreeMap<String, Schema>) -> HashSet<(Option<&str>, &str)> {
+    /// Collects the refs stored inline, rather than behind a `Vec` or map.
+    struct InlineRefs<'a>(Vec<&'a str>);
+
+    impl<'a> Visitor<'a> for InlineRefs<'a> {
+        fn visit_schema(&mut self, cx: &mut Context<'a>, schema: &'a Schema) {
+            match schema.form() {
+                Form::Ref(def) => self.0.push(def),
+                Form::Properties(..) | Form::Discriminator(..) => cx.visit_children(self, schema),
+                _ => {}
+            }
+        }
+    }
+
+    let mut graph = HashMap::new();
+    for (name, sub_schema) in defs {
+        let mut refs = InlineRefs(Vec::new());
+        Walker::new().walk(sub_schema, &mut refs);
+        graph.insert(name.as_str(), refs.0);
+    }
+
+    let reaches = |from: &str, to: &str| {
+        let mut seen = HashSet::new();
+        let mut stack = vec![from];
+        while let Some(node) = stack.pop() {
+            if node == to {
+                return true;
+            }
+
+            if seen.insert(node) {
+                stack.extend(graph.get(node).into_iter().flatten());
+            }
+        }
+
+        false
+    };
+
+    let mut boxed = HashSet::new();
+    for (owner, refs) in &graph {
+        for def in refs {
+            if reaches(def, owner) {
+                boxed.insert((Some(*owner), *def));
+            }
+        }
+    }
+
+    boxed
+}
+
+/// Render a description as a doc comment.
+fn doc_comment(indent: &str, description: Option<&str>) -> String {
+    let mut out = String::new();
+    for line in description.into_iter().flat_map(str::lines) {
+        if line.is_empty() {
+            out.push_str(&format!("{}///\n", indent));
+        } else {
+            out.push_str(&format!("{}/// {}\n", indent, line));
+        }
+    }
+
+    out
+}
+
+/// Make a name usable as a Rust identifier.
+fn identifier(name: &str, fallback: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern",
+        "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use",
+        "where", "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv",
+        "try", "typeof", "unsized", "virtual", "yield",
+    ];
+
+    if name.is_empty() {
+        fallback.to_owned()
+    } else if name.starts_with(|c: char| c.is_numeric()) {
+        format!("_{}", name)
+    } else if ["self", "Self", "super", "crate"].contains(&name) {
+        format!("{}_", name)
+    } else if KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn generate(schema: serde_json::Value) -> String {
+        let schema = Schema::from_serde(serde_json::from_value(schema).unwrap()).unwrap();
+        Generator::new().generate(&schema)
+    }
+
+    #[test]
+    fn properties() {
+        assert_eq!(
+            generate(json!({
+                "description": "A user.",
+                "properties": {
+                    "userId": { "type": "uint64" },
+                    "type": { "enum": ["admin", "guest"] },
+                    "labels": { "values": { "type": "string" } },
+                },
+                "optionalProperties": {
+                    "address": {
+                        "description": "Where they live.",
+                        "properties": {
+                            "street": { "type": "string" },
+                        },
+                    },
+                    "createdAt": { "type": "timestamp" },
+                },
+            })),
+            r#"use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A user.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    /// Where they live.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<RootAddress>,
+    #[serde(rename = "createdAt")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+    pub labels: HashMap<String, String>,
+    pub r#type: RootType,
+    #[serde(rename = "userId")]
+    pub user_id: u64,
+}
+
+/// Where they live.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RootAddress {
+    pub street: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RootType {
+    #[serde(rename = "admin")]
+    Admin,
+    #[serde(rename = "guest")]
+    Guest,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn discriminator() {
+        assert_eq!(
+            generate(json!({
+                "definitions": {
+                    "event": {
+                        "discriminator": {
+                            "tag": "kind",
+                            "mapping": {
+                                "user_created": {
+                                    "properties": {
+                                        "name": { "type": "string" },
+                                    },
+                                },
+                                "deleted": {
+                                    "properties": {},
+                                },
+                            },
+                        },
+                    },
+                },
+                "elements": { "ref": "event" },
+            })),
+            r#"use serde::{Deserialize, Serialize};
+
+pub type Root = Vec<Event>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Event {
+    #[serde(rename = "deleted")]
+    Deleted {},
+    #[serde(rename = "user_created")]
+    UserCreated {
+        name: String,
+    },
+}
+"#
+        );
+    }
+
+    #[test]
+    fn strict_instance_semantics() {
+        let schema = Schema::from_serde(
+            serde_json::from_value(json!({
+                "properties": {
+                    "event": {
+                        "discriminator": {
+                            "tag": "kind",
+                            "mapping": {
+                                "deleted": { "properties": {} },
+                            },
+                        },
+                    },
+                },
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+
+        let mut config = Config::new();
+        config.strict_instance_semantics(true);
+        assert_eq!(
+            Generator::new_with_config(config).generate(&schema),
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Root {
+    pub event: RootEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", deny_unknown_fields)]
+pub enum RootEvent {
+    #[serde(rename = "deleted")]
+    Deleted {},
+}
+"#
+        );
+    }
+
+    #[test]
+    fn recursive_refs() {
+        let code = generate(json!({
+            "definitions": {
+                "node": {
+                    "properties": {
+                        "value": { "type": "int32" },
+                        "next": { "ref": "node" },
+                        "children": { "elements": { "ref": "node" } },
+                    },
+                },
+                "a": {
+                    "optionalProperties": {
+                        "b": { "ref": "b" },
+                    },
+                },
+                "b": {
+                    "properties": {
+                        "a": { "ref": "a" },
+                    },
+                },
+            },
+            "ref": "node",
+        }));
+
+        assert!(code.contains("pub type Root = Node;\n"));
+        assert!(code.contains("    pub next: Box<Node>,\n"));
+        assert!(code.contains("    pub children: Vec<Node>,\n"));
+        assert!(code.contains("    pub b: Option<Box<B>>,\n"));
+        assert!(code.contains("    pub a: Box<A>,\n"));
+    }
+}
@@ -0,0 +1,732 @@
+//! Generate SQL `CREATE TABLE` statements from schemas.
+//!
+//! The root schema, which should be of the properties form, becomes a table.
+//! Each property becomes a column, and optional properties become nullable
+//! columns. Properties which are themselves objects are flattened into the
+//! same table, with their column names joined by `_`.
+//!
+//! Arrays and maps (the elements and values forms) can't be stored in a
+//! single column in standard SQL. Depending on the configured
+//! [`NestedStyle`](enum.NestedStyle.html), they either become JSON columns,
+//! or child tables with a foreign key to their parent table. Child tables
+//! have an `_index` column for arrays, or a `_key` column for maps.
+//!
+//! Every table has a surrogate `_id` primary key, so that child tables have
+//! something to refer to. Integer types are mapped to the smallest column
+//! type which can hold them, with `CHECK` constraints where that type is
+//! larger than the JSL type. Enums are `TEXT` columns with a `CHECK`
+//! constraint listing their values.
+
+use crate::codegen::{
+    child_path, description, mapping_path, property_path, sorted_properties, type_name,
+    Approximation, Names, Output,
+};
+use crate::pointer::JsonPointer;
+use crate::schema::{Form, Schema, Type};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Generates SQL DDL from schemas.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate `CREATE TABLE` statements for a root schema.
+    pub fn generate(&self, schema: &Schema) -> Output {
+        let no_defs = BTreeMap::new();
+        let mut state = State {
+            config: &self.config,
+            defs: schema.definitions().as_ref().unwrap_or(&no_defs),
+            inlining: BTreeSet::new(),
+            table_names: Names::default(),
+            tables: Vec::new(),
+            approximations: Vec::new(),
+        };
+
+        let name = state.table_names.claim(&self.config.table_name);
+        state.table(&name, None, schema, &[]);
+        Output::new(state.tables.join("\n"), state.approximations)
+    }
+}
+
+/// The SQL dialect to generate.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Dialect {
+    /// PostgreSQL.
+    Postgres,
+
+    /// SQLite.
+    Sqlite,
+}
+
+/// How arrays and maps are stored.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum NestedStyle {
+    /// In a child table, with one row per element or entry.
+    ChildTables,
+
+    /// In a single JSON column.
+    Json,
+}
+
+/// Configuration for how SQL DDL should be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    table_name: String,
+    dialect: Dialect,
+    nested_style: NestedStyle,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the table generated for the root schema. Child
+    /// tables are named after it. The default is `root`.
+    pub fn table_name(&mut self, table_name: &str) -> &mut Self {
+        self.table_name = table_name.to_owned();
+        self
+    }
+
+    /// Sets the SQL dialect to generate. The default is
+    /// `Dialect::Postgres`.
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Sets how arrays and maps are stored. The default is
+    /// `NestedStyle::ChildTables`.
+    pub fn nested_style(&mut self, nested_style: NestedStyle) -> &mut Self {
+        self.nested_style = nested_style;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            table_name: "root".to_owned(),
+            dialect: Dialect::Postgres,
+            nested_style: NestedStyle::ChildTables,
+        }
+    }
+}
+
+/// The parent of a child table, and the column identifying each row within
+/// its parent.
+struct Parent<'b> {
+    table: &'b str,
+    column: &'static str,
+    column_type: &'static str,
+}
+
+#[derive(Default)]
+struct Table {
+    names: Names,
+    columns: Vec<String>,
+    constraints: Vec<String>,
+}
+
+struct State<'a> {
+    config: &'a Config,
+    defs: &'a BTreeMap<String, Schema>,
+    inlining: BTreeSet<&'a str>,
+    table_names: Names,
+    tables: Vec<String>,
+    approximations: Vec<Approximation>,
+}
+
+impl<'a> State<'a> {
+    fn approximate(&mut self, path: &[String], message: String) {
+        self.approximations
+            .push(Approximation::new(JsonPointer::new(path.to_vec()), message));
+    }
+
+    /// Emit a table, and any child tables it needs.
+    fn table(&mut self, name: &str, parent: Option<Parent>, schema: &'a Schema, path: &[String]) {
+        let index = self.tables.len();
+        self.tables.push(String::new());
+
+        let (id_type, reference_type) = match self.config.dialect {
+            Dialect::Postgres => ("BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY", "BIGINT"),
+            Dialect::Sqlite => ("INTEGER PRIMARY KEY", "INTEGER"),
+        };
+
+        let mut table = Table::default();
+        table.names.claim("_id");
+        table.columns.push(format!("_id {}", id_type));
+
+        if let Some(ref parent) = parent {
+            table.names.claim("_parent_id");
+            table.names.claim(parent.column);
+            table.columns.push(format!(
+                "_parent_id {} NOT NULL REFERENCES {} (_id) ON DELETE CASCADE",
+                reference_type,
+                identifier(parent.table)
+            ));
+            table
+                .columns
+                .push(format!("{} {} NOT NULL", parent.column, parent.column_type));
+            table
+                .constraints
+                .push(format!("UNIQUE (_parent_id, {})", parent.column));
+        }
+
+        if self.is_object(schema) {
+            self.column(&mut table, name, &[], schema, false, path);
+        } else {
+            if parent.is_none() {
+                self.approximate(
+                    path,
+                    "schema is not an object, so it is stored in a single column named `value`"
+                        .to_owned(),
+                );
+            }
+
+            self.column(&mut table, name, &["value"], schema, false, path);
+        }
+
+        let mut body = table.columns;
+        body.extend(table.constraints);
+        let body: Vec<_> = body.iter().map(|line| indent(line)).collect();
+        self.tables[index] = format!(
+            "CREATE TABLE {} (\n{}\n);\n",
+            identifier(name),
+            body.join(",\n")
+        );
+    }
+
+    /// Follow any refs from a schema, stopping at a cycle.
+    fn resolve(&self, mut schema: &'a Schema) -> &'a Schema {
+        let mut seen = BTreeSet::new();
+        while let Form::Ref(def) = schema.form() {
+            if !seen.insert(def) {
+                break;
+            }
+
+            schema = &self.defs[def];
+        }
+
+        schema
+    }
+
+    /// Whether a schema, following any refs, is flattened into columns.
+    fn is_object(&self, schema: &'a Schema) -> bool {
+        matches!(
+            self.resolve(schema).form(),
+            Form::Properties(..) | Form::Discriminator(..)
+        )
+    }
+
+    /// Add the columns for each property of a properties form.
+    fn columns(
+        &mut self,
+        table: &mut Table,
+        table_name: &str,
+        prefix: &[&str],
+        schema: &'a Schema,
+        nullable: bool,
+        path: &[String],
+    ) {
+        if let Form::Properties(required, optional, _) = schema.form() {
+            for (property, sub_schema, is_optional) in sorted_properties(required, optional) {
+                let mut parts = prefix.to_vec();
+                parts.push(property);
+                let path = property_path(path, property, is_optional);
+                if let Form::Properties(required, _, _) = self.resolve(sub_schema).form() {
+                    if (nullable || is_optional) && !required.is_empty() {
+                        self.approximate(
+                            &path,
+                            "optional object is flattened into nullable columns, so its required properties are nullable too"
+                                .to_owned(),
+                        );
+                    }
+                }
+
+                self.column(
+                    table,
+                    table_name,
+                    &parts,
+                    sub_schema,
+                    nullable || is_optional,
+                    &path,
+                );
+            }
+        }
+    }
+
+    /// Add the column, or columns, for a schema.
+    fn column(
+        &mut self,
+        table: &mut Table,
+        table_name: &str,
+        parts: &[&str],
+        schema: &'a Schema,
+        nullable: bool,
+        path: &[String],
+    ) {
+        let not_null = if nullable { "" } else { " NOT NULL" };
+        let column = |table: &mut Table| table.names.claim(&parts.join("_"));
+
+        match schema.form() {
+            Form::Empty => {
+                let name = column(table);
+                let typ = self.json_type();
+                table.columns.push(
+                    comment(description(schema))
+                        + &format!("{} {}{}", identifier(&name), typ, not_null),
+                );
+            }
+            Form::Ref(def) => {
+                let defs = self.defs;
+                let (def, sub_schema) = defs
+                    .get_key_value(def.as_str())
+                    .expect("reference to non-existent definition");
+
+                if !self.inlining.insert(def) {
+                    self.approximate(
+                        path,
+                        format!("definition {:?} is recursive, so it is stored as JSON", def),
+                    );
+
+                    let name = column(table);
+                    let typ = self.json_type();
+                    table.columns.push(
+                        comment(description(schema))
+                            + &format!("{} {}{}", identifier(&name), typ, not_null),
+                    );
+                    return;
+                }
+
+                let def_path = vec!["definitions".to_owned(), def.clone()];
+                self.column(table, table_name, parts, sub_schema, nullable, &def_path);
+                self.inlining.remove(def.as_str());
+            }
+            Form::Type(typ) => {
+                let name = identifier(&column(table));
+                let (sql, check) = self.scalar(typ, &name, path);
+                let mut def = format!("{} {}{}", name, sql, not_null);
+                if let Some(check) = check {
+                    def.push_str(&format!(" CHECK ({})", check));
+                }
+
+                table.columns.push(comment(description(schema)) + &def);
+            }
+            Form::Enum(values) => {
+                let name = identifier(&column(table));
+                table.columns.push(format!(
+                    "{}{} TEXT{} CHECK ({})",
+                    comment(description(schema)),
+                    name,
+                    not_null,
+                    one_of(&name, values.iter())
+                ));
+            }
+            Form::Properties(..) => {
+                self.columns(table, table_name, parts, schema, nullable, path);
+            }
+            Form::Discriminator(tag, mapping) => {
+                self.approximate(
+                    path,
+                    "discriminator is flattened into nullable columns for every variant".to_owned(),
+                );
+
+                let mut tag_parts = parts.to_vec();
+                tag_parts.push(tag);
+                let name = identifier(&table.names.claim(&tag_parts.join("_")));
+                table.columns.push(format!(
+                    "{} TEXT{} CHECK ({})",
+                    name,
+                    not_null,
+                    one_of(&name, mapping.keys())
+                ));
+
+                for (value, sub_schema) in mapping {
+                    let path = mapping_path(path, value);
+                    self.columns(table, table_name, parts, sub_schema, true, &path);
+                }
+            }
+            Form::Elements(sub_schema) | Form::Values(sub_schema) => {
+                let is_elements = matches!(schema.form(), Form::Elements(_));
+                if self.config.nested_style == NestedStyle::Json {
+                    self.approximate(
+                        path,
+                        "array or map is stored as JSON, so its contents are unconstrained"
+                            .to_owned(),
+                    );
+
+                    let name = column(table);
+                    let typ = self.json_type();
+                    table.columns.push(
+                        comment(description(schema))
+                            + &format!("{} {}{}", identifier(&name), typ, not_null),
+                    );
+                    return;
+                }
+
+                if nullable {
+                    self.approximate(
+                        path,
+                        "a child table cannot distinguish a missing array or map from an empty one"
+                            .to_owned(),
+                    );
+                }
+
+                let child = self
+                    .table_names
+                    .claim(&format!("{}_{}", table_name, parts.join("_")));
+                let (keyword, parent) = if is_elements {
+                    (
+                        "elements",
+                        Parent {
+                            table: table_name,
+                            column: "_index",
+                            column_type: "INTEGER",
+                        },
+                    )
+                } else {
+                    (
+                        "values",
+                        Parent {
+                            table: table_name,
+                            column: "_key",
+                            column_type: "TEXT",
+                        },
+                    )
+                };
+
+                let path = child_path(path, &[keyword]);
+                self.table(&child, Some(parent), sub_schema, &path);
+            }
+        }
+    }
+
+    fn json_type(&self) -> &'static str {
+        match self.config.dialect {
+            Dialect::Postgres => "JSONB",
+            Dialect::Sqlite => "TEXT",
+        }
+    }
+
+    /// The column type for a JSL type, and a `CHECK` constraint if the column
+    /// type is larger than the JSL type.
+    fn scalar(
+        &mut self,
+        typ: &Type,
+        column: &str,
+        path: &[String],
+    ) -> (&'static str, Option<String>) {
+        let range = |min: &str, max: &str| Some(format!("{} BETWEEN {} AND {}", column, min, max));
+
+        match self.config.dialect {
+            Dialect::Postgres => match typ {
+                Type::Boolean => ("BOOLEAN", None),
+                Type::Float32 => ("REAL", None),
+                Type::Number | Type::Float64 => ("DOUBLE PRECISION", None),
+                Type::Int8 => ("SMALLINT", range("-128", "127")),
+                Type::Uint8 => ("SMALLINT", range("0", "255")),
+                Type::Int16 => ("SMALLINT", None),
+                Type::Uint16 => ("INTEGER", range("0", "65535")),
+                Type::Int32 => ("INTEGER", None),
+                Type::Uint32 => ("BIGINT", range("0", "4294967295")),
+                Type::Int64 => ("BIGINT", None),
+                Type::Uint64 => ("NUMERIC(20)", range("0", "18446744073709551615")),
+                Type::String => ("TEXT", None),
+                Type::Timestamp => {
+                    self.approximate(
+                        path,
+                        "TIMESTAMPTZ does not preserve a timestamp's UTC offset".to_owned(),
+                    );
+                    ("TIMESTAMPTZ", None)
+                }
+            },
+            Dialect::Sqlite => match typ {
+                Type::Boolean => ("INTEGER", Some(format!("{} IN (0, 1)", column))),
+                Type::Number | Type::Float32 | Type::Float64 => ("REAL", None),
+                Type::Int8 => ("INTEGER", range("-128", "127")),
+                Type::Uint8 => ("INTEGER", range("0", "255")),
+                Type::Int16 => ("INTEGER", range("-32768", "32767")),
+                Type::Uint16 => ("INTEGER", range("0", "65535")),
+                Type::Int32 => ("INTEGER", range("-2147483648", "2147483647")),
+                Type::Uint32 => ("INTEGER", range("0", "4294967295")),
+                Type::Int64 => ("INTEGER", None),
+                Type::Uint64 => {
+                    self.approximate(
+                        path,
+                        format!(
+                            "SQLite stores {} values above 2^63 - 1 as floating point",
+                            type_name(typ)
+                        ),
+                    );
+                    ("NUMERIC", range("0", "18446744073709551615"))
+                }
+                Type::String | Type::Timestamp => ("TEXT", None),
+            },
+        }
+    }
+}
+
+/// A `CHECK` condition that a column is one of a set of strings.
+fn one_of<'b>(column: &str, values: impl Iterator<Item = &'b String>) -> String {
+    let mut values: Vec<_> = values.map(|v| string_literal(v)).collect();
+    values.sort();
+    format!("{} IN ({})", column, values.join(", "))
+}
+
+fn string_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Render an identifier, quoting it unless it is a plain lowercase name.
+fn identifier(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "all",
+        "and",
+        "as",
+        "asc",
+        "between",
+        "by",
+        "case",
+        "check",
+        "column",
+        "constraint",
+        "create",
+        "default",
+        "delete",
+        "desc",
+        "distinct",
+        "drop",
+        "else",
+        "end",
+        "exists",
+        "foreign",
+        "from",
+        "group",
+        "having",
+        "in",
+        "index",
+        "insert",
+        "into",
+        "is",
+        "join",
+        "key",
+        "like",
+        "limit",
+        "not",
+        "null",
+        "offset",
+        "on",
+        "or",
+        "order",
+        "primary",
+        "references",
+        "select",
+        "set",
+        "table",
+        "then",
+        "to",
+        "union",
+        "unique",
+        "update",
+        "user",
+        "using",
+        "values",
+        "when",
+        "where",
+        "with",
+    ];
+
+    let plain = name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+    if plain && !KEYWORDS.contains(&name) {
+        name.to_owned()
+    } else {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+/// Render a description as SQL comments, to go before a column.
+fn comment(description: Option<&str>) -> String {
+    description
+        .into_iter()
+        .flat_map(str::lines)
+        .map(|line| format!("-- {}\n", line).replace("-- \n", "--\n"))
+        .collect()
+}
+
+fn indent(s: &str) -> String {
+    s.lines()
+        .map(|line| format!("  {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn user() -> Schema {
+        Schema::from_serde(
+            serde_json::from_value(json!({
+                "definitions": {
+                    "address": {
+                        "properties": {
+                            "street": { "type": "string" },
+                        },
+                    },
+                },
+                "properties": {
+                    "id": { "type": "uint64", "description": "The user's ID." },
+                    "age": { "type": "int8" },
+                    "createdAt": { "type": "timestamp" },
+                    "status": { "enum": ["ACTIVE", "INACTIVE"] },
+                    "home": { "ref": "address" },
+                    "tags": { "elements": { "type": "string" } },
+                    "order": {
+                        "values": {
+                            "properties": {
+                                "qty": { "type": "uint32" },
+                            },
+                        },
+                    },
+                },
+                "optionalProperties": {
+                    "work": { "ref": "address" },
+                    "verified": { "type": "boolean" },
+                },
+            }))
+            .unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn paths(output: &Output) -> Vec<String> {
+        output
+            .approximations()
+            .iter()
+            .map(|a| a.schema_path().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn postgres_child_tables() {
+        let mut config = Config::new();
+        config.table_name("users");
+        let output = Generator::new_with_config(config).generate(&user());
+
+        assert_eq!(
+            output.code(),
+            r#"CREATE TABLE users (
+  _id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+  age SMALLINT NOT NULL CHECK (age BETWEEN -128 AND 127),
+  "createdAt" TIMESTAMPTZ NOT NULL,
+  home_street TEXT NOT NULL,
+  -- The user's ID.
+  id NUMERIC(20) NOT NULL CHECK (id BETWEEN 0 AND 18446744073709551615),
+  status TEXT NOT NULL CHECK (status IN ('ACTIVE', 'INACTIVE')),
+  verified BOOLEAN,
+  work_street TEXT
+);
+
+CREATE TABLE users_order (
+  _id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+  _parent_id BIGINT NOT NULL REFERENCES users (_id) ON DELETE CASCADE,
+  _key TEXT NOT NULL,
+  qty BIGINT NOT NULL CHECK (qty BETWEEN 0 AND 4294967295),
+  UNIQUE (_parent_id, _key)
+);
+
+CREATE TABLE users_tags (
+  _id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+  _parent_id BIGINT NOT NULL REFERENCES users (_id) ON DELETE CASCADE,
+  _index INTEGER NOT NULL,
+  value TEXT NOT NULL,
+  UNIQUE (_parent_id, _index)
+);
+"#
+        );
+
+        assert_eq!(
+            paths(&output),
+            vec!["/properties/createdAt", "/optionalProperties/work"]
+        );
+    }
+
+    #[test]
+    fn sqlite_json() {
+        let mut config = Config::new();
+        config
+            .dialect(Dialect::Sqlite)
+            .nested_style(NestedStyle::Json);
+        let output = Generator::new_with_config(config).generate(&user());
+
+        assert_eq!(
+            output.code(),
+            r#"CREATE TABLE root (
+  _id INTEGER PRIMARY KEY,
+  age INTEGER NOT NULL CHECK (age BETWEEN -128 AND 127),
+  "createdAt" TEXT NOT NULL,
+  home_street TEXT NOT NULL,
+  -- The user's ID.
+  id NUMERIC NOT NULL CHECK (id BETWEEN 0 AND 18446744073709551615),
+  "order" TEXT NOT NULL,
+  status TEXT NOT NULL CHECK (status IN ('ACTIVE', 'INACTIVE')),
+  tags TEXT NOT NULL,
+  verified INTEGER CHECK (verified IN (0, 1)),
+  work_street TEXT
+);
+"#
+        );
+
+        assert_eq!(
+            paths(&output),
+            vec![
+                "/properties/id",
+                "/properties/order",
+                "/properties/tags",
+                "/optionalProperties/work",
+            ]
+        );
+    }
+
+    #[test]
+    fn child_table_names() {
+        let schema = Schema::from_serde(
+            serde_json::from_value(json!({
+                "properties": {
+                    "a_b": { "elements": { "type": "string" } },
+                    "a": {
+                        "properties": {
+                            "b": { "elements": { "type": "string" } },
+                        },
+                    },
+                },
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+
+        let output = Generator::new().generate(&schema);
+        assert!(output.code().contains("CREATE TABLE root_a_b (\n"));
+        assert!(output.code().contains("CREATE TABLE root_a_b2 (\n"));
+    }
+}
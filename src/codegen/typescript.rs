@@ -0,0 +1,499 @@
+//! Generate TypeScript type declarations from schemas.
+//!
+//! The output is meant to be saved as a `.d.ts` file. Every definition
+//! becomes an exported type, named in `PascalCase`:
+//!
+//! * Properties forms become interfaces.
+//! * Discriminator forms become a union of interfaces, each of which has the
+//!   tag property typed as a string literal.
+//! * Enum forms become unions of string literals.
+//! * Values forms become `Record<string, T>`.
+//!
+//! TypeScript has only one number type, so every numeric `type` maps to
+//! `number`. Except for `number` itself, the original JSL type is recorded in
+//! a JSDoc `@format` tag, so that tooling and readers can recover the
+//! intended range.
+
+use crate::codegen::{description, pascal_case, type_name, Names};
+use crate::schema::{Form, Schema, Type};
+use std::collections::BTreeMap;
+
+/// Generates TypeScript declarations from schemas.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate TypeScript declarations for a root schema.
+    pub fn generate(&self, schema: &Schema) -> String {
+        let no_defs = BTreeMap::new();
+        let defs = schema.definitions().as_ref().unwrap_or(&no_defs);
+
+        let mut names = Names::default();
+        let root_name = names.claim(&self.config.root_name);
+        let def_names = defs
+            .keys()
+            .map(|name| (name.as_str(), names.claim(&pascal_case(name))))
+            .collect();
+
+        let mut state = State {
+            def_names,
+            names,
+            items: Vec::new(),
+        };
+
+        state.named_type(schema, &root_name);
+        for (name, sub_schema) in defs {
+            let type_name = state.def_names[name.as_str()].clone();
+            state.named_type(sub_schema, &type_name);
+        }
+
+        state.items.join("\n")
+    }
+}
+
+/// Configuration for how TypeScript declarations should be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    root_name: String,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the type generated for the root schema. The default
+    /// is `Root`.
+    pub fn root_name(&mut self, root_name: &str) -> &mut Self {
+        self.root_name = root_name.to_owned();
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            root_name: "Root".to_owned(),
+        }
+    }
+}
+
+struct State<'a> {
+    def_names: BTreeMap<&'a str, String>,
+    names: Names,
+    items: Vec<String>,
+}
+
+impl<'a> State<'a> {
+    /// Emit an exported declaration for a schema.
+    fn named_type(&mut self, schema: &'a Schema, name: &str) {
+        let index = self.items.len();
+        self.items.push(String::new());
+
+        let mut item = jsdoc("", description(schema), format(schema));
+        match schema.form() {
+            Form::Properties(required, optional, _) => {
+                item.push_str(&format!("export interface {} {{\n", name));
+                item.push_str(&self.members(None, required, optional, name));
+                item.push_str("}\n");
+            }
+            Form::Discriminator(tag, mapping) => {
+                let mut variants = Vec::new();
+                for (value, sub_schema) in mapping {
+                    let variant = self.names.claim(&format!("{}{}", name, pascal_case(value)));
+                    let mut variant_item = jsdoc("", description(sub_schema), None);
+                    variant_item.push_str(&format!("export interface {} {{\n", variant));
+
+                    if let Form::Properties(required, optional, _) = sub_schema.form() {
+                        variant_item.push_str(&self.members(
+                            Some((tag, value)),
+                            required,
+                            optional,
+                            &variant,
+                        ));
+                    }
+
+                    variant_item.push_str("}\n");
+                    self.items.push(variant_item);
+                    variants.push(variant);
+                }
+
+                item.push_str(&format!("export type {} = {};\n", name, union(variants)));
+            }
+            _ => {
+                let typ = self.type_expr(schema, name);
+                item.push_str(&format!("export type {} = {};\n", name, typ));
+            }
+        }
+
+        self.items[index] = item;
+    }
+
+    /// The TypeScript type expression for a schema.
+    fn type_expr(&mut self, schema: &'a Schema, hint: &str) -> String {
+        match schema.form() {
+            Form::Empty => "unknown".to_owned(),
+            Form::Ref(def) => self.def_names[def.as_str()].clone(),
+            Form::Type(Type::Boolean) => "boolean".to_owned(),
+            Form::Type(Type::String) | Form::Type(Type::Timestamp) => "string".to_owned(),
+            Form::Type(_) => "number".to_owned(),
+            Form::Enum(values) => {
+                let mut values: Vec<_> = values.iter().map(|v| string_literal(v)).collect();
+                values.sort();
+                union(values)
+            }
+            Form::Elements(sub_schema) => {
+                let typ = self.type_expr(sub_schema, &format!("{}Item", hint));
+                if typ.contains(' ') {
+                    format!("Array<{}>", typ)
+                } else {
+                    format!("{}[]", typ)
+                }
+            }
+            Form::Values(sub_schema) => format!(
+                "Record<string, {}>",
+                self.type_expr(sub_schema, &format!("{}Value", hint))
+            ),
+            Form::Properties(..) | Form::Discriminator(..) => {
+                let name = self.names.claim(hint);
+                self.named_type(schema, &name);
+                name
+            }
+        }
+    }
+
+    /// Render the members of an interface.
+    fn members(
+        &mut self,
+        tag: Option<(&str, &str)>,
+        required: &'a BTreeMap<String, Schema>,
+        optional: &'a BTreeMap<String, Schema>,
+        parent: &str,
+    ) -> String {
+        let mut out = String::new();
+        if let Some((tag, value)) = tag {
+            out.push_str(&format!(
+                "  {}: {};\n",
+                property_name(tag),
+                string_literal(value)
+            ));
+        }
+
+        let mut props: Vec<_> = required
+            .iter()
+            .map(|(k, v)| (k, v, ""))
+            .chain(optional.iter().map(|(k, v)| (k, v, "?")))
+            .collect();
+        props.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (property, sub_schema, question) in props {
+            let typ = self.type_expr(sub_schema, &format!("{}{}", parent, pascal_case(property)));
+            out.push_str(&jsdoc("  ", description(sub_schema), format(sub_schema)));
+            out.push_str(&format!(
+                "  {}{}: {};\n",
+                property_name(property),
+                question,
+                typ
+            ));
+        }
+
+        out
+    }
+}
+
+/// The JSL type which a schema's TypeScript type loses information about, if
+/// any.
+///
+/// Every numeric type other than `number`, and timestamps, are represented as
+/// plain `number` or `string` in TypeScript. This returns the name of the original type, looking
+/// through elements and values, for use in a `@format` tag.
+fn format(schema: &Schema) -> Option<String> {
+    match schema.form() {
+        Form::Type(Type::Boolean) | Form::Type(Type::String) | Form::Type(Type::Number) => None,
+        Form::Type(typ) => Some(type_name(typ).to_owned()),
+        Form::Elements(sub_schema) => format(sub_schema).map(|f| format!("{}[]", f)),
+        Form::Values(sub_schema) => format(sub_schema).map(|f| format!("Record<string, {}>", f)),
+        _ => None,
+    }
+}
+
+/// Render a JSDoc comment from a description and a `@format` tag.
+fn jsdoc(indent: &str, description: Option<&str>, format: Option<String>) -> String {
+    let mut lines: Vec<String> = description
+        .into_iter()
+        .flat_map(str::lines)
+        .map(|line| line.replace("*/", "*\\/"))
+        .collect();
+
+    if let Some(format) = format {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+
+        lines.push(format!("@format {}", format));
+    }
+
+    match lines.len() {
+        0 => String::new(),
+        1 => format!("{}/** {} */\n", indent, lines[0]),
+        _ => {
+            let mut out = format!("{}/**\n", indent);
+            for line in lines {
+                if line.is_empty() {
+                    out.push_str(&format!("{} *\n", indent));
+                } else {
+                    out.push_str(&format!("{} * {}\n", indent, line));
+                }
+            }
+
+            out.push_str(&format!("{} */\n", indent));
+            out
+        }
+    }
+}
+
+fn union(members: Vec<String>) -> String {
+    if members.is_empty() {
+        "never".to_owned()
+    } else {
+        members.join(" | ")
+    }
+}
+
+fn string_literal(value: &str) -> String {
+    serde_json::to_string(value).expect("error serializing string")
+}
+
+/// Render a property name, quoting it if it isn't a valid identifier.
+fn property_name(name: &str) -> String {
+    let mut chars = name.chars();
+    let is_identifier = match chars.next() {
+        Some(first) => {
+            (first.is_ascii_alphabetic() || first == '_' || first == '$')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        }
+        None => false,
+    };
+
+    if is_identifier {
+        name.to_owned()
+    } else {
+        string_literal(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn generate(schema: serde_json::Value) -> String {
+        let schema = Schema::from_serde(serde_json::from_value(schema).unwrap()).unwrap();
+        Generator::new().generate(&schema)
+    }
+
+    #[test]
+    fn declarations() {
+        assert_eq!(
+            generate(json!({
+                "definitions": {
+                    "event": {
+                        "description": "Something that happened.",
+                        "discriminator": {
+                            "tag": "kind",
+                            "mapping": {
+                                "created": {
+                                    "properties": {
+                                        "at": { "type": "timestamp" },
+                                    },
+                                },
+                                "deleted": {
+                                    "properties": {},
+                                },
+                            },
+                        },
+                    },
+                    "status": { "enum": ["ACTIVE", "INACTIVE"] },
+                },
+                "properties": {
+                    "id": {
+                        "description": "The user's ID.",
+                        "type": "uint64",
+                    },
+                    "status": { "ref": "status" },
+                    "events": { "elements": { "ref": "event" } },
+                    "scores": { "values": { "type": "int8" } },
+                },
+                "optionalProperties": {
+                    "display-name": { "type": "string" },
+                    "location": {
+                        "properties": {
+                            "lat": { "type": "float64" },
+                        },
+                    },
+                },
+            })),
+            r#"export interface Root {
+  "display-name"?: string;
+  events: Event[];
+  /**
+   * The user's ID.
+   *
+   * @format uint64
+   */
+  id: number;
+  location?: RootLocation;
+  /** @format Record<string, int8> */
+  scores: Record<string, number>;
+  status: Status;
+}
+
+export interface RootLocation {
+  /** @format float64 */
+  lat: number;
+}
+
+/** Something that happened. */
+export type Event = EventCreated | EventDeleted;
+
+export interface EventCreated {
+  kind: "created";
+  /** @format timestamp */
+  at: string;
+}
+
+export interface EventDeleted {
+  kind: "deleted";
+}
+
+export type Status = "ACTIVE" | "INACTIVE";
+"#
+        );
+    }
+
+    #[test]
+    fn recursive() {
+        assert_eq!(
+            generate(json!({
+                "definitions": {
+                    "node": {
+                        "properties": {
+                            "value": { "type": "number" },
+                            "children": { "elements": { "ref": "node" } },
+                        },
+                        "optionalProperties": {
+                            "parent": { "ref": "node" },
+                        },
+                    },
+                },
+                "ref": "node",
+            })),
+            r#"export type Root = Node;
+
+export interface Node {
+  children: Node[];
+  parent?: Node;
+  value: number;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn containers() {
+        assert_eq!(
+            generate(json!({
+                "properties": {
+                    "grid": { "elements": { "elements": { "type": "uint8" } } },
+                    "labels": { "values": { "elements": { "type": "string" } } },
+                    "levels": { "elements": { "enum": ["LOW", "HIGH"] } },
+                    "nested": { "values": { "values": { "type": "boolean" } } },
+                    "points": {
+                        "elements": {
+                            "properties": { "x": { "type": "float32" } },
+                        },
+                    },
+                    "anything": { "values": {} },
+                },
+            })),
+            r#"export interface Root {
+  anything: Record<string, unknown>;
+  /** @format uint8[][] */
+  grid: number[][];
+  labels: Record<string, string[]>;
+  levels: Array<"HIGH" | "LOW">;
+  nested: Record<string, Record<string, boolean>>;
+  points: RootPointsItem[];
+}
+
+export interface RootPointsItem {
+  /** @format float32 */
+  x: number;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn property_names() {
+        assert_eq!(
+            generate(json!({
+                "properties": {
+                    "$ref": { "type": "string" },
+                    "_id": { "type": "string" },
+                    "1st": { "type": "string" },
+                    "say \"hi\"": { "type": "string" },
+                    "back\\slash": { "type": "string" },
+                    "": { "type": "string" },
+                },
+            })),
+            r#"export interface Root {
+  "": string;
+  $ref: string;
+  "1st": string;
+  _id: string;
+  "back\\slash": string;
+  "say \"hi\"": string;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            generate(json!({
+                "description": "Ends early */ if unescaped.",
+                "properties": {
+                    "a": {
+                        "description": "First line.\n*/ Second line.",
+                        "type": "number",
+                    },
+                },
+            })),
+            r#"/** Ends early *\/ if unescaped. */
+export interface Root {
+  /**
+   * First line.
+   * *\/ Second line.
+   */
+  a: number;
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,504 @@
+//! Measure how much of a schema a corpus of instances exercises.
+//!
+//! A [`Coverage`](struct.Coverage.html) validates instances against a
+//! schema, and counts how often each subschema applied to some part of an
+//! instance. Among other things, this shows:
+//!
+//! * which optional properties were ever present,
+//! * which discriminator mappings were ever chosen, and
+//! * which definitions were ever reached through a `ref`.
+//!
+//! For enum schemas, each of the values is counted as well.
+//!
+//! Coverage can be reported as JSON, or as lcov-like text in which schema
+//! paths stand in for line numbers, and enum values for branches.
+//!
+//! ```
+//! use jsl::coverage::Coverage;
+//! use jsl::{Schema, SerdeSchema};
+//! use serde_json::json;
+//!
+//! let schema: SerdeSchema = serde_json::from_value(json!({
+//!     "properties": { "color": { "enum": ["red", "green"] } },
+//!     "optionalProperties": { "name": { "type": "string" } },
+//! })).unwrap();
+//! let schema = Schema::from_serde(schema).unwrap();
+//!
+//! let mut coverage = Coverage::new(&schema);
+//! coverage.add(&json!({ "color": "red" })).unwrap();
+//! coverage.add(&json!({ "color": "red" })).unwrap();
+//!
+//! let color = &coverage.entries()[1];
+//! assert_eq!(color.path(), "/properties/color");
+//! assert_eq!(color.hits(), 2);
+//! assert_eq!(color.values().unwrap()["green"], 0);
+//!
+//! let name = &coverage.entries()[2];
+//! assert_eq!(name.path(), "/optionalProperties/name");
+//! assert_eq!(name.hits(), 0);
+//! ```
+
+use crate::errors::JslError;
+use crate::pointer::JsonPointer;
+use crate::schema::{Form, Schema};
+use crate::validator::{Config, ValidationError, Validator};
+use crate::visit::{Context, Visitor, Walker};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// The kinds of subschema that coverage is reported for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Kind {
+    /// The root schema, or the schema of elements, values, or a required
+    /// property.
+    Schema,
+
+    /// The schema of an optional property. It applies whenever the property
+    /// is present.
+    OptionalProperty,
+
+    /// A discriminator mapping. It applies whenever its tag value is seen.
+    Mapping,
+
+    /// A definition. It applies whenever a `ref` to it is followed.
+    Definition,
+}
+
+/// A subschema, and how often it applied.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Entry {
+    path: String,
+    kind: Kind,
+    hits: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    values: Option<BTreeMap<String, usize>>,
+}
+
+impl Entry {
+    /// The schema path of the subschema.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// What kind of subschema this is.
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// How many times the subschema applied to part of an instance.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// For enum schemas, how many times each value was seen.
+    pub fn values(&self) -> Option<&BTreeMap<String, usize>> {
+        self.values.as_ref()
+    }
+}
+
+/// Collects coverage of a schema over many instances.
+#[derive(Debug)]
+pub struct Coverage<'a> {
+    schema: &'a Schema,
+    validator: Validator,
+    instances: usize,
+    entries: Vec<Entry>,
+
+    /// The index into `entries` of each subschema, by address.
+    index: BTreeMap<usize, usize>,
+}
+
+impl<'a> Coverage<'a> {
+    /// Constructs a new collector, which validates using the default
+    /// configuration.
+    pub fn new(schema: &'a Schema) -> Self {
+        Self::new_with_config(schema, Config::default())
+    }
+
+    /// Constructs a new collector, which validates using a configuration.
+    ///
+    /// Validation stops at `max_errors`, so parts of an invalid instance
+    /// after that are not counted.
+    pub fn new_with_config(schema: &'a Schema, config: Config) -> Self {
+        let mut entries = Entries::default();
+        Walker::new().walk(schema, &mut entries);
+
+        Coverage {
+            schema,
+            validator: Validator::new_with_config(config),
+            instances: 0,
+            entries: entries.entries,
+            index: entries.index,
+        }
+    }
+
+    /// Validate an instance, counting the subschemas which applied to it.
+    ///
+    /// Invalid instances are counted too. Their validation errors are
+    /// returned, so that a corpus of fixtures can be checked at the same
+    /// time.
+    pub fn add(&mut self, instance: &Value) -> Result<Vec<ValidationError<'static>>, JslError> {
+        let annotations = self.validator.annotate(self.schema, instance)?;
+
+        for annotation in annotations.annotations() {
+            for schema in &annotation.schemas {
+                if let Some(&i) = self.index.get(&address(schema)) {
+                    self.entries[i].hits += 1;
+                }
+            }
+
+            let i = match self.index.get(&address(annotation.schema())) {
+                Some(&i) => i,
+                None => continue,
+            };
+
+            if let Some(values) = &mut self.entries[i].values {
+                let value = instance
+                    .pointer(&annotation.instance_path().to_string())
+                    .and_then(Value::as_str)
+                    .and_then(|value| values.get_mut(value));
+
+                if let Some(hits) = value {
+                    *hits += 1;
+                }
+            }
+        }
+
+        self.instances += 1;
+        Ok(annotations
+            .errors()
+            .iter()
+            .cloned()
+            .map(ValidationError::into_owned)
+            .collect())
+    }
+
+    /// How many instances have been added.
+    pub fn instances(&self) -> usize {
+        self.instances
+    }
+
+    /// The coverage of every subschema: first the root schema and its
+    /// descendants, then each definition and its descendants.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Report coverage as JSON.
+    ///
+    /// The report has the number of instances added, a summary of how many
+    /// subschemas and enum values were covered, and every entry.
+    pub fn to_json(&self) -> Value {
+        let summary = self.summary();
+        json!({
+            "instances": self.instances,
+            "schemas": { "total": summary.schemas, "hit": summary.schemas_hit },
+            "values": { "total": summary.values, "hit": summary.values_hit },
+            "entries": self.entries,
+        })
+    }
+
+    /// Report coverage as lcov-like text.
+    ///
+    /// Each subschema is a `DA` record, with its schema path in place of a
+    /// line number. Each enum value is a `BRDA` record, with the value as the
+    /// branch. `source` is used as the `SF` record.
+    ///
+    /// The root schema's path is written as `/`. In paths and values, `%`,
+    /// `,`, and line breaks are percent-encoded, so that they can't be
+    /// mistaken for the end of a field or record.
+    pub fn to_lcov(&self, source: &str) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "TN:");
+        let _ = writeln!(out, "SF:{}", source);
+
+        for entry in &self.entries {
+            let _ = writeln!(out, "DA:{},{}", lcov_path(&entry.path), entry.hits);
+        }
+
+        for entry in &self.entries {
+            for (value, hits) in entry.values.iter().flatten() {
+                // As in lcov, "-" marks branches which were never reached.
+                let taken = if entry.hits == 0 {
+                    "-".to_string()
+                } else {
+                    hits.to_string()
+                };
+
+                let _ = writeln!(
+                    out,
+                    "BRDA:{},0,{},{}",
+                    lcov_path(&entry.path),
+                    lcov_escape(value),
+                    taken
+                );
+            }
+        }
+
+        let summary = self.summary();
+        let _ = writeln!(out, "BRF:{}", summary.values);
+        let _ = writeln!(out, "BRH:{}", summary.values_hit);
+        let _ = writeln!(out, "LF:{}", summary.schemas);
+        let _ = writeln!(out, "LH:{}", summary.schemas_hit);
+        let _ = writeln!(out, "end_of_record");
+        out
+    }
+
+    fn summary(&self) -> Summary {
+        let mut summary = Summary::default();
+        for entry in &self.entries {
+            summary.schemas += 1;
+            if entry.hits > 0 {
+                summary.schemas_hit += 1;
+            }
+
+            for &hits in entry.values.iter().flat_map(BTreeMap::values) {
+                summary.values += 1;
+                if hits > 0 {
+                    summary.values_hit += 1;
+                }
+            }
+        }
+
+        summary
+    }
+}
+
+#[derive(Default)]
+struct Summary {
+    schemas: usize,
+    schemas_hit: usize,
+    values: usize,
+    values_hit: usize,
+}
+
+/// A schema path as it appears in an lcov record.
+fn lcov_path(path: &str) -> String {
+    if path.is_empty() {
+        String::from("/")
+    } else {
+        lcov_escape(path)
+    }
+}
+
+/// Percent-encode the characters which delimit lcov fields and records.
+fn lcov_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '%' => out.push_str("%25"),
+            ',' => out.push_str("%2C"),
+            '\n' => out.push_str("%0A"),
+            '\r' => out.push_str("%0D"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+fn address(schema: &Schema) -> usize {
+    schema as *const Schema as usize
+}
+
+/// Lists every subschema of a schema, with nothing covered yet.
+#[derive(Default)]
+struct Entries {
+    entries: Vec<Entry>,
+    index: BTreeMap<usize, usize>,
+}
+
+impl<'a> Visitor<'a> for Entries {
+    fn visit_schema(&mut self, cx: &mut Context<'a>, schema: &'a Schema) {
+        let kind = match cx.path() {
+            ["definitions", _] => Kind::Definition,
+            [.., "optionalProperties", _] => Kind::OptionalProperty,
+            [.., "discriminator", "mapping", _] => Kind::Mapping,
+            _ => Kind::Schema,
+        };
+
+        let values = match schema.form() {
+            Form::Enum(values) => Some(values.iter().map(|value| (value.clone(), 0)).collect()),
+            _ => None,
+        };
+
+        self.index.insert(address(schema), self.entries.len());
+        self.entries.push(Entry {
+            path: JsonPointer::new(cx.path().to_vec()).to_string(),
+            kind,
+            hits: 0,
+            values,
+        });
+
+        cx.visit_children(self, schema);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Serde;
+
+    fn schema() -> Schema {
+        Schema::from_serde(
+            serde_json::from_value::<Serde>(json!({
+                "definitions": {
+                    "color": { "enum": ["red", "green", "blue"] },
+                    "unused": {},
+                },
+                "elements": {
+                    "discriminator": {
+                        "tag": "kind",
+                        "mapping": {
+                            "circle": {
+                                "properties": { "color": { "ref": "color" } },
+                                "optionalProperties": { "r": { "type": "float64" } },
+                            },
+                            "square": {
+                                "optionalProperties": { "side": { "type": "float64" } },
+                            },
+                        },
+                    },
+                },
+            }))
+            .unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn entries() {
+        let schema = schema();
+        let mut coverage = Coverage::new(&schema);
+
+        let errors = coverage
+            .add(&json!([
+                { "kind": "circle", "color": "red" },
+                { "kind": "circle", "color": "red", "r": 1 },
+            ]))
+            .unwrap();
+        assert!(errors.is_empty());
+
+        let errors = coverage
+            .add(&json!([{ "kind": "circle", "color": "purple" }]))
+            .unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(coverage.instances(), 2);
+
+        let entries: Vec<_> = coverage
+            .entries()
+            .iter()
+            .map(|entry| (entry.path(), entry.kind(), entry.hits()))
+            .collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                ("", Kind::Schema, 2),
+                ("/elements", Kind::Schema, 3),
+                ("/elements/discriminator/mapping/circle", Kind::Mapping, 3),
+                (
+                    "/elements/discriminator/mapping/circle/properties/color",
+                    Kind::Schema,
+                    3
+                ),
+                (
+                    "/elements/discriminator/mapping/circle/optionalProperties/r",
+                    Kind::OptionalProperty,
+                    1
+                ),
+                ("/elements/discriminator/mapping/square", Kind::Mapping, 0),
+                (
+                    "/elements/discriminator/mapping/square/optionalProperties/side",
+                    Kind::OptionalProperty,
+                    0
+                ),
+                ("/definitions/color", Kind::Definition, 3),
+                ("/definitions/unused", Kind::Definition, 0),
+            ]
+        );
+
+        let color = &coverage.entries()[7];
+        let values: Vec<_> = color.values().unwrap().iter().collect();
+        assert_eq!(
+            values,
+            vec![
+                (&"blue".to_owned(), &0),
+                (&"green".to_owned(), &0),
+                (&"red".to_owned(), &2)
+            ]
+        );
+    }
+
+    #[test]
+    fn reports() {
+        let schema = schema();
+        let mut coverage = Coverage::new(&schema);
+        coverage
+            .add(&json!([{ "kind": "circle", "color": "red" }]))
+            .unwrap();
+
+        let report = coverage.to_json();
+        assert_eq!(report["instances"], json!(1));
+        assert_eq!(report["schemas"], json!({ "total": 9, "hit": 5 }));
+        assert_eq!(report["values"], json!({ "total": 3, "hit": 1 }));
+        assert_eq!(
+            report["entries"][4],
+            json!({
+                "path": "/elements/discriminator/mapping/circle/optionalProperties/r",
+                "kind": "optionalProperty",
+                "hits": 0,
+            })
+        );
+        assert_eq!(
+            report["entries"][7],
+            json!({
+                "path": "/definitions/color",
+                "kind": "definition",
+                "hits": 1,
+                "values": { "blue": 0, "green": 0, "red": 1 },
+            })
+        );
+
+        let lcov = coverage.to_lcov("schema.json");
+        assert!(lcov.starts_with("TN:\nSF:schema.json\nDA:/,1\nDA:/elements,1\n"));
+        assert!(lcov.contains("\nDA:/definitions/unused,0\n"));
+        assert!(lcov.contains("\nBRDA:/definitions/color,0,red,1\n"));
+        assert!(lcov.contains("\nBRDA:/definitions/color,0,blue,0\n"));
+        assert!(lcov.ends_with("BRF:3\nBRH:1\nLF:9\nLH:5\nend_of_record\n"));
+
+        // Branches of a subschema that was never reached are marked "-".
+        let coverage = Coverage::new(&schema);
+        assert!(coverage
+            .to_lcov("schema.json")
+            .contains("\nBRDA:/definitions/color,0,red,-\n"));
+    }
+
+    #[test]
+    fn lcov_escaping() {
+        let schema = Schema::from_serde(
+            serde_json::from_value(json!({
+                "properties": {
+                    "a,b": { "enum": ["x,y", "line\nbreak", "100%"] },
+                },
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+
+        let mut coverage = Coverage::new(&schema);
+        coverage.add(&json!({ "a,b": "x,y" })).unwrap();
+
+        let lcov = coverage.to_lcov("schema.json");
+        assert!(lcov.contains("\nDA:/properties/a%2Cb,1\n"));
+        assert!(lcov.contains("\nBRDA:/properties/a%2Cb,0,x%2Cy,1\n"));
+        assert!(lcov.contains("\nBRDA:/properties/a%2Cb,0,line%0Abreak,0\n"));
+        assert!(lcov.contains("\nBRDA:/properties/a%2Cb,0,100%25,0\n"));
+    }
+}
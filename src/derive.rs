@@ -0,0 +1,235 @@
+//! Derive schemas from Rust types.
+//!
+//! This module contains the [`JslSchema`](trait.JslSchema.html) trait, which
+//! describes how a Rust type is represented as a JSL schema. Implementations
+//! are provided for the standard library types which serde represents as JSON
+//! primitives, arrays, or objects.
+//!
+//! With the `derive` feature enabled, `#[derive(JslSchema)]` implements this
+//! trait for your own structs and enums. The derived implementation honors
+//! `#[serde(rename)]`, `#[serde(rename_all)]`, `#[serde(tag)]`,
+//! `#[serde(default)]` and `#[serde(skip)]`:
+//!
+//! ```
+//! # #[cfg(feature = "derive")]
+//! # fn main() {
+//! use jsl::{Form, JslSchema, Type};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, JslSchema)]
+//! #[serde(rename_all = "camelCase")]
+//! struct User {
+//!     user_id: u64,
+//!     nickname: Option<String>,
+//! }
+//!
+//! let schema = User::jsl_schema();
+//! match schema.form() {
+//!     Form::Properties(required, optional, _) => {
+//!         assert_eq!(required["userId"].form(), &Form::Type(Type::Uint64));
+//!         assert_eq!(optional["nickname"].form(), &Form::Type(Type::String));
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! # }
+//! # #[cfg(not(feature = "derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! Derived structs and enums are named types: wherever they are used inside
+//! another type, they are emitted as a `ref` to an entry in the root schema's
+//! `definitions`. This keeps shared types from being duplicated, and makes
+//! recursive types representable.
+
+use crate::schema::{Form, Schema, Type};
+use chrono::{DateTime, TimeZone};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// A type which can describe its JSON representation as a JSL schema.
+pub trait JslSchema {
+    /// The schema to use when this type appears within another type.
+    ///
+    /// Named types return a `ref` form here, and register their definition in
+    /// `defs` (see [`definition_ref`](fn.definition_ref.html)). By default,
+    /// this is the same as `jsl_schema_inline`.
+    fn jsl_schema_ref(defs: &mut BTreeMap<String, Schema>) -> Schema {
+        Self::jsl_schema_inline(defs)
+    }
+
+    /// The schema describing this type, without any indirection through
+    /// `definitions` for the type itself.
+    ///
+    /// Any named types this type depends on are added to `defs`.
+    fn jsl_schema_inline(defs: &mut BTreeMap<String, Schema>) -> Schema;
+
+    /// Construct a root schema describing this type.
+    fn jsl_schema() -> Schema {
+        let mut defs = BTreeMap::new();
+        let schema = Self::jsl_schema_inline(&mut defs);
+        Schema::from_parts(
+            Some(defs),
+            Box::new(schema.form().clone()),
+            schema.extra().clone(),
+        )
+    }
+}
+
+/// Register `T` in `defs` under `name`, and return a schema referring to it.
+///
+/// This is the implementation of `jsl_schema_ref` for derived types. A
+/// placeholder is inserted before the definition is constructed, so that
+/// recursive types terminate.
+pub fn definition_ref<T: JslSchema + ?Sized>(
+    name: &str,
+    defs: &mut BTreeMap<String, Schema>,
+) -> Schema {
+    if !defs.contains_key(name) {
+        defs.insert(name.to_owned(), empty());
+        let schema = T::jsl_schema_inline(defs);
+        defs.insert(name.to_owned(), schema);
+    }
+
+    from_form(Form::Ref(name.to_owned()))
+}
+
+/// Get the required and optional properties of a discriminator variant.
+///
+/// Mappings in a discriminator must be of the properties form. Derived enums
+/// use this for newtype variants, whose contents must themselves be a struct.
+/// If `schema` is a `ref`, such as when the variant wraps a newtype struct
+/// around a struct, the definition it refers to in `defs` is used instead.
+///
+/// # Panics
+///
+/// Panics if `schema` does not resolve to the properties form.
+pub fn variant_properties(
+    variant: &str,
+    schema: Schema,
+    defs: &BTreeMap<String, Schema>,
+) -> (BTreeMap<String, Schema>, BTreeMap<String, Schema>) {
+    let mut schema = &schema;
+    let mut seen = BTreeSet::new();
+    loop {
+        match schema.form() {
+            Form::Properties(required, optional, _) => return (required.clone(), optional.clone()),
+            Form::Ref(name) if seen.insert(name) => match defs.get(name) {
+                Some(def) => schema = def,
+                None => break,
+            },
+            _ => break,
+        }
+    }
+
+    panic!(
+        "variant {:?} of an internally tagged enum must contain a struct",
+        variant
+    )
+}
+
+fn from_form(form: Form) -> Schema {
+    Schema::from_parts(None, Box::new(form), BTreeMap::new())
+}
+
+fn empty() -> Schema {
+    from_form(Form::Empty)
+}
+
+macro_rules! impl_type {
+    ($($rust:ty => $typ:ident,)*) => {
+        $(
+            impl JslSchema for $rust {
+                fn jsl_schema_inline(_: &mut BTreeMap<String, Schema>) -> Schema {
+                    from_form(Form::Type(Type::$typ))
+                }
+            }
+        )*
+    };
+}
+
+impl_type! {
+    bool => Boolean,
+    f32 => Float32,
+    f64 => Float64,
+    i8 => Int8,
+    u8 => Uint8,
+    i16 => Int16,
+    u16 => Uint16,
+    i32 => Int32,
+    u32 => Uint32,
+    i64 => Int64,
+    u64 => Uint64,
+    isize => Int64,
+    usize => Uint64,
+    char => String,
+    str => String,
+    String => String,
+}
+
+impl<Tz: TimeZone> JslSchema for DateTime<Tz> {
+    fn jsl_schema_inline(_: &mut BTreeMap<String, Schema>) -> Schema {
+        from_form(Form::Type(Type::Timestamp))
+    }
+}
+
+impl JslSchema for Value {
+    fn jsl_schema_inline(_: &mut BTreeMap<String, Schema>) -> Schema {
+        empty()
+    }
+}
+
+impl<T: JslSchema + ?Sized> JslSchema for &T {
+    fn jsl_schema_ref(defs: &mut BTreeMap<String, Schema>) -> Schema {
+        T::jsl_schema_ref(defs)
+    }
+
+    fn jsl_schema_inline(defs: &mut BTreeMap<String, Schema>) -> Schema {
+        T::jsl_schema_inline(defs)
+    }
+}
+
+impl<T: JslSchema + ?Sized> JslSchema for Box<T> {
+    fn jsl_schema_ref(defs: &mut BTreeMap<String, Schema>) -> Schema {
+        T::jsl_schema_ref(defs)
+    }
+
+    fn jsl_schema_inline(defs: &mut BTreeMap<String, Schema>) -> Schema {
+        T::jsl_schema_inline(defs)
+    }
+}
+
+macro_rules! impl_elements {
+    ($($rust:ty,)*) => {
+        $(
+            impl<T: JslSchema> JslSchema for $rust {
+                fn jsl_schema_inline(defs: &mut BTreeMap<String, Schema>) -> Schema {
+                    from_form(Form::Elements(T::jsl_schema_ref(defs)))
+                }
+            }
+        )*
+    };
+}
+
+impl_elements! {
+    [T],
+    Vec<T>,
+    HashSet<T>,
+    BTreeSet<T>,
+}
+
+macro_rules! impl_values {
+    ($($rust:ty,)*) => {
+        $(
+            impl<V: JslSchema> JslSchema for $rust {
+                fn jsl_schema_inline(defs: &mut BTreeMap<String, Schema>) -> Schema {
+                    from_form(Form::Values(V::jsl_schema_ref(defs)))
+                }
+            }
+        )*
+    };
+}
+
+impl_values! {
+    HashMap<String, V>,
+    BTreeMap<String, V>,
+}
@@ -1,9 +1,10 @@
 //! An error type for all JSL-related operations.
 
-use failure::Fail;
+use alloc::string::String;
+use core::fmt;
 
 /// An enum of possible errors that can emerge from this crate.
-#[derive(Debug, Fail, PartialEq, Clone, Eq, Hash)]
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
 pub enum JslError {
     /// A schema-like object did not take on a valid form.
     ///
@@ -11,7 +12,6 @@ pub enum JslError {
     /// schema uses an invalid combination of keywords, it is said to not take on
     /// one of the valid forms. Converting a `SerdeSchema` with an invalid
     /// combination of keywords into a `Schema` will result in this error.
-    #[fail(display = "invalid schema form")]
     InvalidForm,
 
     /// A schema-like object specified a keyword in an ambiguous manner.
@@ -20,7 +20,6 @@ pub enum JslError {
     /// schema. This error arises when a `SerdeSchema`'s `properties`,
     /// `optionalProperties`, or `discriminator.propertyName` share a property in
     /// common, and one attempts to convert this into a `Schema`.
-    #[fail(display = "ambiguous property: {}", property)]
     AmbiguousProperty { property: String },
 
     /// A schema refers to a definition which does not exist.
@@ -28,13 +27,31 @@ pub enum JslError {
     /// Schemas may refer to one another using the `ref` keyword, which may refer
     /// to a `definition` in the root schema. If a schema refers to a definition
     /// which does not exist, this error is returned.
-    #[fail(display = "no such definition: {}", definition)]
     NoSuchDefinition { definition: String },
 
     /// The maximum depth during evaluating was exceeded.
     ///
     /// This likely means that your configured `max_depth` is too small, or that
     /// there is a infinite cyclical definition in your schemas.
-    #[fail(display = "maximum reference depth exceeeded during validation")]
     MaxDepthExceeded,
 }
+
+impl fmt::Display for JslError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JslError::InvalidForm => write!(f, "invalid schema form"),
+            JslError::AmbiguousProperty { property } => {
+                write!(f, "ambiguous property: {}", property)
+            }
+            JslError::NoSuchDefinition { definition } => {
+                write!(f, "no such definition: {}", definition)
+            }
+            JslError::MaxDepthExceeded => {
+                write!(f, "maximum reference depth exceeeded during validation")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for JslError {}
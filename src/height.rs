@@ -0,0 +1,48 @@
+//! How deep the smallest instance of a schema is.
+//!
+//! Generating instances and checking satisfiability both need to know which
+//! schemas have finite instances, and how many refs deep the smallest one
+//! goes.
+
+use crate::schema::{Form, Schema};
+use std::collections::BTreeMap;
+
+/// Find, for each definition with a finite instance, how many refs deep its
+/// smallest instance is.
+pub fn heights(defs: &BTreeMap<String, Schema>) -> BTreeMap<&str, usize> {
+    let mut heights = BTreeMap::new();
+    loop {
+        let mut changed = false;
+        for (name, schema) in defs {
+            if let Some(h) = height(schema, &heights) {
+                if heights.get(name.as_str()).is_none_or(|&old| h < old) {
+                    heights.insert(name.as_str(), h);
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            return heights;
+        }
+    }
+}
+
+/// How many refs deep the smallest instance of a schema is, or `None` if it
+/// has no finite instances.
+pub fn height(schema: &Schema, heights: &BTreeMap<&str, usize>) -> Option<usize> {
+    match schema.form() {
+        Form::Empty | Form::Type(_) | Form::Enum(_) | Form::Elements(_) | Form::Values(_) => {
+            Some(0)
+        }
+        Form::Ref(def) => heights.get(def.as_str()).map(|h| h + 1),
+        Form::Properties(required, _, _) => required
+            .values()
+            .map(|sub_schema| height(sub_schema, heights))
+            .try_fold(0, |max, h| h.map(|h| max.max(h))),
+        Form::Discriminator(_, mapping) => mapping
+            .values()
+            .filter_map(|sub_schema| height(sub_schema, heights))
+            .min(),
+    }
+}
@@ -91,7 +91,7 @@
 //! The [`ValidationError`](validator/struct.ValidationError.html) type that
 //! [`Validator::validate`](validator/struct.Validator.html#method.validate)
 //! produces contains two
-//! [`json_pointer::JsonPointer`s](https://docs.rs/json-pointer/0.3.2/json_pointer/struct.JsonPointer.html).
+//! [`JsonPointer`s](struct.JsonPointer.html).
 //! These paths within these validation errors are standardized, and should be
 //! understood by any implementation of JSL, not just this crate. All compliant
 //! implementations of JSL produce the same paths, making them
@@ -111,12 +111,54 @@
 //!
 //! [jsl-docs]: http://json-schema-language.github.io/docs
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+mod height;
+mod pointer;
+mod timestamp;
 mod vm;
 
+pub mod coverage;
 pub mod errors;
+pub mod patch;
 pub mod schema;
 pub mod validator;
+pub mod visit;
+
+#[cfg(feature = "std")]
+pub mod builder;
+#[cfg(feature = "std")]
+pub mod codegen;
+#[cfg(feature = "std")]
+pub mod derive;
+#[cfg(feature = "std")]
+pub mod mutate;
+#[cfg(feature = "std")]
+pub mod random;
+#[cfg(feature = "std")]
+pub mod satisfiability;
+#[cfg(feature = "std")]
+pub mod subschema;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use crate::errors::JslError;
 pub use crate::schema::{Form, Schema, Serde as SerdeSchema, Type};
-pub use crate::validator::{Config, ValidationError, Validator};
+pub use crate::validator::{
+    Annotation, Annotations, Config, SchemaSource, Validated, ValidationError, ValidationFailure,
+    Validator,
+};
+
+#[cfg(feature = "std")]
+pub use crate::builder::SchemaBuilder;
+#[cfg(feature = "std")]
+pub use crate::derive::JslSchema;
+#[cfg(not(feature = "std"))]
+pub use crate::pointer::JsonPointer;
+
+#[cfg(feature = "derive")]
+pub use jsl_derive::JslSchema;
@@ -0,0 +1,790 @@
+//! Derive invalid instances from valid ones.
+//!
+//! A [`Mutator`](struct.Mutator.html) takes a schema and an instance that is
+//! valid against it, and produces [`Fixture`s](struct.Fixture.html): copies
+//! of the instance, each changed so that it fails validation at exactly one
+//! place. For instance, a fixture might drop one required property, or push
+//! one `uint8` out of range. Each fixture records the error a validator
+//! should produce for it.
+//!
+//! Mutators try to cover every way each part of the schema can reject input,
+//! not just the parts the original instance happens to use. Where the
+//! instance omits an optional property, has an empty array or map, or uses a
+//! different discriminator variant, a mutator fills in a valid value using a
+//! [`random::Generator`](../random/struct.Generator.html) and mutates that
+//! instead. Each definition is only explored once, however many times it is
+//! referred to.
+//!
+//! A [`Suite`](struct.Suite.html) bundles fixtures together with their
+//! schema, in the same format as the JSL spec's test suite files.
+
+use crate::pointer::JsonPointer;
+use crate::random;
+use crate::schema::{Form, Schema, Type};
+use crate::validator::{self, ValidationError, ValidationFailure, Validator};
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Derives invalid instances from valid ones.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Mutator {
+    config: Config,
+}
+
+impl Mutator {
+    /// Constructs a new mutator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new mutator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Derive fixtures from an instance of a root schema.
+    ///
+    /// `instance` must be valid against `schema`, under the configured
+    /// instance semantics, since fixtures derived from an invalid instance
+    /// would fail in more places than they record. Returns
+    /// `ValidationFailure::Invalid` if it isn't, or `ValidationFailure::Eval`
+    /// if it cannot be validated.
+    pub fn mutate(
+        &self,
+        schema: &Schema,
+        instance: &Value,
+    ) -> Result<Vec<Fixture>, ValidationFailure> {
+        let mut config = validator::Config::new();
+        config.strict_instance_semantics(self.config.strict_instance_semantics);
+        let errors = Validator::new_with_config(config)
+            .validate(schema, instance)
+            .map_err(ValidationFailure::Eval)?;
+
+        if !errors.is_empty() {
+            return Err(ValidationFailure::Invalid(
+                errors
+                    .into_iter()
+                    .map(ValidationError::into_owned)
+                    .collect(),
+            ));
+        }
+
+        let no_defs = BTreeMap::new();
+        let mut state = State {
+            config: &self.config,
+            defs: schema.definitions().as_ref().unwrap_or(&no_defs),
+            generator: random::Generator::new(),
+            visited: BTreeSet::new(),
+            fixtures: Vec::new(),
+        };
+
+        state.walk(instance, &[], schema, &[], None);
+        Ok(state.fixtures)
+    }
+
+    /// Derive fixtures from an instance of a root schema, and collect them
+    /// into a test suite. The first instance in the suite is `instance`
+    /// itself, which should produce no errors.
+    ///
+    /// This fails in the same cases as [`mutate`](#method.mutate).
+    pub fn suite(
+        &self,
+        name: &str,
+        schema: &Schema,
+        instance: &Value,
+    ) -> Result<Suite, ValidationFailure> {
+        let mut instances = vec![Fixture {
+            instance: instance.clone(),
+            errors: Vec::new(),
+        }];
+        instances.extend(self.mutate(schema, instance)?);
+
+        Ok(Suite {
+            name: name.to_owned(),
+            schema: serde_json::to_value(schema.clone().into_serde())
+                .expect("error serializing schema"),
+            strict_instance: self.config.strict_instance_semantics,
+            instances,
+        })
+    }
+}
+
+/// Configuration for how fixtures should be derived.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    strict_instance_semantics: bool,
+    seed: u64,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether fixtures should assume strict instance semantics, as
+    /// described in
+    /// [`validator::Config`](../validator/struct.Config.html#method.strict_instance_semantics).
+    ///
+    /// Under strict instance semantics, mutators also add unknown properties
+    /// to objects. By default, this is disabled.
+    pub fn strict_instance_semantics(&mut self, strict_instance_semantics: bool) -> &mut Self {
+        self.strict_instance_semantics = strict_instance_semantics;
+        self
+    }
+
+    /// Sets the seed used to generate values the original instance doesn't
+    /// have. The default is zero.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// An instance, and the errors a validator should produce for it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Fixture {
+    instance: Value,
+    errors: Vec<FixtureError>,
+}
+
+impl Fixture {
+    /// The instance to validate.
+    pub fn instance(&self) -> &Value {
+        &self.instance
+    }
+
+    /// The errors validating the instance should produce.
+    pub fn errors(&self) -> &[FixtureError] {
+        &self.errors
+    }
+}
+
+/// An error a fixture should produce.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FixtureError {
+    #[serde(rename = "instancePath", serialize_with = "serialize_pointer")]
+    instance_path: JsonPointer<String, Vec<String>>,
+
+    #[serde(rename = "schemaPath", serialize_with = "serialize_pointer")]
+    schema_path: JsonPointer<String, Vec<String>>,
+}
+
+impl FixtureError {
+    /// A pointer into the part of the instance which is rejected.
+    pub fn instance_path(&self) -> &JsonPointer<String, Vec<String>> {
+        &self.instance_path
+    }
+
+    /// A pointer into the part of the schema which rejects the instance.
+    pub fn schema_path(&self) -> &JsonPointer<String, Vec<String>> {
+        &self.schema_path
+    }
+}
+
+fn serialize_pointer<S: Serializer>(
+    pointer: &JsonPointer<String, Vec<String>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&pointer.to_string())
+}
+
+/// A schema and a set of fixtures for it, in the format of the JSL spec's
+/// test suite files.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Suite {
+    name: String,
+    schema: Value,
+    #[serde(rename = "strictInstance")]
+    strict_instance: bool,
+    instances: Vec<Fixture>,
+}
+
+impl Suite {
+    /// The fixtures in the suite.
+    pub fn instances(&self) -> &[Fixture] {
+        &self.instances
+    }
+}
+
+struct State<'a> {
+    config: &'a Config,
+    defs: &'a BTreeMap<String, Schema>,
+    generator: random::Generator,
+    visited: BTreeSet<&'a str>,
+    fixtures: Vec<Fixture>,
+}
+
+impl<'a> State<'a> {
+    /// Derive fixtures for the part of `root` at `instance_path`, which is
+    /// valid against `schema`. `mutate` checks this up front, and values
+    /// generated along the way are valid by construction.
+    fn walk(
+        &mut self,
+        root: &Value,
+        instance_path: &[String],
+        schema: &'a Schema,
+        schema_path: &[&str],
+        parent_tag: Option<&str>,
+    ) {
+        let node = node(root, instance_path);
+
+        match schema.form() {
+            Form::Empty => {}
+            Form::Ref(def) => {
+                if self.visited.insert(def) {
+                    let defs = self.defs;
+                    self.walk(root, instance_path, &defs[def], &["definitions", def], None);
+                }
+            }
+            Form::Type(typ) => {
+                for bad in bad_values(typ) {
+                    self.emit(
+                        root,
+                        instance_path,
+                        bad,
+                        instance_path,
+                        schema_path,
+                        &["type"],
+                    );
+                }
+            }
+            Form::Enum(values) => {
+                let unknown =
+                    Value::String(unused_name(values.iter().map(String::as_str), "UNKNOWN"));
+                self.emit(
+                    root,
+                    instance_path,
+                    unknown,
+                    instance_path,
+                    schema_path,
+                    &["enum"],
+                );
+                self.emit(
+                    root,
+                    instance_path,
+                    0.into(),
+                    instance_path,
+                    schema_path,
+                    &["enum"],
+                );
+            }
+            Form::Elements(sub_schema) => {
+                let bad = Value::Object(Map::new());
+                self.emit(
+                    root,
+                    instance_path,
+                    bad,
+                    instance_path,
+                    schema_path,
+                    &["elements"],
+                );
+
+                let elements = node.as_array().expect("instance is not an array");
+                let root = if elements.is_empty() {
+                    match self.generate(sub_schema) {
+                        Some(element) => replace(root, instance_path, Value::Array(vec![element])),
+                        None => return,
+                    }
+                } else {
+                    root.clone()
+                };
+
+                let sub_instance_path = child(instance_path, "0");
+                let sub_schema_path = [schema_path, &["elements"]].concat();
+                self.walk(
+                    &root,
+                    &sub_instance_path,
+                    sub_schema,
+                    &sub_schema_path,
+                    None,
+                );
+            }
+            Form::Values(sub_schema) => {
+                let bad = Value::Array(Vec::new());
+                self.emit(
+                    root,
+                    instance_path,
+                    bad,
+                    instance_path,
+                    schema_path,
+                    &["values"],
+                );
+
+                let entries = node.as_object().expect("instance is not an object");
+                let (root, key) = match entries.keys().next() {
+                    Some(key) => (root.clone(), key.clone()),
+                    None => match self.generate(sub_schema) {
+                        Some(value) => {
+                            let mut entries = Map::new();
+                            entries.insert("key".to_owned(), value);
+                            let root = replace(root, instance_path, Value::Object(entries));
+                            (root, "key".to_owned())
+                        }
+                        None => return,
+                    },
+                };
+
+                let sub_instance_path = child(instance_path, &key);
+                let sub_schema_path = [schema_path, &["values"]].concat();
+                self.walk(
+                    &root,
+                    &sub_instance_path,
+                    sub_schema,
+                    &sub_schema_path,
+                    None,
+                );
+            }
+            Form::Properties(required, optional, has_required) => {
+                // Inside a discriminator, the discriminator itself rejects
+                // anything that isn't an object.
+                if parent_tag.is_none() {
+                    let keyword = if *has_required {
+                        "properties"
+                    } else {
+                        "optionalProperties"
+                    };
+                    self.emit(
+                        root,
+                        instance_path,
+                        Value::Null,
+                        instance_path,
+                        schema_path,
+                        &[keyword],
+                    );
+                }
+
+                let object = node.as_object().expect("instance is not an object");
+                for name in required.keys() {
+                    let mut bad = object.clone();
+                    bad.remove(name);
+                    self.emit(
+                        root,
+                        instance_path,
+                        Value::Object(bad),
+                        instance_path,
+                        schema_path,
+                        &["properties", name],
+                    );
+                }
+
+                if self.config.strict_instance_semantics {
+                    let known = required
+                        .keys()
+                        .chain(optional.keys())
+                        .map(String::as_str)
+                        .chain(parent_tag);
+                    let unknown = unused_name(known, "unknown");
+
+                    let mut bad = object.clone();
+                    bad.insert(unknown.clone(), Value::Null);
+                    self.emit(
+                        root,
+                        instance_path,
+                        Value::Object(bad),
+                        &child(instance_path, &unknown),
+                        schema_path,
+                        &[],
+                    );
+                }
+
+                for (name, sub_schema) in required {
+                    let sub_schema_path = [schema_path, &["properties", name]].concat();
+                    self.walk(
+                        root,
+                        &child(instance_path, name),
+                        sub_schema,
+                        &sub_schema_path,
+                        None,
+                    );
+                }
+
+                for (name, sub_schema) in optional {
+                    let root = if object.contains_key(name) {
+                        root.clone()
+                    } else {
+                        match self.generate(sub_schema) {
+                            Some(value) => {
+                                let mut object = object.clone();
+                                object.insert(name.clone(), value);
+                                replace(root, instance_path, Value::Object(object))
+                            }
+                            None => continue,
+                        }
+                    };
+
+                    let sub_schema_path = [schema_path, &["optionalProperties", name]].concat();
+                    self.walk(
+                        &root,
+                        &child(instance_path, name),
+                        sub_schema,
+                        &sub_schema_path,
+                        None,
+                    );
+                }
+            }
+            Form::Discriminator(tag, mapping) => {
+                let tag_path = child(instance_path, tag);
+                let object = node.as_object().expect("instance is not an object");
+
+                let bad = Value::Null;
+                self.emit(
+                    root,
+                    instance_path,
+                    bad,
+                    instance_path,
+                    schema_path,
+                    &["discriminator"],
+                );
+
+                let mut bad = object.clone();
+                bad.remove(tag);
+                self.emit(
+                    root,
+                    instance_path,
+                    Value::Object(bad),
+                    instance_path,
+                    schema_path,
+                    &["discriminator", "tag"],
+                );
+
+                let mut bad = object.clone();
+                bad.insert(tag.clone(), 0.into());
+                self.emit(
+                    root,
+                    instance_path,
+                    Value::Object(bad),
+                    &tag_path,
+                    schema_path,
+                    &["discriminator", "tag"],
+                );
+
+                let mut bad = object.clone();
+                bad.insert(
+                    tag.clone(),
+                    unused_name(mapping.keys().map(String::as_str), "UNKNOWN").into(),
+                );
+                self.emit(
+                    root,
+                    instance_path,
+                    Value::Object(bad),
+                    &tag_path,
+                    schema_path,
+                    &["discriminator", "mapping"],
+                );
+
+                for (value, sub_schema) in mapping {
+                    let root = if object.get(tag).and_then(Value::as_str) == Some(value) {
+                        root.clone()
+                    } else {
+                        match self.generate(sub_schema) {
+                            Some(mut variant) => {
+                                if let Value::Object(ref mut variant) = variant {
+                                    variant.insert(tag.clone(), value.clone().into());
+                                }
+                                replace(root, instance_path, variant)
+                            }
+                            None => continue,
+                        }
+                    };
+
+                    let sub_schema_path =
+                        [schema_path, &["discriminator", "mapping", value]].concat();
+                    self.walk(
+                        &root,
+                        instance_path,
+                        sub_schema,
+                        &sub_schema_path,
+                        Some(tag),
+                    );
+                }
+            }
+        }
+    }
+
+    fn generate(&self, schema: &Schema) -> Option<Value> {
+        self.generator
+            .generate_with_definitions(self.defs, schema, self.config.seed)
+    }
+
+    /// Record a fixture, made by replacing the part of `root` at `path` with
+    /// `value`.
+    fn emit(
+        &mut self,
+        root: &Value,
+        path: &[String],
+        value: Value,
+        instance_path: &[String],
+        schema_path: &[&str],
+        keywords: &[&str],
+    ) {
+        let schema_path = schema_path
+            .iter()
+            .chain(keywords)
+            .map(|&token| token.to_owned())
+            .collect();
+
+        self.fixtures.push(Fixture {
+            instance: replace(root, path, value),
+            errors: vec![FixtureError {
+                instance_path: JsonPointer::new(instance_path.to_vec()),
+                schema_path: JsonPointer::new(schema_path),
+            }],
+        });
+    }
+}
+
+/// Values that are not valid for a type, one for each way a type can reject
+/// a value.
+fn bad_values(typ: &Type) -> Vec<Value> {
+    let (min, max) = match typ {
+        Type::Boolean => return vec![Value::String("true".to_owned())],
+        Type::Number | Type::Float32 | Type::Float64 => return vec![Value::String("0".to_owned())],
+        Type::String => return vec![0.into()],
+        Type::Timestamp => return vec![Value::String("not a timestamp".to_owned()), 0.into()],
+        Type::Int8 => (-129.0, 128.0),
+        Type::Uint8 => (-1.0, 256.0),
+        Type::Int16 => (-32769.0, 32768.0),
+        Type::Uint16 => (-1.0, 65536.0),
+        Type::Int32 => (-2_147_483_649.0, 2_147_483_648.0),
+        Type::Uint32 => (-1.0, 4_294_967_296.0),
+        // Values just outside the 64-bit ranges round to their bounds as
+        // floats, so step further out.
+        Type::Int64 => (-1e19, 1e19),
+        Type::Uint64 => (-1.0, 1e20),
+    };
+
+    vec![
+        Value::String("0".to_owned()),
+        0.5.into(),
+        min.into(),
+        max.into(),
+    ]
+}
+
+/// A name starting with `base` that is not among `used`.
+fn unused_name<'b, I: Iterator<Item = &'b str>>(used: I, base: &str) -> String {
+    let used: BTreeSet<_> = used.collect();
+    let mut name = base.to_owned();
+    while used.contains(name.as_str()) {
+        name.push('_');
+    }
+    name
+}
+
+fn child(path: &[String], token: &str) -> Vec<String> {
+    let mut path = path.to_vec();
+    path.push(token.to_owned());
+    path
+}
+
+fn node<'v>(root: &'v Value, path: &[String]) -> &'v Value {
+    path.iter().fold(root, |value, token| match value {
+        Value::Array(elements) => &elements[token.parse::<usize>().expect("bad array index")],
+        Value::Object(entries) => &entries[token],
+        _ => panic!("instance path does not exist"),
+    })
+}
+
+/// Copy `root`, with the part at `path` replaced by `value`.
+fn replace(root: &Value, path: &[String], value: Value) -> Value {
+    let mut root = root.clone();
+    let target = path.iter().fold(&mut root, |value, token| match value {
+        Value::Array(elements) => &mut elements[token.parse::<usize>().expect("bad array index")],
+        Value::Object(entries) => entries.get_mut(token).expect("no such property"),
+        _ => panic!("instance path does not exist"),
+    });
+
+    *target = value;
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator::{self, Validator};
+    use serde_json::json;
+
+    fn schema() -> Schema {
+        Schema::from_serde(
+            serde_json::from_value(json!({
+                "definitions": {
+                    "event": {
+                        "discriminator": {
+                            "tag": "type",
+                            "mapping": {
+                                "created": {
+                                    "properties": {
+                                        "at": { "type": "timestamp" },
+                                    },
+                                },
+                                "deleted": {
+                                    "optionalProperties": {
+                                        "reason": { "enum": ["SPAM", "OTHER"] },
+                                    },
+                                },
+                            },
+                        },
+                    },
+                },
+                "properties": {
+                    "id": { "type": "uint8" },
+                    "events": { "elements": { "ref": "event" } },
+                },
+                "optionalProperties": {
+                    "tags": { "values": { "type": "string" } },
+                    "next": { "ref": "event" },
+                },
+            }))
+            .unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn check(strict: bool) -> Vec<Fixture> {
+        let schema = schema();
+        let mut config = Config::new();
+        config.strict_instance_semantics(strict);
+        let fixtures =
+            Mutator::new_with_config(config).mutate(&schema, &json!({ "id": 1, "events": [] }));
+        let fixtures = fixtures.unwrap();
+
+        let mut config = validator::Config::new();
+        config.strict_instance_semantics(strict);
+        let validator = Validator::new_with_config(config);
+
+        for fixture in &fixtures {
+            let errors: Vec<_> = validator
+                .validate(&schema, fixture.instance())
+                .unwrap()
+                .into_iter()
+                .map(|error| {
+                    (
+                        error.instance_path().to_string(),
+                        error.schema_path().to_string(),
+                    )
+                })
+                .collect();
+
+            let expected: Vec<_> = fixture
+                .errors()
+                .iter()
+                .map(|error| {
+                    (
+                        error.instance_path().to_string(),
+                        error.schema_path().to_string(),
+                    )
+                })
+                .collect();
+
+            assert_eq!(errors, expected, "{}", fixture.instance());
+        }
+
+        fixtures
+    }
+
+    #[test]
+    fn fails_exactly_once() {
+        let fixtures = check(false);
+        let schema_paths: BTreeSet<_> = fixtures
+            .iter()
+            .map(|fixture| fixture.errors()[0].schema_path().to_string())
+            .collect();
+
+        for path in &[
+            "/properties",
+            "/properties/id",
+            "/properties/id/type",
+            "/properties/events/elements",
+            "/optionalProperties/tags/values",
+            "/optionalProperties/tags/values/type",
+            "/definitions/event/discriminator",
+            "/definitions/event/discriminator/tag",
+            "/definitions/event/discriminator/mapping",
+            "/definitions/event/discriminator/mapping/created/properties/at",
+            "/definitions/event/discriminator/mapping/created/properties/at/type",
+            "/definitions/event/discriminator/mapping/deleted/optionalProperties/reason/enum",
+        ] {
+            assert!(schema_paths.contains(*path), "missing {}", path);
+        }
+    }
+
+    #[test]
+    fn strict() {
+        let fixtures = check(true);
+        assert!(fixtures
+            .iter()
+            .any(|fixture| fixture.errors()[0].instance_path().to_string() == "/events/0/unknown"));
+    }
+
+    #[test]
+    fn suite() {
+        let suite = Mutator::new()
+            .suite("example", &schema(), &json!({ "id": 1, "events": [] }))
+            .unwrap();
+        let suite = serde_json::to_value(suite).unwrap();
+
+        assert_eq!(suite["name"], json!("example"));
+        assert_eq!(suite["strictInstance"], json!(false));
+        assert_eq!(
+            suite["instances"][0],
+            json!({ "instance": { "id": 1, "events": [] }, "errors": [] })
+        );
+        assert_eq!(
+            suite["instances"][1],
+            json!({
+                "instance": null,
+                "errors": [{ "instancePath": "", "schemaPath": "/properties" }],
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_instance() {
+        let invalid = |schema, instance| {
+            let schema = Schema::from_serde(serde_json::from_value(schema).unwrap()).unwrap();
+            match Mutator::new().mutate(&schema, &instance) {
+                Err(ValidationFailure::Invalid(errors)) => errors
+                    .iter()
+                    .map(|error| error.schema_path().to_string())
+                    .collect::<Vec<_>>(),
+                other => panic!("expected invalid instance, got {:?}", other),
+            }
+        };
+
+        assert_eq!(
+            invalid(
+                json!({ "properties": { "a": { "type": "string" } } }),
+                json!({})
+            ),
+            vec!["/properties/a"]
+        );
+        assert_eq!(
+            invalid(json!({ "elements": { "type": "string" } }), json!(5)),
+            vec!["/elements"]
+        );
+        assert_eq!(
+            invalid(
+                json!({ "discriminator": { "tag": "t", "mapping": {} } }),
+                json!([])
+            ),
+            vec!["/discriminator"]
+        );
+
+        // Unknown properties only make an instance invalid under strict
+        // instance semantics.
+        let schema = Schema::from_serde(
+            serde_json::from_value(json!({ "properties": { "a": { "type": "string" } } })).unwrap(),
+        )
+        .unwrap();
+        let instance = json!({ "a": "x", "b": 1 });
+        assert!(Mutator::new().mutate(&schema, &instance).is_ok());
+
+        let mut config = Config::new();
+        config.strict_instance_semantics(true);
+        assert!(Mutator::new_with_config(config)
+            .mutate(&schema, &instance)
+            .is_err());
+    }
+}
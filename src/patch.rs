@@ -0,0 +1,1005 @@
+//! Validate changes to an instance, expressed as patches.
+//!
+//! [`Validator::validate_patch`](../validator/struct.Validator.html#method.validate_patch)
+//! takes a JSON Patch ([RFC 6902](https://tools.ietf.org/html/rfc6902)), and
+//! [`Validator::validate_merge_patch`](../validator/struct.Validator.html#method.validate_merge_patch)
+//! a JSON Merge Patch ([RFC 7396](https://tools.ietf.org/html/rfc7396)).
+//! Both check whether patching an instance would produce a valid one, without
+//! copying or re-validating the whole instance:
+//!
+//! * The patch is applied to an overlay on top of the instance. Only the
+//!   objects and arrays along the paths the patch changes are copied, and
+//!   only shallowly.
+//! * Only what the patch changed is re-validated: the values it puts in
+//!   place, the required properties it removes, and the objects whose
+//!   discriminator tag it changes.
+//!
+//! As a result, errors in parts of the instance that the patch doesn't touch
+//! are not reported. Paths in errors point into the patched instance.
+//!
+//! ```
+//! use jsl::patch::Operation;
+//! use jsl::{Schema, SerdeSchema, Validator};
+//! use serde_json::json;
+//!
+//! let schema: SerdeSchema = serde_json::from_value(json!({
+//!     "properties": {
+//!         "name": { "type": "string" },
+//!         "tags": { "elements": { "type": "string" } },
+//!     },
+//! })).unwrap();
+//! let schema = Schema::from_serde(schema).unwrap();
+//!
+//! let instance = json!({ "name": "a", "tags": ["b"] });
+//! let patch: Vec<Operation> = serde_json::from_value(json!([
+//!     { "op": "add", "path": "/tags/0", "value": 3 },
+//!     { "op": "remove", "path": "/name" },
+//! ])).unwrap();
+//!
+//! let errors = Validator::new().validate_patch(&schema, &instance, &patch).unwrap();
+//! assert_eq!(errors.len(), 2);
+//!
+//! assert_eq!(errors[0].operation(), 0);
+//! assert_eq!(errors[0].error().instance_path().to_string(), "/tags/0");
+//! assert_eq!(errors[0].error().schema_path().to_string(), "/properties/tags/elements/type");
+//!
+//! assert_eq!(errors[1].operation(), 1);
+//! assert_eq!(errors[1].error().instance_path().to_string(), "");
+//! assert_eq!(errors[1].error().schema_path().to_string(), "/properties/name");
+//! ```
+
+use crate::errors::JslError;
+use crate::pointer::JsonPointer;
+use crate::schema::{Form, Schema};
+use crate::validator::{pointer_tokens, Config, ValidationError};
+use crate::vm::{self, Start};
+use alloc::borrow::{Cow, ToOwned};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A JSON Patch operation, as defined by RFC 6902.
+///
+/// A JSON Patch is a list of operations, and deserializes into a
+/// `Vec<Operation>`. Paths are JSON Pointers.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Operation {
+    /// Add a value to an object, insert it into an array, or replace the
+    /// whole instance.
+    Add { path: String, value: Value },
+
+    /// Remove a value from an object or array.
+    Remove { path: String },
+
+    /// Replace the value at a path.
+    Replace { path: String, value: Value },
+
+    /// Remove the value at `from`, and add it at `path`.
+    Move { from: String, path: String },
+
+    /// Add a copy of the value at `from` at `path`.
+    Copy { from: String, path: String },
+
+    /// Check that the value at a path is equal to `value`.
+    Test { path: String, value: Value },
+}
+
+/// A validation error in a patched instance, and the operation responsible
+/// for it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatchError {
+    operation: usize,
+    error: ValidationError<'static>,
+}
+
+impl PatchError {
+    /// The index, within the patch, of the operation which caused the error.
+    /// If several operations changed the invalid part of the instance, this
+    /// is the last of them.
+    pub fn operation(&self) -> usize {
+        self.operation
+    }
+
+    /// The validation error. Its instance path points into the patched
+    /// instance.
+    pub fn error(&self) -> &ValidationError<'static> {
+        &self.error
+    }
+}
+
+/// The ways in which [`Validator::validate_patch`](../validator/struct.Validator.html#method.validate_patch)
+/// can fail.
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub enum PatchFailure {
+    /// The patch could not be applied to the instance.
+    ///
+    /// This arises when an operation's path does not exist in the instance,
+    /// or a `test` operation fails. Per RFC 6902, a patch with such an
+    /// operation does not apply at all. `operation` is the index of the
+    /// offending operation within the patch.
+    Inapplicable { operation: usize, message: String },
+
+    /// Validation could not be completed, for instance because the maximum
+    /// reference depth was exceeded.
+    Eval(JslError),
+}
+
+impl fmt::Display for PatchFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchFailure::Inapplicable { operation, message } => {
+                write!(f, "invalid patch operation {}: {}", operation, message)
+            }
+            PatchFailure::Eval(error) => write!(f, "error during validation: {}", error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PatchFailure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PatchFailure::Inapplicable { .. } => None,
+            PatchFailure::Eval(error) => Some(error),
+        }
+    }
+}
+
+pub(crate) fn validate_patch(
+    config: &Config,
+    schema: &Schema,
+    instance: &Value,
+    patch: &[Operation],
+) -> Result<Vec<PatchError>, PatchFailure> {
+    let mut root = Node::new(instance);
+    for (operation, op) in patch.iter().enumerate() {
+        apply(&mut root, operation, op)
+            .map_err(|message| PatchFailure::Inapplicable { operation, message })?;
+    }
+
+    Ok(Walk::run(config, schema, &root)
+        .map_err(PatchFailure::Eval)?
+        .into_iter()
+        .map(|(operation, error)| PatchError { operation, error })
+        .collect())
+}
+
+pub(crate) fn validate_merge_patch(
+    config: &Config,
+    schema: &Schema,
+    instance: &Value,
+    patch: &Value,
+) -> Result<Vec<ValidationError<'static>>, JslError> {
+    let mut root = Node::new(instance);
+    merge(&mut root, patch);
+
+    Ok(Walk::run(config, schema, &root)?
+        .into_iter()
+        .map(|(_, error)| error)
+        .collect())
+}
+
+/// A value in a patched instance.
+#[derive(Clone, Debug)]
+struct Node<'a> {
+    content: Content<'a>,
+
+    /// The last operation to put this value in place, if any did.
+    touched: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+enum Content<'a> {
+    /// A value from the instance or the patch, which the patch does not
+    /// change.
+    Value(&'a Value),
+
+    /// An array whose elements the patch changes.
+    Array(Vec<Node<'a>>),
+
+    /// An object whose members the patch changes, along with the members the
+    /// patch removed, and the last operation to remove each of them.
+    Object {
+        members: BTreeMap<String, Node<'a>>,
+        removed: BTreeMap<String, usize>,
+    },
+}
+
+static NULL: Value = Value::Null;
+
+impl<'a> Node<'a> {
+    fn new(value: &'a Value) -> Self {
+        Node {
+            content: Content::Value(value),
+            touched: None,
+        }
+    }
+
+    fn touched(value: &'a Value, operation: usize) -> Self {
+        Node {
+            content: Content::Value(value),
+            touched: Some(operation),
+        }
+    }
+
+    /// Make the members or elements of a borrowed object or array changeable.
+    fn expand(&mut self) {
+        if let Content::Value(value) = self.content {
+            self.content = match value {
+                Value::Array(elements) => Content::Array(elements.iter().map(Node::new).collect()),
+                Value::Object(members) => Content::Object {
+                    members: members
+                        .iter()
+                        .map(|(key, value)| (key.clone(), Node::new(value)))
+                        .collect(),
+                    removed: BTreeMap::new(),
+                },
+                _ => return,
+            };
+        }
+    }
+
+    fn is_object(&self) -> bool {
+        match self.content {
+            Content::Value(value) => value.is_object(),
+            Content::Array(_) => false,
+            Content::Object { .. } => true,
+        }
+    }
+
+    fn as_str(&self) -> Option<&'a str> {
+        match self.content {
+            Content::Value(value) => value.as_str(),
+            _ => None,
+        }
+    }
+
+    fn child(&self, token: &str) -> Option<&Node<'a>> {
+        match &self.content {
+            Content::Value(_) => None,
+            Content::Array(elements) => elements.get(index(token, elements.len())?),
+            Content::Object { members, .. } => members.get(token),
+        }
+    }
+
+    fn get(&self, tokens: &[String]) -> Option<Node<'a>> {
+        let (first, rest) = match tokens.split_first() {
+            Some(split) => split,
+            None => return Some(self.clone()),
+        };
+
+        match &self.content {
+            Content::Value(value) => {
+                let mut value: &'a Value = value;
+                for token in tokens {
+                    value = match value {
+                        Value::Array(elements) => elements.get(index(token, elements.len())?)?,
+                        Value::Object(members) => members.get(token)?,
+                        _ => return None,
+                    };
+                }
+
+                Some(Node::new(value))
+            }
+            _ => self.child(first)?.get(rest),
+        }
+    }
+
+    /// Get a changeable object or array.
+    fn container(&mut self, tokens: &[String]) -> Option<&mut Node<'a>> {
+        let mut node = self;
+        for token in tokens {
+            node.expand();
+            node = match &mut node.content {
+                Content::Value(_) => return None,
+                Content::Array(elements) => {
+                    let i = index(token, elements.len())?;
+                    &mut elements[i]
+                }
+                Content::Object { members, .. } => members.get_mut(token)?,
+            };
+        }
+
+        node.expand();
+        Some(node)
+    }
+
+    fn add(&mut self, tokens: &[String], node: Node<'a>) -> Option<()> {
+        let (last, parent) = match tokens.split_last() {
+            Some(split) => split,
+            None => {
+                *self = node;
+                return Some(());
+            }
+        };
+
+        match &mut self.container(parent)?.content {
+            Content::Value(_) => return None,
+            Content::Array(elements) => {
+                let i = if last == "-" {
+                    elements.len()
+                } else {
+                    index(last, elements.len() + 1)?
+                };
+
+                elements.insert(i, node);
+            }
+            Content::Object { members, .. } => {
+                members.insert(last.clone(), node);
+            }
+        }
+
+        Some(())
+    }
+
+    fn remove(&mut self, tokens: &[String], operation: usize) -> Option<Node<'a>> {
+        let (last, parent) = tokens.split_last()?;
+        match &mut self.container(parent)?.content {
+            Content::Value(_) => None,
+            Content::Array(elements) => {
+                let i = index(last, elements.len())?;
+                Some(elements.remove(i))
+            }
+            Content::Object { members, removed } => {
+                let node = members.remove(last)?;
+                removed.insert(last.clone(), operation);
+                Some(node)
+            }
+        }
+    }
+
+    fn equals(&self, other: &Value) -> bool {
+        match &self.content {
+            Content::Value(value) => *value == other,
+            Content::Array(elements) => other.as_array().is_some_and(|other| {
+                elements.len() == other.len()
+                    && elements.iter().zip(other).all(|(a, b)| a.equals(b))
+            }),
+            Content::Object { members, .. } => other.as_object().is_some_and(|other| {
+                members.len() == other.len()
+                    && members
+                        .iter()
+                        .all(|(key, a)| other.get(key).is_some_and(|b| a.equals(b)))
+            }),
+        }
+    }
+
+    fn to_value(&self) -> Cow<'a, Value> {
+        match &self.content {
+            Content::Value(value) => Cow::Borrowed(value),
+            Content::Array(elements) => Cow::Owned(Value::Array(
+                elements
+                    .iter()
+                    .map(|element| element.to_value().into_owned())
+                    .collect(),
+            )),
+            Content::Object { members, .. } => Cow::Owned(Value::Object(
+                members
+                    .iter()
+                    .map(|(key, member)| (key.clone(), member.to_value().into_owned()))
+                    .collect::<Map<_, _>>(),
+            )),
+        }
+    }
+}
+
+/// Parse an array index. Indices must not have leading zeros, and must be
+/// less than `len`.
+fn index(token: &str, len: usize) -> Option<usize> {
+    if token.is_empty()
+        || !token.bytes().all(|b| b.is_ascii_digit())
+        || (token.len() > 1 && token.starts_with('0'))
+    {
+        return None;
+    }
+
+    token.parse().ok().filter(|&i| i < len)
+}
+
+/// Split a JSON Pointer into its unescaped reference tokens.
+fn tokens(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+
+    if !pointer.starts_with('/') {
+        return Err(format!("invalid JSON Pointer: {:?}", pointer));
+    }
+
+    pointer[1..]
+        .split('/')
+        .map(|token| {
+            let mut out = String::new();
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                out.push(match (c, c == '~') {
+                    (_, false) => c,
+                    (_, true) => match chars.next() {
+                        Some('0') => '~',
+                        Some('1') => '/',
+                        _ => return Err(format!("invalid JSON Pointer: {:?}", pointer)),
+                    },
+                });
+            }
+
+            Ok(out)
+        })
+        .collect()
+}
+
+fn apply<'a>(root: &mut Node<'a>, operation: usize, op: &'a Operation) -> Result<(), String> {
+    let missing = |path: &str| format!("no value at {:?}", path);
+    let unaddable = |path: &str| format!("cannot add a value at {:?}", path);
+
+    match op {
+        Operation::Add { path, value } => root
+            .add(&tokens(path)?, Node::touched(value, operation))
+            .ok_or_else(|| unaddable(path)),
+        Operation::Remove { path } => root
+            .remove(&tokens(path)?, operation)
+            .map(drop)
+            .ok_or_else(|| missing(path)),
+        Operation::Replace { path, value } => {
+            let tokens = tokens(path)?;
+            if !tokens.is_empty() {
+                root.remove(&tokens, operation)
+                    .ok_or_else(|| missing(path))?;
+            }
+
+            root.add(&tokens, Node::touched(value, operation))
+                .ok_or_else(|| unaddable(path))
+        }
+        Operation::Move { from, path } => {
+            let from_tokens = tokens(from)?;
+            let tokens = tokens(path)?;
+            if tokens.len() > from_tokens.len() && tokens.starts_with(&from_tokens) {
+                return Err(format!("cannot move {:?} into itself", from));
+            }
+
+            let mut node = root
+                .remove(&from_tokens, operation)
+                .ok_or_else(|| missing(from))?;
+            node.touched = Some(operation);
+            root.add(&tokens, node).ok_or_else(|| unaddable(path))
+        }
+        Operation::Copy { from, path } => {
+            let mut node = root.get(&tokens(from)?).ok_or_else(|| missing(from))?;
+            node.touched = Some(operation);
+            root.add(&tokens(path)?, node)
+                .ok_or_else(|| unaddable(path))
+        }
+        Operation::Test { path, value } => {
+            let node = root.get(&tokens(path)?).ok_or_else(|| missing(path))?;
+            if node.equals(value) {
+                Ok(())
+            } else {
+                Err(format!("test failed at {:?}", path))
+            }
+        }
+    }
+}
+
+fn merge<'a>(node: &mut Node<'a>, patch: &'a Value) {
+    let patch_members = match patch {
+        Value::Object(members) => members,
+        _ => {
+            *node = Node::touched(patch, 0);
+            return;
+        }
+    };
+
+    if !node.is_object() {
+        *node = Node {
+            content: Content::Object {
+                members: BTreeMap::new(),
+                removed: BTreeMap::new(),
+            },
+            touched: Some(0),
+        };
+    }
+
+    node.expand();
+    if let Content::Object { members, removed } = &mut node.content {
+        for (key, value) in patch_members {
+            if value.is_null() {
+                if members.remove(key).is_some() {
+                    removed.insert(key.clone(), 0);
+                }
+            } else {
+                let member = members
+                    .entry(key.clone())
+                    .or_insert_with(|| Node::touched(&NULL, 0));
+                merge(member, value);
+            }
+        }
+    }
+}
+
+/// Re-validates the parts of a patched instance that a patch changed.
+struct Walk<'n, 'a> {
+    config: &'n Config,
+    root_schema: &'n Schema,
+    root: &'n Node<'a>,
+    errors: Vec<(usize, ValidationError<'static>)>,
+}
+
+impl<'n, 'a> Walk<'n, 'a> {
+    fn run(
+        config: &'n Config,
+        root_schema: &'n Schema,
+        root: &'n Node<'a>,
+    ) -> Result<Vec<(usize, ValidationError<'static>)>, JslError> {
+        let mut walk = Walk {
+            config,
+            root_schema,
+            root,
+            errors: vec![],
+        };
+
+        walk.visit(root, root_schema, vec![vec![]], vec![])?;
+
+        let mut errors = walk.errors;
+        errors.sort_by_key(|&(operation, _)| operation);
+        if config.max_errors != 0 {
+            errors.truncate(config.max_errors);
+        }
+
+        Ok(errors)
+    }
+
+    fn visit(
+        &mut self,
+        node: &Node<'a>,
+        schema: &'n Schema,
+        mut frames: Vec<Vec<String>>,
+        path: Vec<String>,
+    ) -> Result<(), JslError> {
+        if let Some(operation) = node.touched {
+            return self.validate(node, schema, &frames, &path, operation);
+        }
+
+        let members = match &node.content {
+            Content::Value(_) => return Ok(()),
+            Content::Array(elements) => {
+                if let Form::Elements(sub_schema) = self.resolve(schema, &mut frames)?.form() {
+                    for (i, element) in elements.iter().enumerate() {
+                        let frames = push(&frames, &["elements"]);
+                        self.visit(element, sub_schema, frames, child(&path, &i.to_string()))?;
+                    }
+                }
+
+                return Ok(());
+            }
+            Content::Object { members, removed } => (members, removed),
+        };
+
+        let (members, removed) = members;
+        let mut schema = self.resolve(schema, &mut frames)?;
+        let mut parent_tag = None;
+
+        if let Form::Discriminator(tag, mapping) = schema.form() {
+            // Changing the tag changes which schema applies to the whole
+            // object.
+            let changed = match members.get(tag) {
+                Some(member) => member.touched,
+                None => removed.get(tag).copied(),
+            };
+
+            if let Some(operation) = changed {
+                return self.validate(node, schema, &frames, &path, operation);
+            }
+
+            let instance_tag = match members.get(tag).and_then(Node::as_str) {
+                Some(instance_tag) => instance_tag,
+                None => return Ok(()),
+            };
+
+            schema = match mapping.get(instance_tag) {
+                Some(sub_schema) => sub_schema,
+                None => return Ok(()),
+            };
+
+            frames = push(&frames, &["discriminator", "mapping", instance_tag]);
+            parent_tag = Some(tag);
+        }
+
+        match schema.form() {
+            Form::Properties(required, optional, _) => {
+                for (key, member) in members {
+                    if parent_tag == Some(key) {
+                        continue;
+                    }
+
+                    if let Some(sub_schema) = required.get(key) {
+                        let frames = push(&frames, &["properties", key]);
+                        self.visit(member, sub_schema, frames, child(&path, key))?;
+                    } else if let Some(sub_schema) = optional.get(key) {
+                        let frames = push(&frames, &["optionalProperties", key]);
+                        self.visit(member, sub_schema, frames, child(&path, key))?;
+                    } else if let Some(operation) = member.touched {
+                        if self.config.strict_instance_semantics {
+                            self.push_err(operation, child(&path, key), last(&frames).to_vec());
+                        }
+                    }
+                }
+
+                for (key, &operation) in removed {
+                    if required.contains_key(key) && !members.contains_key(key) {
+                        let schema_path = push(&frames, &["properties", key]);
+                        self.push_err(operation, path.clone(), last(&schema_path).to_vec());
+                    }
+                }
+            }
+            Form::Values(sub_schema) => {
+                for (key, member) in members {
+                    let frames = push(&frames, &["values"]);
+                    self.visit(member, sub_schema, frames, child(&path, key))?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Follow references, the way the validator would.
+    fn resolve(
+        &self,
+        mut schema: &'n Schema,
+        frames: &mut Vec<Vec<String>>,
+    ) -> Result<&'n Schema, JslError> {
+        while let Form::Ref(def) = schema.form() {
+            if frames.len() == self.config.max_depth {
+                return Err(JslError::MaxDepthExceeded);
+            }
+
+            frames.push(vec!["definitions".to_owned(), def.clone()]);
+            schema = &self.root_schema.definitions().as_ref().unwrap()[def];
+        }
+
+        Ok(schema)
+    }
+
+    /// Validate a whole value, attributing its errors to `operation` or to
+    /// any later operation that changed part of it.
+    fn validate(
+        &mut self,
+        node: &Node<'a>,
+        schema: &Schema,
+        frames: &[Vec<String>],
+        path: &[String],
+        operation: usize,
+    ) -> Result<(), JslError> {
+        let instance = node.to_value();
+        let start = Start {
+            schema,
+            schema_tokens: frames
+                .iter()
+                .map(|frame| frame.iter().map(|token| token.into()).collect())
+                .collect(),
+            instance_tokens: path.iter().map(|token| token.into()).collect(),
+        };
+
+        let errors = vm::validate_from(
+            self.config.max_errors,
+            self.config.max_depth,
+            self.config.strict_instance_semantics,
+            self.root_schema,
+            start,
+            &instance,
+        )?;
+
+        for error in errors {
+            let error = error.into_owned();
+            let operation = self
+                .attribute(&error)
+                .map_or(operation, |o| o.max(operation));
+            self.errors.push((operation, error));
+        }
+
+        Ok(())
+    }
+
+    /// Find the last operation to change the part of the patched instance an
+    /// error is about.
+    fn attribute(&self, error: &ValidationError) -> Option<usize> {
+        let mut node = self.root;
+        let mut operation = node.touched;
+        for token in pointer_tokens(error.instance_path()) {
+            node = match node.child(&token) {
+                Some(node) => node,
+                None => return operation,
+            };
+
+            operation = operation.max(node.touched);
+        }
+
+        if let Content::Object { removed, .. } = &node.content {
+            operation = operation.max(removed.values().copied().max());
+        }
+
+        operation
+    }
+
+    fn push_err(&mut self, operation: usize, instance_path: Vec<String>, schema_path: Vec<String>) {
+        let error = ValidationError::new(
+            JsonPointer::new(instance_path.into_iter().map(Cow::Owned).collect()),
+            JsonPointer::new(schema_path.into_iter().map(Cow::Owned).collect()),
+        );
+
+        self.errors.push((operation, error));
+    }
+}
+
+fn push(frames: &[Vec<String>], tokens: &[&str]) -> Vec<Vec<String>> {
+    let mut frames = frames.to_vec();
+    frames
+        .last_mut()
+        .unwrap()
+        .extend(tokens.iter().map(|&token| token.to_owned()));
+    frames
+}
+
+fn last(frames: &[Vec<String>]) -> &[String] {
+    frames.last().unwrap()
+}
+
+fn child(path: &[String], token: &str) -> Vec<String> {
+    let mut path = path.to_vec();
+    path.push(token.to_owned());
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Serde;
+    use crate::validator::Validator;
+    use serde_json::json;
+
+    fn schema(value: Value) -> Schema {
+        Schema::from_serde(serde_json::from_value::<Serde>(value).unwrap()).unwrap()
+    }
+
+    fn patch(value: Value) -> Vec<Operation> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn paths(errors: &[PatchError]) -> Vec<(usize, String, String)> {
+        errors
+            .iter()
+            .map(|e| {
+                (
+                    e.operation(),
+                    e.error().instance_path().to_string(),
+                    e.error().schema_path().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn test_schema() -> Schema {
+        schema(json!({
+            "definitions": {
+                "shape": {
+                    "discriminator": {
+                        "tag": "kind",
+                        "mapping": {
+                            "circle": { "properties": { "r": { "type": "float64" } } },
+                            "square": { "properties": { "side": { "type": "float64" } } },
+                        },
+                    },
+                },
+            },
+            "properties": {
+                "name": { "type": "string" },
+                "shapes": { "elements": { "ref": "shape" } },
+            },
+            "optionalProperties": {
+                "labels": { "values": { "type": "string" } },
+            },
+        }))
+    }
+
+    #[test]
+    fn attributes_errors_to_operations() {
+        let instance = json!({
+            "name": "a",
+            "shapes": [{ "kind": "circle", "r": 1 }],
+        });
+
+        let errors = Validator::new()
+            .validate_patch(
+                &test_schema(),
+                &instance,
+                &patch(json!([
+                    { "op": "add", "path": "/shapes/-", "value": { "kind": "circle", "r": "x" } },
+                    { "op": "add", "path": "/shapes/0", "value": { "kind": "square", "side": 2 } },
+                    { "op": "remove", "path": "/name" },
+                    { "op": "replace", "path": "/shapes/1/kind", "value": "square" },
+                    { "op": "add", "path": "/labels", "value": { "a": 1 } },
+                ])),
+            )
+            .unwrap();
+
+        assert_eq!(
+            paths(&errors),
+            vec![
+                (
+                    0,
+                    "/shapes/2/r".to_owned(),
+                    "/definitions/shape/discriminator/mapping/circle/properties/r/type".to_owned()
+                ),
+                (2, "".to_owned(), "/properties/name".to_owned()),
+                (
+                    3,
+                    "/shapes/1".to_owned(),
+                    "/definitions/shape/discriminator/mapping/square/properties/side".to_owned()
+                ),
+                (
+                    4,
+                    "/labels/a".to_owned(),
+                    "/optionalProperties/labels/values/type".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_untouched_errors() {
+        let instance = json!({ "name": 1, "shapes": [] });
+        let errors = Validator::new()
+            .validate_patch(
+                &test_schema(),
+                &instance,
+                &patch(json!([{ "op": "add", "path": "/shapes/0", "value": { "kind": "circle", "r": 1 } }])),
+            )
+            .unwrap();
+
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn strict_instance_semantics() {
+        let instance = json!({ "name": "a", "shapes": [] });
+        let ops = patch(json!([{ "op": "copy", "from": "/name", "path": "/extra" }]));
+
+        let errors = Validator::new()
+            .validate_patch(&test_schema(), &instance, &ops)
+            .unwrap();
+        assert!(errors.is_empty());
+
+        let mut config = Config::new();
+        config.strict_instance_semantics(true);
+        let errors = Validator::new_with_config(config)
+            .validate_patch(&test_schema(), &instance, &ops)
+            .unwrap();
+        assert_eq!(
+            paths(&errors),
+            vec![(0, "/extra".to_owned(), "".to_owned())]
+        );
+    }
+
+    #[test]
+    fn invalid_patches() {
+        let instance = json!({ "name": "a", "shapes": [1] });
+        let cases = vec![
+            json!([{ "op": "remove", "path": "/nope" }]),
+            json!([{ "op": "add", "path": "/shapes/2", "value": 1 }]),
+            json!([{ "op": "add", "path": "/shapes/01", "value": 1 }]),
+            json!([{ "op": "replace", "path": "/shapes/-", "value": 1 }]),
+            json!([{ "op": "remove", "path": "" }]),
+            json!([{ "op": "move", "from": "/shapes", "path": "/shapes/0" }]),
+            json!([{ "op": "test", "path": "/name", "value": "b" }]),
+            json!([{ "op": "add", "path": "name", "value": 1 }]),
+            json!([{ "op": "add", "path": "/~2", "value": 1 }]),
+            json!([{ "op": "add", "path": "/name/x", "value": 1 }]),
+        ];
+
+        for case in cases {
+            let mut ops = patch(json!([{ "op": "test", "path": "/shapes/0", "value": 1 }]));
+            ops.extend(patch(case.clone()));
+
+            let err = Validator::new()
+                .validate_patch(&test_schema(), &instance, &ops)
+                .unwrap_err();
+            match err {
+                PatchFailure::Inapplicable { operation, .. } => {
+                    assert_eq!(operation, 1, "{}", case)
+                }
+                err => panic!("unexpected error: {}", err),
+            }
+        }
+    }
+
+    #[test]
+    fn agrees_with_full_validation() {
+        let instance = json!({
+            "name": "a",
+            "shapes": [{ "kind": "circle", "r": 1 }, { "kind": "square", "side": 2 }],
+            "labels": { "x": "y" },
+        });
+
+        let cases = vec![
+            json!([{ "op": "move", "from": "/shapes/0", "path": "/shapes/-" }]),
+            json!([{ "op": "move", "from": "/shapes/0/r", "path": "/shapes/1/r" }]),
+            json!([{ "op": "remove", "path": "/shapes/1/kind" }]),
+            json!([{ "op": "replace", "path": "/shapes/0/kind", "value": "triangle" }]),
+            json!([{ "op": "copy", "from": "/labels", "path": "/shapes/0" }]),
+            json!([{ "op": "replace", "path": "", "value": [] }]),
+            json!([{ "op": "add", "path": "/labels/z", "value": null }, { "op": "remove", "path": "/labels/x" }]),
+            json!([{ "op": "replace", "path": "/shapes/1/side", "value": "x" }, { "op": "test", "path": "/shapes/1/side", "value": "x" }]),
+        ];
+
+        for case in cases {
+            let ops = patch(case.clone());
+
+            let mut root = Node::new(&instance);
+            for (i, op) in ops.iter().enumerate() {
+                apply(&mut root, i, op).unwrap();
+            }
+            let patched = root.to_value().into_owned();
+
+            let schema = test_schema();
+            let mut expected: Vec<_> = Validator::new()
+                .validate(&schema, &patched)
+                .unwrap()
+                .into_iter()
+                .map(|e| (e.instance_path().to_string(), e.schema_path().to_string()))
+                .collect();
+            let mut actual: Vec<_> = Validator::new()
+                .validate_patch(&schema, &instance, &ops)
+                .unwrap()
+                .into_iter()
+                .map(|e| {
+                    (
+                        e.error().instance_path().to_string(),
+                        e.error().schema_path().to_string(),
+                    )
+                })
+                .collect();
+
+            expected.sort();
+            actual.sort();
+            assert_eq!(expected, actual, "{}", case);
+        }
+    }
+
+    #[test]
+    fn merge_patch() {
+        let instance = json!({
+            "name": "a",
+            "shapes": [],
+            "labels": { "x": "y" },
+        });
+
+        let errors = Validator::new()
+            .validate_merge_patch(
+                &test_schema(),
+                &instance,
+                &json!({ "name": null, "labels": { "x": null, "z": 1 }, "other": null }),
+            )
+            .unwrap();
+
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|e| (e.instance_path().to_string(), e.schema_path().to_string()))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    "/labels/z".to_owned(),
+                    "/optionalProperties/labels/values/type".to_owned()
+                ),
+                ("".to_owned(), "/properties/name".to_owned()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! The JSON Pointer type used in validation errors.
+//!
+//! This is a minimal JSON Pointer, in the shape of the `json-pointer` crate's:
+//! it can be constructed from reference tokens, compared, and formatted as a
+//! string. The `json-pointer` crate requires `std`, so it can't be used in
+//! every configuration of this crate.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+/// A JSON Pointer, as a sequence of unescaped reference tokens.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsonPointer<S: AsRef<str>, C: AsRef<[S]>> {
+    ref_toks: C,
+    _phantom: PhantomData<S>,
+}
+
+impl<S: AsRef<str>, C: AsRef<[S]>> JsonPointer<S, C> {
+    /// Creates a new JsonPointer from the given reference tokens.
+    pub fn new(ref_toks: C) -> JsonPointer<S, C> {
+        JsonPointer {
+            ref_toks,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<S: AsRef<str>, C: AsRef<[S]>> fmt::Display for JsonPointer<S, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in self.ref_toks.as_ref() {
+            f.write_str("/")?;
+            for c in token.as_ref().chars() {
+                match c {
+                    '~' => f.write_str("~0")?,
+                    '/' => f.write_str("~1")?,
+                    c => fmt::Write::write_char(f, c)?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,677 @@
+//! Generate random instances that are valid against a schema.
+//!
+//! A [`Generator`](struct.Generator.html) turns a schema and a seed into a
+//! `serde_json::Value` that the schema accepts. The same schema and seed
+//! always produce the same instance, so failures found this way can be
+//! reproduced.
+//!
+//! Integers are drawn from the full range of their `Type`, with a bias
+//! towards the edges of that range. Timestamps are valid RFC 3339 strings,
+//! with arbitrary offsets. Enums and discriminators choose uniformly between
+//! their values and variants.
+//!
+//! Schemas can recurse through `ref`s. Once a generator has followed
+//! `max_depth` refs, it only makes the choices that lead to the smallest
+//! possible instance: arrays and maps are empty, optional properties are
+//! omitted, and discriminators choose the variant with the shallowest
+//! nesting. Some schemas, like a definition which requires a property of its
+//! own type, have no finite instances at all. Generators return `None` for
+//! those.
+//!
+//! With the `proptest` feature enabled,
+//! [`Generator::strategy`](struct.Generator.html#method.strategy) makes the
+//! same instances available as a `proptest` `Strategy`, which shrinks failing
+//! instances towards smaller arrays, fewer optional properties, and values
+//! closer to zero.
+
+use crate::height::{height, heights};
+use crate::schema::{Form, Schema, Type};
+use chrono::{DateTime, FixedOffset, SecondsFormat};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// The first second of 0001-01-01, plus a day of margin for offsets.
+const MIN_TIMESTAMP: i64 = -62_135_596_800 + 86_400;
+
+/// The last second of 9999-12-31, minus a day of margin for offsets.
+const MAX_TIMESTAMP: i64 = 253_402_300_799 - 86_400;
+
+/// The largest offset, in minutes, that RFC 3339 can express.
+const MAX_OFFSET: i32 = 23 * 60 + 59;
+
+const CHARS: &[char] = &[
+    'a', 'b', 'c', 'x', 'y', 'z', 'A', 'Z', '0', '9', ' ', '-', '_', '"', '\\', '/', 'é', 'ß',
+    '字', '😀',
+];
+
+/// Generates random instances of schemas.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Generator {
+    config: Config,
+}
+
+impl Generator {
+    /// Constructs a new generator using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new generator using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Generate an instance of a root schema from a seed.
+    ///
+    /// Returns `None` if the schema has no finite instances.
+    pub fn generate(&self, schema: &Schema, seed: u64) -> Option<Value> {
+        let no_defs = BTreeMap::new();
+        let defs = schema.definitions().as_ref().unwrap_or(&no_defs);
+        self.generate_with_definitions(defs, schema, seed)
+    }
+
+    /// Generate an instance of a schema, which may be a sub-schema of a root
+    /// schema with the given definitions.
+    pub(crate) fn generate_with_definitions(
+        &self,
+        defs: &BTreeMap<String, Schema>,
+        schema: &Schema,
+        seed: u64,
+    ) -> Option<Value> {
+        let heights = heights(defs);
+        height(schema, &heights)?;
+
+        let mut state = State {
+            config: &self.config,
+            defs,
+            heights: &heights,
+            rng: Rng(seed),
+        };
+
+        Some(state.value(schema, 0))
+    }
+
+    /// Make a `proptest` strategy that generates instances of a root schema.
+    ///
+    /// Returns `None` if the schema has no finite instances.
+    #[cfg(feature = "proptest")]
+    pub fn strategy(&self, schema: &Schema) -> Option<proptest::strategy::BoxedStrategy<Value>> {
+        let no_defs = BTreeMap::new();
+        let defs = schema.definitions().as_ref().unwrap_or(&no_defs);
+        let heights = heights(defs);
+        height(schema, &heights)?;
+
+        let strategies = strategy::Strategies {
+            config: &self.config,
+            defs,
+            heights: &heights,
+        };
+
+        Some(strategies.value(schema, 0))
+    }
+}
+
+/// Configuration for how instances should be generated.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    max_depth: usize,
+    max_size: usize,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of refs to follow before only generating the smallest
+    /// possible instances. The default is 4.
+    pub fn max_depth(&mut self, max_depth: usize) -> &mut Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the maximum number of elements in generated arrays, entries in
+    /// generated maps, and characters in generated strings. The default is 4.
+    pub fn max_size(&mut self, max_size: usize) -> &mut Self {
+        self.max_size = max_size;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            max_size: 4,
+        }
+    }
+}
+
+/// The variants of a discriminator that can be generated, in sorted order.
+/// When `minimal`, only the shallowest variant is included.
+fn variants<'a>(
+    mapping: &'a BTreeMap<String, Schema>,
+    heights: &BTreeMap<&str, usize>,
+    minimal: bool,
+) -> Vec<(&'a String, &'a Schema)> {
+    let mut variants: Vec<_> = mapping
+        .iter()
+        .filter_map(|(value, sub_schema)| {
+            height(sub_schema, heights).map(|h| (h, value, sub_schema))
+        })
+        .collect();
+    variants.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+    if minimal {
+        variants.truncate(1);
+    } else {
+        variants.sort_by(|a, b| a.1.cmp(b.1));
+    }
+
+    variants
+        .into_iter()
+        .map(|(_, value, sub_schema)| (value, sub_schema))
+        .collect()
+}
+
+/// The inclusive range of an integer type.
+pub(crate) fn int_range(typ: &Type) -> Option<(i64, u64)> {
+    match typ {
+        Type::Int8 => Some((i8::MIN.into(), i8::MAX as u64)),
+        Type::Uint8 => Some((0, u8::MAX.into())),
+        Type::Int16 => Some((i16::MIN.into(), i16::MAX as u64)),
+        Type::Uint16 => Some((0, u16::MAX.into())),
+        Type::Int32 => Some((i32::MIN.into(), i32::MAX as u64)),
+        Type::Uint32 => Some((0, u32::MAX.into())),
+        Type::Int64 => Some((i64::MIN, i64::MAX as u64)),
+        Type::Uint64 => Some((0, u64::MAX)),
+        _ => None,
+    }
+}
+
+fn timestamp(seconds: i64, millis: u32, offset_minutes: i32) -> Value {
+    let offset = FixedOffset::east_opt(offset_minutes * 60).expect("offset out of range");
+    let time = DateTime::from_timestamp(seconds, millis * 1_000_000)
+        .expect("timestamp out of range")
+        .with_timezone(&offset);
+
+    Value::String(time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+}
+
+/// A small, fast, seedable source of randomness (SplitMix64).
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`.
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    /// A number in `0.0..1.0`.
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn chance(&mut self, one_in: u64) -> bool {
+        self.below(one_in) == 0
+    }
+}
+
+struct State<'a> {
+    config: &'a Config,
+    defs: &'a BTreeMap<String, Schema>,
+    heights: &'a BTreeMap<&'a str, usize>,
+    rng: Rng,
+}
+
+impl<'a> State<'a> {
+    fn value(&mut self, schema: &Schema, depth: usize) -> Value {
+        let minimal = depth >= self.config.max_depth;
+
+        match schema.form() {
+            Form::Empty => self.any(),
+            Form::Ref(def) => {
+                let defs = self.defs;
+                self.value(&defs[def], depth + 1)
+            }
+            Form::Type(typ) => self.typ(typ),
+            Form::Enum(values) => {
+                let values: Vec<_> = values.iter().collect();
+                let i = self.rng.below(values.len() as u64) as usize;
+                Value::String(values[i].clone())
+            }
+            Form::Elements(sub_schema) => {
+                let len = self.len(sub_schema, minimal);
+                Value::Array((0..len).map(|_| self.value(sub_schema, depth)).collect())
+            }
+            Form::Values(sub_schema) => {
+                let len = self.len(sub_schema, minimal);
+                let mut out = Map::new();
+                for _ in 0..len {
+                    let key = self.string();
+                    let value = self.value(sub_schema, depth);
+                    out.insert(key, value);
+                }
+                Value::Object(out)
+            }
+            Form::Properties(required, optional, _) => {
+                let mut out = Map::new();
+                for (name, sub_schema) in required {
+                    out.insert(name.clone(), self.value(sub_schema, depth));
+                }
+
+                for (name, sub_schema) in optional {
+                    if !minimal && height(sub_schema, self.heights).is_some() && self.rng.chance(2)
+                    {
+                        out.insert(name.clone(), self.value(sub_schema, depth));
+                    }
+                }
+
+                Value::Object(out)
+            }
+            Form::Discriminator(tag, mapping) => {
+                let variants = variants(mapping, self.heights, minimal);
+                let (value, sub_schema) = variants[self.rng.below(variants.len() as u64) as usize];
+
+                let mut out = self.value(sub_schema, depth);
+                if let Value::Object(ref mut out) = out {
+                    out.insert(tag.clone(), Value::String(value.clone()));
+                }
+                out
+            }
+        }
+    }
+
+    fn len(&mut self, sub_schema: &Schema, minimal: bool) -> u64 {
+        if minimal || height(sub_schema, self.heights).is_none() {
+            0
+        } else {
+            self.rng.below(self.config.max_size as u64 + 1)
+        }
+    }
+
+    fn any(&mut self) -> Value {
+        match self.rng.below(4) {
+            0 => Value::Null,
+            1 => Value::Bool(self.rng.chance(2)),
+            2 => self.typ(&Type::Int32),
+            _ => Value::String(self.string()),
+        }
+    }
+
+    fn typ(&mut self, typ: &Type) -> Value {
+        if let Some((min, max)) = int_range(typ) {
+            // Edges of the range are where bugs are most likely, so favor
+            // them.
+            return match self.rng.below(8) {
+                0 => min.into(),
+                1 => max.into(),
+                _ if min == 0 => {
+                    if max == u64::MAX {
+                        self.rng.next().into()
+                    } else {
+                        self.rng.below(max + 1).into()
+                    }
+                }
+                _ if min == i64::MIN => (self.rng.next() as i64).into(),
+                _ => (min + self.rng.below(max + min.unsigned_abs() + 1) as i64).into(),
+            };
+        }
+
+        match typ {
+            Type::Boolean => Value::Bool(self.rng.chance(2)),
+            Type::Number | Type::Float32 | Type::Float64 => {
+                let exponent = self.rng.below(13) as i32 - 6;
+                let mut n = (self.rng.unit() * 2.0 - 1.0) * 10f64.powi(exponent);
+                if let Type::Float32 = typ {
+                    n = f64::from(n as f32);
+                }
+                n.into()
+            }
+            Type::String => Value::String(self.string()),
+            Type::Timestamp => {
+                let span = (MAX_TIMESTAMP - MIN_TIMESTAMP) as u64;
+                let seconds = MIN_TIMESTAMP + self.rng.below(span + 1) as i64;
+                let millis = if self.rng.chance(2) {
+                    0
+                } else {
+                    self.rng.below(1000) as u32
+                };
+                let offset = self.rng.below(2 * MAX_OFFSET as u64 + 1) as i32 - MAX_OFFSET;
+                timestamp(seconds, millis, offset)
+            }
+            _ => unreachable!("integer types handled above"),
+        }
+    }
+
+    fn string(&mut self) -> String {
+        let len = self.rng.below(self.config.max_size as u64 + 1);
+        (0..len)
+            .map(|_| CHARS[self.rng.below(CHARS.len() as u64) as usize])
+            .collect()
+    }
+}
+
+#[cfg(feature = "proptest")]
+mod strategy {
+    use super::*;
+    use proptest::collection::{btree_map, vec};
+    use proptest::prelude::*;
+    use proptest::strategy::Union;
+
+    fn string(max_size: usize) -> BoxedStrategy<String> {
+        vec(any::<char>(), 0..=max_size)
+            .prop_map(|chars| chars.into_iter().collect())
+            .boxed()
+    }
+
+    pub(super) struct Strategies<'a> {
+        pub(super) config: &'a Config,
+        pub(super) defs: &'a BTreeMap<String, Schema>,
+        pub(super) heights: &'a BTreeMap<&'a str, usize>,
+    }
+
+    impl<'a> Strategies<'a> {
+        pub(super) fn value(&self, schema: &Schema, depth: usize) -> BoxedStrategy<Value> {
+            let minimal = depth >= self.config.max_depth;
+            let size = if minimal { 0 } else { self.config.max_size };
+
+            match schema.form() {
+                Form::Empty => prop_oneof![
+                    Just(Value::Null),
+                    any::<bool>().prop_map(Value::from),
+                    any::<i32>().prop_map(Value::from),
+                    string(self.config.max_size).prop_map(Value::from),
+                ]
+                .boxed(),
+                Form::Ref(def) => self.value(&self.defs[def], depth + 1),
+                Form::Type(typ) => self.typ(typ),
+                Form::Enum(values) => {
+                    let values: Vec<_> = values.iter().cloned().collect();
+                    proptest::sample::select(values)
+                        .prop_map(Value::String)
+                        .boxed()
+                }
+                Form::Elements(sub_schema) => {
+                    if height(sub_schema, self.heights).is_none() {
+                        return Just(Value::Array(Vec::new())).boxed();
+                    }
+
+                    vec(self.value(sub_schema, depth), 0..=size)
+                        .prop_map(Value::Array)
+                        .boxed()
+                }
+                Form::Values(sub_schema) => {
+                    if height(sub_schema, self.heights).is_none() {
+                        return Just(Value::Object(Map::new())).boxed();
+                    }
+
+                    btree_map(
+                        string(self.config.max_size),
+                        self.value(sub_schema, depth),
+                        0..=size,
+                    )
+                    .prop_map(|entries| Value::Object(entries.into_iter().collect()))
+                    .boxed()
+                }
+                Form::Properties(required, optional, _) => {
+                    let required: Vec<_> = required
+                        .iter()
+                        .map(|(name, sub_schema)| {
+                            let name = name.clone();
+                            self.value(sub_schema, depth)
+                                .prop_map(move |value| (name.clone(), Some(value)))
+                                .boxed()
+                        })
+                        .collect();
+
+                    let optional: Vec<_> = optional
+                        .iter()
+                        .filter(|(_, sub_schema)| {
+                            !minimal && height(sub_schema, self.heights).is_some()
+                        })
+                        .map(|(name, sub_schema)| {
+                            let name = name.clone();
+                            proptest::option::of(self.value(sub_schema, depth))
+                                .prop_map(move |value| (name.clone(), value))
+                                .boxed()
+                        })
+                        .collect();
+
+                    (required, optional)
+                        .prop_map(|(required, optional)| {
+                            Value::Object(
+                                required
+                                    .into_iter()
+                                    .chain(optional)
+                                    .filter_map(|(name, value)| value.map(|value| (name, value)))
+                                    .collect(),
+                            )
+                        })
+                        .boxed()
+                }
+                Form::Discriminator(tag, mapping) => {
+                    let variants = variants(mapping, self.heights, minimal).into_iter().map(
+                        |(value, sub_schema)| {
+                            let tag = tag.clone();
+                            let value = value.clone();
+                            self.value(sub_schema, depth)
+                                .prop_map(move |mut out| {
+                                    if let Value::Object(ref mut out) = out {
+                                        out.insert(tag.clone(), Value::String(value.clone()));
+                                    }
+                                    out
+                                })
+                                .boxed()
+                        },
+                    );
+
+                    Union::new(variants).boxed()
+                }
+            }
+        }
+
+        fn typ(&self, typ: &Type) -> BoxedStrategy<Value> {
+            match typ {
+                Type::Boolean => any::<bool>().prop_map(Value::from).boxed(),
+                Type::Number | Type::Float64 => {
+                    (prop::num::f64::NORMAL | prop::num::f64::SUBNORMAL | prop::num::f64::ZERO)
+                        .prop_map(Value::from)
+                        .boxed()
+                }
+                Type::Float32 => {
+                    (prop::num::f32::NORMAL | prop::num::f32::SUBNORMAL | prop::num::f32::ZERO)
+                        .prop_map(|n| Value::from(f64::from(n)))
+                        .boxed()
+                }
+                Type::Int8 => any::<i8>().prop_map(Value::from).boxed(),
+                Type::Uint8 => any::<u8>().prop_map(Value::from).boxed(),
+                Type::Int16 => any::<i16>().prop_map(Value::from).boxed(),
+                Type::Uint16 => any::<u16>().prop_map(Value::from).boxed(),
+                Type::Int32 => any::<i32>().prop_map(Value::from).boxed(),
+                Type::Uint32 => any::<u32>().prop_map(Value::from).boxed(),
+                Type::Int64 => any::<i64>().prop_map(Value::from).boxed(),
+                Type::Uint64 => any::<u64>().prop_map(Value::from).boxed(),
+                Type::String => string(self.config.max_size).prop_map(Value::from).boxed(),
+                Type::Timestamp => (
+                    MIN_TIMESTAMP..=MAX_TIMESTAMP,
+                    0..1000u32,
+                    -MAX_OFFSET..=MAX_OFFSET,
+                )
+                    .prop_map(|(seconds, millis, offset)| timestamp(seconds, millis, offset))
+                    .boxed(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Validator;
+    use serde_json::json;
+
+    fn schema(data: Value) -> Schema {
+        Schema::from_serde(serde_json::from_value(data).unwrap()).unwrap()
+    }
+
+    fn tree() -> Schema {
+        schema(json!({
+            "definitions": {
+                "node": {
+                    "discriminator": {
+                        "tag": "type",
+                        "mapping": {
+                            "leaf": {
+                                "properties": {
+                                    "value": { "type": "int8" },
+                                    "status": { "enum": ["ON", "OFF"] },
+                                },
+                            },
+                            "branch": {
+                                "properties": {
+                                    "left": { "ref": "node" },
+                                    "right": { "ref": "node" },
+                                },
+                                "optionalProperties": {
+                                    "at": { "type": "timestamp" },
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+            "properties": {
+                "root": { "ref": "node" },
+                "counts": { "values": { "type": "uint32" } },
+                "weights": { "elements": { "type": "float32" } },
+            },
+            "optionalProperties": {
+                "extra": {},
+                "ids": { "elements": { "type": "uint64" } },
+            },
+        }))
+    }
+
+    #[test]
+    fn valid() {
+        let schema = tree();
+        let generator = Generator::new();
+        let validator = Validator::new();
+
+        for seed in 0..500 {
+            let instance = generator.generate(&schema, seed).unwrap();
+            let errors = validator.validate(&schema, &instance).unwrap();
+            assert!(errors.is_empty(), "seed {}: {}", seed, instance);
+        }
+    }
+
+    #[test]
+    fn deterministic() {
+        let schema = tree();
+        let generator = Generator::new();
+
+        assert_eq!(
+            generator.generate(&schema, 7),
+            generator.generate(&schema, 7)
+        );
+        assert_ne!(
+            generator.generate(&schema, 7),
+            generator.generate(&schema, 8)
+        );
+    }
+
+    #[test]
+    fn bounded_recursion() {
+        let schema = schema(json!({
+            "definitions": {
+                "list": {
+                    "optionalProperties": {
+                        "next": { "ref": "list" },
+                    },
+                },
+            },
+            "ref": "list",
+        }));
+
+        let mut config = Config::new();
+        config.max_depth(2);
+        let generator = Generator::new_with_config(config);
+
+        for seed in 0..100 {
+            let instance = generator.generate(&schema, seed).unwrap();
+            assert!(instance.pointer("/next/next").is_none(), "{}", instance);
+        }
+    }
+
+    #[test]
+    fn infinite() {
+        let finite = schema(json!({
+            "definitions": {
+                "loop": {
+                    "properties": {
+                        "next": { "ref": "loop" },
+                    },
+                },
+            },
+            "properties": {
+                "loops": { "elements": { "ref": "loop" } },
+            },
+        }));
+
+        assert_eq!(
+            Generator::new().generate(&finite, 0),
+            Some(json!({ "loops": [] }))
+        );
+
+        let schema = schema(json!({
+            "definitions": {
+                "loop": {
+                    "properties": {
+                        "next": { "ref": "loop" },
+                    },
+                },
+            },
+            "ref": "loop",
+        }));
+
+        assert_eq!(Generator::new().generate(&schema, 0), None);
+    }
+
+    #[cfg(feature = "proptest")]
+    mod strategy {
+        use super::*;
+        use proptest::strategy::{Strategy, ValueTree};
+        use proptest::test_runner::TestRunner;
+
+        #[test]
+        fn valid() {
+            let schema = tree();
+            let strategy = Generator::new().strategy(&schema).unwrap();
+            let validator = Validator::new();
+            let mut runner = TestRunner::deterministic();
+
+            for _ in 0..200 {
+                let mut tree = strategy.new_tree(&mut runner).unwrap();
+                loop {
+                    let instance = tree.current();
+                    let errors = validator.validate(&schema, &instance).unwrap();
+                    assert!(errors.is_empty(), "{}", instance);
+
+                    if !tree.simplify() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
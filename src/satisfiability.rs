@@ -0,0 +1,322 @@
+//! Find schemas that no finite instance can satisfy.
+//!
+//! `Schema::from_serde` accepts some schemas which reject every instance, or
+//! which only accept infinitely deep instances. For example, this definition
+//! requires a property which is itself a `node`, so any `node` would have to
+//! contain infinitely many others:
+//!
+//! ```json
+//! { "definitions": { "node": { "properties": { "next": { "ref": "node" }}}}}
+//! ```
+//!
+//! [`check`](fn.check.html) reports every definition and subschema with
+//! such a problem, along with what causes it.
+
+use crate::height::{height, heights};
+use crate::pointer::JsonPointer;
+use crate::schema::{Form, Schema};
+use crate::visit::{Context, Visitor, Walker};
+use std::collections::BTreeMap;
+
+/// A subschema which no finite instance satisfies.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Issue {
+    schema_path: JsonPointer<String, Vec<String>>,
+    problem: Problem,
+}
+
+impl Issue {
+    /// A pointer to the subschema with the problem.
+    pub fn schema_path(&self) -> &JsonPointer<String, Vec<String>> {
+        &self.schema_path
+    }
+
+    /// What is wrong with the subschema.
+    pub fn problem(&self) -> &Problem {
+        &self.problem
+    }
+}
+
+/// Why a subschema has no finite instances.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    /// No instance, finite or not, satisfies the subschema.
+    ///
+    /// `cause` points to the discriminator with no variants that the
+    /// subschema ultimately requires.
+    Unsatisfiable {
+        cause: JsonPointer<String, Vec<String>>,
+    },
+
+    /// Only infinitely deep instances satisfy the subschema.
+    ///
+    /// `cycle` lists the definitions responsible, in order. Each requires
+    /// an instance of the next, and the last requires an instance of the
+    /// first.
+    Infinite { cycle: Vec<String> },
+}
+
+/// Find every subschema of a root schema, including in its definitions, that
+/// has no finite instances.
+///
+/// Issues are ordered by where they appear: first the root schema, then each
+/// definition by name. Within each of those, parents come before their
+/// subschemas.
+pub fn check(schema: &Schema) -> Vec<Issue> {
+    let no_defs = BTreeMap::new();
+    let defs = schema.definitions().as_ref().unwrap_or(&no_defs);
+
+    let mut state = State {
+        defs,
+        heights: heights(defs),
+        causes: causes(defs),
+        issues: Vec::new(),
+    };
+
+    Walker::new().walk(schema, &mut state);
+    state.issues
+}
+
+/// Find, for each unsatisfiable definition, the path to the empty
+/// discriminator that makes it so.
+///
+/// Definitions are only marked unsatisfiable once something they require is
+/// known to be, so causes never refer back to the definition being marked.
+fn causes(defs: &BTreeMap<String, Schema>) -> BTreeMap<&str, Vec<String>> {
+    let mut causes = BTreeMap::new();
+    loop {
+        let mut changed = false;
+        for (name, schema) in defs {
+            if causes.contains_key(name.as_str()) {
+                continue;
+            }
+
+            let mut path = vec!["definitions".to_owned(), name.clone()];
+            if let Some(cause) = cause(schema, &mut path, &causes) {
+                causes.insert(name.as_str(), cause);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return causes;
+        }
+    }
+}
+
+/// If `schema`, at `path`, is unsatisfiable given the definitions already
+/// known to be, find why.
+fn cause(
+    schema: &Schema,
+    path: &mut Vec<String>,
+    causes: &BTreeMap<&str, Vec<String>>,
+) -> Option<Vec<String>> {
+    match schema.form() {
+        Form::Ref(def) => causes.get(def.as_str()).cloned(),
+        Form::Properties(required, _, _) => required.iter().find_map(|(name, sub)| {
+            path.extend(vec!["properties".to_owned(), name.clone()]);
+            let cause = cause(sub, path, causes);
+            path.truncate(path.len() - 2);
+            cause
+        }),
+        Form::Discriminator(_, mapping) => {
+            if mapping.is_empty() {
+                return Some(path.clone());
+            }
+
+            let mut first = None;
+            for (value, sub) in mapping {
+                path.extend(vec![
+                    "discriminator".to_owned(),
+                    "mapping".to_owned(),
+                    value.clone(),
+                ]);
+                let cause = cause(sub, path, causes);
+                path.truncate(path.len() - 3);
+
+                match cause {
+                    Some(cause) => {
+                        first.get_or_insert(cause);
+                    }
+                    None => return None,
+                }
+            }
+
+            first
+        }
+        _ => None,
+    }
+}
+
+struct State<'a> {
+    defs: &'a BTreeMap<String, Schema>,
+    heights: BTreeMap<&'a str, usize>,
+    causes: BTreeMap<&'a str, Vec<String>>,
+    issues: Vec<Issue>,
+}
+
+impl<'a> Visitor<'a> for State<'a> {
+    fn visit_schema(&mut self, cx: &mut Context<'a>, schema: &'a Schema) {
+        if height(schema, &self.heights).is_none() {
+            let path: Vec<_> = cx.path().iter().map(|&token| token.to_owned()).collect();
+            let problem = match cause(schema, &mut path.clone(), &self.causes) {
+                Some(cause) => Problem::Unsatisfiable {
+                    cause: JsonPointer::new(cause),
+                },
+                None => Problem::Infinite {
+                    cycle: self.cycle(schema),
+                },
+            };
+
+            self.issues.push(Issue {
+                schema_path: JsonPointer::new(path),
+                problem,
+            });
+        }
+
+        cx.visit_children(self, schema);
+    }
+}
+
+impl<'a> State<'a> {
+    /// Find the cycle of definitions that keeps a satisfiable schema from
+    /// having finite instances.
+    fn cycle(&self, schema: &'a Schema) -> Vec<String> {
+        let mut chain: Vec<&str> = Vec::new();
+        let mut next = self.required_ref(schema);
+
+        while let Some(def) = next {
+            if let Some(start) = chain.iter().position(|&seen| seen == def) {
+                // Start from the first definition by name, so that every
+                // issue caused by the same cycle reports it the same way.
+                let mut cycle: Vec<_> = chain[start..].iter().map(|&def| def.to_owned()).collect();
+                let first = (0..cycle.len()).min_by_key(|&i| &cycle[i]).unwrap();
+                cycle.rotate_left(first);
+                return cycle;
+            }
+
+            chain.push(def);
+            next = self.required_ref(&self.defs[def]);
+        }
+
+        unreachable!("satisfiable schema without finite instances has no cycle")
+    }
+
+    /// Find a definition without finite instances that instances of a
+    /// satisfiable schema without finite instances require.
+    fn required_ref(&self, schema: &'a Schema) -> Option<&'a str> {
+        match schema.form() {
+            Form::Ref(def) => Some(def),
+            Form::Properties(required, _, _) => required
+                .iter()
+                .filter(|(_, sub)| height(sub, &self.heights).is_none())
+                .find_map(|(_, sub)| self.required_ref(sub)),
+            Form::Discriminator(_, mapping) => mapping
+                .iter()
+                .filter(|(_, sub)| cause(sub, &mut Vec::new(), &self.causes).is_none())
+                .find_map(|(_, sub)| self.required_ref(sub)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn issues(data: serde_json::Value) -> Vec<(String, Problem)> {
+        let schema = Schema::from_serde(serde_json::from_value(data).unwrap()).unwrap();
+        check(&schema)
+            .into_iter()
+            .map(|issue| (issue.schema_path().to_string(), issue.problem().clone()))
+            .collect()
+    }
+
+    fn pointer(path: &[&str]) -> JsonPointer<String, Vec<String>> {
+        JsonPointer::new(path.iter().map(|&token| token.to_owned()).collect())
+    }
+
+    #[test]
+    fn satisfiable() {
+        assert_eq!(
+            issues(json!({
+                "definitions": {
+                    "node": {
+                        "properties": {
+                            "children": { "elements": { "ref": "node" } },
+                        },
+                        "optionalProperties": {
+                            "parent": { "ref": "node" },
+                        },
+                    },
+                },
+                "ref": "node",
+            })),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn infinite() {
+        let cycle = || Problem::Infinite {
+            cycle: vec!["a".to_owned(), "b".to_owned()],
+        };
+
+        assert_eq!(
+            issues(json!({
+                "definitions": {
+                    "a": { "properties": { "b": { "ref": "b" } } },
+                    "b": {
+                        "discriminator": {
+                            "tag": "type",
+                            "mapping": {
+                                "x": { "properties": { "a": { "ref": "a" } } },
+                            },
+                        },
+                    },
+                },
+                "optionalProperties": {
+                    "a": { "ref": "a" },
+                },
+            })),
+            vec![
+                ("/optionalProperties/a".to_owned(), cycle()),
+                ("/definitions/a".to_owned(), cycle()),
+                ("/definitions/a/properties/b".to_owned(), cycle()),
+                ("/definitions/b".to_owned(), cycle()),
+                ("/definitions/b/discriminator/mapping/x".to_owned(), cycle()),
+                (
+                    "/definitions/b/discriminator/mapping/x/properties/a".to_owned(),
+                    cycle()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsatisfiable() {
+        let cause = || Problem::Unsatisfiable {
+            cause: pointer(&["definitions", "never"]),
+        };
+
+        assert_eq!(
+            issues(json!({
+                "definitions": {
+                    "never": { "discriminator": { "tag": "type", "mapping": {} } },
+                    "wrapper": { "properties": { "never": { "ref": "never" } } },
+                },
+                "properties": {
+                    "wrapper": { "ref": "wrapper" },
+                },
+            })),
+            vec![
+                ("".to_owned(), cause()),
+                ("/properties/wrapper".to_owned(), cause()),
+                ("/definitions/never".to_owned(), cause()),
+                ("/definitions/wrapper".to_owned(), cause()),
+                ("/definitions/wrapper/properties/never".to_owned(), cause()),
+            ]
+        );
+    }
+}
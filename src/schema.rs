@@ -5,10 +5,16 @@
 //! representation of JSL schemas.
 
 use crate::errors::JslError;
-use failure::{bail, Error};
+use crate::pointer::JsonPointer;
+use crate::validator::pointer_tokens;
+use crate::visit::{Context, Visitor, Walker};
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::{HashMap, HashSet};
 
 /// An abstract representation of a JSL schema.
 ///
@@ -17,9 +23,9 @@ use std::collections::{HashMap, HashSet};
 /// schemas, instead use [`Serde`](struct.Serde.html).
 #[derive(Clone, PartialEq, Debug)]
 pub struct Schema {
-    defs: Option<HashMap<String, Schema>>,
+    defs: Option<BTreeMap<String, Schema>>,
     form: Box<Form>,
-    extra: HashMap<String, Value>,
+    extra: BTreeMap<String, Value>,
 }
 
 impl Schema {
@@ -31,16 +37,30 @@ impl Schema {
     /// they can unwrap. Likewise, some tooling will assume that any schema
     /// which has non-`None` definitions are root schemas.
     pub fn from_parts(
-        defs: Option<HashMap<String, Schema>>,
+        defs: Option<BTreeMap<String, Schema>>,
         form: Box<Form>,
-        extra: HashMap<String, Value>,
+        extra: BTreeMap<String, Value>,
     ) -> Schema {
         Schema { defs, form, extra }
     }
 
+    /// Break this schema into its constituent parts.
+    ///
+    /// This is the inverse of [`from_parts`](#method.from_parts).
+    #[allow(clippy::type_complexity)]
+    pub fn into_parts(
+        self,
+    ) -> (
+        Option<BTreeMap<String, Schema>>,
+        Box<Form>,
+        BTreeMap<String, Value>,
+    ) {
+        (self.defs, self.form, self.extra)
+    }
+
     /// Construct a new, root schema from a `Serde`.
-    pub fn from_serde(mut serde_schema: Serde) -> Result<Self, Error> {
-        let mut defs = HashMap::new();
+    pub fn from_serde(mut serde_schema: Serde) -> Result<Self, JslError> {
+        let mut defs = BTreeMap::new();
         let serde_defs = serde_schema.defs;
         serde_schema.defs = None;
 
@@ -51,15 +71,12 @@ impl Schema {
         let mut schema = Self::_from_serde(serde_schema)?;
         schema.defs = Some(defs);
 
-        Self::check_refs(&schema.defs.as_ref().unwrap(), &schema)?;
-        for sub_schema in schema.defs.as_ref().unwrap().values() {
-            Self::check_refs(&schema.defs.as_ref().unwrap(), &sub_schema)?;
-        }
+        Self::check_refs(&schema)?;
 
         Ok(schema)
     }
 
-    fn _from_serde(serde_schema: Serde) -> Result<Self, Error> {
+    fn _from_serde(serde_schema: Serde) -> Result<Self, JslError> {
         let mut form = Form::Empty;
 
         if let Some(rxf) = serde_schema.rxf {
@@ -68,7 +85,7 @@ impl Schema {
 
         if let Some(typ) = serde_schema.typ {
             if form != Form::Empty {
-                bail!(JslError::InvalidForm);
+                return Err(JslError::InvalidForm);
             }
 
             form = Form::Type(match typ.as_ref() {
@@ -86,26 +103,26 @@ impl Schema {
                 "uint64" => Type::Uint64,
                 "string" => Type::String,
                 "timestamp" => Type::Timestamp,
-                _ => bail!(JslError::InvalidForm),
+                _ => return Err(JslError::InvalidForm),
             });
         }
 
         if let Some(enm) = serde_schema.enm {
             if form != Form::Empty {
-                bail!(JslError::InvalidForm);
+                return Err(JslError::InvalidForm);
             }
 
-            let mut values = HashSet::new();
+            let mut values = BTreeSet::new();
             for val in enm {
                 if values.contains(&val) {
-                    bail!(JslError::InvalidForm);
+                    return Err(JslError::InvalidForm);
                 } else {
                     values.insert(val);
                 }
             }
 
             if values.is_empty() {
-                bail!(JslError::InvalidForm);
+                return Err(JslError::InvalidForm);
             }
 
             form = Form::Enum(values);
@@ -113,7 +130,7 @@ impl Schema {
 
         if let Some(elements) = serde_schema.elems {
             if form != Form::Empty {
-                bail!(JslError::InvalidForm);
+                return Err(JslError::InvalidForm);
             }
 
             form = Form::Elements(Self::_from_serde(*elements)?);
@@ -121,20 +138,20 @@ impl Schema {
 
         if serde_schema.props.is_some() || serde_schema.opt_props.is_some() {
             if form != Form::Empty {
-                bail!(JslError::InvalidForm);
+                return Err(JslError::InvalidForm);
             }
 
             let has_required = serde_schema.props.is_some();
 
-            let mut required = HashMap::new();
+            let mut required = BTreeMap::new();
             for (name, sub_schema) in serde_schema.props.unwrap_or_default() {
                 required.insert(name, Self::_from_serde(sub_schema)?);
             }
 
-            let mut optional = HashMap::new();
+            let mut optional = BTreeMap::new();
             for (name, sub_schema) in serde_schema.opt_props.unwrap_or_default() {
                 if required.contains_key(&name) {
-                    bail!(JslError::AmbiguousProperty { property: name });
+                    return Err(JslError::AmbiguousProperty { property: name });
                 }
 
                 optional.insert(name, Self::_from_serde(sub_schema)?);
@@ -145,7 +162,7 @@ impl Schema {
 
         if let Some(values) = serde_schema.values {
             if form != Form::Empty {
-                bail!(JslError::InvalidForm);
+                return Err(JslError::InvalidForm);
             }
 
             form = Form::Values(Self::_from_serde(*values)?);
@@ -153,10 +170,10 @@ impl Schema {
 
         if let Some(discriminator) = serde_schema.discriminator {
             if form != Form::Empty {
-                bail!(JslError::InvalidForm);
+                return Err(JslError::InvalidForm);
             }
 
-            let mut mapping = HashMap::new();
+            let mut mapping = BTreeMap::new();
             for (name, sub_schema) in discriminator.mapping {
                 let sub_schema = Self::_from_serde(sub_schema)?;
                 match sub_schema.form.as_ref() {
@@ -164,12 +181,12 @@ impl Schema {
                         if required.contains_key(&discriminator.tag)
                             || optional.contains_key(&discriminator.tag)
                         {
-                            bail!(JslError::AmbiguousProperty {
+                            return Err(JslError::AmbiguousProperty {
                                 property: discriminator.tag,
                             });
                         }
                     }
-                    _ => bail!(JslError::InvalidForm),
+                    _ => return Err(JslError::InvalidForm),
                 };
 
                 mapping.insert(name, sub_schema);
@@ -185,39 +202,39 @@ impl Schema {
         })
     }
 
-    fn check_refs(defs: &HashMap<String, Schema>, schema: &Schema) -> Result<(), Error> {
-        match schema.form() {
-            Form::Ref(ref def) => {
-                if !defs.contains_key(def) {
-                    bail!(JslError::NoSuchDefinition {
-                        definition: def.clone()
-                    })
-                }
-            }
-            Form::Elements(ref schema) => {
-                Self::check_refs(defs, schema)?;
-            }
-            Form::Properties(ref required, ref optional, _) => {
-                for schema in required.values() {
-                    Self::check_refs(defs, schema)?;
-                }
+    fn check_refs(schema: &Schema) -> Result<(), JslError> {
+        if let Some(def) = schema.missing_ref() {
+            return Err(JslError::NoSuchDefinition {
+                definition: def.to_owned(),
+            });
+        }
 
-                for schema in optional.values() {
-                    Self::check_refs(defs, schema)?;
-                }
-            }
-            Form::Values(ref schema) => {
-                Self::check_refs(defs, schema)?;
-            }
-            Form::Discriminator(_, ref mapping) => {
-                for schema in mapping.values() {
-                    Self::check_refs(defs, schema)?;
+        Ok(())
+    }
+
+    /// Find the first `ref` in a root schema, or its definitions, to a
+    /// definition that doesn't exist.
+    pub(crate) fn missing_ref(&self) -> Option<&str> {
+        struct Refs<'a> {
+            missing: Option<&'a str>,
+        }
+
+        impl<'a> Visitor<'a> for Refs<'a> {
+            fn visit_schema(&mut self, cx: &mut Context<'a>, schema: &'a Schema) {
+                if let Form::Ref(ref def) = schema.form() {
+                    let defined = cx.definitions().is_some_and(|defs| defs.contains_key(def));
+                    if !defined && self.missing.is_none() {
+                        self.missing = Some(def);
+                    }
                 }
+
+                cx.visit_children(self, schema);
             }
-            _ => {}
-        };
+        }
 
-        Ok(())
+        let mut refs = Refs { missing: None };
+        Walker::new().walk(self, &mut refs);
+        refs.missing
     }
 
     /// Convert this schema into a `Serde`.
@@ -306,6 +323,11 @@ impl Schema {
             }
         }
 
+        out.defs = self.defs.map(|defs| {
+            defs.into_iter()
+                .map(|(k, v)| (k, v.into_serde()))
+                .collect()
+        });
         out.extra = self.extra;
         out
     }
@@ -321,7 +343,7 @@ impl Schema {
     /// Get the definitions associated with this schema.
     ///
     /// If this schema is non-root, this returns None.
-    pub fn definitions(&self) -> &Option<HashMap<String, Schema>> {
+    pub fn definitions(&self) -> &Option<BTreeMap<String, Schema>> {
         &self.defs
     }
 
@@ -335,9 +357,150 @@ impl Schema {
     /// Essentially, this function returns a JSON object of properties that
     /// aren't JSL keywords, but which were included in the schema's JSON. You
     /// might use these nonstandard fields to implement custom behavior.
-    pub fn extra(&self) -> &HashMap<String, Value> {
+    pub fn extra(&self) -> &BTreeMap<String, Value> {
         &self.extra
     }
+
+    /// Get the subschema at a schema path, such as the one of a
+    /// [`ValidationError`](../validator/struct.ValidationError.html#method.schema_path).
+    ///
+    /// The path is relative to this schema, and `ref`s along it are not
+    /// followed. A path which ends at a keyword rather than at a subschema,
+    /// like `/properties/age/type` or `/discriminator/tag`, gets the schema
+    /// that keyword belongs to. Returns `None` if the path does not exist in
+    /// this schema.
+    pub fn get<S: AsRef<str>, C: AsRef<[S]>>(
+        &self,
+        pointer: &JsonPointer<S, C>,
+    ) -> Option<&Schema> {
+        self.get_tokens(&pointer_tokens(pointer))
+    }
+
+    fn get_tokens(&self, tokens: &[String]) -> Option<&Schema> {
+        let (schema, rest) = match (self.form(), tokens) {
+            (_, []) => return Some(self),
+            (_, [keyword, def, rest @ ..]) if keyword == "definitions" => {
+                (self.defs.as_ref()?.get(def)?, rest)
+            }
+            (Form::Ref(_), [keyword]) if keyword == "ref" => return Some(self),
+            (Form::Type(_), [keyword]) if keyword == "type" => return Some(self),
+            (Form::Enum(_), [keyword]) if keyword == "enum" => return Some(self),
+            (Form::Elements(sub_schema), [keyword, rest @ ..]) if keyword == "elements" => {
+                (sub_schema, rest)
+            }
+            (Form::Properties(..), [keyword])
+                if keyword == "properties" || keyword == "optionalProperties" =>
+            {
+                return Some(self)
+            }
+            (Form::Properties(required, _, _), [keyword, property, rest @ ..])
+                if keyword == "properties" =>
+            {
+                (required.get(property)?, rest)
+            }
+            (Form::Properties(_, optional, _), [keyword, property, rest @ ..])
+                if keyword == "optionalProperties" =>
+            {
+                (optional.get(property)?, rest)
+            }
+            (Form::Values(sub_schema), [keyword, rest @ ..]) if keyword == "values" => {
+                (sub_schema, rest)
+            }
+            (Form::Discriminator(..), [keyword]) if keyword == "discriminator" => {
+                return Some(self)
+            }
+            (Form::Discriminator(..), [keyword, field])
+                if keyword == "discriminator" && (field == "tag" || field == "mapping") =>
+            {
+                return Some(self)
+            }
+            (Form::Discriminator(_, mapping), [keyword, field, tag, rest @ ..])
+                if keyword == "discriminator" && field == "mapping" =>
+            {
+                (mapping.get(tag)?, rest)
+            }
+            _ => return None,
+        };
+
+        schema.get_tokens(rest)
+    }
+
+    /// Get the subschema that a part of an instance is validated against.
+    ///
+    /// `pointer` is an instance path into `instance`, such as the one of a
+    /// [`ValidationError`](../validator/struct.ValidationError.html#method.instance_path).
+    /// This must be a root schema: `ref`s are followed into its definitions,
+    /// and discriminators are resolved using the tags in `instance`, so the
+    /// result is never a `ref`, and is only a discriminator if the instance's
+    /// tag is missing or unknown.
+    ///
+    /// Returns `None` if `pointer` does not exist in `instance`, or if no
+    /// subschema describes that part of it, as with additional properties and
+    /// discriminator tags. `ref`s in the result still refer to this schema's
+    /// definitions, so validate against the result with care.
+    pub fn schema_for_instance_path<S: AsRef<str>, C: AsRef<[S]>>(
+        &self,
+        instance: &Value,
+        pointer: &JsonPointer<S, C>,
+    ) -> Option<&Schema> {
+        let mut schema = self.effective(self, instance)?;
+        let mut instance = instance;
+
+        for token in pointer_tokens(pointer) {
+            let (sub_schema, sub_instance) = match (schema.form(), instance) {
+                (Form::Elements(sub_schema), Value::Array(elements)) => {
+                    (sub_schema, elements.get(array_index(&token)?)?)
+                }
+                (Form::Properties(required, optional, _), Value::Object(members)) => (
+                    required.get(&token).or_else(|| optional.get(&token))?,
+                    members.get(&token)?,
+                ),
+                (Form::Values(sub_schema), Value::Object(members)) => {
+                    (sub_schema, members.get(&token)?)
+                }
+                _ => return None,
+            };
+
+            schema = self.effective(sub_schema, sub_instance)?;
+            instance = sub_instance;
+        }
+
+        Some(schema)
+    }
+
+    /// Follow `ref`s and discriminators from a subschema of this one.
+    fn effective<'a>(&'a self, mut schema: &'a Schema, instance: &Value) -> Option<&'a Schema> {
+        // Following more refs than there are definitions means they loop.
+        for _ in 0..=self.defs.as_ref().map_or(0, BTreeMap::len) {
+            match schema.form() {
+                Form::Ref(def) => schema = self.defs.as_ref()?.get(def)?,
+                Form::Discriminator(tag, mapping) => {
+                    return Some(
+                        instance
+                            .get(tag)
+                            .and_then(Value::as_str)
+                            .and_then(|tag| mapping.get(tag))
+                            .unwrap_or(schema),
+                    )
+                }
+                _ => return Some(schema),
+            }
+        }
+
+        None
+    }
+}
+
+/// Parse an array index from an instance path. Leading zeros are not allowed.
+fn array_index(token: &str) -> Option<usize> {
+    if token.is_empty()
+        || !token.bytes().all(|b| b.is_ascii_digit())
+        || (token.len() > 1 && token.starts_with('0'))
+    {
+        return None;
+    }
+
+    token.parse().ok()
 }
 
 /// The various forms which a schema may take on, and their respective data.
@@ -364,7 +527,7 @@ pub enum Form {
     ///
     /// This schema asserts that the data is a string, and that it is one of a
     /// set of values.
-    Enum(HashSet<String>),
+    Enum(BTreeSet<String>),
 
     /// The elements form.
     ///
@@ -384,7 +547,7 @@ pub enum Form {
     /// This allows implementations to distinguish the case of an empty
     /// `properties` field from an omitted one. This is necessary for tooling
     /// which wants to link to a particular part of a schema in JSON form.
-    Properties(HashMap<String, Schema>, HashMap<String, Schema>, bool),
+    Properties(BTreeMap<String, Schema>, BTreeMap<String, Schema>, bool),
 
     /// The values form.
     ///
@@ -401,7 +564,7 @@ pub enum Form {
     ///
     /// The first parameter is the name of the tag property. The second
     /// parameter is the mapping from tag values to their corresponding schemas.
-    Discriminator(String, HashMap<String, Schema>),
+    Discriminator(String, BTreeMap<String, Schema>),
 }
 
 /// The values that the "type" keyword may check for.
@@ -470,7 +633,7 @@ pub enum Type {
 pub struct Serde {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "definitions")]
-    pub defs: Option<HashMap<String, Serde>>,
+    pub defs: Option<BTreeMap<String, Serde>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "ref")]
@@ -490,11 +653,11 @@ pub struct Serde {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "properties")]
-    pub props: Option<HashMap<String, Serde>>,
+    pub props: Option<BTreeMap<String, Serde>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "optionalProperties")]
-    pub opt_props: Option<HashMap<String, Serde>>,
+    pub opt_props: Option<BTreeMap<String, Serde>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub values: Option<Box<Serde>>,
@@ -502,9 +665,9 @@ pub struct Serde {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub discriminator: Option<SerdeDiscriminator>,
 
-    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
     #[serde(flatten)]
-    pub extra: HashMap<String, Value>,
+    pub extra: BTreeMap<String, Value>,
 }
 
 /// A serialization/deserialization-friendly representation of a JSL
@@ -516,7 +679,7 @@ pub struct Serde {
 pub struct SerdeDiscriminator {
     #[serde(rename = "tag")]
     pub tag: String,
-    pub mapping: HashMap<String, Serde>,
+    pub mapping: BTreeMap<String, Serde>,
 }
 
 #[cfg(test)]
@@ -598,6 +761,19 @@ mod tests {
         assert_eq!(round_trip, data);
     }
 
+    #[test]
+    fn into_serde_root() {
+        let data = json!({
+            "definitions": {
+                "a": { "type": "boolean" },
+            },
+            "ref": "a",
+        });
+
+        let schema = Schema::from_serde(serde_json::from_value(data.clone()).unwrap()).unwrap();
+        assert_eq!(serde_json::to_value(schema.into_serde()).unwrap(), data);
+    }
+
     #[test]
     fn from_serde_root() {
         assert_eq!(
@@ -617,7 +793,7 @@ mod tests {
                         Schema {
                             defs: None,
                             form: Box::new(Form::Type(Type::Boolean)),
-                            extra: HashMap::new(),
+                            extra: BTreeMap::new(),
                         },
                     )]
                     .iter()
@@ -625,7 +801,7 @@ mod tests {
                     .collect()
                 ),
                 form: Box::new(Form::Empty),
-                extra: HashMap::new(),
+                extra: BTreeMap::new(),
             }
         );
     }
@@ -635,9 +811,9 @@ mod tests {
         assert_eq!(
             Schema::from_serde(serde_json::from_value(json!({})).unwrap()).unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(BTreeMap::new()),
                 form: Box::new(Form::Empty),
-                extra: HashMap::new(),
+                extra: BTreeMap::new(),
             }
         );
     }
@@ -647,7 +823,7 @@ mod tests {
         assert_eq!(
             Schema::from_serde(serde_json::from_value(json!({ "foo": "bar" })).unwrap()).unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(BTreeMap::new()),
                 form: Box::new(Form::Empty),
                 extra: serde_json::from_value(json!({ "foo": "bar" })).unwrap(),
             }
@@ -674,7 +850,7 @@ mod tests {
                         Schema {
                             defs: None,
                             form: Box::new(Form::Type(Type::Boolean)),
-                            extra: HashMap::new(),
+                            extra: BTreeMap::new(),
                         },
                     )]
                     .iter()
@@ -682,7 +858,7 @@ mod tests {
                     .collect()
                 ),
                 form: Box::new(Form::Ref("a".to_owned())),
-                extra: HashMap::new(),
+                extra: BTreeMap::new(),
             }
         );
 
@@ -709,9 +885,9 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(BTreeMap::new()),
                 form: Box::new(Form::Type(Type::Boolean)),
-                extra: HashMap::new(),
+                extra: BTreeMap::new(),
             },
         );
 
@@ -724,9 +900,9 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(BTreeMap::new()),
                 form: Box::new(Form::Type(Type::Number)),
-                extra: HashMap::new(),
+                extra: BTreeMap::new(),
             },
         );
 
@@ -739,9 +915,9 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(BTreeMap::new()),
                 form: Box::new(Form::Type(Type::String)),
-                extra: HashMap::new(),
+                extra: BTreeMap::new(),
             },
         );
 
@@ -754,9 +930,9 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(BTreeMap::new()),
                 form: Box::new(Form::Type(Type::Timestamp)),
-                extra: HashMap::new(),
+                extra: BTreeMap::new(),
             },
         );
 
@@ -780,14 +956,14 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(BTreeMap::new()),
                 form: Box::new(Form::Enum(
                     vec!["FOO".to_owned(), "BAR".to_owned()]
                         .iter()
                         .cloned()
                         .collect()
                 )),
-                extra: HashMap::new(),
+                extra: BTreeMap::new(),
             },
         );
 
@@ -821,13 +997,13 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(BTreeMap::new()),
                 form: Box::new(Form::Elements(Schema {
                     defs: None,
                     form: Box::new(Form::Type(Type::Boolean)),
-                    extra: HashMap::new(),
+                    extra: BTreeMap::new(),
                 })),
-                extra: HashMap::new(),
+                extra: BTreeMap::new(),
             }
         );
     }
@@ -848,14 +1024,14 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(BTreeMap::new()),
                 form: Box::new(Form::Properties(
                     [(
                         "a".to_owned(),
                         Schema {
                             defs: None,
                             form: Box::new(Form::Type(Type::Boolean)),
-                            extra: HashMap::new(),
+                            extra: BTreeMap::new(),
                         }
                     )]
                     .iter()
@@ -866,7 +1042,7 @@ mod tests {
                         Schema {
                             defs: None,
                             form: Box::new(Form::Type(Type::Boolean)),
-                            extra: HashMap::new(),
+                            extra: BTreeMap::new(),
                         }
                     )]
                     .iter()
@@ -874,7 +1050,7 @@ mod tests {
                     .collect(),
                     true,
                 )),
-                extra: HashMap::new(),
+                extra: BTreeMap::new(),
             }
         );
 
@@ -889,15 +1065,15 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(BTreeMap::new()),
                 form: Box::new(Form::Properties(
-                    HashMap::new(),
+                    BTreeMap::new(),
                     [(
                         "b".to_owned(),
                         Schema {
                             defs: None,
                             form: Box::new(Form::Type(Type::Boolean)),
-                            extra: HashMap::new(),
+                            extra: BTreeMap::new(),
                         }
                     )]
                     .iter()
@@ -905,7 +1081,7 @@ mod tests {
                     .collect(),
                     false,
                 )),
-                extra: HashMap::new(),
+                extra: BTreeMap::new(),
             }
         );
 
@@ -936,13 +1112,13 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(BTreeMap::new()),
                 form: Box::new(Form::Values(Schema {
                     defs: None,
                     form: Box::new(Form::Type(Type::Boolean)),
-                    extra: HashMap::new(),
+                    extra: BTreeMap::new(),
                 })),
-                extra: HashMap::new(),
+                extra: BTreeMap::new(),
             }
         );
     }
@@ -964,7 +1140,7 @@ mod tests {
             )
             .unwrap(),
             Schema {
-                defs: Some(HashMap::new()),
+                defs: Some(BTreeMap::new()),
                 form: Box::new(Form::Discriminator(
                     "foo".to_owned(),
                     [
@@ -973,11 +1149,11 @@ mod tests {
                             Schema {
                                 defs: None,
                                 form: Box::new(Form::Properties(
-                                    HashMap::new(),
-                                    HashMap::new(),
+                                    BTreeMap::new(),
+                                    BTreeMap::new(),
                                     true
                                 )),
-                                extra: HashMap::new(),
+                                extra: BTreeMap::new(),
                             }
                         ),
                         (
@@ -985,11 +1161,11 @@ mod tests {
                             Schema {
                                 defs: None,
                                 form: Box::new(Form::Properties(
-                                    HashMap::new(),
-                                    HashMap::new(),
+                                    BTreeMap::new(),
+                                    BTreeMap::new(),
                                     true
                                 )),
-                                extra: HashMap::new(),
+                                extra: BTreeMap::new(),
                             }
                         )
                     ]
@@ -997,7 +1173,7 @@ mod tests {
                     .cloned()
                     .collect(),
                 )),
-                extra: HashMap::new(),
+                extra: BTreeMap::new(),
             }
         );
 
@@ -1031,4 +1207,165 @@ mod tests {
         )
         .is_err());
     }
+
+    #[test]
+    fn from_serde_errors() {
+        let error = |data| {
+            Schema::from_serde(serde_json::from_value(data).unwrap()).unwrap_err()
+        };
+
+        assert_eq!(
+            error(json!({ "type": "string", "enum": ["a"] })),
+            JslError::InvalidForm
+        );
+        assert_eq!(
+            error(json!({ "properties": { "a": {} }, "optionalProperties": { "a": {} } })),
+            JslError::AmbiguousProperty {
+                property: "a".to_owned()
+            }
+        );
+        assert_eq!(
+            error(json!({ "ref": "a" })),
+            JslError::NoSuchDefinition {
+                definition: "a".to_owned()
+            }
+        );
+    }
+
+    fn pointer(s: &str) -> JsonPointer<String, Vec<String>> {
+        JsonPointer::new(s.split('/').skip(1).map(str::to_owned).collect())
+    }
+
+    fn nested_schema() -> Schema {
+        Schema::from_serde(
+            serde_json::from_value(json!({
+                "definitions": {
+                    "node": {
+                        "properties": { "value": { "type": "string" } },
+                        "optionalProperties": { "children": { "elements": { "ref": "node" } } },
+                    },
+                    "loop": { "ref": "loop" },
+                },
+                "values": {
+                    "discriminator": {
+                        "tag": "kind",
+                        "mapping": {
+                            "tree": { "properties": { "root": { "ref": "node" } } },
+                            "list": { "properties": { "items": { "enum": ["a"] } } },
+                            "loop": { "properties": { "x": { "ref": "loop" } } },
+                        },
+                    },
+                },
+            }))
+            .unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn get() {
+        let schema = nested_schema();
+        let node = &schema.definitions().as_ref().unwrap()["node"];
+        let tree = match schema.get(&pointer("/values")).unwrap().form() {
+            Form::Discriminator(_, mapping) => &mapping["tree"],
+            _ => panic!(),
+        };
+
+        assert_eq!(schema.get(&pointer("")), Some(&schema));
+        assert_eq!(schema.get(&pointer("/definitions/node")), Some(node));
+        assert_eq!(
+            schema.get(&pointer("/values/discriminator/mapping/tree")),
+            Some(tree)
+        );
+        assert_eq!(
+            schema.get(&pointer("/values/discriminator/tag")),
+            schema.get(&pointer("/values"))
+        );
+        assert_eq!(
+            schema.get(&pointer("/definitions/node/properties/value/type")),
+            schema.get(&pointer("/definitions/node/properties/value"))
+        );
+        assert_eq!(
+            schema.get(&pointer(
+                "/definitions/node/optionalProperties/children/elements/ref"
+            )),
+            schema.get(&pointer(
+                "/definitions/node/optionalProperties/children/elements"
+            ))
+        );
+
+        assert_eq!(schema.get(&pointer("/definitions/nope")), None);
+        assert_eq!(schema.get(&pointer("/values/definitions/node")), None);
+        assert_eq!(schema.get(&pointer("/values/type")), None);
+        assert_eq!(
+            schema.get(&pointer("/definitions/node/properties/nope")),
+            None
+        );
+        assert_eq!(
+            schema.get(&pointer("/definitions/node/optionalProperties/value")),
+            None
+        );
+        assert_eq!(
+            schema.get(&pointer("/values/discriminator/mapping/nope")),
+            None
+        );
+
+        // Every error's schema path resolves.
+        let instance = json!({
+            "a": { "kind": "tree", "root": { "value": 1, "children": [{}, 1] } },
+            "b": { "kind": "list", "items": 1 },
+            "c": { "kind": "nope" },
+            "d": { "kind": 1 },
+            "e": {},
+            "f": 1,
+        });
+        let errors = crate::Validator::new()
+            .validate(&schema, &instance)
+            .unwrap();
+        assert_eq!(errors.len(), 8);
+        for error in errors {
+            assert!(schema.get(error.schema_path()).is_some(), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn schema_for_instance_path() {
+        let schema = nested_schema();
+        let get = |path| schema.get(&pointer(path));
+        let instance = json!({
+            "a": {
+                "kind": "tree",
+                "root": { "value": "x", "children": [{ "value": "y" }] },
+            },
+            "b": { "kind": "list", "items": "a" },
+            "c": { "kind": "nope" },
+            "d": { "kind": "loop", "x": 1 },
+        });
+        let lookup = |path| schema.schema_for_instance_path(&instance, &pointer(path));
+
+        assert_eq!(lookup(""), Some(&schema));
+        assert_eq!(lookup("/a"), get("/values/discriminator/mapping/tree"));
+        assert_eq!(lookup("/a/root"), get("/definitions/node"));
+        assert_eq!(
+            lookup("/a/root/value"),
+            get("/definitions/node/properties/value")
+        );
+        assert_eq!(lookup("/a/root/children/0"), get("/definitions/node"));
+        assert_eq!(
+            lookup("/a/root/children/0/value"),
+            get("/definitions/node/properties/value")
+        );
+        assert_eq!(
+            lookup("/b/items"),
+            get("/values/discriminator/mapping/list/properties/items")
+        );
+        assert_eq!(lookup("/c"), get("/values"));
+
+        assert_eq!(lookup("/a/kind"), None);
+        assert_eq!(lookup("/a/root/children/1"), None);
+        assert_eq!(lookup("/a/root/children/00"), None);
+        assert_eq!(lookup("/c/kind"), None);
+        assert_eq!(lookup("/d/x"), None);
+        assert_eq!(lookup("/e"), None);
+    }
 }
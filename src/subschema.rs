@@ -0,0 +1,669 @@
+//! Check whether one schema accepts a subset of what another accepts.
+//!
+//! This is useful for contract testing: if a producer's schema is a
+//! subschema of a consumer's schema, then everything the producer can send
+//! is something the consumer can read.
+//!
+//! The check works form by form, rather than by enumerating instances.
+//! Integer types are compared by their ranges, enums by their sets of
+//! values, and objects property by property. Recursive schemas are handled
+//! coinductively: while checking a pair of schemas reached through a `ref`,
+//! that same pair is assumed to be fine if it comes up again.
+//!
+//! When a schema is not a subschema of another, the check produces a
+//! [`Counterexample`](struct.Counterexample.html): a concrete instance that
+//! the first schema accepts and the second rejects.
+
+use crate::codegen::sorted_properties;
+use crate::random::{self, int_range};
+use crate::schema::{Form, Schema, Type};
+use chrono::DateTime;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// An instance that one schema accepts, but another rejects.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Counterexample {
+    instance: Value,
+}
+
+impl Counterexample {
+    /// The instance that demonstrates the difference between the schemas.
+    pub fn instance(&self) -> &Value {
+        &self.instance
+    }
+
+    /// Convert the counterexample into its instance.
+    pub fn into_instance(self) -> Value {
+        self.instance
+    }
+}
+
+/// Check whether every instance `a` accepts is also accepted by `b`.
+///
+/// `strict` determines whether both schemas are evaluated with strict
+/// instance semantics, as described in
+/// [`validator::Config`](../validator/struct.Config.html#method.strict_instance_semantics).
+///
+/// Returns `Ok(true)` if `a` is a subschema of `b`, and a counterexample if
+/// it is not. Returns `Ok(false)` if the check cannot tell: this happens when
+/// a discriminator in `a` is compared against a discriminator in `b` with a
+/// different tag.
+pub fn is_subschema(a: &Schema, b: &Schema, strict: bool) -> Result<bool, Counterexample> {
+    let no_defs = BTreeMap::new();
+    let mut checker = Checker {
+        a_defs: a.definitions().as_ref().unwrap_or(&no_defs),
+        b_defs: b.definitions().as_ref().unwrap_or(&no_defs),
+        strict,
+        generator: random::Generator::new(),
+        assumed: BTreeSet::new(),
+    };
+
+    match checker.check(a, b, None) {
+        Outcome::Subset => Ok(true),
+        Outcome::Unknown => Ok(false),
+        Outcome::Counterexample(instance) => Err(Counterexample { instance }),
+    }
+}
+
+enum Outcome {
+    Subset,
+    Unknown,
+    Counterexample(Value),
+}
+
+impl Outcome {
+    /// Combine the outcomes of several checks which must all succeed,
+    /// stopping at the first counterexample.
+    fn all<I: IntoIterator<Item = Outcome>>(outcomes: I) -> Outcome {
+        let mut result = Outcome::Subset;
+        for outcome in outcomes {
+            match outcome {
+                Outcome::Subset => {}
+                Outcome::Unknown => result = Outcome::Unknown,
+                Outcome::Counterexample(_) => return outcome,
+            }
+        }
+
+        result
+    }
+
+    fn map<F: FnOnce(Value) -> Value>(self, f: F) -> Outcome {
+        match self {
+            Outcome::Counterexample(instance) => Outcome::Counterexample(f(instance)),
+            _ => self,
+        }
+    }
+}
+
+struct Checker<'a> {
+    a_defs: &'a BTreeMap<String, Schema>,
+    b_defs: &'a BTreeMap<String, Schema>,
+    strict: bool,
+    generator: random::Generator,
+    assumed: BTreeSet<(*const Schema, *const Schema, Option<&'a str>)>,
+}
+
+impl<'a> Checker<'a> {
+    /// Check whether `x`, from `a`, is a subschema of `y`, from `b`.
+    ///
+    /// When `tag` is set, `x` and `y` are the variants of discriminators with
+    /// that tag, and the tag property has already been checked.
+    fn check(&mut self, x: &'a Schema, y: &'a Schema, tag: Option<&'a str>) -> Outcome {
+        let x = resolve(self.a_defs, x);
+        let y = resolve(self.b_defs, y);
+
+        if !self.assumed.insert((x as *const _, y as *const _, tag)) {
+            return Outcome::Subset;
+        }
+
+        // A schema with no finite instances accepts nothing at all.
+        let witness = match self.witness(x) {
+            Some(witness) => witness,
+            None => return Outcome::Subset,
+        };
+
+        match (x.form(), y.form()) {
+            (Form::Ref(_), _) | (_, Form::Ref(_)) => Outcome::Unknown,
+            (_, Form::Empty) => Outcome::Subset,
+            (Form::Empty, _) => Outcome::Counterexample(Value::Null),
+            (Form::Type(t), Form::Type(u)) => types(t, u, witness),
+            (Form::Type(Type::String), Form::Enum(values)) => {
+                Outcome::Counterexample(unused_name(values.iter(), "UNKNOWN").into())
+            }
+            (Form::Type(Type::Timestamp), Form::Enum(values)) => {
+                Outcome::Counterexample(unused_timestamp(values.iter()).into())
+            }
+            (Form::Enum(values), Form::Enum(others)) => {
+                let values: Vec<_> = values.difference(others).collect();
+                match values.first() {
+                    Some(value) => Outcome::Counterexample((*value).clone().into()),
+                    None => Outcome::Subset,
+                }
+            }
+            (Form::Enum(_), Form::Type(Type::String)) => Outcome::Subset,
+            (Form::Enum(values), Form::Type(Type::Timestamp)) => {
+                let values: Vec<_> = values
+                    .iter()
+                    .filter(|value| DateTime::parse_from_rfc3339(value).is_err())
+                    .collect();
+                match values.first() {
+                    Some(value) => Outcome::Counterexample((*value).clone().into()),
+                    None => Outcome::Subset,
+                }
+            }
+            (Form::Elements(x_elements), Form::Elements(y_elements)) => self
+                .check(x_elements, y_elements, None)
+                .map(|instance| Value::Array(vec![instance])),
+            (Form::Values(x_values), Form::Values(y_values)) => {
+                self.check(x_values, y_values, None).map(|instance| {
+                    let mut out = Map::new();
+                    out.insert("key".to_owned(), instance);
+                    Value::Object(out)
+                })
+            }
+            (Form::Values(x_values), Form::Properties(required, optional, _)) => {
+                self.values_properties(x_values, required, optional)
+            }
+            (Form::Values(_), _) => Outcome::Counterexample(Value::Object(Map::new())),
+            (
+                Form::Properties(x_required, x_optional, _),
+                Form::Properties(required, optional, _),
+            ) => {
+                self.properties_properties(witness, x_required, x_optional, required, optional, tag)
+            }
+            (Form::Properties(required, optional, _), Form::Values(y_values)) => {
+                self.properties_values(witness, required, optional, y_values, tag)
+            }
+            (Form::Properties(required, _, _), Form::Discriminator(y_tag, mapping)) => {
+                self.properties_discriminator(witness, x, required, y_tag, mapping)
+            }
+            (Form::Discriminator(x_tag, x_mapping), _) => {
+                self.discriminator(witness, x_tag, x_mapping, y)
+            }
+            _ => Outcome::Counterexample(witness),
+        }
+    }
+
+    fn witness(&self, schema: &Schema) -> Option<Value> {
+        self.generator
+            .generate_with_definitions(self.a_defs, schema, 0)
+    }
+
+    fn values_properties(
+        &mut self,
+        x_values: &'a Schema,
+        required: &'a BTreeMap<String, Schema>,
+        optional: &'a BTreeMap<String, Schema>,
+    ) -> Outcome {
+        if !required.is_empty() {
+            return Outcome::Counterexample(Value::Object(Map::new()));
+        }
+
+        let mut outcomes = Vec::new();
+        for (name, y_schema) in optional {
+            let outcome = self.check(x_values, y_schema, None).map(|instance| {
+                let mut out = Map::new();
+                out.insert(name.clone(), instance);
+                Value::Object(out)
+            });
+
+            if let Outcome::Counterexample(_) = outcome {
+                return outcome;
+            }
+            outcomes.push(outcome);
+        }
+
+        if self.strict {
+            if let Some(value) = self.witness(x_values) {
+                let mut out = Map::new();
+                out.insert(unused_name(optional.keys(), "unknown"), value);
+                return Outcome::Counterexample(Value::Object(out));
+            }
+        }
+
+        Outcome::all(outcomes)
+    }
+
+    fn properties_properties(
+        &mut self,
+        witness: Value,
+        x_required: &'a BTreeMap<String, Schema>,
+        x_optional: &'a BTreeMap<String, Schema>,
+        required: &'a BTreeMap<String, Schema>,
+        optional: &'a BTreeMap<String, Schema>,
+        tag: Option<&'a str>,
+    ) -> Outcome {
+        for name in required.keys() {
+            if Some(name.as_str()) != tag && !x_required.contains_key(name) {
+                return Outcome::Counterexample(without(witness, name));
+            }
+        }
+
+        let mut outcomes = Vec::new();
+        for (name, x_schema, is_optional) in sorted_properties(x_required, x_optional) {
+            if Some(name.as_str()) == tag {
+                continue;
+            }
+
+            let outcome = match required.get(name).or_else(|| optional.get(name)) {
+                Some(y_schema) => self.check(x_schema, y_schema, None),
+                None if self.strict => match self.witness(x_schema) {
+                    Some(value) => Outcome::Counterexample(value),
+                    None if is_optional => continue,
+                    None => unreachable!("witness exists, so every required property does"),
+                },
+                None => continue,
+            };
+
+            if let Outcome::Counterexample(_) = outcome {
+                return outcome.map(|instance| with(witness, name, instance));
+            }
+            outcomes.push(outcome);
+        }
+
+        // Without strict instance semantics, instances of x can have any
+        // value for properties x doesn't mention.
+        if !self.strict {
+            for (name, y_schema) in optional {
+                if Some(name.as_str()) == tag
+                    || x_required.contains_key(name)
+                    || x_optional.contains_key(name)
+                {
+                    continue;
+                }
+
+                if !is_empty(self.b_defs, y_schema) {
+                    return Outcome::Counterexample(with(witness, name, Value::Null));
+                }
+            }
+        }
+
+        Outcome::all(outcomes)
+    }
+
+    fn properties_values(
+        &mut self,
+        witness: Value,
+        required: &'a BTreeMap<String, Schema>,
+        optional: &'a BTreeMap<String, Schema>,
+        y_values: &'a Schema,
+        tag: Option<&'a str>,
+    ) -> Outcome {
+        let mut outcomes = Vec::new();
+        for (name, x_schema, _) in sorted_properties(required, optional) {
+            if Some(name.as_str()) == tag {
+                continue;
+            }
+
+            let outcome = self.check(x_schema, y_values, None);
+            if let Outcome::Counterexample(_) = outcome {
+                return outcome.map(|instance| with(witness, name, instance));
+            }
+            outcomes.push(outcome);
+        }
+
+        if !self.strict && !is_empty(self.b_defs, y_values) {
+            let name = unused_name(required.keys().chain(optional.keys()), "unknown");
+            return Outcome::Counterexample(with(witness, &name, Value::Null));
+        }
+
+        Outcome::all(outcomes)
+    }
+
+    fn properties_discriminator(
+        &mut self,
+        witness: Value,
+        x: &'a Schema,
+        required: &'a BTreeMap<String, Schema>,
+        y_tag: &'a str,
+        mapping: &'a BTreeMap<String, Schema>,
+    ) -> Outcome {
+        let tag_schema = match required.get(y_tag) {
+            Some(tag_schema) => resolve(self.a_defs, tag_schema),
+            None => return Outcome::Counterexample(without(witness, y_tag)),
+        };
+
+        let values = match tag_schema.form() {
+            Form::Enum(values) => values,
+            Form::Empty | Form::Type(Type::String) => {
+                let value = unused_name(mapping.keys(), "UNKNOWN");
+                return Outcome::Counterexample(with(witness, y_tag, value.into()));
+            }
+            Form::Type(Type::Timestamp) => {
+                let value = unused_timestamp(mapping.keys());
+                return Outcome::Counterexample(with(witness, y_tag, value.into()));
+            }
+            _ => return Outcome::Counterexample(witness),
+        };
+
+        let values: Vec<_> = values.iter().collect();
+
+        let mut outcomes = Vec::new();
+        for value in values {
+            let outcome = match mapping.get(value) {
+                Some(variant) => self.check(x, variant, Some(y_tag)),
+                None => Outcome::Counterexample(witness.clone()),
+            };
+
+            if let Outcome::Counterexample(_) = outcome {
+                return outcome.map(|instance| with(instance, y_tag, value.clone().into()));
+            }
+            outcomes.push(outcome);
+        }
+
+        Outcome::all(outcomes)
+    }
+
+    fn discriminator(
+        &mut self,
+        witness: Value,
+        x_tag: &'a str,
+        x_mapping: &'a BTreeMap<String, Schema>,
+        y: &'a Schema,
+    ) -> Outcome {
+        let mut outcomes = Vec::new();
+        for (value, x_variant) in x_mapping {
+            let variant_witness = match self.witness(x_variant) {
+                Some(variant_witness) => variant_witness,
+                None => continue,
+            };
+
+            let outcome = match y.form() {
+                Form::Discriminator(y_tag, mapping) if y_tag == x_tag => match mapping.get(value) {
+                    Some(y_variant) => self.check(x_variant, y_variant, Some(x_tag)),
+                    None => Outcome::Counterexample(variant_witness),
+                },
+                Form::Discriminator(..) => Outcome::Unknown,
+                Form::Properties(required, optional, _) => {
+                    match required.get(x_tag).or_else(|| optional.get(x_tag)) {
+                        Some(y_schema) if !accepts_string(self.b_defs, y_schema, value) => {
+                            Outcome::Counterexample(variant_witness)
+                        }
+                        None if self.strict => Outcome::Counterexample(variant_witness),
+                        _ => self.check(x_variant, y, Some(x_tag)),
+                    }
+                }
+                Form::Values(y_values) => {
+                    if accepts_string(self.b_defs, y_values, value) {
+                        self.check(x_variant, y, Some(x_tag))
+                    } else {
+                        Outcome::Counterexample(variant_witness)
+                    }
+                }
+                _ => return Outcome::Counterexample(witness),
+            };
+
+            if let Outcome::Counterexample(_) = outcome {
+                return outcome.map(|instance| with(instance, x_tag, value.clone().into()));
+            }
+            outcomes.push(outcome);
+        }
+
+        Outcome::all(outcomes)
+    }
+}
+
+/// Follow `ref`s until reaching a schema of some other form. Gives up on
+/// cycles of nothing but `ref`s.
+fn resolve<'a>(defs: &'a BTreeMap<String, Schema>, mut schema: &'a Schema) -> &'a Schema {
+    for _ in 0..=defs.len() {
+        match schema.form() {
+            Form::Ref(def) => schema = &defs[def],
+            _ => break,
+        }
+    }
+
+    schema
+}
+
+fn is_empty(defs: &BTreeMap<String, Schema>, schema: &Schema) -> bool {
+    matches!(resolve(defs, schema).form(), Form::Empty)
+}
+
+fn accepts_string(defs: &BTreeMap<String, Schema>, schema: &Schema, value: &str) -> bool {
+    match resolve(defs, schema).form() {
+        Form::Empty | Form::Type(Type::String) => true,
+        Form::Type(Type::Timestamp) => DateTime::parse_from_rfc3339(value).is_ok(),
+        Form::Enum(values) => values.contains(value),
+        _ => false,
+    }
+}
+
+fn types(x: &Type, y: &Type, witness: Value) -> Outcome {
+    let is_float = |typ: &Type| matches!(typ, Type::Number | Type::Float32 | Type::Float64);
+
+    if x == y {
+        return Outcome::Subset;
+    }
+
+    match (int_range(x), int_range(y)) {
+        (Some(_), None) if is_float(y) => Outcome::Subset,
+        (Some((x_min, x_max)), Some((y_min, y_max))) => {
+            if x_min < y_min {
+                Outcome::Counterexample(x_min.into())
+            } else if x_max > y_max {
+                Outcome::Counterexample(x_max.into())
+            } else {
+                Outcome::Subset
+            }
+        }
+        (None, Some(_)) if is_float(x) => Outcome::Counterexample(0.5.into()),
+        (None, None) if is_float(x) && is_float(y) => Outcome::Subset,
+        (None, None) if *x == Type::Timestamp && *y == Type::String => Outcome::Subset,
+        (None, None) if *x == Type::String && *y == Type::Timestamp => {
+            Outcome::Counterexample("".into())
+        }
+        _ => Outcome::Counterexample(witness),
+    }
+}
+
+/// A name starting with `base` that is not among `used`.
+fn unused_name<'b, I: Iterator<Item = &'b String>>(used: I, base: &str) -> String {
+    let used: BTreeSet<_> = used.collect();
+    let mut name = base.to_owned();
+    while used.contains(&name) {
+        name.push('_');
+    }
+    name
+}
+
+/// A valid timestamp that is not among `used`.
+fn unused_timestamp<'b, I: Iterator<Item = &'b String>>(used: I) -> String {
+    let used: BTreeSet<_> = used.collect();
+    (0..)
+        .map(|second| format!("1970-01-01T00:{:02}:{:02}Z", second / 60, second % 60))
+        .find(|timestamp| !used.contains(timestamp))
+        .expect("ran out of timestamps")
+}
+
+fn with(instance: Value, name: &str, value: Value) -> Value {
+    match instance {
+        Value::Object(mut out) => {
+            out.insert(name.to_owned(), value);
+            Value::Object(out)
+        }
+        _ => instance,
+    }
+}
+
+fn without(instance: Value, name: &str) -> Value {
+    match instance {
+        Value::Object(mut out) => {
+            out.remove(name);
+            Value::Object(out)
+        }
+        _ => instance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator::{Config, Validator};
+    use serde_json::json;
+
+    fn schema(data: Value) -> Schema {
+        Schema::from_serde(serde_json::from_value(data).unwrap()).unwrap()
+    }
+
+    /// Check `is_subschema`, and that any counterexample really is one.
+    fn check(a: Value, b: Value, strict: bool) -> Result<bool, Value> {
+        let (a, b) = (schema(a), schema(b));
+        let result = is_subschema(&a, &b, strict);
+
+        if let Err(ref counterexample) = result {
+            let mut config = Config::new();
+            config.strict_instance_semantics(strict);
+            let validator = Validator::new_with_config(config);
+
+            let instance = counterexample.instance();
+            assert!(validator.validate(&a, instance).unwrap().is_empty());
+            assert!(!validator.validate(&b, instance).unwrap().is_empty());
+        }
+
+        result.map_err(Counterexample::into_instance)
+    }
+
+    #[test]
+    fn types() {
+        let uint8 = json!({ "type": "uint8" });
+        let int16 = json!({ "type": "int16" });
+        let float32 = json!({ "type": "float32" });
+        let timestamp = json!({ "type": "timestamp" });
+        let string = json!({ "type": "string" });
+
+        assert_eq!(check(uint8.clone(), int16.clone(), false), Ok(true));
+        assert_eq!(
+            check(int16.clone(), uint8.clone(), false),
+            Err(json!(-32768))
+        );
+        assert_eq!(check(int16.clone(), float32.clone(), false), Ok(true));
+        assert_eq!(check(float32, int16, false), Err(json!(0.5)));
+        assert_eq!(check(timestamp.clone(), string.clone(), false), Ok(true));
+        assert_eq!(check(string, timestamp, false), Err(json!("")));
+        assert_eq!(check(uint8, json!({}), false), Ok(true));
+        assert_eq!(
+            check(json!({}), json!({ "type": "boolean" }), false),
+            Err(json!(null))
+        );
+    }
+
+    #[test]
+    fn enums() {
+        assert_eq!(
+            check(
+                json!({ "enum": ["A"] }),
+                json!({ "enum": ["A", "B"] }),
+                false
+            ),
+            Ok(true)
+        );
+        assert_eq!(
+            check(
+                json!({ "enum": ["A", "B"] }),
+                json!({ "enum": ["A"] }),
+                false
+            ),
+            Err(json!("B"))
+        );
+        assert_eq!(
+            check(
+                json!({ "enum": ["A"] }),
+                json!({ "type": "timestamp" }),
+                false
+            ),
+            Err(json!("A"))
+        );
+    }
+
+    #[test]
+    fn properties() {
+        let producer = json!({
+            "properties": {
+                "id": { "type": "uint32" },
+                "name": { "type": "string" },
+            },
+        });
+        let consumer = json!({
+            "properties": { "id": { "type": "int64" } },
+            "optionalProperties": { "name": { "type": "string" } },
+        });
+
+        assert_eq!(check(producer.clone(), consumer.clone(), false), Ok(true));
+        assert_eq!(check(producer.clone(), consumer.clone(), true), Ok(true));
+        assert!(check(consumer.clone(), producer.clone(), false).is_err());
+
+        let narrow = json!({ "properties": { "id": { "type": "int64" } } });
+        assert_eq!(check(producer.clone(), narrow.clone(), false), Ok(true));
+        assert!(check(producer, narrow.clone(), true).is_err());
+
+        // Without strict semantics, producers can send anything as "name".
+        let wide = json!({ "properties": { "id": { "type": "int64" } } });
+        assert!(check(wide, consumer, false).is_err());
+    }
+
+    #[test]
+    fn recursive() {
+        let list = |typ: &str| {
+            json!({
+                "definitions": {
+                    "node": {
+                        "properties": { "value": { "type": typ } },
+                        "optionalProperties": { "next": { "ref": "node" } },
+                    },
+                },
+                "ref": "node",
+            })
+        };
+
+        assert_eq!(check(list("uint8"), list("int32"), false), Ok(true));
+
+        let counterexample = check(list("int32"), list("uint8"), false).unwrap_err();
+        assert_eq!(counterexample["value"], json!(-2147483648i64));
+    }
+
+    #[test]
+    fn discriminators() {
+        let events = |variants: &[&str]| {
+            let mut mapping = Map::new();
+            for variant in variants {
+                mapping.insert(
+                    variant.to_string(),
+                    json!({ "properties": { "at": { "type": "timestamp" } } }),
+                );
+            }
+
+            json!({ "discriminator": { "tag": "type", "mapping": mapping } })
+        };
+
+        assert_eq!(check(events(&["a"]), events(&["a", "b"]), true), Ok(true));
+        assert_eq!(
+            check(events(&["a", "b"]), events(&["a"]), true).unwrap_err()["type"],
+            json!("b")
+        );
+
+        let object = json!({
+            "properties": {
+                "type": { "enum": ["a", "b"] },
+                "at": { "type": "timestamp" },
+            },
+        });
+        assert_eq!(check(events(&["a", "b"]), object.clone(), true), Ok(true));
+        assert_eq!(check(object.clone(), events(&["a", "b"]), true), Ok(true));
+        assert!(check(object, events(&["a"]), true).is_err());
+
+        let other_tag = json!({ "discriminator": { "tag": "kind", "mapping": {} } });
+        assert_eq!(check(events(&["a"]), other_tag, true), Ok(false));
+    }
+
+    #[test]
+    fn values() {
+        let values = json!({ "values": { "type": "uint8" } });
+        let optional = json!({ "optionalProperties": { "a": { "type": "int16" } } });
+
+        assert_eq!(check(values.clone(), optional.clone(), false), Ok(true));
+        assert!(check(values.clone(), optional.clone(), true).is_err());
+        assert!(check(optional, values, false).is_err());
+    }
+}
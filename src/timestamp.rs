@@ -0,0 +1,167 @@
+//! Checking the `timestamp` type.
+//!
+//! With `std`, timestamps are parsed with `chrono`. Without it, they're
+//! checked by a small parser here, which accepts exactly the strings that
+//! `chrono::DateTime::parse_from_rfc3339` does.
+
+/// Whether a string is an RFC 3339 timestamp.
+#[cfg(feature = "std")]
+pub fn is_rfc3339(s: &str) -> bool {
+    chrono::DateTime::parse_from_rfc3339(s).is_ok()
+}
+
+/// Whether a string is an RFC 3339 timestamp.
+#[cfg(not(feature = "std"))]
+pub fn is_rfc3339(s: &str) -> bool {
+    parse(s).is_some()
+}
+
+#[cfg(any(not(feature = "std"), test))]
+fn parse(s: &str) -> Option<()> {
+    let mut bytes = s.as_bytes();
+
+    let year = digits(&mut bytes, 4)?;
+    literal(&mut bytes, b"-")?;
+    let month = digits(&mut bytes, 2)?;
+    literal(&mut bytes, b"-")?;
+    let day = digits(&mut bytes, 2)?;
+    if day == 0 || day > days_in_month(year, month)? {
+        return None;
+    }
+
+    // chrono also allows a lowercase "t", or a space.
+    literal(&mut bytes, b"Tt ")?;
+
+    let hour = digits(&mut bytes, 2)?;
+    literal(&mut bytes, b":")?;
+    let minute = digits(&mut bytes, 2)?;
+    literal(&mut bytes, b":")?;
+    // 60 is a leap second, which is allowed at any time of day.
+    let second = digits(&mut bytes, 2)?;
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    if literal(&mut bytes, b".").is_some() {
+        let fraction = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+        if fraction == 0 {
+            return None;
+        }
+
+        bytes = &bytes[fraction..];
+    }
+
+    if literal(&mut bytes, b"Zz").is_none() {
+        // chrono also allows U+2212 MINUS SIGN.
+        if bytes.starts_with("\u{2212}".as_bytes()) {
+            bytes = &bytes["\u{2212}".len()..];
+        } else {
+            literal(&mut bytes, b"+-")?;
+        }
+
+        let hour = digits(&mut bytes, 2)?;
+        literal(&mut bytes, b":")?;
+        let minute = digits(&mut bytes, 2)?;
+        if hour > 23 || minute > 59 {
+            return None;
+        }
+    }
+
+    if bytes.is_empty() {
+        Some(())
+    } else {
+        None
+    }
+}
+
+/// Consume exactly `n` ASCII digits, returning their value.
+#[cfg(any(not(feature = "std"), test))]
+fn digits(bytes: &mut &[u8], n: usize) -> Option<u32> {
+    if bytes.len() < n || !bytes[..n].iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    let value = bytes[..n]
+        .iter()
+        .fold(0, |value, b| value * 10 + u32::from(b - b'0'));
+    *bytes = &bytes[n..];
+    Some(value)
+}
+
+/// Consume a byte, which must be one of `allowed`.
+#[cfg(any(not(feature = "std"), test))]
+fn literal(bytes: &mut &[u8], allowed: &[u8]) -> Option<()> {
+    let (first, rest) = bytes.split_first()?;
+    if !allowed.contains(first) {
+        return None;
+    }
+
+    *bytes = rest;
+    Some(())
+}
+
+#[cfg(any(not(feature = "std"), test))]
+#[allow(clippy::manual_is_multiple_of)]
+fn days_in_month(year: u32, month: u32) -> Option<u32> {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => Some(31),
+        4 | 6 | 9 | 11 => Some(30),
+        2 if leap => Some(29),
+        2 => Some(28),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_chrono() {
+        let cases = [
+            "1985-04-12T23:20:50.52Z",
+            "1996-12-19T16:39:57-08:00",
+            "1990-12-31T23:59:60Z",
+            "1990-12-31T15:59:60-08:00",
+            "1937-01-01T12:00:27.87+00:20",
+            "0000-01-01T00:00:00Z",
+            "2000-02-29T00:00:00Z",
+            "1900-02-29T00:00:00Z",
+            "2001-02-29T00:00:00Z",
+            "2001-04-31T00:00:00Z",
+            "2001-13-01T00:00:00Z",
+            "2001-00-01T00:00:00Z",
+            "2001-01-00T00:00:00Z",
+            "2001-01-01t00:00:00z",
+            "2001-01-01 00:00:00Z",
+            "2001-01-01_00:00:00Z",
+            "2001-01-01T24:00:00Z",
+            "2001-01-01T00:60:00Z",
+            "2001-01-01T00:00:61Z",
+            "2001-01-01T00:00:00.Z",
+            "2001-01-01T00:00:00.1234567890123Z",
+            "2001-01-01T00:00:00",
+            "2001-01-01T00:00:00+23:59",
+            "2001-01-01T00:00:00+24:00",
+            "2001-01-01T00:00:00+00:60",
+            "2001-01-01T00:00:00+0000",
+            "2001-01-01T00:00:00+00",
+            "2001-01-01T00:00:00\u{2212}01:00",
+            "2001-01-01T00:00:00Z ",
+            "2001-1-01T00:00:00Z",
+            "+2001-01-01T00:00:00Z",
+            "2001-01-01",
+            "",
+        ];
+
+        for case in cases.iter() {
+            assert_eq!(
+                parse(case).is_some(),
+                is_rfc3339(case),
+                "disagreement on {:?}",
+                case
+            );
+        }
+    }
+}
@@ -6,11 +6,21 @@
 //!
 //! See the docs for [`Validator`](struct.Validator.html) for more.
 
-use crate::schema::Schema;
-use crate::vm::validate;
-use failure::Error;
-use json_pointer::JsonPointer;
-use std::borrow::Cow;
+use crate::errors::JslError;
+use crate::patch::{self, Operation, PatchError, PatchFailure};
+use crate::pointer::JsonPointer;
+use crate::schema::{Form, Schema, Type};
+use crate::vm::{self, validate};
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::Deref;
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
 
 /// Validates instances against schemas.
 #[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
@@ -42,7 +52,7 @@ impl Validator {
         &self,
         schema: &'a Schema,
         instance: &'a serde_json::Value,
-    ) -> Result<Vec<ValidationError<'a>>, Error> {
+    ) -> Result<Vec<ValidationError<'a>>, JslError> {
         validate(
             self.config.max_errors,
             self.config.max_depth,
@@ -51,14 +61,256 @@ impl Validator {
             instance,
         )
     }
+
+    /// Validate an instance against a schema, and record which schema applied
+    /// at each location in the instance.
+    ///
+    /// This is for tooling that needs to know what each part of an instance
+    /// is, such as editors that render values with widgets suited to their
+    /// type. Validation errors are returned alongside the annotations. If
+    /// `max_errors` is reached, annotation stops there too.
+    ///
+    /// Returns an error if the maximum reference depth is exceeded.
+    pub fn annotate<'a>(
+        &self,
+        schema: &'a Schema,
+        instance: &'a Value,
+    ) -> Result<Annotations<'a>, JslError> {
+        let (errors, annotations) = vm::annotate(
+            self.config.max_errors,
+            self.config.max_depth,
+            self.config.strict_instance_semantics,
+            schema,
+            instance,
+        )?;
+
+        let index = annotations
+            .iter()
+            .enumerate()
+            .map(|(i, annotation)| (annotation.instance_path.to_string(), i))
+            .collect();
+
+        Ok(Annotations {
+            errors,
+            annotations,
+            index,
+        })
+    }
+
+    /// Validate an instance against a schema, and then deserialize it into
+    /// `T`.
+    ///
+    /// This is a shorthand for calling [`validate`](#method.validate),
+    /// checking that no validation errors were produced, and then calling
+    /// `serde_json::from_value`. Because the instance is consumed, any
+    /// validation errors are converted into owned `ValidationError`s.
+    pub fn validate_into<T: DeserializeOwned>(
+        &self,
+        schema: &Schema,
+        instance: Value,
+    ) -> Result<T, ValidationFailure> {
+        let errors = self
+            .validate(schema, &instance)
+            .map_err(ValidationFailure::Eval)?;
+
+        if !errors.is_empty() {
+            return Err(ValidationFailure::Invalid(
+                errors.into_iter().map(ValidationError::into_owned).collect(),
+            ));
+        }
+
+        serde_json::from_value(instance).map_err(ValidationFailure::Deserialize)
+    }
+
+    /// Validate the result of applying a JSON Patch to an instance, without
+    /// applying it.
+    ///
+    /// Only the parts of the instance that the patch changes are
+    /// re-validated, so errors elsewhere in the instance are not reported.
+    /// Each error records the index of the operation responsible for it. See
+    /// the [`patch`](../patch/index.html) module for details.
+    ///
+    /// Returns an error if the patch cannot be applied to the instance, or if
+    /// the maximum reference depth is exceeded.
+    pub fn validate_patch(
+        &self,
+        schema: &Schema,
+        instance: &Value,
+        patch: &[Operation],
+    ) -> Result<Vec<PatchError>, PatchFailure> {
+        patch::validate_patch(&self.config, schema, instance, patch)
+    }
+
+    /// Validate the result of applying a JSON Merge Patch to an instance,
+    /// without applying it.
+    ///
+    /// As with [`validate_patch`](#method.validate_patch), only the parts of
+    /// the instance that the patch changes are re-validated.
+    pub fn validate_merge_patch(
+        &self,
+        schema: &Schema,
+        instance: &Value,
+        patch: &Value,
+    ) -> Result<Vec<ValidationError<'static>>, JslError> {
+        patch::validate_merge_patch(&self.config, schema, instance, patch)
+    }
+}
+
+/// The ways in which [`Validator::validate_into`](struct.Validator.html#method.validate_into)
+/// can fail.
+#[derive(Debug)]
+pub enum ValidationFailure {
+    /// The instance was not valid against the schema.
+    ///
+    /// This contains every validation error produced, subject to the
+    /// validator's configured `max_errors`.
+    Invalid(Vec<ValidationError<'static>>),
+
+    /// Validation could not be completed, for instance because the maximum
+    /// reference depth was exceeded.
+    Eval(JslError),
+
+    /// The instance was valid against the schema, but could not be
+    /// deserialized into the requested type.
+    Deserialize(serde_json::Error),
+}
+
+impl fmt::Display for ValidationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationFailure::Invalid(errors) => {
+                write!(f, "instance is invalid against schema:")?;
+                for error in errors {
+                    write!(
+                        f,
+                        " (instance path: {:?}, schema path: {:?})",
+                        error.instance_path().to_string(),
+                        error.schema_path().to_string()
+                    )?;
+                }
+
+                Ok(())
+            }
+            ValidationFailure::Eval(error) => write!(f, "error during validation: {}", error),
+            ValidationFailure::Deserialize(error) => {
+                write!(f, "error deserializing valid instance: {}", error)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ValidationFailure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ValidationFailure::Invalid(_) => None,
+            ValidationFailure::Eval(error) => Some(error),
+            ValidationFailure::Deserialize(error) => Some(error),
+        }
+    }
+}
+
+/// A type which has a schema and validator associated with it.
+///
+/// Implement this trait on a marker type to use it as the `S` parameter of
+/// [`Validated`](struct.Validated.html). Because `schema` returns a `'static`
+/// reference, implementors will typically parse their schema once and cache
+/// it, for example using `std::sync::OnceLock`.
+pub trait SchemaSource {
+    /// The schema that data must satisfy.
+    fn schema() -> &'static Schema;
+
+    /// The validator to check data with. Defaults to `Validator::new()`.
+    fn validator() -> Validator {
+        Validator::new()
+    }
+}
+
+/// A value of type `T` which is known to satisfy the schema of `S`.
+///
+/// `Validated` implements `Deserialize` by first deserializing into a
+/// `serde_json::Value`, validating that value against `S::schema()`, and only
+/// then deserializing into `T`. This makes it usable as a field in other serde
+/// types, or with any framework extractor that is built on serde, such as a
+/// JSON request body.
+///
+/// Validation errors are reported as a custom deserialization error. Use
+/// [`Validator::validate_into`](struct.Validator.html#method.validate_into)
+/// directly if you need the structured list of errors.
+pub struct Validated<T, S> {
+    value: T,
+    source: PhantomData<S>,
+}
+
+impl<T, S: SchemaSource> Validated<T, S> {
+    /// Validate and deserialize an instance.
+    pub fn from_value(instance: Value) -> Result<Self, ValidationFailure>
+    where
+        T: DeserializeOwned,
+    {
+        let value = S::validator().validate_into(S::schema(), instance)?;
+        Ok(Self {
+            value,
+            source: PhantomData,
+        })
+    }
+}
+
+impl<T, S> Validated<T, S> {
+    /// Discard the validation marker, returning the underlying value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T, S> Deref for Validated<T, S> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: fmt::Debug, S> fmt::Debug for Validated<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Validated").field(&self.value).finish()
+    }
+}
+
+impl<T: Clone, S> Clone for Validated<T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            source: PhantomData,
+        }
+    }
+}
+
+impl<T: PartialEq, S> PartialEq for Validated<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: Serialize, S> Serialize for Validated<T, S> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        self.value.serialize(serializer)
+    }
+}
+
+impl<'de, T: DeserializeOwned, S: SchemaSource> Deserialize<'de> for Validated<T, S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let instance = Value::deserialize(deserializer)?;
+        Self::from_value(instance).map_err(D::Error::custom)
+    }
 }
 
 /// Configuration for how validation should proceed.
 #[derive(Debug, Eq, PartialEq, Clone, Hash)]
 pub struct Config {
-    max_errors: usize,
-    max_depth: usize,
-    strict_instance_semantics: bool,
+    pub(crate) max_errors: usize,
+    pub(crate) max_depth: usize,
+    pub(crate) strict_instance_semantics: bool,
 }
 
 impl Config {
@@ -151,14 +403,149 @@ impl<'a> ValidationError<'a> {
     pub fn schema_path(&self) -> &JsonPointer<Cow<'a, str>, Vec<Cow<'a, str>>> {
         &self.schema_path
     }
+
+    /// Copy any borrowed parts of this error, so that it no longer borrows
+    /// from the schema or instance.
+    pub fn into_owned(self) -> ValidationError<'static> {
+        ValidationError {
+            instance_path: owned_pointer(&self.instance_path),
+            schema_path: owned_pointer(&self.schema_path),
+        }
+    }
+}
+
+/// The result of [`Validator::annotate`](struct.Validator.html#method.annotate).
+///
+/// Every location in the instance which some schema describes has an
+/// [`Annotation`](struct.Annotation.html). Properties that no schema mentions,
+/// the tags of discriminators, and anything beneath an empty schema are not
+/// annotated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotations<'a> {
+    errors: Vec<ValidationError<'a>>,
+    annotations: Vec<Annotation<'a>>,
+    index: BTreeMap<String, usize>,
+}
+
+impl<'a> Annotations<'a> {
+    /// The validation errors produced, as by
+    /// [`Validator::validate`](struct.Validator.html#method.validate).
+    pub fn errors(&self) -> &[ValidationError<'a>] {
+        &self.errors
+    }
+
+    /// Every annotation, in the order their locations were reached.
+    pub fn annotations(&self) -> &[Annotation<'a>] {
+        &self.annotations
+    }
+
+    /// Get the annotation for a location in the instance.
+    pub fn get<S: AsRef<str>, C: AsRef<[S]>>(
+        &self,
+        instance_path: &JsonPointer<S, C>,
+    ) -> Option<&Annotation<'a>> {
+        self.index
+            .get(&instance_path.to_string())
+            .map(|&i| &self.annotations[i])
+    }
+}
+
+/// The schema which applied at a location in an instance.
+///
+/// Any `ref`s are followed, and for discriminators the mapping branch chosen
+/// by the instance's tag is used. The schema which was reached this way is
+/// the _effective_ schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation<'a> {
+    pub(crate) instance_path: JsonPointer<Cow<'a, str>, Vec<Cow<'a, str>>>,
+    pub(crate) schema_path: JsonPointer<Cow<'a, str>, Vec<Cow<'a, str>>>,
+    pub(crate) schemas: Vec<&'a Schema>,
+    pub(crate) definition: Option<&'a str>,
+    pub(crate) discriminator: Option<(&'a str, &'a str)>,
+}
+
+impl<'a> Annotation<'a> {
+    /// A pointer to the annotated location in the instance.
+    pub fn instance_path(&self) -> &JsonPointer<Cow<'a, str>, Vec<Cow<'a, str>>> {
+        &self.instance_path
+    }
+
+    /// A pointer to the effective schema. Like those of validation errors,
+    /// this is relative to the root schema, or to the definition which was
+    /// last followed.
+    pub fn schema_path(&self) -> &JsonPointer<Cow<'a, str>, Vec<Cow<'a, str>>> {
+        &self.schema_path
+    }
+
+    /// The effective schema.
+    pub fn schema(&self) -> &'a Schema {
+        self.schemas[self.schemas.len() - 1]
+    }
+
+    /// The type of the effective schema, if it is of the type form.
+    pub fn typ(&self) -> Option<&'a Type> {
+        match self.schema().form() {
+            Form::Type(typ) => Some(typ),
+            _ => None,
+        }
+    }
+
+    /// The name of the definition the last `ref` on the way to the effective
+    /// schema resolved to, if there was one.
+    pub fn definition(&self) -> Option<&'a str> {
+        self.definition
+    }
+
+    /// The tag property and value of the discriminator which chose the
+    /// effective schema, if there was one.
+    pub fn discriminator(&self) -> Option<(&'a str, &'a str)> {
+        self.discriminator
+    }
+
+    /// Look up a non-keyword property, as in [`Schema::extra`](../schema/struct.Schema.html#method.extra).
+    ///
+    /// Every schema on the way to the effective schema is searched, starting
+    /// with the one nearest the instance. For instance, a `description` next
+    /// to a `ref` takes precedence over one in the referred-to definition.
+    pub fn extra(&self, key: &str) -> Option<&'a Value> {
+        self.schemas
+            .iter()
+            .find_map(|schema| schema.extra().get(key))
+    }
+}
+
+/// Split a pointer into its unescaped reference tokens.
+///
+/// `JsonPointer` does not expose its tokens, so this works from its `Display`
+/// representation instead.
+pub(crate) fn pointer_tokens<S: AsRef<str>, C: AsRef<[S]>>(
+    pointer: &JsonPointer<S, C>,
+) -> Vec<String> {
+    pointer
+        .to_string()
+        .split('/')
+        .skip(1)
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+fn owned_pointer<S: AsRef<str>, C: AsRef<[S]>>(
+    pointer: &JsonPointer<S, C>,
+) -> JsonPointer<Cow<'static, str>, Vec<Cow<'static, str>>> {
+    JsonPointer::new(pointer_tokens(pointer).into_iter().map(Cow::Owned).collect())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::schema::Schema;
+    use failure::Error;
     use serde_json::json;
 
+    fn pointer(path: &str) -> JsonPointer<String, Vec<String>> {
+        JsonPointer::new(path.split('/').skip(1).map(str::to_owned).collect())
+    }
+
     #[test]
     fn infinite_loop() -> Result<(), Error> {
         let validator = Validator::new();
@@ -198,4 +585,258 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn annotate() -> Result<(), Error> {
+        let schema = Schema::from_serde(serde_json::from_value(json!({
+            "definitions": {
+                "point": {
+                    "properties": {
+                        "x": { "type": "float64", "unit": "px" },
+                        "y": { "type": "float64" },
+                    },
+                },
+            },
+            "properties": {
+                "shapes": {
+                    "elements": {
+                        "discriminator": {
+                            "tag": "kind",
+                            "mapping": {
+                                "circle": {
+                                    "properties": {
+                                        "center": { "ref": "point", "description": "middle" },
+                                        "r": { "type": "float64" },
+                                    },
+                                },
+                            },
+                        },
+                    },
+                },
+                "meta": {},
+            },
+        }))?)?;
+
+        let instance = json!({
+            "shapes": [
+                { "kind": "circle", "center": { "x": 1, "y": "2" }, "r": 3 },
+                { "kind": "square" },
+            ],
+            "meta": { "a": 1 },
+            "extra": 1,
+        });
+
+        let annotations = Validator::new().annotate(&schema, &instance)?;
+        let mut paths: Vec<_> = annotations
+            .annotations()
+            .iter()
+            .map(|a| (a.instance_path().to_string(), a.schema_path().to_string()))
+            .collect();
+
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                ("".to_owned(), "".to_owned()),
+                ("/meta".to_owned(), "/properties/meta".to_owned()),
+                ("/shapes".to_owned(), "/properties/shapes".to_owned()),
+                (
+                    "/shapes/0".to_owned(),
+                    "/properties/shapes/elements/discriminator/mapping/circle".to_owned()
+                ),
+                (
+                    "/shapes/0/center".to_owned(),
+                    "/definitions/point".to_owned()
+                ),
+                (
+                    "/shapes/0/center/x".to_owned(),
+                    "/definitions/point/properties/x".to_owned()
+                ),
+                (
+                    "/shapes/0/center/y".to_owned(),
+                    "/definitions/point/properties/y".to_owned()
+                ),
+                (
+                    "/shapes/0/r".to_owned(),
+                    "/properties/shapes/elements/discriminator/mapping/circle/properties/r"
+                        .to_owned()
+                ),
+                (
+                    "/shapes/1".to_owned(),
+                    "/properties/shapes/elements".to_owned()
+                ),
+            ]
+        );
+
+        // Errors are reported alongside annotations.
+        let errors: Vec<_> = annotations
+            .errors()
+            .iter()
+            .map(|e| e.instance_path().to_string())
+            .collect();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.contains(&"/shapes/0/center/y".to_owned()));
+        assert!(errors.contains(&"/shapes/1/kind".to_owned()));
+
+        let get = |path: &str| {
+annotations.get(&pointer(path)).unwrap()
+        };
+
+        let circle = get("/shapes/0");
+        assert_eq!(circle.discriminator(), Some(("kind", "circle")));
+        assert_eq!(circle.definition(), None);
+        assert_eq!(circle.typ(), None);
+
+        let center = get("/shapes/0/center");
+        assert_eq!(center.definition(), Some("point"));
+        assert_eq!(center.extra("description"), Some(&json!("middle")));
+        assert_eq!(Some(center.schema()), schema.get(center.schema_path()));
+
+        let x = get("/shapes/0/center/x");
+        assert_eq!(x.typ(), Some(&Type::Float64));
+        assert_eq!(x.definition(), None);
+        assert_eq!(x.extra("unit"), Some(&json!("px")));
+        assert_eq!(x.extra("description"), None);
+
+        let square = get("/shapes/1");
+        assert_eq!(square.discriminator(), None);
+        assert!(matches!(square.schema().form(), Form::Discriminator(..)));
+
+        assert!(annotations.get(&pointer("/extra")).is_none());
+        assert!(annotations.get(&pointer("/meta/a")).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_into() -> Result<(), Error> {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct User {
+            name: String,
+            age: u8,
+        }
+
+        let schema = Schema::from_serde(serde_json::from_value(json!({
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "uint8" },
+            },
+        }))?)?;
+
+        let validator = Validator::new();
+        assert_eq!(
+            validator
+                .validate_into::<User>(&schema, json!({ "name": "john", "age": 43 }))
+                .unwrap(),
+            User {
+                name: "john".to_owned(),
+                age: 43,
+            }
+        );
+
+        match validator.validate_into::<User>(&schema, json!({ "name": "john", "age": 256 })) {
+            Err(ValidationFailure::Invalid(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].instance_path().to_string(), "/age");
+                assert_eq!(errors[0].schema_path().to_string(), "/properties/age/type");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn validated() -> Result<(), Error> {
+        use std::sync::OnceLock;
+
+        struct Names;
+
+        impl SchemaSource for Names {
+            fn schema() -> &'static Schema {
+                static SCHEMA: OnceLock<Schema> = OnceLock::new();
+                SCHEMA.get_or_init(|| {
+                    Schema::from_serde(
+                        serde_json::from_value(json!({
+                            "elements": { "type": "string" },
+                        }))
+                        .unwrap(),
+                    )
+                    .unwrap()
+                })
+            }
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Request {
+            names: Validated<Vec<String>, Names>,
+        }
+
+        let request: Request = serde_json::from_value(json!({ "names": ["a", "b"] }))?;
+        assert_eq!(*request.names, vec!["a".to_owned(), "b".to_owned()]);
+        assert_eq!(serde_json::to_value(&request.names)?, json!(["a", "b"]));
+
+        assert!(serde_json::from_value::<Request>(json!({ "names": ["a", 1] })).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn discriminator_schema_paths() -> Result<(), Error> {
+        let mapping = json!({
+            "tag": "kind",
+            "mapping": { "a": { "properties": { "x": { "type": "string" } } } },
+        });
+
+        let cases = vec![
+            (
+                json!({ "values": { "discriminator": mapping } }),
+                json!({ "p": { "kind": "a", "x": 1 }, "q": { "kind": "a", "x": 2 } }),
+                vec![
+                    ("/p/x", "/values/discriminator/mapping/a/properties/x/type"),
+                    ("/q/x", "/values/discriminator/mapping/a/properties/x/type"),
+                ],
+            ),
+            (
+                json!({ "elements": { "discriminator": mapping } }),
+                json!([{ "kind": "a", "x": 1 }, { "kind": "b" }, { "kind": "a", "x": 2 }]),
+                vec![
+                    (
+                        "/0/x",
+                        "/elements/discriminator/mapping/a/properties/x/type",
+                    ),
+                    ("/1/kind", "/elements/discriminator/mapping"),
+                    (
+                        "/2/x",
+                        "/elements/discriminator/mapping/a/properties/x/type",
+                    ),
+                ],
+            ),
+        ];
+
+        for (schema, instance, expected) in cases {
+            let schema = Schema::from_serde(serde_json::from_value(schema)?)?;
+            let errors = Validator::new().validate(&schema, &instance)?;
+            let errors: Vec<_> = errors
+                .iter()
+                .map(|error| {
+                    (
+                        error.instance_path().to_string(),
+                        error.schema_path().to_string(),
+                    )
+                })
+                .collect();
+
+            let expected: Vec<_> = expected
+                .into_iter()
+                .map(|(instance_path, schema_path)| {
+                    (instance_path.to_owned(), schema_path.to_owned())
+                })
+                .collect();
+
+            assert_eq!(errors, expected);
+        }
+
+        Ok(())
+    }
 }
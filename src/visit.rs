@@ -0,0 +1,424 @@
+//! Traverse and rewrite schemas.
+//!
+//! Most tooling built on [`Schema`](../schema/struct.Schema.html) needs to
+//! look at every subschema, recursing through elements, properties, values,
+//! and discriminator mappings. This module does that recursion once:
+//!
+//! * A [`Visitor`](trait.Visitor.html) looks at each subschema in turn,
+//!   along with its schema path.
+//! * A [`Fold`](trait.Fold.html) rebuilds a schema, rewriting each subschema
+//!   along the way.
+//!
+//! Both are driven by a [`Walker`](struct.Walker.html). Subschemas are
+//! always visited in a deterministic order: definitions by name, properties
+//! by name (required ones first), and discriminator variants by tag value.
+//!
+//! ```
+//! use jsl::visit::{Context, Visitor, Walker};
+//! use jsl::{Form, Schema, SerdeSchema};
+//! use serde_json::json;
+//!
+//! struct Refs(Vec<String>);
+//!
+//! impl<'a> Visitor<'a> for Refs {
+//!     fn visit_schema(&mut self, cx: &mut Context<'a>, schema: &'a Schema) {
+//!         if let Form::Ref(_) = schema.form() {
+//!             self.0.push(cx.path().join("/"));
+//!         }
+//!
+//!         cx.visit_children(self, schema);
+//!     }
+//! }
+//!
+//! let schema: SerdeSchema = serde_json::from_value(json!({
+//!     "definitions": { "a": {} },
+//!     "elements": { "ref": "a" },
+//! })).unwrap();
+//! let schema = Schema::from_serde(schema).unwrap();
+//!
+//! let mut refs = Refs(Vec::new());
+//! Walker::new().walk(&schema, &mut refs);
+//! assert_eq!(refs.0, vec!["elements"]);
+//! ```
+
+// Within this crate, `Schema::missing_ref`, coverage, satisfiability, Rust
+// codegen's boxing analysis, and proto's `assign_numbers` are built on this
+// module. The other traversals stay hand-written, because none of them is a
+// walk over one schema that visits every subschema the same way:
+//
+// * `Schema::into_serde` produces a `SerdeSchema`, and a `Fold` can only
+//   produce a `Schema`.
+// * The code generators decide how to recurse from the parent: whether a
+//   subschema is inlined or named, what it is named, whether it is nullable.
+// * `random` builds each value out of values generated for its subschemas,
+//   and `mutate` walks an instance alongside the schema.
+// * `subschema` walks two schemas side by side.
+
+use crate::schema::{Form, Schema};
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A read-only traversal of a schema.
+pub trait Visitor<'a> {
+    /// Visit a schema.
+    ///
+    /// The default implementation visits the schema's children. Overriding
+    /// implementations should call
+    /// [`Context::visit_children`](struct.Context.html#method.visit_children)
+    /// themselves, unless they mean to skip the children.
+    fn visit_schema(&mut self, cx: &mut Context<'a>, schema: &'a Schema) {
+        cx.visit_children(self, schema);
+    }
+}
+
+/// A rewriting traversal of a schema.
+pub trait Fold {
+    /// Rewrite a schema, found at `path`.
+    ///
+    /// The default implementation rewrites the schema's children. Overriding
+    /// implementations should call [`fold_children`](fn.fold_children.html)
+    /// themselves, unless they mean to leave the children as they are.
+    fn fold_schema(&mut self, path: &[String], schema: Schema) -> Schema {
+        fold_children(self, path, schema)
+    }
+}
+
+/// Rewrite each of the immediate subschemas of a schema using `folder`.
+///
+/// Definitions are not considered children. A [`Walker`](struct.Walker.html)
+/// folds them separately.
+pub fn fold_children<F: Fold + ?Sized>(folder: &mut F, path: &[String], schema: Schema) -> Schema {
+    let mut fold = |tokens: &[&str], sub_schema: Schema| {
+        let mut sub_path = path.to_vec();
+        sub_path.extend(tokens.iter().map(|&token| token.to_owned()));
+        folder.fold_schema(&sub_path, sub_schema)
+    };
+
+    let (defs, form, extra) = schema.into_parts();
+    let form = match *form {
+        Form::Elements(sub_schema) => Form::Elements(fold(&["elements"], sub_schema)),
+        Form::Values(sub_schema) => Form::Values(fold(&["values"], sub_schema)),
+        Form::Properties(required, optional, has_required) => {
+            let required = required
+                .into_iter()
+                .map(|(name, sub_schema)| {
+                    let sub_schema = fold(&["properties", &name], sub_schema);
+                    (name, sub_schema)
+                })
+                .collect();
+
+            let optional = optional
+                .into_iter()
+                .map(|(name, sub_schema)| {
+                    let sub_schema = fold(&["optionalProperties", &name], sub_schema);
+                    (name, sub_schema)
+                })
+                .collect();
+
+            Form::Properties(required, optional, has_required)
+        }
+        Form::Discriminator(tag, mapping) => {
+            let mapping = mapping
+                .into_iter()
+                .map(|(value, sub_schema)| {
+                    let sub_schema = fold(&["discriminator", "mapping", &value], sub_schema);
+                    (value, sub_schema)
+                })
+                .collect();
+
+            Form::Discriminator(tag, mapping)
+        }
+        form => form,
+    };
+
+    Schema::from_parts(defs, Box::new(form), extra)
+}
+
+/// Drives visitors and folds over schemas.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct Walker {
+    config: Config,
+}
+
+impl Walker {
+    /// Constructs a new walker using the default configuration.
+    pub fn new() -> Self {
+        Self::new_with_config(Config::default())
+    }
+
+    /// Constructs a new walker using a configuration.
+    pub fn new_with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Visit a root schema, and then each of its definitions.
+    pub fn walk<'a, V: Visitor<'a> + ?Sized>(&self, schema: &'a Schema, visitor: &mut V) {
+        let defs = schema.definitions().as_ref();
+        let mut cx = Context {
+            defs,
+            follow_refs: self.config.follow_refs,
+            path: Vec::new(),
+            refs: Vec::new(),
+        };
+
+        visitor.visit_schema(&mut cx, schema);
+
+        if let (true, Some(defs)) = (self.config.definitions, defs) {
+            for (name, sub_schema) in defs {
+                cx.path = vec!["definitions", name];
+                cx.refs = vec![name];
+                visitor.visit_schema(&mut cx, sub_schema);
+            }
+        }
+    }
+
+    /// Rewrite each definition of a root schema, and then the schema itself.
+    pub fn fold<F: Fold + ?Sized>(&self, schema: Schema, folder: &mut F) -> Schema {
+        let (defs, form, extra) = schema.into_parts();
+        let defs = defs.map(|defs| {
+            if !self.config.definitions {
+                return defs;
+            }
+
+            defs.into_iter()
+                .map(|(name, sub_schema)| {
+                    let path = vec!["definitions".to_owned(), name.clone()];
+                    let sub_schema = folder.fold_schema(&path, sub_schema);
+                    (name, sub_schema)
+                })
+                .collect()
+        });
+
+        folder.fold_schema(&[], Schema::from_parts(defs, form, extra))
+    }
+}
+
+/// Configuration for how a walker traverses schemas.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Config {
+    follow_refs: bool,
+    definitions: bool,
+}
+
+impl Config {
+    /// Create a new, default `Config`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether visitors should follow `ref`s into the definitions they
+    /// refer to. By default, refs are not followed.
+    ///
+    /// A definition reached through a ref is visited with the path
+    /// `/definitions/<name>`, just as validation errors report it. To avoid
+    /// infinite recursion, a ref is not followed if its definition is
+    /// already being visited.
+    pub fn follow_refs(&mut self, follow_refs: bool) -> &mut Self {
+        self.follow_refs = follow_refs;
+        self
+    }
+
+    /// Sets whether the definitions of a root schema are visited or folded,
+    /// after the root schema itself. By default, they are.
+    pub fn definitions(&mut self, definitions: bool) -> &mut Self {
+        self.definitions = definitions;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            follow_refs: false,
+            definitions: true,
+        }
+    }
+}
+
+/// The state of a traversal by a [`Visitor`](trait.Visitor.html).
+#[derive(Debug)]
+pub struct Context<'a> {
+    defs: Option<&'a BTreeMap<String, Schema>>,
+    follow_refs: bool,
+    path: Vec<&'a str>,
+    refs: Vec<&'a str>,
+}
+
+impl<'a> Context<'a> {
+    /// The schema path of the schema being visited.
+    pub fn path(&self) -> &[&'a str] {
+        &self.path
+    }
+
+    /// The definitions of the root schema being visited, if it has any.
+    pub fn definitions(&self) -> Option<&'a BTreeMap<String, Schema>> {
+        self.defs
+    }
+
+    /// Visit each of the immediate subschemas of a schema, and the definition
+    /// it refers to if it is a `ref` and refs are being followed.
+    pub fn visit_children<V: Visitor<'a> + ?Sized>(&mut self, visitor: &mut V, schema: &'a Schema) {
+        match schema.form() {
+            Form::Ref(def) => {
+                let target = self.defs.and_then(|defs| defs.get(def));
+                if let Some(target) = target {
+                    if self.follow_refs && !self.refs.contains(&def.as_str()) {
+                        let path = core::mem::replace(&mut self.path, vec!["definitions", def]);
+                        self.refs.push(def);
+                        visitor.visit_schema(self, target);
+                        self.refs.pop();
+                        self.path = path;
+                    }
+                }
+            }
+            Form::Elements(sub_schema) => self.visit(visitor, &["elements"], sub_schema),
+            Form::Values(sub_schema) => self.visit(visitor, &["values"], sub_schema),
+            Form::Properties(required, optional, _) => {
+                for (name, sub_schema) in required {
+                    self.visit(visitor, &["properties", name], sub_schema);
+                }
+
+                for (name, sub_schema) in optional {
+                    self.visit(visitor, &["optionalProperties", name], sub_schema);
+                }
+            }
+            Form::Discriminator(_, mapping) => {
+                for (value, sub_schema) in mapping {
+                    self.visit(visitor, &["discriminator", "mapping", value], sub_schema);
+                }
+            }
+            Form::Empty | Form::Type(_) | Form::Enum(_) => {}
+        }
+    }
+
+    fn visit<V: Visitor<'a> + ?Sized>(
+        &mut self,
+        visitor: &mut V,
+        tokens: &[&'a str],
+        sub_schema: &'a Schema,
+    ) {
+        self.path.extend(tokens);
+        visitor.visit_schema(self, sub_schema);
+        self.path.truncate(self.path.len() - tokens.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Type;
+    use serde_json::json;
+
+    fn schema() -> Schema {
+        Schema::from_serde(
+            serde_json::from_value(json!({
+                "definitions": {
+                    "node": {
+                        "properties": {
+                            "value": { "type": "int8" },
+                        },
+                        "optionalProperties": {
+                            "next": { "ref": "node" },
+                        },
+                    },
+                },
+                "elements": { "ref": "node" },
+            }))
+            .unwrap(),
+        )
+        .unwrap()
+    }
+
+    struct Paths(Vec<String>);
+
+    impl<'a> Visitor<'a> for Paths {
+        fn visit_schema(&mut self, cx: &mut Context<'a>, schema: &'a Schema) {
+            self.0.push(format!("/{}", cx.path().join("/")));
+            cx.visit_children(self, schema);
+        }
+    }
+
+    #[test]
+    fn visit() {
+        let schema = schema();
+        let mut paths = Paths(Vec::new());
+        Walker::new().walk(&schema, &mut paths);
+
+        assert_eq!(
+            paths.0,
+            vec![
+                "/",
+                "/elements",
+                "/definitions/node",
+                "/definitions/node/properties/value",
+                "/definitions/node/optionalProperties/next",
+            ]
+        );
+    }
+
+    #[test]
+    fn follow_refs() {
+        let schema = schema();
+        let mut config = Config::new();
+        config.follow_refs(true).definitions(false);
+
+        let mut paths = Paths(Vec::new());
+        Walker::new_with_config(config).walk(&schema, &mut paths);
+
+        assert_eq!(
+            paths.0,
+            vec![
+                "/",
+                "/elements",
+                "/definitions/node",
+                "/definitions/node/properties/value",
+                "/definitions/node/optionalProperties/next",
+            ]
+        );
+    }
+
+    struct Widen;
+
+    impl Fold for Widen {
+        fn fold_schema(&mut self, path: &[String], schema: Schema) -> Schema {
+            let schema = fold_children(self, path, schema);
+            match schema.form() {
+                Form::Type(Type::Int8) => {
+                    let mut extra = BTreeMap::new();
+                    extra.insert("path".to_owned(), json!(path.join("/")));
+                    Schema::from_parts(None, Box::new(Form::Type(Type::Int64)), extra)
+                }
+                _ => schema,
+            }
+        }
+    }
+
+    #[test]
+    fn fold() {
+        let schema = Walker::new().fold(schema(), &mut Widen);
+
+        assert_eq!(
+            serde_json::to_value(schema.into_serde()).unwrap(),
+            json!({
+                "definitions": {
+                    "node": {
+                        "properties": {
+                            "value": {
+                                "type": "int64",
+                                "path": "definitions/node/properties/value",
+                            },
+                        },
+                        "optionalProperties": {
+                            "next": { "ref": "node" },
+                        },
+                    },
+                },
+                "elements": { "ref": "node" },
+            })
+        );
+    }
+}
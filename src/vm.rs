@@ -1,11 +1,14 @@
 use crate::errors::JslError;
+use crate::pointer::JsonPointer;
 use crate::schema::{Form, Schema, Type};
-use crate::validator::ValidationError;
-use chrono::DateTime;
-use failure::{err_msg, Error};
-use json_pointer::JsonPointer;
+use crate::timestamp::is_rfc3339;
+use crate::validator::{Annotation, ValidationError};
+use alloc::borrow::Cow;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
 use serde_json::Value;
-use std::borrow::Cow;
 
 pub fn validate<'a>(
     max_failures: usize,
@@ -13,26 +16,82 @@ pub fn validate<'a>(
     strict_instance_semantics: bool,
     schema: &'a Schema,
     instance: &'a Value,
-) -> Result<Vec<ValidationError<'a>>, Error> {
-    let mut vm = Vm {
+) -> Result<Vec<ValidationError<'a>>, JslError> {
+    let start = Start {
+        schema,
+        schema_tokens: vec![vec![]],
+        instance_tokens: vec![],
+    };
+
+    validate_from(
         max_failures,
         max_depth,
         strict_instance_semantics,
-        root_schema: schema,
-        instance_tokens: vec![],
-        schema_tokens: vec![vec![]],
-        errors: vec![],
-    };
+        schema,
+        start,
+        instance,
+    )
+}
 
-    match vm.eval(schema, instance, None) {
+/// Where in a schema and instance evaluation should begin, for validating
+/// part of an instance.
+pub struct Start<'a> {
+    /// The subschema to evaluate against.
+    pub schema: &'a Schema,
+
+    /// The schema path to the subschema, as one list of tokens per reference
+    /// followed to reach it. Only the last list appears in errors.
+    pub schema_tokens: Vec<Vec<Cow<'a, str>>>,
+
+    /// The instance path to the part of the instance being evaluated.
+    pub instance_tokens: Vec<Cow<'a, str>>,
+}
+
+pub fn validate_from<'a>(
+    max_failures: usize,
+    max_depth: usize,
+    strict_instance_semantics: bool,
+    root_schema: &'a Schema,
+    start: Start<'a>,
+    instance: &'a Value,
+) -> Result<Vec<ValidationError<'a>>, JslError> {
+    let mut vm = Vm::new(
+        max_failures,
+        max_depth,
+        strict_instance_semantics,
+        root_schema,
+    );
+    vm.instance_tokens = start.instance_tokens;
+    vm.schema_tokens = start.schema_tokens;
+
+    match vm.eval(start.schema, instance, None) {
         Ok(()) | Err(EvalError::Internal) => Ok(vm.errors),
         Err(EvalError::Actual(error)) => Err(error),
     }
 }
 
+/// Validate an instance, also recording which schema applied at each location
+/// in it.
+#[allow(clippy::type_complexity)]
+pub fn annotate<'a>(
+    max_failures: usize,
+    max_depth: usize,
+    strict_instance_semantics: bool,
+    schema: &'a Schema,
+    instance: &'a Value,
+) -> Result<(Vec<ValidationError<'a>>, Vec<Annotation<'a>>), JslError> {
+    let mut vm = Vm::new(max_failures, max_depth, strict_instance_semantics, schema);
+    vm.annotations = Some(vec![]);
+
+    match vm.eval(schema, instance, None) {
+        Ok(()) | Err(EvalError::Internal) => Ok((vm.errors, vm.annotations.unwrap_or_default())),
+        Err(EvalError::Actual(error)) => Err(error),
+    }
+}
+
 enum EvalError {
     Internal,
-    Actual(Error),
+    Actual(JslError),
 }
 
 struct Vm<'a> {
@@ -43,22 +102,58 @@ struct Vm<'a> {
     instance_tokens: Vec<Cow<'a, str>>,
     schema_tokens: Vec<Vec<Cow<'a, str>>>,
     errors: Vec<ValidationError<'a>>,
+
+    /// Annotations, if they're being recorded.
+    annotations: Option<Vec<Annotation<'a>>>,
+
+    /// The schemas, definition, and discriminator tag passed through on the
+    /// way to the next annotation.
+    chain: Vec<&'a Schema>,
+    definition: Option<&'a str>,
+    discriminator: Option<(&'a str, &'a str)>,
 }
 
 impl<'a> Vm<'a> {
+    fn new(
+        max_failures: usize,
+        max_depth: usize,
+        strict_instance_semantics: bool,
+        root_schema: &'a Schema,
+    ) -> Self {
+        Vm {
+            max_failures,
+            max_depth,
+            strict_instance_semantics,
+            root_schema,
+            instance_tokens: vec![],
+            schema_tokens: vec![vec![]],
+            errors: vec![],
+            annotations: None,
+            chain: vec![],
+            definition: None,
+            discriminator: None,
+        }
+    }
+
     fn eval(
         &mut self,
         schema: &'a Schema,
         instance: &'a Value,
         parent_tag: Option<&'a str>,
     ) -> Result<(), EvalError> {
+        if self.annotations.is_some() {
+            self.annotate(schema, instance);
+        }
+
         match schema.form() {
             Form::Empty => {}
             Form::Ref(ref def) => {
                 if self.schema_tokens.len() == self.max_depth {
-                    return Err(EvalError::Actual(err_msg(JslError::MaxDepthExceeded)));
+                    return Err(EvalError::Actual(JslError::MaxDepthExceeded));
                 }
 
+                self.definition = Some(def);
+
                 let refd_schema = &self.root_schema.definitions().as_ref().unwrap()[def];
                 self.schema_tokens
                     .push(vec!["definitions".into(), def.into()]);
@@ -113,7 +208,7 @@ impl<'a> Vm<'a> {
                 }
                 Type::Timestamp => {
                     if let Some(s) = instance.as_str() {
-                        if DateTime::parse_from_rfc3339(s).is_err() {
+                        if !is_rfc3339(s) {
                             self.push_schema_token("type");
                             self.push_err()?;
                             self.pop_schema_token();
@@ -228,6 +323,7 @@ impl<'a> Vm<'a> {
                     if let Some(instance_tag) = obj.get(tag) {
                         if let Some(instance_tag) = instance_tag.as_str() {
                             if let Some(sub_schema) = mapping.get(instance_tag) {
+                                self.discriminator = Some((tag, instance_tag));
                                 self.push_schema_token("mapping");
                                 self.push_schema_token(instance_tag);
                                 self.eval(sub_schema, instance, Some(tag))?;
@@ -255,15 +351,53 @@ impl<'a> Vm<'a> {
                 } else {
                     self.push_err()?;
                 }
+                self.pop_schema_token();
             }
         }
 
         Ok(())
     }
 
+    /// Record the schema applying to the current location in the instance,
+    /// unless it defers to another one.
+    fn annotate(&mut self, schema: &'a Schema, instance: &'a Value) {
+        self.chain.push(schema);
+
+        let defers = match schema.form() {
+            Form::Ref(_) => true,
+            Form::Discriminator(tag, mapping) => instance
+                .get(tag)
+                .and_then(Value::as_str)
+                .is_some_and(|tag| mapping.contains_key(tag)),
+            _ => false,
+        };
+
+        if defers {
+            return;
+        }
+
+        let schema_path = self
+            .schema_tokens
+            .last()
+            .expect("unreachable: empty schema stack")
+            .clone();
+
+        let annotation = Annotation {
+            instance_path: JsonPointer::new(self.instance_tokens.clone()),
+            schema_path: JsonPointer::new(schema_path),
+            schemas: mem::take(&mut self.chain),
+            definition: self.definition.take(),
+            discriminator: self.discriminator.take(),
+        };
+
+        if let Some(annotations) = &mut self.annotations {
+            annotations.push(annotation);
+        }
+    }
+
     fn check_int(&mut self, instance: &Value, min: f64, max: f64) -> Result<(), EvalError> {
         if let Some(n) = instance.as_f64() {
-            if n.fract() != 0.0 || n < min || n > max {
+            if n % 1.0 != 0.0 || n < min || n > max {
                 self.push_schema_token("type");
                 self.push_err()?;
                 self.pop_schema_token();
@@ -0,0 +1,131 @@
+//! JavaScript bindings, for use in WebAssembly.
+//!
+//! With the `wasm` feature enabled, this module exposes `Schema` and
+//! `Validator` classes to JavaScript. The `jsl_wasm` crate re-exports them
+//! from a `cdylib`, which can be built with
+//! [`wasm-pack`](https://rustwasm.github.io/wasm-pack/) into a package that
+//! validates data with exactly the same semantics as Rust code using this
+//! crate:
+//!
+//! ```js
+//! import { Schema, Validator } from "jsl";
+//!
+//! const schema = new Schema({ properties: { name: { type: "string" } } });
+//! const validator = new Validator({ strict: true, maxErrors: 10 });
+//!
+//! validator.validate(schema, { name: 3 });
+//! // [{ instancePath: "/name", schemaPath: "/properties/name/type" }]
+//! ```
+//!
+//! Schemas and instances are plain JavaScript objects, as `JSON.parse` would
+//! produce. A `Schema` holds the parsed schema on the WebAssembly side, so it
+//! can be reused across calls to `validate` without being converted again.
+//!
+//! Run the tests for these bindings under Node with `wasm-pack test --node
+//! jsl_wasm`.
+
+use crate::schema::{Schema, Serde};
+use crate::validator::{Config, Validator};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// A parsed, root schema.
+#[wasm_bindgen(js_name = Schema)]
+pub struct WasmSchema {
+    schema: Schema,
+}
+
+#[wasm_bindgen(js_class = Schema)]
+impl WasmSchema {
+    /// Parse a schema from a plain object. Throws an `Error` if the object
+    /// isn't a valid schema.
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: JsValue) -> Result<WasmSchema, JsError> {
+        let serde: Serde = serde_wasm_bindgen::from_value(data)?;
+        let schema = Schema::from_serde(serde).map_err(|err| JsError::new(&err.to_string()))?;
+        Ok(WasmSchema { schema })
+    }
+
+    /// Convert the schema back into a plain object.
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsError> {
+        to_js(&self.schema.clone().into_serde())
+    }
+}
+
+/// Validates instances against schemas.
+#[wasm_bindgen(js_name = Validator)]
+pub struct WasmValidator {
+    validator: Validator,
+}
+
+/// The options a `Validator` can be constructed with, mirroring
+/// [`Config`](../validator/struct.Config.html).
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Options {
+    #[serde(default)]
+    strict: bool,
+    max_errors: Option<usize>,
+    max_depth: Option<usize>,
+}
+
+/// A validation error, as it is given to JavaScript.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Error {
+    instance_path: String,
+    schema_path: String,
+}
+
+#[wasm_bindgen(js_class = Validator)]
+impl WasmValidator {
+    /// Construct a validator. `options` may be omitted, or be an object with
+    /// any of `strict`, `maxErrors` and `maxDepth`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(options: JsValue) -> Result<WasmValidator, JsError> {
+        let options: Option<Options> = serde_wasm_bindgen::from_value(options)?;
+        let options = options.unwrap_or_default();
+
+        let mut config = Config::new();
+        config.strict_instance_semantics(options.strict);
+        if let Some(max_errors) = options.max_errors {
+            config.max_errors(max_errors);
+        }
+        if let Some(max_depth) = options.max_depth {
+            config.max_depth(max_depth);
+        }
+
+        Ok(WasmValidator {
+            validator: Validator::new_with_config(config),
+        })
+    }
+
+    /// Validate an instance against a schema, returning an array of
+    /// `{ instancePath, schemaPath }` objects. Throws an `Error` if the
+    /// maximum reference depth is exceeded.
+    pub fn validate(&self, schema: &WasmSchema, instance: JsValue) -> Result<JsValue, JsError> {
+        let instance: serde_json::Value = serde_wasm_bindgen::from_value(instance)?;
+        let errors = self
+            .validator
+            .validate(&schema.schema, &instance)
+            .map_err(|err| JsError::new(&err.to_string()))?;
+
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|error| Error {
+                instance_path: error.instance_path().to_string(),
+                schema_path: error.schema_path().to_string(),
+            })
+            .collect();
+
+        to_js(&errors)
+    }
+}
+
+/// Convert a value into plain JavaScript objects and arrays, rather than the
+/// `Map`s that `serde_wasm_bindgen` produces by default.
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    Ok(value.serialize(&serializer)?)
+}
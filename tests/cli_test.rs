@@ -0,0 +1,270 @@
+use pretty_assertions::assert_eq;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// Write some files into a fresh directory for one test.
+fn fixtures(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(test);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    for (name, data) in files {
+        fs::write(dir.join(name), data).unwrap();
+    }
+
+    dir
+}
+
+fn jsl(dir: &Path, args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_jsl"))
+        .current_dir(dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // The tool may exit without reading standard input, such as when the
+    // schema is invalid, which closes the pipe.
+    match child.stdin.take().unwrap().write_all(stdin.as_bytes()) {
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => {}
+        result => result.unwrap(),
+    }
+
+    child.wait_with_output().unwrap()
+}
+
+const SCHEMA: &str = r#"{ "properties": { "name": { "type": "string" } } }"#;
+
+#[test]
+fn valid() {
+    let dir = fixtures(
+        "valid",
+        &[("schema.json", SCHEMA), ("a.json", r#"{ "name": "a" }"#)],
+    );
+
+    let output = jsl(&dir, &["validate", "schema.json", "a.json"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "a.json: valid\n");
+}
+
+#[test]
+fn invalid() {
+    let dir = fixtures(
+        "invalid",
+        &[
+            ("schema.json", SCHEMA),
+            ("a.json", r#"{ "name": "a" }"#),
+            ("b.json", r#"{ "name": "b", "extra": true }"#),
+            ("c.json", r#"{}"#),
+        ],
+    );
+
+    let output = jsl(
+        &dir,
+        &[
+            "validate",
+            "--strict",
+            "--format",
+            "json",
+            "schema.json",
+            "*.json",
+        ],
+        "",
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+
+    // The glob also matches the schema, which is not valid against itself.
+    let results: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        results,
+        json!([
+            { "instance": "a.json", "valid": true, "errors": [] },
+            {
+                "instance": "b.json",
+                "valid": false,
+                "errors": [{ "instancePath": "/extra", "schemaPath": "" }],
+            },
+            {
+                "instance": "c.json",
+                "valid": false,
+                "errors": [{ "instancePath": "", "schemaPath": "/properties/name" }],
+            },
+            {
+                "instance": "schema.json",
+                "valid": false,
+                "errors": [
+                    { "instancePath": "", "schemaPath": "/properties/name" },
+                    { "instancePath": "/properties", "schemaPath": "" },
+                ],
+            },
+        ])
+    );
+}
+
+#[test]
+fn max_errors() {
+    let dir = fixtures(
+        "max_errors",
+        &[("schema.json", r#"{ "elements": {"type": "string"} }"#)],
+    );
+
+    let output = jsl(
+        &dir,
+        &["validate", "--max-errors", "1", "schema.json", "-"],
+        "[1, 2, 3]",
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "<stdin>: invalid\n  instance path: \"/0\", schema path: \"/elements/type\"\n"
+    );
+}
+
+#[test]
+fn unparseable() {
+    let dir = fixtures(
+        "unparseable",
+        &[
+            ("schema.json", SCHEMA),
+            ("a.json", r#"{}"#),
+            ("b.json", r#"{ "name": "#),
+        ],
+    );
+
+    let output = jsl(
+        &dir,
+        &["validate", "schema.json", "a.json", "b.json", "*.yaml"],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(3));
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines[0], "a.json: invalid");
+    assert!(lines[2].starts_with("b.json: error: "));
+    assert_eq!(lines[3], "*.yaml: error: no files match pattern");
+
+    let output = jsl(&dir, &["validate", "b.json", "a.json"], "");
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn invalid_schema() {
+    let dir = fixtures(
+        "invalid_schema",
+        &[
+            ("bad_form.json", r#"{ "type": "string", "elements": {} }"#),
+            ("bad_ref.json", r#"{ "ref": "missing" }"#),
+            ("bad_keyword.json", r#"{ "type": 3 }"#),
+            (
+                "loop.json",
+                r#"{ "definitions": { "a": { "ref": "a" } }, "ref": "a" }"#,
+            ),
+        ],
+    );
+
+    for schema in &["bad_form.json", "bad_ref.json", "bad_keyword.json"] {
+        let output = jsl(&dir, &["validate", schema], "{}");
+        assert_eq!(output.status.code(), Some(4), "{}", schema);
+    }
+
+    let output = jsl(&dir, &["validate", "--max-depth", "4", "loop.json"], "{}");
+    assert_eq!(output.status.code(), Some(4));
+}
+
+#[test]
+fn usage() {
+    let dir = fixtures("usage", &[]);
+
+    let output = jsl(
+        &dir,
+        &["validate", "--max-errors", "lots", "schema.json"],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = jsl(&dir, &["validate", "--help"], "");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn coverage() {
+    let dir = fixtures(
+        "coverage",
+        &[
+            (
+                "schema.json",
+                r#"{
+                    "properties": { "name": { "type": "string" } },
+                    "optionalProperties": { "color": { "enum": ["red", "blue"] } }
+                }"#,
+            ),
+            ("a.json", r#"{ "name": "a" }"#),
+            ("b.json", r#"{ "name": "b", "color": "red" }"#),
+            ("c.json", r#"{ "name": 3 }"#),
+        ],
+    );
+
+    let output = jsl(&dir, &["coverage", "schema.json", "a.json", "b.json"], "");
+    assert_eq!(output.status.code(), Some(0));
+
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        report,
+        json!({
+            "instances": 2,
+            "schemas": { "total": 3, "hit": 3 },
+            "values": { "total": 2, "hit": 1 },
+            "entries": [
+                { "path": "", "kind": "schema", "hits": 2 },
+                { "path": "/properties/name", "kind": "schema", "hits": 2 },
+                {
+                    "path": "/optionalProperties/color",
+                    "kind": "optionalProperty",
+                    "hits": 1,
+                    "values": { "blue": 0, "red": 1 },
+                },
+            ],
+        })
+    );
+
+    let output = jsl(
+        &dir,
+        &[
+            "coverage",
+            "--format",
+            "lcov",
+            "schema.json",
+            "a.json",
+            "c.json",
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "c.json: invalid\n"
+    );
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "TN:\n\
+         SF:schema.json\n\
+         DA:/,2\n\
+         DA:/properties/name,2\n\
+         DA:/optionalProperties/color,0\n\
+         BRDA:/optionalProperties/color,0,blue,-\n\
+         BRDA:/optionalProperties/color,0,red,-\n\
+         BRF:2\n\
+         BRH:0\n\
+         LF:3\n\
+         LH:2\n\
+         end_of_record\n"
+    );
+}
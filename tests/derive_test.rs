@@ -0,0 +1,259 @@
+use chrono::{DateTime, Utc};
+use jsl::JslSchema as _;
+use jsl::{Schema, Validator};
+use jsl_derive::JslSchema;
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+fn schema_json(schema: Schema) -> serde_json::Value {
+    serde_json::to_value(schema.into_serde()).unwrap()
+}
+
+#[allow(dead_code)]
+#[derive(JslSchema)]
+#[serde(rename_all = "camelCase")]
+struct User {
+    user_id: u64,
+    age: u8,
+    balance: i64,
+    created_at: DateTime<Utc>,
+    nickname: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(rename = "prefs")]
+    preferences: BTreeMap<String, bool>,
+    #[serde(skip)]
+    cache: Vec<u8>,
+    home: Address,
+    work: Option<Address>,
+}
+
+#[allow(dead_code)]
+#[derive(JslSchema)]
+struct Address {
+    street: String,
+}
+
+#[allow(dead_code)]
+#[derive(JslSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum Status {
+    Active,
+    OnHold,
+}
+
+#[allow(dead_code)]
+#[derive(JslSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Event {
+    Created { user: User },
+    StatusChanged { status: Status },
+    Moved(Address),
+    Deleted,
+}
+
+#[allow(dead_code)]
+#[derive(JslSchema)]
+struct Tree {
+    value: i32,
+    children: Vec<Tree>,
+}
+
+#[allow(dead_code)]
+#[derive(JslSchema)]
+struct Id(u32);
+
+#[allow(dead_code)]
+#[derive(JslSchema)]
+#[serde(default)]
+struct Settings {
+    theme: String,
+    font_size: Option<u8>,
+}
+
+#[allow(dead_code)]
+#[derive(JslSchema)]
+struct Home(Address);
+
+#[allow(dead_code)]
+#[derive(JslSchema)]
+#[serde(tag = "type")]
+enum Location {
+    Home(Home),
+}
+
+#[test]
+fn derive_struct() {
+    assert_eq!(
+        schema_json(User::jsl_schema()),
+        json!({
+            "definitions": {
+                "Address": {
+                    "properties": {
+                        "street": { "type": "string" },
+                    },
+                },
+            },
+            "properties": {
+                "userId": { "type": "uint64" },
+                "age": { "type": "uint8" },
+                "balance": { "type": "int64" },
+                "createdAt": { "type": "timestamp" },
+                "prefs": { "values": { "type": "boolean" } },
+                "home": { "ref": "Address" },
+            },
+            "optionalProperties": {
+                "nickname": { "type": "string" },
+                "tags": { "elements": { "type": "string" } },
+                "work": { "ref": "Address" },
+            },
+        })
+    );
+}
+
+#[test]
+fn derive_enum() {
+    assert_eq!(
+        schema_json(Status::jsl_schema())["enum"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    let schema = Event::jsl_schema();
+    let json = schema_json(schema.clone());
+    assert_eq!(json["discriminator"]["tag"], json!("type"));
+    assert_eq!(
+        json["discriminator"]["mapping"]["moved"],
+        json!({ "properties": { "street": { "type": "string" } } })
+    );
+    assert_eq!(
+        json["discriminator"]["mapping"]["status_changed"],
+        json!({ "properties": { "status": { "ref": "Status" } } })
+    );
+    assert_eq!(
+        json["discriminator"]["mapping"]["deleted"],
+        json!({ "properties": {} })
+    );
+
+    let mut defs: Vec<_> = schema
+        .definitions()
+        .as_ref()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect();
+    defs.sort();
+    assert_eq!(defs, vec!["Address", "Status", "User"]);
+
+    // The derived schema must be a valid schema.
+    let schema = Schema::from_serde(serde_json::from_value(json).unwrap()).unwrap();
+    let instance = json!({ "type": "status_changed", "status": "ON_HOLD" });
+    let errors = Validator::new().validate(&schema, &instance).unwrap();
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn derive_recursive() {
+    assert_eq!(
+        schema_json(Tree::jsl_schema()),
+        json!({
+            "definitions": {
+                "Tree": {
+                    "properties": {
+                        "value": { "type": "int32" },
+                        "children": { "elements": { "ref": "Tree" } },
+                    },
+                },
+            },
+            "properties": {
+                "value": { "type": "int32" },
+                "children": { "elements": { "ref": "Tree" } },
+            },
+        })
+    );
+}
+
+#[test]
+fn derive_newtype() {
+    assert_eq!(
+        schema_json(Id::jsl_schema()),
+        json!({ "definitions": {}, "type": "uint32" })
+    );
+}
+
+#[test]
+fn derive_container_default() {
+    assert_eq!(
+        schema_json(Settings::jsl_schema()),
+        json!({
+            "definitions": {},
+            "optionalProperties": {
+                "theme": { "type": "string" },
+                "font_size": { "type": "uint8" },
+            },
+        })
+    );
+}
+
+#[test]
+fn derive_newtype_variant_of_newtype() {
+    let json = schema_json(Location::jsl_schema());
+    assert_eq!(
+        json["discriminator"]["mapping"]["Home"],
+        json!({ "properties": { "street": { "type": "string" } } })
+    );
+}
+
+/// The names `#[serde(rename_all = $rule)]` gives the field `user_id` and the
+/// variant `OnHold`.
+macro_rules! renamed {
+    ($rule:literal) => {{
+        #[allow(dead_code)]
+        #[derive(JslSchema)]
+        #[serde(rename_all = $rule)]
+        struct Fields {
+            user_id: u64,
+        }
+
+        #[allow(dead_code)]
+        #[derive(JslSchema)]
+        #[serde(rename_all = $rule)]
+        enum Variants {
+            OnHold,
+        }
+
+        let fields = schema_json(Fields::jsl_schema());
+        let variants = schema_json(Variants::jsl_schema());
+        (
+            fields["properties"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .next()
+                .unwrap()
+                .clone(),
+            variants["enum"][0].as_str().unwrap().to_owned(),
+        )
+    }};
+}
+
+#[test]
+fn derive_rename_all() {
+    assert_eq!(renamed!("lowercase"), ("user_id".into(), "onhold".into()));
+    assert_eq!(renamed!("UPPERCASE"), ("USER_ID".into(), "ONHOLD".into()));
+    assert_eq!(renamed!("PascalCase"), ("UserId".into(), "OnHold".into()));
+    assert_eq!(renamed!("camelCase"), ("userId".into(), "onHold".into()));
+    assert_eq!(renamed!("snake_case"), ("user_id".into(), "on_hold".into()));
+    assert_eq!(
+        renamed!("SCREAMING_SNAKE_CASE"),
+        ("USER_ID".into(), "ON_HOLD".into())
+    );
+    assert_eq!(renamed!("kebab-case"), ("user-id".into(), "on-hold".into()));
+    assert_eq!(
+        renamed!("SCREAMING-KEBAB-CASE"),
+        ("USER-ID".into(), "ON-HOLD".into())
+    );
+}
@@ -0,0 +1,179 @@
+use pretty_assertions::assert_eq;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// Drives `jsl-lsp` over stdio, one JSON-RPC message at a time.
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_jsl-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        Self {
+            child,
+            stdin,
+            stdout,
+            next_id: 1,
+        }
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut len = 0;
+        loop {
+            let mut line = String::new();
+            self.stdout.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some(value) = line.strip_prefix("Content-Length: ") {
+                len = value.parse().unwrap();
+            }
+        }
+
+        let mut body = vec![0; len];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+
+        let response = self.receive();
+        assert_eq!(response["id"], json!(id));
+        response["result"].clone()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+}
+
+const URI: &str = "file:///schema.json";
+
+/// A position within the test schema, as `[line, character]`.
+fn at(line: u32, character: u32) -> Value {
+    json!({
+        "textDocument": { "uri": URI },
+        "position": { "line": line, "character": character },
+    })
+}
+
+fn range(start: (u32, u32), end: (u32, u32)) -> Value {
+    json!({
+        "start": { "line": start.0, "character": start.1 },
+        "end": { "line": end.0, "character": end.1 },
+    })
+}
+
+#[test]
+fn session() {
+    let mut client = Client::start();
+
+    let result = client.request("initialize", json!({ "capabilities": {} }));
+    assert_eq!(result["capabilities"]["hoverProvider"], json!(true));
+    client.notify("initialized", json!({}));
+
+    let text = [
+        r#"{"#,
+        r#"  "definitions": { "node": { "properties": { "next": { "ref": "node" } } } },"#,
+        r#"  "elements": { "ref": "node", "type": "string" },"#,
+        r#"  "values": { "ref": "missing" }"#,
+        r#"}"#,
+    ]
+    .join("\n");
+
+    client.notify(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": { "uri": URI, "languageId": "json", "version": 1, "text": text },
+        }),
+    );
+
+    let diagnostics = client.receive();
+    assert_eq!(diagnostics["method"], "textDocument/publishDiagnostics");
+    let diagnostics: Vec<_> = diagnostics["params"]["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|diagnostic| (diagnostic["range"].clone(), diagnostic["message"].clone()))
+        .collect();
+
+    assert_eq!(
+        diagnostics,
+        vec![
+            (
+                range((3, 21), (3, 30)),
+                json!("no such definition: missing")
+            ),
+            (range((0, 0), (4, 1)), json!("invalid schema form")),
+            (range((2, 14), (2, 49)), json!("invalid schema form")),
+        ]
+    );
+
+    let definition = client.request("textDocument/definition", at(2, 25));
+    assert_eq!(
+        definition,
+        json!({ "uri": URI, "range": range((1, 19), (1, 25)) })
+    );
+
+    let mut params = at(1, 21);
+    params["context"] = json!({ "includeDeclaration": false });
+    let references = client.request("textDocument/references", params);
+    assert_eq!(
+        references,
+        json!([
+            { "uri": URI, "range": range((1, 62), (1, 68)) },
+            { "uri": URI, "range": range((2, 23), (2, 29)) },
+        ])
+    );
+
+    let hover = client.request("textDocument/hover", at(1, 30));
+    assert_eq!(hover["contents"]["value"], json!("`{ next: node }`"));
+
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{ "text": r#"{ "definitions": { "a": {} }, "ref": "" }"# }],
+        }),
+    );
+
+    let diagnostics = client.receive();
+    assert_eq!(
+        diagnostics["params"]["diagnostics"]
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let completion = client.request("textDocument/completion", at(0, 38));
+    assert_eq!(completion[0]["label"], json!("a"));
+
+    assert_eq!(client.request("shutdown", Value::Null), Value::Null);
+    client.notify("exit", Value::Null);
+    assert!(client.child.wait().unwrap().success());
+}
@@ -0,0 +1,24 @@
+//! Checks that the library still builds without the `std` feature.
+//!
+//! This builds for the host by default, which catches uses of `std` in this
+//! crate. To also catch dependencies that pull in `std`, set
+//! `JSL_NO_STD_TARGET` to a target without it, such as `thumbv7em-none-eabi`.
+
+use std::env;
+use std::process::Command;
+
+#[test]
+fn no_std() {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned());
+    let mut build = Command::new(cargo);
+    build.args(["build", "--quiet", "--lib", "--no-default-features"]);
+    if let Ok(target) = env::var("JSL_NO_STD_TARGET") {
+        build.args(["--target", &target]);
+    }
+
+    let status = build
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "building without std failed");
+}